@@ -0,0 +1,174 @@
+//! `--account all`: runs a read-only shared command against every
+//! configured account, in sequence.
+
+use std::{fmt, path::PathBuf};
+
+use anyhow::{Context, Result, bail};
+use log::debug;
+use pimalaya_cli::printer::Printer;
+use serde::{Serialize, Serializer, ser::SerializeMap};
+
+use crate::{
+    account::context::Account,
+    backend::Backend,
+    cli::load_or_wizard,
+    shared::client::{EmailClient, build_email_client},
+};
+
+/// Reserved `--account` value selecting every configured account.
+pub const ALL_ACCOUNTS: &str = "all";
+
+/// Bails when `name` is [`ALL_ACCOUNTS`]: `--account all` would
+/// never select such an account.
+pub fn check_account_name(name: &str) -> Result<()> {
+    if name == ALL_ACCOUNTS {
+        bail!(
+            "Account name `{ALL_ACCOUNTS}` is reserved for `--account {ALL_ACCOUNTS}`, rename it"
+        );
+    }
+
+    Ok(())
+}
+
+/// Outputs of the same command gathered across accounts, in account
+/// name order, or the error the command failed with for an account.
+/// Renders each output under an account heading, or as a JSON object
+/// keyed by account name, failed accounts holding an `error` entry.
+pub struct PerAccount<T>(Vec<(String, Result<T, String>)>);
+
+impl<T> PerAccount<T> {
+    /// Prints the outputs, then fails when the command failed for at
+    /// least one account.
+    pub fn print(self, printer: &mut impl Printer) -> Result<()>
+    where
+        T: Serialize + fmt::Display,
+    {
+        let total = self.0.len();
+        let failed = self.0.iter().filter(|(_, output)| output.is_err()).count();
+
+        printer.out(self)?;
+
+        if failed > 0 {
+            bail!("Command failed for {failed} of {total} account(s)");
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Serialize> Serialize for PerAccount<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Failure<'a> {
+            error: &'a str,
+        }
+
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, output) in &self.0 {
+            match output {
+                Ok(output) => map.serialize_entry(name, output)?,
+                Err(error) => map.serialize_entry(name, &Failure { error })?,
+            }
+        }
+        map.end()
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for PerAccount<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, output)) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "Account: {name}")?;
+            match output {
+                // NOTE: single-line outputs (e.g. counts) are not
+                // terminated, the printer ends them
+                Ok(output) => {
+                    let output = output.to_string();
+                    write!(f, "{output}")?;
+                    if !output.ends_with('\n') {
+                        writeln!(f)?;
+                    }
+                }
+                Err(err) => writeln!(f, "Error: {err}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds a shared client for each configured account, one after the
+/// other, and collects what `run` returns for it. An account failing
+/// does not stop the others: its error is collected in place of its
+/// output.
+pub fn collect<T>(
+    config_paths: &[PathBuf],
+    backend: Backend,
+    mut run: impl FnMut(&mut Account, &mut EmailClient) -> Result<T>,
+) -> Result<PerAccount<T>> {
+    let mut names: Vec<String> = load_or_wizard(config_paths)?.accounts.into_keys().collect();
+    names.sort();
+
+    let mut outputs = Vec::with_capacity(names.len());
+
+    for name in names {
+        let output = build_email_client(config_paths, Some(&name), backend)
+            .context("Build client error")
+            .and_then(|(mut account, mut client)| run(&mut account, &mut client))
+            .map_err(|err| {
+                debug!("account `{name}` error: {err:?}");
+                format!("{err:#}")
+            });
+
+        outputs.push((name, output));
+    }
+
+    Ok(PerAccount(outputs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserves_the_all_account_name() {
+        assert!(check_account_name(ALL_ACCOUNTS).is_err());
+        assert!(check_account_name("All").is_ok());
+        assert!(check_account_name("work").is_ok());
+    }
+
+    #[test]
+    fn renders_failed_accounts_in_place() {
+        let outputs = PerAccount(vec![
+            (String::from("perso"), Ok(String::from("2 envelopes\n"))),
+            (
+                String::from("work"),
+                Err(String::from("Build client error: timeout")),
+            ),
+        ]);
+
+        assert_eq!(
+            outputs.to_string(),
+            "Account: perso\n2 envelopes\n\nAccount: work\nError: Build client error: timeout\n"
+        );
+        assert_eq!(
+            serde_json::to_string(&outputs).unwrap(),
+            r#"{"perso":"2 envelopes\n","work":{"error":"Build client error: timeout"}}"#
+        );
+    }
+
+    #[test]
+    fn terminates_single_line_outputs() {
+        let outputs = PerAccount::<u64>(vec![
+            (String::from("perso"), Ok(2)),
+            (String::from("work"), Ok(5)),
+        ]);
+
+        assert_eq!(
+            outputs.to_string(),
+            "Account: perso\n2\n\nAccount: work\n5\n"
+        );
+    }
+}
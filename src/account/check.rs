@@ -1,14 +1,23 @@
-use std::{fmt, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::PathBuf,
+};
 
 use anyhow::{Result, bail};
 use clap::Parser;
-use pimalaya_cli::printer::Printer;
+use pimalaya_cli::{printer::Printer, prompt};
 use pimalaya_config::toml::TomlConfig;
 use serde::Serialize;
 
 use crate::{
+    account::{
+        keyring,
+        network::{NetworkProbe, NetworkStep},
+    },
     backend::Backend,
     config::{AccountConfig, Config},
+    shared::client::EmailClient,
 };
 
 /// Validate the account configuration.
@@ -18,8 +27,30 @@ use crate::{
 /// allowed by `--backend`. The check tries to instantiate a client per
 /// backend, which exercises the same handshake / authentication paths
 /// the other commands would take.
+///
+/// IMAP and SMTP connections are walked layer by layer (DNS
+/// resolution, TCP connect, TLS handshake, login), each step being
+/// reported, so a failure points at the layer to fix. Certificate
+/// errors (expired, self-signed, wrong host) come with a hint.
+///
+/// Mailbox aliases (`[mailbox.alias]`) are then checked against the
+/// mailboxes the storage backend lists, so an alias pointing to a
+/// missing mailbox (e.g. `sent = "Sent Items"` on a server calling it
+/// `Sent`) is reported up front.
+///
+/// Secrets written in clear text in the configuration (`raw`
+/// passwords and tokens) are reported as well, and so are several
+/// accounts set as default: none of them is then used by default.
 #[derive(Debug, Parser)]
-pub struct AccountCheckCommand;
+pub struct AccountCheckCommand {
+    /// Offer to remove or remap each alias pointing to a missing
+    /// mailbox, and to move each plaintext secret to the system
+    /// keyring, then save the configuration. Secrets are moved in
+    /// place, while alias fixes do not preserve the comments of the
+    /// configuration file.
+    #[arg(long)]
+    pub fix: bool,
+}
 
 impl AccountCheckCommand {
     pub fn execute(
@@ -29,7 +60,7 @@ impl AccountCheckCommand {
         account_name: Option<&str>,
         backend: Backend,
     ) -> Result<()> {
-        let mut config = match Config::from_paths_or_default(config_paths)? {
+        let mut config = match Config::load(config_paths)? {
             Some(config) => config,
             None => bail!(
                 "No configuration found. Run `himalaya` once to launch the wizard, \
@@ -37,84 +68,124 @@ impl AccountCheckCommand {
             ),
         };
 
-        let (name, account_config) = config
-            .take_account(account_name)?
-            .ok_or_else(|| anyhow::anyhow!("Cannot find account"))?;
+        let defaults: Vec<String> = config
+            .default_accounts()
+            .into_iter()
+            .map(str::to_owned)
+            .collect();
+
+        let (name, account_config) = config.select_account(account_name)?;
 
         let mut report = CheckReport {
-            account: name,
+            account: name.clone(),
             backends: Vec::new(),
+            aliases: Vec::new(),
+            aliases_error: None,
+            secrets: keyring::plaintext_secrets(&account_config)?,
+            defaults: if defaults.len() > 1 {
+                defaults
+            } else {
+                Vec::new()
+            },
+            fixes: Vec::new(),
         };
 
-        #[cfg(feature = "imap")]
-        if backend.allows_imap() {
-            if let Some(imap_config) = account_config.imap.clone() {
-                report
-                    .backends
-                    .push(check_imap(&config, &account_config, imap_config));
-            }
+        report.backends = check_backends(&config, &account_config, backend);
+
+        if report.backends.is_empty() {
+            bail!("No backend matching `{backend}` is configured for this account");
         }
 
-        #[cfg(feature = "jmap")]
-        if backend.allows_jmap() {
-            if let Some(jmap_config) = account_config.jmap.clone() {
-                report
-                    .backends
-                    .push(check_jmap(&config, &account_config, jmap_config));
+        let aliases = alias_targets(&config, &account_config);
+        let storage_ok = report
+            .backends
+            .iter()
+            .any(|check| check.ok && check.backend != "smtp");
+
+        if !aliases.is_empty() && storage_ok {
+            match list_mailboxes(config, account_config, backend) {
+                Ok(mailboxes) => {
+                    report.aliases = check_aliases(aliases, &mailboxes);
+
+                    let dangling: Vec<&AliasCheck> =
+                        report.aliases.iter().filter(|check| !check.ok).collect();
+
+                    if self.fix && !dangling.is_empty() {
+                        report.fixes = fix_aliases(config_paths, &name, &dangling, &mailboxes)?;
+                    }
+                }
+                Err(err) => report.aliases_error = Some(format!("{err:#}")),
             }
         }
 
-        #[cfg(feature = "gmail")]
-        if backend.allows_gmail() {
-            if let Some(gmail_config) = account_config.gmail.clone() {
-                report
-                    .backends
-                    .push(check_gmail(&config, &account_config, gmail_config));
-            }
+        if self.fix && !report.secrets.is_empty() {
+            let fixes = keyring::migrate(config_paths, &name, &report.secrets)?;
+            report.fixes.extend(fixes);
         }
 
-        #[cfg(feature = "msgraph")]
-        if backend.allows_msgraph() {
-            if let Some(msgraph_config) = account_config.msgraph.clone() {
-                report
-                    .backends
-                    .push(check_msgraph(&config, &account_config, msgraph_config));
-            }
+        printer.out(report)
+    }
+}
+
+/// Checks the connection of each backend of the account allowed by
+/// `backend`, in the order storage backends first, then SMTP.
+pub(super) fn check_backends(
+    config: &Config,
+    account_config: &AccountConfig,
+    backend: Backend,
+) -> Vec<BackendCheck> {
+    let mut checks = Vec::new();
+
+    #[cfg(feature = "imap")]
+    if backend.allows_imap() {
+        if let Some(imap_config) = account_config.imap.clone() {
+            checks.push(check_imap(config, account_config, imap_config));
         }
+    }
 
-        #[cfg(feature = "maildir")]
-        if backend.allows_maildir() {
-            if let Some(maildir_config) = account_config.maildir.clone() {
-                report
-                    .backends
-                    .push(check_maildir(&config, &account_config, maildir_config));
-            }
+    #[cfg(feature = "jmap")]
+    if backend.allows_jmap() {
+        if let Some(jmap_config) = account_config.jmap.clone() {
+            checks.push(check_jmap(config, account_config, jmap_config));
         }
+    }
 
-        #[cfg(feature = "m2dir")]
-        if backend.allows_m2dir() {
-            if let Some(m2dir_config) = account_config.m2dir.clone() {
-                report
-                    .backends
-                    .push(check_m2dir(&config, &account_config, m2dir_config));
-            }
+    #[cfg(feature = "gmail")]
+    if backend.allows_gmail() {
+        if let Some(gmail_config) = account_config.gmail.clone() {
+            checks.push(check_gmail(config, account_config, gmail_config));
         }
+    }
 
-        #[cfg(feature = "smtp")]
-        if backend.allows_smtp() {
-            if let Some(smtp_config) = account_config.smtp.clone() {
-                report
-                    .backends
-                    .push(check_smtp(&config, &account_config, smtp_config));
-            }
+    #[cfg(feature = "msgraph")]
+    if backend.allows_msgraph() {
+        if let Some(msgraph_config) = account_config.msgraph.clone() {
+            checks.push(check_msgraph(config, account_config, msgraph_config));
         }
+    }
 
-        if report.backends.is_empty() {
-            bail!("No backend matching `{backend}` is configured for this account");
+    #[cfg(feature = "maildir")]
+    if backend.allows_maildir() {
+        if let Some(maildir_config) = account_config.maildir.clone() {
+            checks.push(check_maildir(config, account_config, maildir_config));
         }
+    }
 
-        printer.out(report)
+    #[cfg(feature = "m2dir")]
+    if backend.allows_m2dir() {
+        if let Some(m2dir_config) = account_config.m2dir.clone() {
+            checks.push(check_m2dir(config, account_config, m2dir_config));
+        }
+    }
+
+    #[cfg(feature = "smtp")]
+    if backend.allows_smtp() {
+        if let Some(smtp_config) = account_config.smtp.clone() {
+            checks.push(check_smtp(config, account_config, smtp_config));
+        }
     }
+
+    checks
 }
 
 #[cfg(feature = "imap")]
@@ -126,12 +197,23 @@ fn check_imap(
     use io_imap::client::ImapClientStd;
     use pimalaya_stream::sasl::Sasl;
 
-    use crate::imap::id::resolve_auto_id_params;
+    use crate::{
+        imap::{client::detect_hierarchy_delimiter, id::resolve_auto_id_params},
+        wizard::app_password,
+    };
 
-    let result = (|| -> Result<()> {
-        let tls = imap_config.tls.clone().into_tls(imap_config.alpn.clone());
+    let mut probe = NetworkProbe::default();
+
+    let result = (|| -> Result<Vec<String>> {
+        let tls = imap_config.tls.clone().into_tls(imap_config.alpn.clone())?;
         let auto_id = resolve_auto_id_params(&imap_config.id)?;
         let server = crate::imap::client::parse_imap_server(&imap_config.server)?;
+
+        probe.connect(&server, imap_config.starttls, || {
+            ImapClientStd::connect(&server, &tls, imap_config.starttls, None, None)?;
+            Ok(())
+        })?;
+
         let sasl: Option<Sasl> = imap_config
             .sasl
             .clone()
@@ -141,11 +223,23 @@ fn check_imap(
                 Some(cfg.try_into_sasl(host, port))
             })
             .transpose()?;
-        let _ = ImapClientStd::connect(&server, &tls, imap_config.starttls, sasl, auto_id)?;
-        Ok(())
+        let (mut client, _) = probe.login(
+            ImapClientStd::connect(&server, &tls, imap_config.starttls, sasl, auto_id)
+                .map_err(|err| app_password::explain(server.host_str(), err.into())),
+        )?;
+
+        let delimiter = match imap_config.hierarchy_delimiter {
+            Some(delim) => format!("`{delim}` (config override)"),
+            None => match detect_hierarchy_delimiter(&mut client, "")? {
+                Some(delim) => format!("`{delim}` (detected)"),
+                None => String::from("none (flat namespace)"),
+            },
+        };
+
+        Ok(vec![format!("hierarchy delimiter: {delimiter}")])
     })();
 
-    BackendCheck::from("imap", result)
+    BackendCheck::with_details("imap", result).with_steps(probe.steps)
 }
 
 #[cfg(feature = "jmap")]
@@ -159,7 +253,7 @@ fn check_jmap(
     use crate::jmap::client::{jmap_http_auth, parse_server_url};
 
     let result = (|| -> Result<()> {
-        let tls = jmap_config.tls.clone().into_tls(jmap_config.alpn.clone());
+        let tls = jmap_config.tls.clone().into_tls(jmap_config.alpn.clone())?;
         let http_auth = jmap_http_auth(jmap_config.auth.clone())?;
         let url = parse_server_url(&jmap_config.server)?;
         let mut client = JmapClientStd::connect(&url, &tls, http_auth)?;
@@ -182,7 +276,10 @@ fn check_gmail(
     use crate::gmail::client::gmail_token;
 
     let result = (|| -> Result<()> {
-        let tls = gmail_config.tls.clone().into_tls(gmail_config.alpn.clone());
+        let tls = gmail_config
+            .tls
+            .clone()
+            .into_tls(gmail_config.alpn.clone())?;
         let token = gmail_token(gmail_config.auth.clone())?;
         let options = GmailClientStdConnectOptions {
             tls,
@@ -211,7 +308,7 @@ fn check_msgraph(
         let tls = msgraph_config
             .tls
             .clone()
-            .into_tls(msgraph_config.alpn.clone());
+            .into_tls(msgraph_config.alpn.clone())?;
         let token = msgraph_token(msgraph_config.auth.clone())?;
         let options = MsgraphClientStdConnectOptions {
             tls,
@@ -274,10 +371,21 @@ fn check_smtp(
     use io_smtp::{client::SmtpClientStd, rfc5321::types::ehlo_domain::EhloDomain};
     use pimalaya_stream::sasl::Sasl;
 
+    use crate::wizard::app_password;
+
+    let mut probe = NetworkProbe::default();
+
     let result = (|| -> Result<()> {
-        let tls = smtp_config.tls.clone().into_tls(smtp_config.alpn.clone());
+        let tls = smtp_config.tls.clone().into_tls(smtp_config.alpn.clone())?;
         let domain: EhloDomain<'static> = Ipv4Addr::new(127, 0, 0, 1).into();
         let server = crate::smtp::client::parse_smtp_server(&smtp_config.server)?;
+
+        probe.connect(&server, smtp_config.starttls, || {
+            let domain = Ipv4Addr::new(127, 0, 0, 1).into();
+            SmtpClientStd::connect(&server, &tls, smtp_config.starttls, domain, None)?;
+            Ok(())
+        })?;
+
         let sasl: Option<Sasl> = smtp_config
             .sasl
             .clone()
@@ -287,18 +395,174 @@ fn check_smtp(
                 Some(cfg.try_into_sasl(host, port))
             })
             .transpose()?;
-        let _client = SmtpClientStd::connect(&server, &tls, smtp_config.starttls, domain, sasl)?;
+        let _client = probe.login(
+            SmtpClientStd::connect(&server, &tls, smtp_config.starttls, domain, sasl)
+                .map_err(|err| app_password::explain(server.host_str(), err.into())),
+        )?;
         Ok(())
     })();
 
-    BackendCheck::from("smtp", result)
+    BackendCheck::from("smtp", result).with_steps(probe.steps)
+}
+
+/// Mailbox aliases in effect for the account, keyed by lowercased
+/// alias: global entries overridden by account ones.
+fn alias_targets(config: &Config, account_config: &AccountConfig) -> BTreeMap<String, String> {
+    config
+        .mailbox
+        .aliases
+        .iter()
+        .chain(&account_config.mailbox.aliases)
+        .map(|(alias, target)| (alias.to_lowercase(), target.clone()))
+        .collect()
 }
 
-/// Aggregated account check result: one outcome per backend.
+/// Ids and names of every mailbox of the account's storage backend.
+fn list_mailboxes(
+    config: Config,
+    account_config: AccountConfig,
+    backend: Backend,
+) -> Result<Vec<String>> {
+    let (_, mut client) = EmailClient::new(config, account_config, backend)?;
+
+    let mut mailboxes: Vec<String> = client
+        .list_mailboxes(false)?
+        .into_iter()
+        .flat_map(|mailbox| [mailbox.id, mailbox.name])
+        .collect();
+    mailboxes.sort();
+    mailboxes.dedup();
+
+    Ok(mailboxes)
+}
+
+fn check_aliases(aliases: BTreeMap<String, String>, mailboxes: &[String]) -> Vec<AliasCheck> {
+    aliases
+        .into_iter()
+        .map(|(alias, target)| {
+            // NOTE: the IMAP Inbox name is case-insensitive (RFC 3501 §5.1)
+            let ok = mailboxes.iter().any(|mailbox| {
+                *mailbox == target
+                    || mailbox.eq_ignore_ascii_case("inbox") && target.eq_ignore_ascii_case("inbox")
+            });
+
+            AliasCheck { alias, target, ok }
+        })
+        .collect()
+}
+
+/// What to do with an alias pointing to a missing mailbox.
+#[derive(Clone, Debug)]
+enum AliasFix {
+    Keep,
+    Remove,
+    Remap(String),
+}
+
+impl fmt::Display for AliasFix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Keep => f.write_str("Keep it"),
+            Self::Remove => f.write_str("Remove it"),
+            Self::Remap(mailbox) => write!(f, "Point it to `{mailbox}`"),
+        }
+    }
+}
+
+/// Prompts for a fix for each dangling alias, then writes the
+/// updated configuration. Returns a description of each change.
+fn fix_aliases(
+    config_paths: &[PathBuf],
+    account_name: &str,
+    dangling: &[&AliasCheck],
+    mailboxes: &[String],
+) -> Result<Vec<String>> {
+    let Some(mut config) = Config::load(config_paths)? else {
+        bail!("No configuration found");
+    };
+
+    let mut fixes = Vec::new();
+
+    for check in dangling {
+        let mut choices = vec![AliasFix::Keep, AliasFix::Remove];
+        choices.extend(mailboxes.iter().cloned().map(AliasFix::Remap));
+
+        let question = format!(
+            "Alias `{}` points to missing mailbox `{}`:",
+            check.alias, check.target
+        );
+
+        let fix = prompt::item(&question, choices, None)?;
+
+        // The alias lives either in the account or in the global
+        // table, under any letter case.
+        let aliases = match config.accounts.get_mut(account_name) {
+            Some(account) if has_alias(&account.mailbox.aliases, &check.alias) => {
+                &mut account.mailbox.aliases
+            }
+            _ => &mut config.mailbox.aliases,
+        };
+
+        let Some(key) = aliases
+            .keys()
+            .find(|key| key.eq_ignore_ascii_case(&check.alias))
+            .cloned()
+        else {
+            continue;
+        };
+
+        match fix {
+            AliasFix::Keep => continue,
+            AliasFix::Remove => {
+                aliases.remove(&key);
+                fixes.push(format!("alias {}: removed", check.alias));
+            }
+            AliasFix::Remap(mailbox) => {
+                fixes.push(format!("alias {}: now points to {mailbox}", check.alias));
+                aliases.insert(key, mailbox);
+            }
+        }
+    }
+
+    if !fixes.is_empty() {
+        config.write(&Config::target_path(config_paths)?)?;
+    }
+
+    Ok(fixes)
+}
+
+fn has_alias(aliases: &HashMap<String, String>, alias: &str) -> bool {
+    aliases.keys().any(|key| key.eq_ignore_ascii_case(alias))
+}
+
+/// Aggregated account check result: one outcome per backend, then
+/// the mailbox alias checks.
 #[derive(Clone, Debug, Serialize)]
 pub struct CheckReport {
     pub account: String,
     pub backends: Vec<BackendCheck>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<AliasCheck>,
+    /// Why the aliases could not be checked (mailbox listing failed).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aliases_error: Option<String>,
+    /// Dotted paths of the secrets set in clear text (`raw`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<String>,
+    /// Accounts set as default, when there are several.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub defaults: Vec<String>,
+    /// Alias and secret changes saved by `--fix`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fixes: Vec<String>,
+}
+
+/// Whether a mailbox alias points to an existing mailbox.
+#[derive(Clone, Debug, Serialize)]
+pub struct AliasCheck {
+    pub alias: String,
+    pub target: String,
+    pub ok: bool,
 }
 
 /// Outcome of checking a single backend's connection.
@@ -307,23 +571,44 @@ pub struct BackendCheck {
     pub backend: &'static str,
     pub ok: bool,
     pub error: Option<String>,
+    /// Extra facts learnt while checking (e.g. the IMAP hierarchy
+    /// delimiter).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+    /// Network path layers (DNS, TCP, TLS, login) checked, up to the
+    /// first failing one.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<NetworkStep>,
 }
 
 impl BackendCheck {
     fn from(backend: &'static str, result: Result<()>) -> Self {
+        Self::with_details(backend, result.map(|()| Vec::new()))
+    }
+
+    fn with_details(backend: &'static str, result: Result<Vec<String>>) -> Self {
         match result {
-            Ok(()) => Self {
+            Ok(details) => Self {
                 backend,
                 ok: true,
                 error: None,
+                details,
+                steps: Vec::new(),
             },
             Err(err) => Self {
                 backend,
                 ok: false,
                 error: Some(format!("{err:#}")),
+                details: Vec::new(),
+                steps: Vec::new(),
             },
         }
     }
+
+    fn with_steps(mut self, steps: Vec<NetworkStep>) -> Self {
+        self.steps = steps;
+        self
+    }
 }
 
 impl fmt::Display for CheckReport {
@@ -334,7 +619,43 @@ impl fmt::Display for CheckReport {
                 None => writeln!(f, "  {}: OK", check.backend)?,
                 Some(err) => writeln!(f, "  {}: FAIL ({err})", check.backend)?,
             }
+            for step in &check.steps {
+                writeln!(f, "    {step}")?;
+            }
+            for detail in &check.details {
+                writeln!(f, "    {detail}")?;
+            }
+        }
+
+        if let Some(err) = &self.aliases_error {
+            writeln!(f, "  aliases: FAIL ({err})")?;
+        } else if !self.aliases.is_empty() {
+            writeln!(f, "  aliases:")?;
+            for check in &self.aliases {
+                let status = if check.ok { "OK" } else { "MISSING" };
+                writeln!(f, "    {} -> {}: {status}", check.alias, check.target)?;
+            }
+        }
+
+        if !self.secrets.is_empty() {
+            writeln!(f, "  plaintext secrets:")?;
+            for path in &self.secrets {
+                writeln!(f, "    {path}: WARN (move it to the keyring with --fix)")?;
+            }
+        }
+
+        if !self.defaults.is_empty() {
+            let defaults = self.defaults.join(", ");
+            writeln!(
+                f,
+                "  default: FAIL (accounts {defaults} are all set as default)"
+            )?;
         }
+
+        for fix in &self.fixes {
+            writeln!(f, "  fixed {fix}")?;
+        }
+
         Ok(())
     }
 }
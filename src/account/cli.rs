@@ -34,7 +34,7 @@ impl AccountCommand {
         backend: Backend,
     ) -> Result<()> {
         match self {
-            Self::List(cmd) => cmd.execute(printer, config_paths),
+            Self::List(cmd) => cmd.execute(printer, config_paths, backend),
             Self::Check(cmd) => cmd.execute(printer, config_paths, account_name, backend),
             Self::Configure(cmd) => cmd.execute(printer, config_paths),
         }
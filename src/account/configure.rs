@@ -5,7 +5,7 @@ use clap::Parser;
 use pimalaya_cli::printer::Printer;
 use pimalaya_config::toml::TomlConfig;
 
-use crate::{config::Config, wizard};
+use crate::{account::all::check_account_name, config::Config, wizard};
 
 /// Edit (or create) the given account through the wizard.
 ///
@@ -14,20 +14,32 @@ use crate::{config::Config, wizard};
 /// discovery is skipped: the wizard prompts you for each field with
 /// what you previously had. Creates a new account if `name` is not
 /// known.
+///
+/// Re-running it is safe: leave a password prompt empty to keep the
+/// current secret, settings the wizard does not ask for are kept,
+/// and the changes are shown for confirmation before anything is
+/// written.
 #[derive(Debug, Parser)]
 pub struct AccountConfigureCommand {
     /// Name of the account to edit. A new entry is created if no
     /// account with this name exists in the configuration.
     #[arg(value_name = "NAME")]
     pub name: String,
+
+    /// Do not keep the current secrets when a password prompt is left
+    /// empty.
+    #[arg(long)]
+    pub reset_secrets: bool,
 }
 
 impl AccountConfigureCommand {
     pub fn execute(self, _printer: &mut impl Printer, config_paths: &[PathBuf]) -> Result<()> {
+        check_account_name(&self.name)?;
+
         let target = Config::target_path(config_paths)?;
-        let config = Config::from_paths_or_default(config_paths)?.unwrap_or_default();
+        let config = Config::load(config_paths)?.unwrap_or_default();
 
-        wizard::edit::edit_account(&target, config, &self.name)?;
+        wizard::edit::edit_account(&target, config, &self.name, self.reset_secrets)?;
 
         Ok(())
     }
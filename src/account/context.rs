@@ -11,36 +11,99 @@
 //! methods, not baked in during merge — keeping `Option<T>` fields
 //! lets layers compose cleanly.
 
-use std::{collections::HashMap, env::temp_dir, path::PathBuf};
+use std::{
+    collections::HashMap,
+    env::{self, temp_dir},
+    path::{Path, PathBuf},
+};
 
+use anyhow::{Result, bail};
 use comfy_table::{Color as TableColor, ContentArrangement, presets};
 use crossterm::style::Color;
 use dirs::download_dir;
 
 use crate::config::{
-    AccountConfig, AttachmentListTableConfig, Config, EnvelopeListTableConfig,
-    MailboxListTableConfig, TableArrangementConfig,
+    AccountConfig, AttachmentListTableConfig, Config, EnvelopeColumn, EnvelopeDateFormat,
+    EnvelopeListTableConfig, IdentityConfig, MailboxListTableConfig, ReadReceipts,
+    TableArrangementConfig, TemplateConfig, TimezoneConfig, table_preset,
 };
 
 const DEFAULT_DATETIME_FMT: &str = "%F %R%:z";
 const DEFAULT_MAILBOX_ALIAS: &str = "inbox";
+const DEFAULT_TRASH_MAILBOX: &str = "Trash";
 const DEFAULT_ENVELOPES_LIST_PAGE_SIZE: u32 = 25;
+const DEFAULT_ENVELOPES_LIST_COUNT_FORMAT: &str = "{unseen}/{total}";
+pub(crate) const DEFAULT_MAILBOXES_LIST_CACHE_TTL: u64 = 300;
+
+const DEFAULT_ENVELOPES_LIST_COLUMNS: [EnvelopeColumn; 7] = [
+    EnvelopeColumn::Id,
+    EnvelopeColumn::Flags,
+    EnvelopeColumn::Att,
+    EnvelopeColumn::Subject,
+    EnvelopeColumn::From,
+    EnvelopeColumn::Date,
+    EnvelopeColumn::Size,
+];
 
 const DEFAULT_UNSEEN_CHAR: char = '*';
 const DEFAULT_REPLIED_CHAR: char = 'R';
 const DEFAULT_FLAGGED_CHAR: char = '!';
 const DEFAULT_ATTACHMENT_CHAR: char = '@';
 
+/// Sender of a composed message, see [`Account::identity`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Identity<'a> {
+    pub address: Option<&'a str>,
+    pub name: Option<&'a str>,
+    pub signature: Option<&'a str>,
+}
+
+impl<'a> Identity<'a> {
+    /// `From` address and display name: `from` when given, otherwise
+    /// this identity's.
+    pub fn from(&self, from: Option<&'a str>) -> (Option<&'a str>, Option<&'a str>) {
+        match from {
+            Some(from) => (Some(from), None),
+            None => (self.address, self.name),
+        }
+    }
+
+    /// Inline signature: `signature`, otherwise this identity's unless
+    /// a signature file is given.
+    pub fn signature(
+        &self,
+        signature: Option<&'a str>,
+        signature_file: Option<&Path>,
+    ) -> Option<&'a str> {
+        match (signature, signature_file) {
+            (None, None) => self.signature,
+            (signature, _) => signature,
+        }
+    }
+}
+
 /// Merged runtime account settings consumed by every command.
 #[derive(Debug, Default)]
 pub struct Account {
+    /// Address of the account, its display name and its other
+    /// sending identities (see [`Account::identity`]).
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+    pub identities: Vec<IdentityConfig>,
+
     pub downloads_dir: Option<PathBuf>,
     pub table_preset: Option<String>,
     pub table_arrangement: Option<TableArrangementConfig>,
+    pub tree_ascii: Option<bool>,
 
     pub datetime_fmt: Option<String>,
     pub datetime_local_tz: Option<bool>,
+    pub timezone: Option<TimezoneConfig>,
     pub envelopes_list_page_size: Option<u32>,
+    pub envelopes_list_count_format: Option<String>,
+    /// Lifetime of the `mailboxes list` cache, in seconds (see
+    /// [`Account::mailboxes_list_cache_ttl`]).
+    pub mailboxes_list_cache_ttl: Option<u64>,
 
     /// Per-column color + flag glyph overrides for `envelopes list`.
     pub envelopes_list_table: EnvelopeListTableConfig,
@@ -53,6 +116,50 @@ pub struct Account {
     /// `mailbox.alias` at the global and account levels; account
     /// entries overwrite same-named global entries.
     pub mailbox_alias: HashMap<String, String>,
+    /// Names of the virtual mailboxes declared under
+    /// `[virtual.<name>]` (global only).
+    pub virtual_mailboxes: Vec<String>,
+
+    /// Whether `message compose --header` may set the `From`,
+    /// `Sender`, `Date` and `Message-ID` headers.
+    pub compose_allow_header_override: Option<bool>,
+    /// Spell checker run before sending (see
+    /// [`crate::shared::message::spellcheck::check`]).
+    pub compose_spellcheck_command: Option<String>,
+
+    /// Maximum outgoing message size in bytes, `0` meaning
+    /// unlimited. Falls back to the SMTP provider's known limit (see
+    /// [`crate::shared::client::EmailClient::new`]).
+    pub send_max_size: Option<u64>,
+
+    /// Whether sent messages are blind-copied to their `From`
+    /// address (see [`crate::shared::message::handler::apply`]).
+    pub send_bcc_self: Option<bool>,
+    /// Address sent messages are blind-copied to.
+    pub send_always_bcc: Option<String>,
+    /// Mailbox sent messages are saved to by default (see
+    /// [`crate::shared::message::handler::save_to`]).
+    pub send_save_to: Option<String>,
+
+    /// Pager command for `message read`, an empty string disabling
+    /// it (see [`Account::read_pager`]).
+    pub read_pager: Option<String>,
+
+    /// Charset overrides for `message read`, keyed by lowercased
+    /// sender address or domain. Account entries overwrite
+    /// same-named global entries.
+    pub read_charsets: HashMap<String, String>,
+    pub read_send_receipts: Option<ReadReceipts>,
+
+    /// Spam filter training commands of `message junk` and
+    /// `message not-junk`.
+    pub junk_spam_command: Option<String>,
+    pub junk_ham_command: Option<String>,
+
+    /// Named compose templates. Populated from `templates` at the
+    /// global and account levels; account entries overwrite
+    /// same-named global entries.
+    pub templates: HashMap<String, TemplateConfig>,
 }
 
 impl Account {
@@ -63,16 +170,53 @@ impl Account {
         let mut mailbox_alias = self.mailbox_alias;
         mailbox_alias.extend(other.mailbox_alias);
 
+        let mut virtual_mailboxes = self.virtual_mailboxes;
+        virtual_mailboxes.extend(other.virtual_mailboxes);
+
+        let mut templates = self.templates;
+        templates.extend(other.templates);
+
+        let mut read_charsets = self.read_charsets;
+        read_charsets.extend(other.read_charsets);
+
+        let mut identities = self.identities;
+        identities.extend(other.identities);
+
         Self {
+            email: other.email.or(self.email),
+            display_name: other.display_name.or(self.display_name),
+            identities,
             downloads_dir: other.downloads_dir.or(self.downloads_dir),
+            compose_allow_header_override: other
+                .compose_allow_header_override
+                .or(self.compose_allow_header_override),
+            compose_spellcheck_command: other
+                .compose_spellcheck_command
+                .or(self.compose_spellcheck_command),
+            send_max_size: other.send_max_size.or(self.send_max_size),
+            send_bcc_self: other.send_bcc_self.or(self.send_bcc_self),
+            send_always_bcc: other.send_always_bcc.or(self.send_always_bcc),
+            send_save_to: other.send_save_to.or(self.send_save_to),
+            read_pager: other.read_pager.or(self.read_pager),
+            read_send_receipts: other.read_send_receipts.or(self.read_send_receipts),
+            junk_spam_command: other.junk_spam_command.or(self.junk_spam_command),
+            junk_ham_command: other.junk_ham_command.or(self.junk_ham_command),
             table_preset: other.table_preset.or(self.table_preset),
             table_arrangement: other.table_arrangement.or(self.table_arrangement),
+            tree_ascii: other.tree_ascii.or(self.tree_ascii),
 
             datetime_fmt: other.datetime_fmt.or(self.datetime_fmt),
             datetime_local_tz: other.datetime_local_tz.or(self.datetime_local_tz),
+            timezone: other.timezone.or(self.timezone),
             envelopes_list_page_size: other
                 .envelopes_list_page_size
                 .or(self.envelopes_list_page_size),
+            envelopes_list_count_format: other
+                .envelopes_list_count_format
+                .or(self.envelopes_list_count_format),
+            mailboxes_list_cache_ttl: other
+                .mailboxes_list_cache_ttl
+                .or(self.mailboxes_list_cache_ttl),
 
             envelopes_list_table: merge_envelope_table(
                 self.envelopes_list_table,
@@ -88,6 +232,9 @@ impl Account {
             ),
 
             mailbox_alias,
+            virtual_mailboxes,
+            read_charsets,
+            templates,
         }
     }
 
@@ -104,12 +251,16 @@ impl Account {
             .unwrap_or_else(temp_dir)
     }
 
-    /// Effective `comfy_table` preset string. Defaults to
+    /// Effective `comfy_table` preset string, named presets resolved
+    /// (see [`config::table_preset`]). Defaults to
     /// `UTF8_FULL_CONDENSED`.
+    ///
+    /// [`config::table_preset`]: crate::config::table_preset
     pub fn table_preset(&self) -> &str {
-        self.table_preset
-            .as_deref()
-            .unwrap_or(presets::UTF8_FULL_CONDENSED)
+        match self.table_preset.as_deref() {
+            Some(preset) => table_preset(preset),
+            None => presets::UTF8_FULL_CONDENSED,
+        }
     }
 
     /// Effective `comfy_table` content arrangement. Defaults to
@@ -121,16 +272,27 @@ impl Account {
             .into()
     }
 
+    /// Whether trees are drawn with ASCII characters. Defaults to
+    /// `false`.
+    pub fn tree_ascii(&self) -> bool {
+        self.tree_ascii.unwrap_or(false)
+    }
+
     /// Effective `chrono` `strftime` format for envelope DATE
     /// columns. Defaults to `%F %R%:z`.
     pub fn datetime_fmt(&self) -> &str {
         self.datetime_fmt.as_deref().unwrap_or(DEFAULT_DATETIME_FMT)
     }
 
-    /// Whether to convert envelope `Date:` headers to the system
-    /// local timezone. Defaults to `false`.
-    pub fn datetime_local_tz(&self) -> bool {
-        self.datetime_local_tz.unwrap_or(false)
+    /// Effective timezone dates are rendered in: `timezone`, else the
+    /// local one when `datetime-local-tz` is set, else the original
+    /// `Date:` header offset.
+    pub fn datetime_tz(&self) -> TimezoneConfig {
+        match self.timezone {
+            Some(tz) => tz,
+            None if self.datetime_local_tz.unwrap_or(false) => TimezoneConfig::Local,
+            None => TimezoneConfig::Original,
+        }
     }
 
     /// Effective default page size for `envelopes list` when the
@@ -140,6 +302,21 @@ impl Account {
             .unwrap_or(DEFAULT_ENVELOPES_LIST_PAGE_SIZE)
     }
 
+    /// Format of `envelopes list --output count-only`. Defaults to
+    /// `{unseen}/{total}`.
+    pub fn envelopes_list_count_format(&self) -> &str {
+        self.envelopes_list_count_format
+            .as_deref()
+            .unwrap_or(DEFAULT_ENVELOPES_LIST_COUNT_FORMAT)
+    }
+
+    /// Lifetime of the `mailboxes list` cache, in seconds, `0` when
+    /// disabled. Defaults to 300.
+    pub fn mailboxes_list_cache_ttl(&self) -> u64 {
+        self.mailboxes_list_cache_ttl
+            .unwrap_or(DEFAULT_MAILBOXES_LIST_CACHE_TTL)
+    }
+
     /// Resolves `name` through the alias map.
     ///
     /// Lookup is case-insensitive on the alias name. When `name`
@@ -154,6 +331,25 @@ impl Account {
             .unwrap_or(name)
     }
 
+    /// Whether the virtual mailbox `name` (without its `@` prefix) is
+    /// declared under `[virtual.<name>]`.
+    pub fn has_virtual_mailbox(&self, name: &str) -> bool {
+        self.virtual_mailboxes.iter().any(|known| known == name)
+    }
+
+    /// Whether `mailbox` is the sent mailbox: the one the `sent`
+    /// alias resolves to, or `sent` itself when unset, compared
+    /// case-insensitively.
+    pub fn is_sent_mailbox(&self, mailbox: &str) -> bool {
+        self.resolve_mailbox("sent").eq_ignore_ascii_case(mailbox)
+    }
+
+    /// Resolved id of the trash mailbox: the one bound to the `trash`
+    /// alias, `Trash` when unset.
+    pub fn trash_mailbox(&self) -> &str {
+        self.resolve_mailbox(DEFAULT_TRASH_MAILBOX)
+    }
+
     /// Resolved id of the implicit default mailbox.
     ///
     /// Returns the id mapped to the `inbox` alias (case-insensitive),
@@ -165,6 +361,136 @@ impl Account {
             .map(String::as_str)
     }
 
+    /// Addresses counting as the account's own: `email`, then the
+    /// `identities`.
+    pub fn own_addresses(&self) -> Vec<&str> {
+        self.email
+            .iter()
+            .map(|addr| addr.trim())
+            .chain(self.identities.iter().map(IdentityConfig::address))
+            .filter(|addr| !addr.is_empty())
+            .collect()
+    }
+
+    /// Sending identity matching `name`, by address
+    /// (case-insensitively) or display name. Without `name`, the
+    /// default identity: the account `email` and `display-name`,
+    /// empty when no `email` is configured.
+    pub fn identity(&self, name: Option<&str>) -> Result<Identity<'_>> {
+        let default = Identity {
+            address: self.email.as_deref().map(str::trim),
+            name: self.display_name.as_deref(),
+            signature: None,
+        };
+
+        let Some(name) = name else {
+            return Ok(default);
+        };
+
+        if default
+            .address
+            .is_some_and(|addr| addr.eq_ignore_ascii_case(name))
+        {
+            return Ok(default);
+        }
+
+        let identity = self.identities.iter().find(|identity| {
+            identity.address().eq_ignore_ascii_case(name) || identity.name() == Some(name)
+        });
+
+        match identity {
+            Some(identity) => Ok(Identity {
+                address: Some(identity.address()),
+                name: identity.name(),
+                signature: identity.signature(),
+            }),
+            None => {
+                let known = self.own_addresses().join(", ");
+                bail!("Cannot find identity `{name}`, known identities: {known}")
+            }
+        }
+    }
+
+    /// Whether `message compose --header` may set protected headers,
+    /// `false` by default.
+    pub fn compose_allow_header_override(&self) -> bool {
+        self.compose_allow_header_override.unwrap_or(false)
+    }
+
+    /// Effective maximum outgoing message size in bytes, `None` when
+    /// unlimited.
+    pub fn send_max_size(&self) -> Option<u64> {
+        self.send_max_size.filter(|size| *size > 0)
+    }
+
+    /// Pager command for `message read`: the configured one, then
+    /// `$PAGER`, then `less -R`. `None` when paging is disabled with
+    /// an empty command.
+    pub fn read_pager(&self) -> Option<String> {
+        let pager = match &self.read_pager {
+            Some(pager) => pager.clone(),
+            None => env::var("PAGER").unwrap_or_else(|_| String::from("less -R")),
+        };
+
+        Some(pager).filter(|pager| !pager.trim().is_empty())
+    }
+
+    /// Read receipt policy of `message read`. Defaults to never
+    /// sending any.
+    pub fn read_send_receipts(&self) -> ReadReceipts {
+        self.read_send_receipts.unwrap_or_default()
+    }
+
+    /// Charset to force when reading messages from `sender`: the
+    /// override for the address itself, then for its domain.
+    pub fn read_charset(&self, sender: &str) -> Option<&str> {
+        let sender = sender.to_lowercase();
+
+        if let Some(charset) = self.read_charsets.get(&sender) {
+            return Some(charset);
+        }
+
+        let (_, domain) = sender.rsplit_once('@')?;
+        self.read_charsets.get(domain).map(String::as_str)
+    }
+
+    /// Compose template named `name`.
+    ///
+    /// Bails with the list of available templates when no such
+    /// template is configured.
+    pub fn template(&self, name: &str) -> Result<&TemplateConfig> {
+        if let Some(template) = self.templates.get(name) {
+            return Ok(template);
+        }
+
+        let mut names: Vec<&str> = self.templates.keys().map(String::as_str).collect();
+        names.sort_unstable();
+
+        if names.is_empty() {
+            bail!(
+                "Cannot find template `{name}`: no template configured under `[templates.<name>]`"
+            );
+        }
+
+        bail!(
+            "Cannot find template `{name}`, available templates: {}",
+            names.join(", ")
+        )
+    }
+
+    // ── envelopes list — columns ─────────────────────────────────────────
+
+    pub fn envelopes_list_table_columns(&self) -> Vec<EnvelopeColumn> {
+        match &self.envelopes_list_table.columns {
+            Some(columns) => columns.clone(),
+            None => DEFAULT_ENVELOPES_LIST_COLUMNS.to_vec(),
+        }
+    }
+
+    pub fn envelopes_list_table_date_format(&self) -> EnvelopeDateFormat {
+        self.envelopes_list_table.date_format.unwrap_or_default()
+    }
+
     // ── envelopes list — flag glyphs ─────────────────────────────────────
 
     pub fn envelopes_list_table_unseen_char(&self) -> char {
@@ -220,6 +546,23 @@ impl Account {
         // New in v2, no v1 precedent.
         map_color_or(self.envelopes_list_table.size_color, Color::Reset)
     }
+    pub fn envelopes_list_table_highlight_color(&self) -> Color {
+        // New in v2, a background color: no table color mapping.
+        self.envelopes_list_table
+            .highlight_color
+            .unwrap_or(Color::DarkGrey)
+    }
+    pub fn envelopes_list_table_highlight_senders_color(&self) -> Option<TableColor> {
+        // New in v2, no color unless configured: rows are made bold.
+        let color = self.envelopes_list_table.highlight_senders_color?;
+        Some(map_color_or(Some(color), color))
+    }
+
+    /// Lowercased `highlight-senders` addresses.
+    pub fn envelopes_list_table_highlight_senders(&self) -> Vec<String> {
+        let senders = self.envelopes_list_table.highlight_senders.iter().flatten();
+        senders.map(|addr| addr.trim().to_lowercase()).collect()
+    }
 
     // ── mailboxes list — column colors ───────────────────────────────────
     //
@@ -294,6 +637,8 @@ fn merge_envelope_table(
     over: EnvelopeListTableConfig,
 ) -> EnvelopeListTableConfig {
     EnvelopeListTableConfig {
+        columns: over.columns.or(base.columns),
+        date_format: over.date_format.or(base.date_format),
         unseen_char: over.unseen_char.or(base.unseen_char),
         replied_char: over.replied_char.or(base.replied_char),
         flagged_char: over.flagged_char.or(base.flagged_char),
@@ -306,6 +651,11 @@ fn merge_envelope_table(
         to_color: over.to_color.or(base.to_color),
         date_color: over.date_color.or(base.date_color),
         size_color: over.size_color.or(base.size_color),
+        highlight_color: over.highlight_color.or(base.highlight_color),
+        highlight_senders: over.highlight_senders.or(base.highlight_senders),
+        highlight_senders_color: over
+            .highlight_senders_color
+            .or(base.highlight_senders_color),
     }
 }
 
@@ -349,19 +699,39 @@ fn lowercase_alias_keys(aliases: HashMap<String, String>) -> HashMap<String, Str
 impl From<Config> for Account {
     fn from(config: Config) -> Self {
         Self {
+            email: None,
+            display_name: None,
+            identities: Vec::new(),
             downloads_dir: config.downloads_dir,
             table_preset: config.table.preset,
             table_arrangement: config.table.arrangement,
+            tree_ascii: config.tree.ascii,
 
             datetime_fmt: config.envelope.list.datetime_fmt,
             datetime_local_tz: config.envelope.list.datetime_local_tz,
+            timezone: config.envelope.list.timezone,
             envelopes_list_page_size: config.envelope.list.page_size,
+            envelopes_list_count_format: config.envelope.list.count_format,
+            mailboxes_list_cache_ttl: config.mailbox.list.cache_ttl,
 
             envelopes_list_table: config.envelope.list.table,
             mailboxes_list_table: config.mailbox.list.table,
             attachments_list_table: config.attachment.list.table,
 
             mailbox_alias: lowercase_alias_keys(config.mailbox.aliases),
+            virtual_mailboxes: config.virtual_mailboxes.into_keys().collect(),
+            compose_allow_header_override: config.compose.allow_header_override,
+            compose_spellcheck_command: config.compose.spellcheck_command,
+            send_max_size: config.send.max_size,
+            send_bcc_self: config.send.bcc_self,
+            send_always_bcc: config.send.always_bcc,
+            send_save_to: config.send.save_to,
+            read_pager: config.read.pager,
+            read_send_receipts: config.read.send_read_receipts,
+            junk_spam_command: config.junk.spam_command,
+            junk_ham_command: config.junk.ham_command,
+            read_charsets: lowercase_alias_keys(config.read.charsets),
+            templates: config.templates,
         }
     }
 }
@@ -369,19 +739,39 @@ impl From<Config> for Account {
 impl From<AccountConfig> for Account {
     fn from(config: AccountConfig) -> Self {
         Self {
+            email: config.email,
+            display_name: config.display_name,
+            identities: config.identities,
             downloads_dir: config.downloads_dir,
             table_preset: config.table.preset,
             table_arrangement: config.table.arrangement,
+            tree_ascii: config.tree.ascii,
 
             datetime_fmt: config.envelope.list.datetime_fmt,
             datetime_local_tz: config.envelope.list.datetime_local_tz,
+            timezone: config.envelope.list.timezone,
             envelopes_list_page_size: config.envelope.list.page_size,
+            envelopes_list_count_format: config.envelope.list.count_format,
+            mailboxes_list_cache_ttl: config.mailbox.list.cache_ttl,
 
             envelopes_list_table: config.envelope.list.table,
             mailboxes_list_table: config.mailbox.list.table,
             attachments_list_table: config.attachment.list.table,
 
             mailbox_alias: lowercase_alias_keys(config.mailbox.aliases),
+            virtual_mailboxes: Vec::new(),
+            compose_allow_header_override: config.compose.allow_header_override,
+            compose_spellcheck_command: config.compose.spellcheck_command,
+            send_max_size: config.send.max_size,
+            send_bcc_self: config.send.bcc_self,
+            send_always_bcc: config.send.always_bcc,
+            send_save_to: config.send.save_to,
+            read_pager: config.read.pager,
+            read_send_receipts: config.read.send_read_receipts,
+            junk_spam_command: config.junk.spam_command,
+            junk_ham_command: config.junk.ham_command,
+            read_charsets: lowercase_alias_keys(config.read.charsets),
+            templates: config.templates,
         }
     }
 }
@@ -450,6 +840,16 @@ mod tests {
         assert_eq!(account.default_mailbox(), None);
     }
 
+    #[test]
+    fn sent_mailbox_follows_sent_alias() {
+        let account = account_with_aliases(&[("sent", "Sent Items")]);
+        assert!(account.is_sent_mailbox("sent items"));
+        assert!(!account.is_sent_mailbox("Sent"));
+
+        let account = account_with_aliases(&[]);
+        assert!(account.is_sent_mailbox("Sent"));
+    }
+
     #[test]
     fn merge_lets_account_override_global_alias() {
         let global = account_with_aliases(&[("inbox", "INBOX"), ("sent", "Sent")]);
@@ -458,4 +858,72 @@ mod tests {
         assert_eq!(merged.resolve_mailbox("inbox"), "Mailbox/0");
         assert_eq!(merged.resolve_mailbox("sent"), "Sent");
     }
+
+    #[test]
+    fn table_preset_resolves_named_presets() {
+        let mut account = Account::default();
+        assert_eq!(account.table_preset(), presets::UTF8_FULL_CONDENSED);
+
+        account.table_preset = Some("Markdown".into());
+        assert_eq!(account.table_preset(), presets::ASCII_MARKDOWN);
+
+        account.table_preset = Some(presets::UTF8_FULL.into());
+        assert_eq!(account.table_preset(), presets::UTF8_FULL);
+    }
+
+    #[test]
+    fn template_error_lists_available_templates() {
+        let mut account = Account::default();
+        let err = account.template("weekly").unwrap_err().to_string();
+        assert!(err.contains("no template configured"), "{err}");
+
+        account.templates = ["standup", "invoice"]
+            .into_iter()
+            .map(|name| (name.to_owned(), TemplateConfig::default()))
+            .collect();
+
+        assert!(account.template("standup").is_ok());
+
+        let err = account.template("weekly").unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Cannot find template `weekly`, available templates: invoice, standup"
+        );
+    }
+
+    #[test]
+    fn identity_matches_address_or_name() {
+        let config: AccountConfig = toml::from_str(
+            r#"
+            email = "me@example.org"
+            display-name = "Me"
+            identities = [
+                "old@example.org",
+                { address = "team@example.org", name = "Team", signature = "The team" },
+            ]
+            "#,
+        )
+        .unwrap();
+        let account = Account::from(config);
+
+        let default = account.identity(None).unwrap();
+        assert_eq!(default.address, Some("me@example.org"));
+        assert_eq!(default.name, Some("Me"));
+
+        let team = account.identity(Some("Team")).unwrap();
+        assert_eq!(team.address, Some("team@example.org"));
+        assert_eq!(team.signature, Some("The team"));
+        assert_eq!(team.signature(None, None), Some("The team"));
+        assert_eq!(team.signature(Some("Bye"), None), Some("Bye"));
+
+        let old = account.identity(Some("OLD@example.org")).unwrap();
+        assert_eq!(old.address, Some("old@example.org"));
+        assert_eq!(old.name, None);
+
+        assert!(account.identity(Some("nobody@example.org")).is_err());
+        assert_eq!(
+            account.own_addresses(),
+            ["me@example.org", "old@example.org", "team@example.org"]
+        );
+    }
 }
@@ -0,0 +1,347 @@
+//! Migration of plaintext secrets into the system keyring.
+//!
+//! There is no keyring variant for secrets in the configuration: a
+//! secret is either `raw` or read from a `command`. A migrated secret
+//! is therefore stored with the platform keyring tool (`secret-tool`
+//! on Linux and BSD, `security` on macOS), and its `raw` value is
+//! replaced by the `command` reading it back.
+
+use std::{fs, path::PathBuf};
+#[cfg(unix)]
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result, bail};
+use pimalaya_cli::prompt;
+use pimalaya_config::toml::TomlConfig;
+use toml::{Table, Value};
+use toml_edit::{DocumentMut, Item, TableLike};
+
+use crate::config::{AccountConfig, Config};
+
+/// Service name the secrets are stored under.
+const SERVICE: &str = "himalaya";
+
+/// Dotted paths of the non-empty `raw` secrets of `account`, e.g.
+/// `imap.sasl.plain.passwd`.
+pub fn plaintext_secrets(account: &AccountConfig) -> Result<Vec<String>> {
+    let value = Value::try_from(account).context("Serialize account config error")?;
+    let mut paths = Vec::new();
+    find_raw("", &value, &mut paths);
+    Ok(paths)
+}
+
+fn find_raw(prefix: &str, value: &Value, paths: &mut Vec<String>) {
+    let Value::Table(table) = value else {
+        return;
+    };
+
+    if let Some(raw) = raw_secret(table) {
+        if !raw.is_empty() && !prefix.is_empty() {
+            paths.push(prefix.to_owned());
+        }
+        return;
+    }
+
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        find_raw(&key, value, paths);
+    }
+}
+
+/// The value of a `{ raw = "…" }` secret table.
+fn raw_secret(table: &Table) -> Option<&str> {
+    match table.get("raw") {
+        Some(Value::String(raw)) if table.len() == 1 => Some(raw),
+        _ => None,
+    }
+}
+
+/// Asks, for each of the plaintext `secrets` of the account, whether
+/// to move it to the system keyring, then edits the configuration
+/// files defining them in place, comments and layout kept. Returns a
+/// description of each change.
+pub fn migrate(
+    config_paths: &[PathBuf],
+    account_name: &str,
+    secrets: &[String],
+) -> Result<Vec<String>> {
+    let paths = match config_paths {
+        [] => vec![Config::target_path(config_paths)?],
+        paths => paths.to_vec(),
+    };
+
+    let mut documents = Vec::new();
+
+    for path in paths.into_iter().filter(|path| path.is_file()) {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Read TOML config `{}` error", path.display()))?;
+        let document: DocumentMut = content
+            .parse()
+            .with_context(|| format!("Parse TOML config `{}` error", path.display()))?;
+        documents.push((path, document, false));
+    }
+
+    let mut fixes = Vec::new();
+
+    for path in secrets {
+        let question = format!("Move the plaintext secret `{path}` to the system keyring?");
+
+        if !prompt::bool(&question, true)? {
+            continue;
+        }
+
+        // NOTE: the last file defining the secret is the one the
+        // merged configuration takes it from
+        let Some((table, changed)) =
+            documents
+                .iter_mut()
+                .rev()
+                .find_map(|(_, document, changed)| {
+                    secret_table(document, account_name, path).map(|table| (table, changed))
+                })
+        else {
+            fixes.push(format!(
+                "secret {path}: not found in the configuration files, left as is"
+            ));
+            continue;
+        };
+
+        let Some(secret) = table.get("raw").and_then(Item::as_str) else {
+            continue;
+        };
+
+        let entry = format!("{account_name}.{path}");
+        store(&entry, secret).with_context(|| format!("Store secret `{path}` error"))?;
+
+        replace_raw(table, lookup_command(&entry));
+        *changed = true;
+
+        fixes.push(format!("secret {path}: moved to the system keyring"));
+    }
+
+    for (path, document, _) in documents.iter().filter(|(_, _, changed)| *changed) {
+        fs::write(path, document.to_string())
+            .with_context(|| format!("Write TOML config `{}` error", path.display()))?;
+    }
+
+    Ok(fixes)
+}
+
+/// The `{ raw = "…" }` table of the secret at the dotted `path` of
+/// `account` in `document`, whatever its TOML layout.
+fn secret_table<'a>(
+    document: &'a mut DocumentMut,
+    account: &str,
+    path: &str,
+) -> Option<&'a mut dyn TableLike> {
+    let table = ["accounts", account]
+        .into_iter()
+        .chain(path.split('.'))
+        .try_fold(document.as_item_mut(), |item, key| {
+            item.as_table_like_mut()?.get_mut(key)
+        })?
+        .as_table_like_mut()?;
+
+    if table.get("raw").is_some_and(Item::is_str) {
+        Some(table)
+    } else {
+        None
+    }
+}
+
+/// Replaces the `raw` entry of the secret `table` with the `command`
+/// reading it back, keeping the comments around it.
+fn replace_raw(table: &mut dyn TableLike, command: String) {
+    let Some(key) = table.key("raw").cloned() else {
+        return;
+    };
+
+    let Some(Item::Value(raw)) = table.remove("raw") else {
+        return;
+    };
+
+    let mut value = toml_edit::Value::from(command);
+    *value.decor_mut() = raw.decor().clone();
+    table.insert("command", Item::Value(value));
+
+    if let Some(mut key_mut) = table.key_mut("command") {
+        *key_mut.leaf_decor_mut() = key.leaf_decor().clone();
+        *key_mut.dotted_decor_mut() = key.dotted_decor().clone();
+    }
+}
+
+/// Runs `command`, writing `input` to its standard input, so secrets
+/// never show in the process list.
+#[cfg(unix)]
+fn run_with_stdin(command: &mut Command, input: &[u8]) -> Result<()> {
+    let name = command.get_program().to_string_lossy().into_owned();
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Run `{name}` error"))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input)
+            .context("Write secret to stdin error")?;
+    }
+
+    let status = child
+        .wait()
+        .with_context(|| format!("Wait for `{name}` error"))?;
+
+    if !status.success() {
+        bail!("Command `{name}` failed with {status}");
+    }
+
+    Ok(())
+}
+
+/// Stores `secret` in the keyring under the `entry` account of the
+/// himalaya service, replacing any previous value.
+#[cfg(target_os = "macos")]
+fn store(entry: &str, secret: &str) -> Result<()> {
+    // NOTE: `security` only takes the secret as an argument, which
+    // other users can read from the process list: the command is
+    // fed to its interactive mode through stdin instead
+    let command = format!(
+        "add-generic-password -U -s {SERVICE} -a {} -w {}\n",
+        dquote(entry),
+        dquote(secret)
+    );
+
+    run_with_stdin(Command::new("security").arg("-i"), command.as_bytes())?;
+
+    // NOTE: the interactive mode exits successfully whatever the
+    // commands it ran, so the entry is looked up to be sure
+    let status = Command::new("security")
+        .args(["find-generic-password", "-s", SERVICE, "-a", entry])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Run `security` error")?;
+
+    if !status.success() {
+        bail!("Cannot find the secret in the keychain after storing it");
+    }
+
+    Ok(())
+}
+
+/// Quotes `arg` for the interactive mode of `security`.
+#[cfg(target_os = "macos")]
+fn dquote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(target_os = "macos")]
+fn lookup_command(entry: &str) -> String {
+    format!(
+        "security find-generic-password -s {SERVICE} -a {} -w",
+        quote(entry)
+    )
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn store(entry: &str, secret: &str) -> Result<()> {
+    let label = format!("{SERVICE} {entry}");
+
+    let mut command = Command::new("secret-tool");
+    command.args([
+        "store", "--label", &label, "service", SERVICE, "account", entry,
+    ]);
+
+    run_with_stdin(&mut command, secret.as_bytes())
+        .context("Store secret with `secret-tool` error, is libsecret installed?")
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn lookup_command(entry: &str) -> String {
+    format!(
+        "secret-tool lookup service {SERVICE} account {}",
+        quote(entry)
+    )
+}
+
+#[cfg(not(unix))]
+fn store(_entry: &str, _secret: &str) -> Result<()> {
+    bail!("Moving secrets to the system keyring is not supported on this platform")
+}
+
+#[cfg(not(unix))]
+fn lookup_command(_entry: &str) -> String {
+    String::new()
+}
+
+/// Quotes `arg` for the shell secret commands run through.
+#[cfg(unix)]
+fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_raw_secrets_only() {
+        let value: Value = toml::from_str(
+            r#"
+            [imap.sasl.plain]
+            authcid = "me"
+            passwd.raw = "hunter2"
+
+            [smtp.sasl.login]
+            username = "me"
+            password.command = "pass show mail"
+
+            [jmap.auth.bearer]
+            secret.raw = ""
+            "#,
+        )
+        .unwrap();
+
+        let mut paths = Vec::new();
+        find_raw("", &value, &mut paths);
+
+        assert_eq!(paths, ["imap.sasl.plain.passwd"]);
+    }
+
+    #[test]
+    fn replaces_secrets_in_place() {
+        let mut document: DocumentMut = r#"# accounts
+[accounts.me]
+# imap
+imap.sasl.plain.passwd.raw = "hunter2" # old
+smtp.sasl.login = { username = "me", password = { raw = "x" } }
+"#
+        .parse()
+        .unwrap();
+
+        for path in ["imap.sasl.plain.passwd", "smtp.sasl.login.password"] {
+            let table = secret_table(&mut document, "me", path).unwrap();
+            replace_raw(table, String::from("lookup"));
+        }
+
+        assert!(secret_table(&mut document, "me", "imap.sasl.plain.passwd").is_none());
+        assert!(secret_table(&mut document, "me", "jmap.auth").is_none());
+        assert_eq!(
+            document.to_string(),
+            r#"# accounts
+[accounts.me]
+# imap
+imap.sasl.plain.passwd.command = "lookup" # old
+smtp.sasl.login = { username = "me", password = { command = "lookup" } }
+"#
+        );
+    }
+}
@@ -1,34 +1,67 @@
-use std::{fmt, path::PathBuf};
+use std::{
+    fmt,
+    path::PathBuf,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
 use clap::Parser;
 use comfy_table::{Cell, Color, ContentArrangement, Row, Table};
 use crossterm::style::Color as CrosstermColor;
 use pimalaya_cli::printer::Printer;
-use pimalaya_config::toml::TomlConfig;
 use serde::Serialize;
 
 use crate::{
-    account::context::map_color_or,
-    config::{AccountConfig, Config, TableArrangementConfig},
+    account::{
+        check::{BackendCheck, check_backends},
+        context::map_color_or,
+    },
+    backend::Backend,
+    config::{AccountConfig, Config, TableArrangementConfig, table_preset},
 };
 
 /// List all accounts declared in the configuration.
 ///
 /// Each row shows the account name, the backends with a config block,
 /// and whether it is the default account.
+///
+/// With `--show-status`, each account is pinged as well: its backends
+/// (restricted by the global `--backend` flag) are connected to and
+/// logged in, all accounts at once, and a STATUS column tells whether
+/// the account is `ok`, `auth-failed` or `unreachable`. An account
+/// not answering within `--timeout` is reported unreachable.
 #[derive(Debug, Parser)]
-pub struct AccountListCommand;
+pub struct AccountListCommand {
+    /// Ping each account and report its status and latency.
+    #[arg(long)]
+    pub show_status: bool,
+
+    /// Seconds to wait for an account to answer before reporting it
+    /// unreachable.
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 10,
+        requires = "show_status"
+    )]
+    pub timeout: u64,
+}
 
 impl AccountListCommand {
-    pub fn execute(self, printer: &mut impl Printer, config_paths: &[PathBuf]) -> Result<()> {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        config_paths: &[PathBuf],
+        backend: Backend,
+    ) -> Result<()> {
         let config = load_config(config_paths)?;
 
-        let preset = config
-            .table
-            .preset
-            .clone()
-            .unwrap_or_else(|| comfy_table::presets::UTF8_FULL_CONDENSED.to_string());
+        let preset = match config.table.preset.as_deref() {
+            Some(preset) => table_preset(preset).to_string(),
+            None => comfy_table::presets::UTF8_FULL_CONDENSED.to_string(),
+        };
         let arrangement = config
             .table
             .arrangement
@@ -51,7 +84,13 @@ impl AccountListCommand {
             .collect();
         accounts.sort_by(|a, b| a.name.cmp(&b.name));
 
+        if self.show_status {
+            let timeout = Duration::from_secs(self.timeout);
+            ping_accounts(&config, &mut accounts, backend, timeout);
+        }
+
         let table = AccountsTable {
+            show_status: self.show_status,
             preset,
             arrangement,
             colors,
@@ -69,8 +108,55 @@ struct AccountColors {
     default: Color,
 }
 
+/// Pings every account concurrently, one thread each, then fills in
+/// the status of each row. Threads still running once `timeout` is
+/// over are left behind and their account reported unreachable.
+fn ping_accounts(
+    config: &Config,
+    accounts: &mut [AccountRow],
+    backend: Backend,
+    timeout: Duration,
+) {
+    let (tx, rx) = mpsc::channel();
+
+    for (i, account) in accounts.iter().enumerate() {
+        let Some(account_config) = config.accounts.get(&account.name).cloned() else {
+            continue;
+        };
+
+        let config = config.clone();
+        let tx = tx.clone();
+
+        thread::spawn(move || {
+            let start = Instant::now();
+            let checks = check_backends(&config, &account_config, backend);
+            let latency = start.elapsed().as_millis() as u64;
+            let _ = tx.send((i, AccountPing::from_checks(&checks, latency)));
+        });
+    }
+
+    drop(tx);
+
+    let deadline = Instant::now() + timeout;
+
+    while let Some(wait) = deadline.checked_duration_since(Instant::now()) {
+        match rx.recv_timeout(wait) {
+            Ok((i, ping)) => accounts[i].ping = Some(ping),
+            Err(_) => break,
+        }
+    }
+
+    for account in accounts.iter_mut().filter(|a| a.ping.is_none()) {
+        account.ping = Some(AccountPing {
+            status: AccountStatus::Unreachable,
+            latency_ms: None,
+            error: Some(format!("no answer within {}s", timeout.as_secs())),
+        });
+    }
+}
+
 fn load_config(paths: &[PathBuf]) -> Result<Config> {
-    match Config::from_paths_or_default(paths)? {
+    match Config::load(paths)? {
         Some(config) => Ok(config),
         None => anyhow::bail!(
             "No configuration found. Run `himalaya` once to launch the wizard, \
@@ -85,6 +171,91 @@ pub struct AccountRow {
     pub name: String,
     pub default: bool,
     pub backends: Vec<&'static str>,
+    /// Outcome of `--show-status`.
+    #[serde(flatten)]
+    pub ping: Option<AccountPing>,
+}
+
+/// Outcome of pinging an account.
+#[derive(Clone, Debug, Serialize)]
+pub struct AccountPing {
+    pub status: AccountStatus,
+    /// Time taken to check every backend, unset when the account did
+    /// not answer in time.
+    pub latency_ms: Option<u64>,
+    /// Error of the first failing backend.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AccountPing {
+    /// Status of an account from the checks of its backends: the
+    /// first failing one tells whether it could not be reached or
+    /// refused the credentials.
+    fn from_checks(checks: &[BackendCheck], latency: u64) -> Self {
+        let Some(failed) = checks.iter().find(|check| !check.ok) else {
+            return Self {
+                status: AccountStatus::Ok,
+                latency_ms: Some(latency),
+                error: None,
+            };
+        };
+
+        let error = failed.error.clone().unwrap_or_default();
+        let login_failed = failed
+            .steps
+            .iter()
+            .any(|step| !step.ok && step.step == "login");
+
+        let status = if login_failed || is_auth_error(&error) {
+            AccountStatus::AuthFailed
+        } else {
+            AccountStatus::Unreachable
+        };
+
+        Self {
+            status,
+            latency_ms: Some(latency),
+            error: Some(format!("{}: {error}", failed.backend)),
+        }
+    }
+}
+
+/// Whether a backend error, without network steps to tell (HTTP
+/// backends), looks like rejected credentials.
+fn is_auth_error(error: &str) -> bool {
+    let error = error.to_lowercase();
+
+    [
+        "401",
+        "403",
+        "auth",
+        "credential",
+        "login",
+        "password",
+        "token",
+        "unauthorized",
+    ]
+    .iter()
+    .any(|needle| error.contains(needle))
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AccountStatus {
+    Ok,
+    AuthFailed,
+    Unreachable,
+}
+
+impl fmt::Display for AccountStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok => write!(f, "ok"),
+            Self::AuthFailed => write!(f, "auth-failed"),
+            Self::Unreachable => write!(f, "unreachable"),
+        }
+    }
 }
 
 impl AccountRow {
@@ -116,6 +287,7 @@ impl AccountRow {
             name: name.to_owned(),
             default: account.default,
             backends,
+            ping: None,
         }
     }
 }
@@ -123,6 +295,8 @@ impl AccountRow {
 /// Renderable table for the account list command.
 #[derive(Clone, Debug, Serialize)]
 pub struct AccountsTable {
+    #[serde(skip)]
+    pub show_status: bool,
     #[serde(skip)]
     pub preset: String,
     #[serde(skip)]
@@ -136,14 +310,21 @@ impl fmt::Display for AccountsTable {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut table = Table::new();
 
+        let mut header = vec![
+            Cell::new("NAME"),
+            Cell::new("BACKENDS"),
+            Cell::new("DEFAULT"),
+        ];
+
+        if self.show_status {
+            header.push(Cell::new("STATUS"));
+            header.push(Cell::new("LATENCY"));
+        }
+
         table
             .load_preset(&self.preset)
             .set_content_arrangement(self.arrangement.clone())
-            .set_header(Row::from(vec![
-                Cell::new("NAME"),
-                Cell::new("BACKENDS"),
-                Cell::new("DEFAULT"),
-            ]))
+            .set_header(Row::from(header))
             .add_rows(self.accounts.iter().map(|account| {
                 let mut row = Row::new();
                 row.max_height(1);
@@ -152,6 +333,17 @@ impl fmt::Display for AccountsTable {
                 row.add_cell(
                     Cell::new(if account.default { "yes" } else { "" }).fg(self.colors.default),
                 );
+                if let Some(ping) = &account.ping {
+                    let color = match ping.status {
+                        AccountStatus::Ok => Color::Green,
+                        AccountStatus::AuthFailed | AccountStatus::Unreachable => Color::Red,
+                    };
+                    row.add_cell(Cell::new(ping.status).fg(color));
+                    row.add_cell(Cell::new(match ping.latency_ms {
+                        Some(ms) => format!("{ms}ms"),
+                        None => String::new(),
+                    }));
+                }
                 row
             }));
 
@@ -159,3 +351,73 @@ impl fmt::Display for AccountsTable {
         writeln!(f, "{table}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::account::network::NetworkStep;
+
+    use super::*;
+
+    fn check(backend: &'static str, error: Option<&str>, steps: Vec<NetworkStep>) -> BackendCheck {
+        BackendCheck {
+            backend,
+            ok: error.is_none(),
+            error: error.map(str::to_owned),
+            details: Vec::new(),
+            steps,
+        }
+    }
+
+    fn step(step: &'static str, ok: bool) -> NetworkStep {
+        NetworkStep {
+            step,
+            ok,
+            detail: None,
+            error: (!ok).then(|| String::from("failed")),
+        }
+    }
+
+    #[test]
+    fn pings_from_the_first_failing_backend() {
+        let ok = AccountPing::from_checks(&[check("imap", None, Vec::new())], 42);
+        assert_eq!(ok.status, AccountStatus::Ok);
+        assert_eq!(ok.latency_ms, Some(42));
+        assert_eq!(ok.error, None);
+
+        let steps = vec![step("dns", true), step("tcp", true), step("login", false)];
+        let login = AccountPing::from_checks(
+            &[
+                check("imap", Some("connection closed"), steps),
+                check("smtp", Some("connection refused"), Vec::new()),
+            ],
+            7,
+        );
+        assert_eq!(login.status, AccountStatus::AuthFailed);
+        assert_eq!(login.error.as_deref(), Some("imap: connection closed"));
+
+        let steps = vec![step("dns", true), step("tcp", false)];
+        let down = AccountPing::from_checks(
+            &[
+                check("imap", None, Vec::new()),
+                check("smtp", Some("connection refused"), steps),
+            ],
+            7,
+        );
+        assert_eq!(down.status, AccountStatus::Unreachable);
+        assert_eq!(down.error.as_deref(), Some("smtp: connection refused"));
+
+        let http = AccountPing::from_checks(&[check("jmap", Some("HTTP 401"), Vec::new())], 7);
+        assert_eq!(http.status, AccountStatus::AuthFailed);
+    }
+
+    #[test]
+    fn tells_auth_errors_apart() {
+        assert!(is_auth_error("HTTP 401 Unauthorized"));
+        assert!(is_auth_error("Invalid credentials"));
+        assert!(is_auth_error("AUTHENTICATE failed"));
+        assert!(is_auth_error("access token expired"));
+        assert!(!is_auth_error("connection timed out"));
+        assert!(!is_auth_error("dns error: failed to lookup address"));
+        assert!(!is_auth_error(""));
+    }
+}
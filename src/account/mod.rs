@@ -1,5 +1,8 @@
+pub mod all;
 pub mod check;
 pub mod cli;
 pub mod configure;
 pub mod context;
+pub mod keyring;
 pub mod list;
+pub mod network;
@@ -0,0 +1,197 @@
+//! Layer-by-layer connection diagnostics for `account check`.
+//!
+//! A failing connect only tells that *something* went wrong. The
+//! probe here walks the network path one layer at a time (DNS
+//! resolution, TCP connect, TLS handshake, then login) and stops at
+//! the first failing one, so the report points at the layer to fix.
+
+use std::{
+    fmt,
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow, bail};
+use serde::Serialize;
+use url::Url;
+
+const TCP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of one layer of the network path.
+#[derive(Clone, Debug, Serialize)]
+pub struct NetworkStep {
+    pub step: &'static str,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl NetworkStep {
+    fn new(step: &'static str, result: Result<String>) -> Self {
+        match result {
+            Ok(detail) => Self {
+                step,
+                ok: true,
+                detail: Some(detail).filter(|detail| !detail.is_empty()),
+                error: None,
+            },
+            Err(err) => Self {
+                step,
+                ok: false,
+                detail: None,
+                error: Some(format!("{err:#}")),
+            },
+        }
+    }
+}
+
+impl fmt::Display for NetworkStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.error, &self.detail) {
+            (Some(err), _) => write!(f, "{}: FAIL ({err})", self.step),
+            (None, Some(detail)) => write!(f, "{}: OK ({detail})", self.step),
+            (None, None) => write!(f, "{}: OK", self.step),
+        }
+    }
+}
+
+/// Network path of one backend, filled step by step.
+#[derive(Debug, Default)]
+pub struct NetworkProbe {
+    pub steps: Vec<NetworkStep>,
+}
+
+impl NetworkProbe {
+    /// Checks that `server` resolves and accepts TCP connections,
+    /// then runs `handshake`, which must open the protocol session
+    /// without authenticating (greeting, STARTTLS when `starttls`).
+    ///
+    /// Bails with the error of the first failing step.
+    pub fn connect(
+        &mut self,
+        server: &Url,
+        starttls: bool,
+        handshake: impl FnOnce() -> Result<()>,
+    ) -> Result<()> {
+        let addrs = self.step("dns", || {
+            let addrs = resolve(server)?;
+            let list: Vec<String> = addrs.iter().map(|addr| addr.ip().to_string()).collect();
+            Ok((list.join(", "), addrs))
+        })?;
+
+        self.step("tcp", || {
+            let addr = tcp_connect(&addrs)?;
+            Ok((format!("connected to {addr}"), ()))
+        })?;
+
+        let tls = starttls || matches!(server.scheme(), "imaps" | "smtps");
+        let name = if tls { "tls" } else { "greeting" };
+
+        self.step(name, || match handshake() {
+            Ok(()) => Ok((String::new(), ())),
+            Err(err) => Err(explain_tls(err)),
+        })
+    }
+
+    /// Records the outcome of the login step.
+    pub fn login<T>(&mut self, result: Result<T>) -> Result<T> {
+        self.step("login", || result.map(|ok| (String::new(), ok)))
+    }
+
+    fn step<T>(
+        &mut self,
+        name: &'static str,
+        f: impl FnOnce() -> Result<(String, T)>,
+    ) -> Result<T> {
+        match f() {
+            Ok((detail, ok)) => {
+                self.steps.push(NetworkStep::new(name, Ok(detail)));
+                Ok(ok)
+            }
+            Err(err) => {
+                self.steps
+                    .push(NetworkStep::new(name, Err(anyhow!("{err:#}"))));
+                Err(err.context(format!("{name} step failed")))
+            }
+        }
+    }
+}
+
+/// Addresses `server` resolves to, on its explicit or default port.
+fn resolve(server: &Url) -> Result<Vec<SocketAddr>> {
+    let Some(host) = server.host_str() else {
+        bail!("Cannot derive host from server `{server}`");
+    };
+
+    let port = match server.port() {
+        Some(port) => port,
+        None => match server.scheme() {
+            "imap" => 143,
+            "imaps" => 993,
+            "smtp" => 25,
+            "smtps" => 465,
+            scheme => bail!("Cannot derive port of `{scheme}` server `{server}`"),
+        },
+    };
+
+    let addrs: Vec<SocketAddr> = (host, port)
+        .to_socket_addrs()
+        .map_err(|err| anyhow!("cannot resolve `{host}`: {err}"))?
+        .collect();
+
+    if addrs.is_empty() {
+        bail!("`{host}` resolves to no address");
+    }
+
+    Ok(addrs)
+}
+
+/// Connects to the first reachable address of `addrs`.
+fn tcp_connect(addrs: &[SocketAddr]) -> Result<SocketAddr> {
+    let mut errors = Vec::new();
+
+    for addr in addrs {
+        match TcpStream::connect_timeout(addr, TCP_TIMEOUT) {
+            Ok(_) => return Ok(*addr),
+            Err(err) => errors.push(format!("{addr}: {err}")),
+        }
+    }
+
+    bail!(
+        "cannot connect (check the port and any firewall): {}",
+        errors.join("; ")
+    )
+}
+
+/// Adds an actionable hint on top of a failed handshake when the
+/// error names a known certificate or protocol problem.
+fn explain_tls(err: anyhow::Error) -> anyhow::Error {
+    let msg = format!("{err:#}").to_lowercase();
+
+    let hint = if msg.contains("expired") {
+        "the server certificate has expired; ask the server administrator to renew it"
+    } else if msg.contains("self-signed")
+        || msg.contains("self signed")
+        || msg.contains("unknownissuer")
+        || msg.contains("unknown issuer")
+        || msg.contains("local issuer")
+    {
+        "the server certificate is self-signed or issued by a private CA; \
+         point `tls.cert` to the CA certificate (PEM) to trust it"
+    } else if msg.contains("notvalidforname") || msg.contains("not valid for") {
+        "the server certificate does not match the host name; \
+         check the host of `server` against the certificate"
+    } else if msg.contains("wrong version number")
+        || msg.contains("invalidcontenttype")
+        || msg.contains("corrupt message")
+    {
+        "the server did not answer with TLS; for a plain port (143, 25, 587) \
+         use an `imap://` or `smtp://` server with `starttls = true`"
+    } else {
+        return err;
+    };
+
+    err.context(hint)
+}
@@ -0,0 +1,190 @@
+//! User-defined command aliases (`[aliases]` config table).
+//!
+//! An alias maps a custom command word to a full subcommand string,
+//! e.g. `unread = "envelope search not flag seen"`. Aliases are
+//! expanded on the raw arguments, before clap dispatches them, and
+//! only when clap does not recognize the top-level subcommand: the
+//! config is not even loaded for built-in commands.
+
+use std::{collections::HashMap, ffi::OsString, path::PathBuf};
+
+use anyhow::{Result, bail};
+use clap::{
+    CommandFactory, FromArgMatches,
+    error::{ContextKind, ContextValue, ErrorKind},
+};
+use log::debug;
+use pimalaya_cli::clap::args::ConfigFlags;
+
+use crate::{cli::Cli, config::Config};
+
+/// Expands the alias standing for the top-level subcommand of `args`,
+/// if any, then the aliases it expands to, until clap recognizes the
+/// subcommand.
+///
+/// Bails when an alias shadows a built-in subcommand or when the
+/// expansion loops back on an alias already expanded.
+pub fn expand(args: Vec<OsString>) -> Result<Vec<OsString>> {
+    let Some(word) = unknown_subcommand(&args) else {
+        return Ok(args);
+    };
+
+    let Some(config) = Config::load(&config_paths(&args))? else {
+        return Ok(args);
+    };
+
+    if config.aliases.is_empty() {
+        return Ok(args);
+    }
+
+    check_conflicts(&config.aliases, &builtin_words())?;
+
+    let mut args = args;
+    let mut chain: Vec<String> = Vec::new();
+    let mut next = Some(word);
+
+    while let Some(word) = next {
+        let Some(expansion) = config.aliases.get(&word) else {
+            break;
+        };
+
+        if chain.contains(&word) {
+            chain.push(word);
+            bail!("Alias `{}` is recursive: {}", chain[0], chain.join(" -> "));
+        }
+
+        debug!("expanding alias {word} to `{expansion}`");
+        args = substitute(args, &word, expansion);
+        chain.push(word);
+        next = unknown_subcommand(&args);
+    }
+
+    Ok(args)
+}
+
+/// The top-level subcommand of `args` clap does not know about.
+fn unknown_subcommand(args: &[OsString]) -> Option<String> {
+    let err = Cli::command().try_get_matches_from(args).err()?;
+
+    if err.kind() != ErrorKind::InvalidSubcommand {
+        return None;
+    }
+
+    // NOTE: a typo in a nested subcommand (`envelope lst`) raises
+    // the same error, but leaves the top-level subcommand matched
+    let matches = Cli::command()
+        .ignore_errors(true)
+        .try_get_matches_from(args)
+        .ok()?;
+
+    if matches.subcommand_name().is_some() {
+        return None;
+    }
+
+    match err.get(ContextKind::InvalidSubcommand)? {
+        ContextValue::String(word) => Some(word.clone()),
+        _ => None,
+    }
+}
+
+/// Config paths of `-c/--config` and `--config-dir`, resolved like
+/// the command itself does (see [`Config::resolve_paths`]). Parsed
+/// leniently since `args` do not parse as a whole yet.
+fn config_paths(args: &[OsString]) -> Vec<PathBuf> {
+    let Ok(matches) = Cli::command()
+        .ignore_errors(true)
+        .try_get_matches_from(args)
+    else {
+        return Config::resolve_paths(&[], &[]);
+    };
+
+    let paths = ConfigFlags::from_arg_matches(&matches)
+        .map(|flags| flags.paths)
+        .unwrap_or_default();
+
+    let dirs: Vec<PathBuf> = matches
+        .try_get_many::<PathBuf>("config_dirs")
+        .ok()
+        .flatten()
+        .map(|dirs| dirs.cloned().collect())
+        .unwrap_or_default();
+
+    Config::resolve_paths(&paths, &dirs)
+}
+
+/// Names and aliases of the built-in top-level subcommands.
+fn builtin_words() -> Vec<String> {
+    let cmd = Cli::command();
+    let mut words = vec![String::from("help")];
+
+    for sub in cmd.get_subcommands() {
+        words.push(sub.get_name().to_owned());
+        words.extend(sub.get_all_aliases().map(str::to_owned));
+    }
+
+    words
+}
+
+/// Bails on the first alias equal to a built-in word, or to a prefix
+/// of one: subcommands can be shortened, so clap would pick the
+/// built-in before the alias is ever looked up.
+fn check_conflicts(aliases: &HashMap<String, String>, builtins: &[String]) -> Result<()> {
+    let mut names: Vec<&String> = aliases.keys().collect();
+    names.sort();
+
+    for name in names {
+        if let Some(builtin) = builtins.iter().find(|b| b.starts_with(name.as_str())) {
+            bail!("Alias `{name}` conflicts with the built-in subcommand `{builtin}`");
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces the first occurrence of `word` in `args` (past the binary
+/// name) by the whitespace-separated words of `expansion`.
+fn substitute(args: Vec<OsString>, word: &str, expansion: &str) -> Vec<OsString> {
+    let Some(pos) = args.iter().skip(1).position(|arg| arg == word) else {
+        return args;
+    };
+
+    let pos = pos + 1;
+    let mut expanded = Vec::with_capacity(args.len() + 4);
+    expanded.extend_from_slice(&args[..pos]);
+    expanded.extend(expansion.split_whitespace().map(OsString::from));
+    expanded.extend_from_slice(&args[pos + 1..]);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_the_alias_word_only() {
+        let args: Vec<OsString> = ["himalaya", "-a", "work", "unread", "--json"]
+            .into_iter()
+            .map(OsString::from)
+            .collect();
+
+        let args = substitute(args, "unread", "envelope search  not flag seen");
+
+        assert_eq!(
+            args,
+            [
+                "himalaya", "-a", "work", "envelope", "search", "not", "flag", "seen", "--json"
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_aliases_shadowing_builtins() {
+        let builtins = vec![String::from("envelope"), String::from("mbox")];
+        let aliases = |name: &str| HashMap::from([(name.to_owned(), String::from("x"))]);
+
+        assert!(check_conflicts(&aliases("envelope"), &builtins).is_err());
+        assert!(check_conflicts(&aliases("env"), &builtins).is_err());
+        assert!(check_conflicts(&aliases("mb"), &builtins).is_err());
+        assert!(check_conflicts(&aliases("unread"), &builtins).is_ok());
+    }
+}
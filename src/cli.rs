@@ -1,4 +1,4 @@
-use std::{path::PathBuf, process::exit};
+use std::{io::stdout, path::PathBuf, process::exit};
 
 use anyhow::{Result, bail};
 use clap::{CommandFactory, Parser, Subcommand};
@@ -27,12 +27,23 @@ use crate::msgraph::{cli::MsgraphCommand, client::build_msgraph_client};
 #[cfg(feature = "smtp")]
 use crate::smtp::{cli::SmtpCommand, client::build_smtp_client};
 use crate::{
-    account::cli::AccountCommand,
+    account::{
+        all::{self, ALL_ACCOUNTS},
+        cli::AccountCommand,
+        context::Account,
+    },
     backend::Backend,
-    config::Config,
+    config::{Config, cli::ConfigCommand},
+    dry_run,
+    outbox::cli::OutboxCommand,
+    profile,
     shared::{
-        attachment::cli::AttachmentCommand, client::EmailClient, envelope::cli::EnvelopeCommand,
-        flag::cli::FlagCommand, mailbox::cli::MailboxCommand, message::cli::MessageCommand,
+        attachment::cli::AttachmentCommand,
+        client::{EmailClient, merged_account},
+        envelope::{cli::EnvelopeCommand, list::EnvelopesOutput},
+        flag::cli::FlagCommand,
+        mailbox::{self, cli::MailboxCommand},
+        message::cli::MessageCommand,
     },
     wizard,
 };
@@ -49,6 +60,20 @@ pub struct Cli {
 
     #[command(flatten)]
     pub config: ConfigFlags,
+    /// Directory to look for a configuration file in
+    /// (`.himalaya.toml`, `himalaya.toml`, then `config.toml`), e.g.
+    /// `.` for a per-project configuration. Repeatable: directories
+    /// are walked in order and the first file found is used, the
+    /// default locations being looked at when none is.
+    ///
+    /// Overrides the `HIMALAYA_CONFIG_DIRS` environment variable
+    /// (directories separated by `:`). Both are ignored when
+    /// `--config` is given. The closest `.himalaya.toml` of the
+    /// current directory is merged on top of the file found, unless
+    /// `HIMALAYA_NO_PROJECT_CONFIG` is set; it cannot set keys running
+    /// commands, nor the remote backends of the accounts.
+    #[arg(long = "config-dir", global = true, value_name = "DIR")]
+    pub config_dirs: Vec<PathBuf>,
     #[command(flatten)]
     pub account: AccountFlag,
     /// Force a specific backend for cross-protocol commands.
@@ -62,8 +87,25 @@ pub struct Cli {
     /// implementation for it (e.g. `--backend smtp mailboxes list`).
     #[arg(short, long, global = true, default_value_t)]
     pub backend: Backend,
+    /// Print the duration of the main phases of the command (config
+    /// load, backend connection, fetch, render) to stderr.
+    #[arg(long, global = true)]
+    pub profile: bool,
+    /// Resolve the mailboxes and messages the command would change
+    /// (flag add, set and remove, message move, junk and not-junk,
+    /// imap store and expunge, jmap email delete, maildir and m2dir
+    /// delete), print them, then stop without changing anything.
+    ///
+    /// Read-only commands run as usual, the others refuse it.
+    #[arg(long, global = true)]
+    pub dry_run: bool,
     #[command(flatten)]
     pub json: JsonFlag,
+    /// Print plain text, even when the account sets `output = "json"`.
+    ///
+    /// Cannot be combined with `--json`.
+    #[arg(long, global = true)]
+    pub plain: bool,
     #[command(flatten)]
     pub log: LogFlags,
 }
@@ -112,16 +154,18 @@ pub enum Command {
     //
     #[command(subcommand)]
     Account(AccountCommand),
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    #[command(subcommand)]
+    Outbox(OutboxCommand),
     Completion(CompletionCommand),
     Manual(ManualCommand),
 }
 
 /// Loads `Config` from the merged `config_paths` or, when no file
-/// exists, runs the wizard to bootstrap one at the target path. Used
-/// by every `build_*_client` helper to get a populated `Config` before
-/// the per-backend client opens its connection.
+/// exists, runs the wizard to bootstrap one at the target path.
 pub fn load_or_wizard(config_paths: &[PathBuf]) -> Result<Config> {
-    if let Some(config) = Config::from_paths_or_default(config_paths)? {
+    if let Some(config) = Config::load(config_paths)? {
         return Ok(config);
     }
 
@@ -131,50 +175,148 @@ pub fn load_or_wizard(config_paths: &[PathBuf]) -> Result<Config> {
     }
 }
 
+/// `config` when it could already be loaded (e.g. by `main` to pick
+/// the output format of the account), otherwise loaded from
+/// `config_paths` by [`load_or_wizard`].
+fn loaded_or_wizard(config: Option<Config>, config_paths: &[PathBuf]) -> Result<Config> {
+    match config {
+        Some(config) => Ok(config),
+        None => profile::time("config", || load_or_wizard(config_paths)),
+    }
+}
+
 impl Command {
+    /// Runs the command. `config` is the configuration loaded from
+    /// `config_paths`, when it could already be: it is then not
+    /// parsed again.
     pub fn execute(
         self,
         printer: &mut impl Printer,
         config_paths: &[PathBuf],
+        config: Option<Config>,
         account_name: Option<&str>,
         backend: Backend,
     ) -> Result<()> {
-        let configs = || {
-            let mut config = load_or_wizard(config_paths)?;
+        if dry_run::enabled() && !self.supports_dry_run() {
+            bail!(
+                "`--dry-run` is only supported by read-only commands, `flag add|set|remove`, \
+                 `message move|junk|not-junk`, `imap store|expunge`, `jmap email delete`, \
+                 `maildir delete` and `m2dir delete`"
+            );
+        }
+
+        if account_name == Some(ALL_ACCOUNTS) {
+            return self.execute_all(printer, config_paths, backend);
+        }
 
-            let Some((_, account_config)) = config.take_account(account_name)? else {
-                bail!("Cannot find account")
-            };
+        let configs = |config| {
+            let mut config = loaded_or_wizard(config, config_paths)?;
 
-            Ok((config, account_config))
+            let (name, account_config) = config.select_account(account_name)?;
+
+            Ok((name, config, account_config))
         };
 
-        match self {
+        let changes_mailboxes = self.changes_mailboxes();
+
+        let result = match self {
             // --- Shared API
             //
             Self::Mailbox(cmd) => {
-                let (config, account_config) = configs()?;
-                let (mut account, mut client) = EmailClient::new(config, account_config, backend)?;
-                cmd.execute(printer, &mut account, &mut client)
+                let (name, config, account_config) = configs(config)?;
+
+                // NOTE: a fresh cached listing is served without
+                // connecting to the backend
+                let ttl = config.mailboxes_list_cache_ttl(&account_config);
+                let cache_key = mailbox::cache::key(config_paths, backend, &name);
+
+                if let Some(cached) = cmd.cached(&cache_key, ttl)? {
+                    let mut account = Account::from(config).merge(Account::from(account_config));
+                    return cmd.execute_cached(printer, &mut account, cached);
+                }
+
+                let (mut account, mut client) = profile::time("connect", || {
+                    EmailClient::new(config, account_config, backend)
+                })?;
+                cmd.execute(printer, &mut account, &mut client, &cache_key)
+            }
+            // NOTE: virtual mailboxes span several accounts, each
+            // connected by the command itself. `@name` only targets
+            // one when declared, otherwise it names a mailbox.
+            Self::Envelope(EnvelopeCommand::List(cmd)) if cmd.mailbox.virtual_name().is_some() => {
+                let mut config = loaded_or_wizard(config, config_paths)?;
+
+                let declared = cmd
+                    .mailbox
+                    .virtual_name()
+                    .is_some_and(|name| config.virtual_mailboxes.contains_key(name));
+
+                if !declared {
+                    let (name, account_config) = config.select_account(account_name)?;
+                    let (mut account, mut client) = profile::time("connect", || {
+                        EmailClient::new(config, account_config, backend)
+                    })?;
+                    let cmd = EnvelopeCommand::List(cmd);
+                    return cmd.execute(printer, &mut account, &mut client, &name);
+                }
+
+                if cmd.output == EnvelopesOutput::CountOnly {
+                    bail!("`--output count-only` cannot be combined with a virtual mailbox");
+                }
+
+                if cmd.follow {
+                    bail!("`--follow` cannot be combined with a virtual mailbox");
+                }
+
+                let envelopes =
+                    profile::time("fetch", || cmd.list_virtual(&config, config_paths, backend))?;
+
+                if cmd.output == EnvelopesOutput::JsonLines {
+                    return envelopes.write_json_lines(&mut stdout().lock());
+                }
+
+                profile::time("render", || printer.out(envelopes))
             }
             Self::Envelope(cmd) => {
-                let (config, account_config) = configs()?;
-                let (mut account, mut client) = EmailClient::new(config, account_config, backend)?;
-                cmd.execute(printer, &mut account, &mut client)
+                let (name, config, account_config) = configs(config)?;
+                let (mut account, mut client) = profile::time("connect", || {
+                    EmailClient::new(config, account_config, backend)
+                })?;
+                cmd.execute(printer, &mut account, &mut client, &name)
             }
             Self::Flag(cmd) => {
-                let (config, account_config) = configs()?;
-                let (mut account, mut client) = EmailClient::new(config, account_config, backend)?;
-                cmd.execute(printer, &mut account, &mut client)
+                let (name, config, account_config) = configs(config)?;
+                let (mut account, mut client) = profile::time("connect", || {
+                    EmailClient::new(config, account_config, backend)
+                })?;
+                cmd.execute(printer, &mut account, &mut client, &name)
+            }
+            // NOTE: scheduling only writes to the outbox, no need to
+            // connect to the backend
+            Self::Message(MessageCommand::Send(cmd)) if cmd.schedule.is_some() => {
+                let (name, config, account_config) = configs(config)?;
+                let account = merged_account(config, account_config);
+                cmd.schedule(printer, &account, &name)
             }
             Self::Message(cmd) => {
-                let (config, account_config) = configs()?;
-                let (mut account, mut client) = EmailClient::new(config, account_config, backend)?;
-                cmd.execute(printer, &mut account, &mut client)
+                let (name, config, account_config) = configs(config)?;
+                let (mut account, mut client) = profile::time("connect", || {
+                    EmailClient::new(config, account_config, backend)
+                })?;
+                cmd.execute(
+                    printer,
+                    &mut account,
+                    &mut client,
+                    &name,
+                    config_paths,
+                    backend,
+                )
             }
             Self::Attachment(cmd) => {
-                let (config, account_config) = configs()?;
-                let (mut account, mut client) = EmailClient::new(config, account_config, backend)?;
+                let (_, config, account_config) = configs(config)?;
+                let (mut account, mut client) = profile::time("connect", || {
+                    EmailClient::new(config, account_config, backend)
+                })?;
                 cmd.execute(printer, &mut account, &mut client)
             }
 
@@ -182,45 +324,200 @@ impl Command {
             //
             #[cfg(feature = "imap")]
             Self::Imap(cmd) => {
-                let (mut account, mut client) = build_imap_client(config_paths, account_name)?;
+                let (mut account, mut client) = profile::time("connect", || {
+                    build_imap_client(loaded_or_wizard(config, config_paths)?, account_name)
+                })?;
                 cmd.execute(printer, &mut account, &mut client)
             }
             #[cfg(feature = "jmap")]
             Self::Jmap(cmd) => {
-                let (mut account, mut client) = build_jmap_client(config_paths, account_name)?;
+                let (mut account, mut client) = profile::time("connect", || {
+                    build_jmap_client(loaded_or_wizard(config, config_paths)?, account_name)
+                })?;
                 cmd.execute(printer, &mut account, &mut client)
             }
             #[cfg(feature = "gmail")]
             Self::Gmail(cmd) => {
-                let (mut account, mut client) = build_gmail_client(config_paths, account_name)?;
+                let (mut account, mut client) = profile::time("connect", || {
+                    build_gmail_client(loaded_or_wizard(config, config_paths)?, account_name)
+                })?;
                 cmd.execute(printer, &mut account, &mut client)
             }
             #[cfg(feature = "msgraph")]
             Self::Msgraph(cmd) => {
-                let (mut account, mut client) = build_msgraph_client(config_paths, account_name)?;
+                let (mut account, mut client) = profile::time("connect", || {
+                    build_msgraph_client(loaded_or_wizard(config, config_paths)?, account_name)
+                })?;
                 cmd.execute(printer, &mut account, &mut client)
             }
             #[cfg(feature = "maildir")]
             Self::Maildir(cmd) => {
-                let (mut account, mut client) = build_maildir_client(config_paths, account_name)?;
+                let (mut account, mut client) = profile::time("connect", || {
+                    build_maildir_client(loaded_or_wizard(config, config_paths)?, account_name)
+                })?;
                 cmd.execute(printer, &mut account, &mut client)
             }
             #[cfg(feature = "m2dir")]
             Self::M2dir(cmd) => {
-                let (mut account, mut client) = build_m2dir_client(config_paths, account_name)?;
+                let (mut account, mut client) = profile::time("connect", || {
+                    build_m2dir_client(loaded_or_wizard(config, config_paths)?, account_name)
+                })?;
                 cmd.execute(printer, &mut account, &mut client)
             }
             #[cfg(feature = "smtp")]
             Self::Smtp(cmd) => {
-                let (_account, mut client) = build_smtp_client(config_paths, account_name)?;
+                let (_account, mut client) = profile::time("connect", || {
+                    build_smtp_client(loaded_or_wizard(config, config_paths)?, account_name)
+                })?;
                 cmd.execute(printer, &mut client)
             }
 
             // --- Meta
             //
             Self::Account(cmd) => cmd.execute(printer, config_paths, account_name, backend),
+            Self::Config(cmd) => cmd.execute(printer),
+            Self::Outbox(cmd) => cmd.execute(printer, config_paths, account_name, backend),
             Self::Completion(cmd) => cmd.execute(printer, Cli::command()),
             Self::Manual(cmd) => cmd.execute(printer, Cli::command()),
+        };
+
+        // NOTE: dropped whatever the outcome, a failed mutation may
+        // have partly succeeded
+        if changes_mailboxes {
+            mailbox::cache::invalidate();
+        }
+
+        result
+    }
+
+    /// Whether the command may run under `--dry-run`: it either
+    /// reports its changes instead of making them (see [`dry_run`]),
+    /// or changes nothing.
+    fn supports_dry_run(&self) -> bool {
+        match self {
+            Self::Mailbox(_) | Self::Envelope(_) | Self::Flag(_) => true,
+            Self::Message(cmd) => matches!(
+                cmd,
+                MessageCommand::Move(_) | MessageCommand::Junk(_) | MessageCommand::NotJunk(_)
+            ),
+            Self::Attachment(cmd) => matches!(cmd, AttachmentCommand::List(_)),
+            #[cfg(feature = "imap")]
+            Self::Imap(cmd) => cmd.supports_dry_run(),
+            #[cfg(feature = "jmap")]
+            Self::Jmap(cmd) => cmd.supports_dry_run(),
+            #[cfg(feature = "maildir")]
+            Self::Maildir(cmd) => cmd.supports_dry_run(),
+            #[cfg(feature = "m2dir")]
+            Self::M2dir(cmd) => cmd.supports_dry_run(),
+            Self::Account(cmd) => !matches!(cmd, AccountCommand::Configure(_)),
+            Self::Outbox(cmd) => matches!(cmd, OutboxCommand::List(_)),
+            Self::Config(_) | Self::Completion(_) | Self::Manual(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the command creates, deletes or renames mailboxes,
+    /// which invalidates the `mailbox list` cache.
+    fn changes_mailboxes(&self) -> bool {
+        match self {
+            #[cfg(feature = "imap")]
+            Self::Imap(cmd) => cmd.changes_mailboxes(),
+            #[cfg(feature = "jmap")]
+            Self::Jmap(cmd) => cmd.changes_mailboxes(),
+            #[cfg(feature = "gmail")]
+            Self::Gmail(cmd) => cmd.changes_mailboxes(),
+            #[cfg(feature = "msgraph")]
+            Self::Msgraph(cmd) => cmd.changes_mailboxes(),
+            #[cfg(feature = "maildir")]
+            Self::Maildir(cmd) => cmd.changes_mailboxes(),
+            #[cfg(feature = "m2dir")]
+            Self::M2dir(cmd) => cmd.changes_mailboxes(),
+            _ => false,
+        }
+    }
+
+    /// Runs the command against every configured account (`--account
+    /// all`). Only read-only shared commands are accepted: fanning a
+    /// mutation out to all accounts is too easy to trigger by mistake.
+    fn execute_all(
+        self,
+        printer: &mut impl Printer,
+        config_paths: &[PathBuf],
+        backend: Backend,
+    ) -> Result<()> {
+        match self {
+            Self::Mailbox(MailboxCommand::List(cmd)) => {
+                all::collect(config_paths, backend, |account, client| {
+                    cmd.list(account, client)
+                })?
+                .print(printer)
+            }
+            Self::Envelope(EnvelopeCommand::List(cmd))
+                if matches!(
+                    cmd.output,
+                    EnvelopesOutput::CountOnly | EnvelopesOutput::JsonLines
+                ) || cmd.follow =>
+            {
+                bail!(
+                    "`--output count-only`, `--output json-lines` and `--follow` cannot be \
+                     combined with `--account {ALL_ACCOUNTS}`: pass a single account name instead"
+                )
+            }
+            Self::Envelope(EnvelopeCommand::List(cmd)) if cmd.since_last_check => bail!(
+                "`--since-last-check` cannot be combined with `--account {ALL_ACCOUNTS}`: \
+                 pass a single account name instead"
+            ),
+            Self::Envelope(EnvelopeCommand::List(cmd)) => {
+                all::collect(config_paths, backend, |account, client| {
+                    cmd.list(account, client)
+                })?
+                .print(printer)
+            }
+            Self::Envelope(EnvelopeCommand::Search(cmd)) => {
+                all::collect(config_paths, backend, |account, client| {
+                    cmd.search(account, client)
+                })?
+                .print(printer)
+            }
+            Self::Envelope(EnvelopeCommand::Count(cmd)) => {
+                all::collect(config_paths, backend, |account, client| {
+                    cmd.count(account, client)
+                })?
+                .print(printer)
+            }
+            _ => bail!(
+                "`--account {ALL_ACCOUNTS}` only applies to read-only commands \
+                 (`mailbox list`, `envelope list|search|count`): \
+                 pass a single account name instead"
+            ),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(args: &[&str]) -> Command {
+        Cli::try_parse_from([&["himalaya", "--dry-run"], args].concat())
+            .unwrap()
+            .cmd
+    }
+
+    #[test]
+    fn dry_run_is_refused_by_unsupported_commands() {
+        assert!(command(&["message", "move", "--to", "Archive", "1"]).supports_dry_run());
+        assert!(!command(&["message", "copy", "--to", "Archive", "1"]).supports_dry_run());
+        assert!(command(&["mailbox", "list"]).supports_dry_run());
+        assert!(command(&["envelope", "list"]).supports_dry_run());
+
+        #[cfg(feature = "imap")]
+        {
+            assert!(command(&["imap", "expunge", "INBOX"]).supports_dry_run());
+            assert!(!command(&["imap", "delete", "INBOX"]).supports_dry_run());
+        }
+
+        #[cfg(feature = "jmap")]
+        assert!(command(&["jmap", "email", "delete", "M1"]).supports_dry_run());
+    }
+}
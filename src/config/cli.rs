@@ -0,0 +1,19 @@
+use anyhow::Result;
+use clap::Subcommand;
+use pimalaya_cli::printer::Printer;
+
+use crate::config::schema::ConfigSchemaCommand;
+
+/// Inspect the TOML configuration file format.
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    Schema(ConfigSchemaCommand),
+}
+
+impl ConfigCommand {
+    pub fn execute(self, printer: &mut impl Printer) -> Result<()> {
+        match self {
+            Self::Schema(cmd) => cmd.execute(printer),
+        }
+    }
+}
@@ -0,0 +1,1848 @@
+pub mod cli;
+pub mod schema;
+
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::{collections::HashMap, env, fs, fs::OpenOptions, io::Write, path::Path, path::PathBuf};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, Utc};
+use chrono_tz::Tz;
+use comfy_table::{ContentArrangement, presets};
+use crossterm::style::Color;
+use log::{debug, warn};
+use pimalaya_config::{
+    secret::Secret,
+    toml::{TomlConfig, shell_expanded_string},
+};
+use pimalaya_stream::{
+    sasl::{
+        Sasl, SaslAnonymous, SaslLogin, SaslOauthbearer, SaslPlain, SaslScramSha256, SaslXoauth2,
+    },
+    tls::{Rustls, RustlsCrypto, Tls, TlsProvider},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::account::{all::check_account_name, context::DEFAULT_MAILBOXES_LIST_CACHE_TTL};
+use crate::config::schema::{ColorSchema, SecretSchema};
+use crate::shared::levenshtein::levenshtein;
+
+/// Environment variable listing the directories to look for a
+/// configuration file in, separated like `PATH`. See
+/// [`Config::resolve_paths`].
+pub const CONFIG_DIRS_ENV: &str = "HIMALAYA_CONFIG_DIRS";
+
+/// Environment variable disabling the project configuration lookup
+/// when set, see [`Config::resolve_paths`].
+pub const NO_PROJECT_CONFIG_ENV: &str = "HIMALAYA_NO_PROJECT_CONFIG";
+
+/// Name of the project configuration file.
+const PROJECT_CONFIG_FILE_NAME: &str = ".himalaya.toml";
+
+/// Configuration file names looked for in each configuration
+/// directory, in order.
+const CONFIG_FILE_NAMES: &[&str] = &[PROJECT_CONFIG_FILE_NAME, "himalaya.toml", "config.toml"];
+
+/// Global configuration.
+///
+/// Represents the whole TOML user's configuration file.
+/// `deny_unknown_fields` is intentionally omitted so the same TOML
+/// file can be shared with `himalaya-tui`: top-level TUI-only fields
+/// (`display-name`, `signature`, `signature-delim`) are silently
+/// ignored here.
+#[derive(Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    pub downloads_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub table: TableConfig,
+    #[serde(default)]
+    pub tree: TreeConfig,
+    #[serde(default)]
+    pub envelope: EnvelopeConfig,
+    #[serde(default)]
+    pub mailbox: MailboxConfig,
+    #[serde(default)]
+    pub attachment: AttachmentConfig,
+    #[serde(default)]
+    pub compose: ComposeConfig,
+    #[serde(default)]
+    pub send: SendConfig,
+    #[serde(default)]
+    pub read: ReadConfig,
+    #[serde(default)]
+    pub junk: JunkConfig,
+    /// `account list` rendering options (global only — there is no
+    /// per-account override for the listing of accounts).
+    #[serde(default)]
+    pub account: AccountListingConfig,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub templates: HashMap<String, TemplateConfig>,
+    /// Custom command words expanding to full subcommand strings,
+    /// e.g. `unread = "envelope search not flag seen"` (global only: they
+    /// are expanded before any account is selected).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub aliases: HashMap<String, String>,
+    /// Virtual mailboxes gathering mailboxes of several accounts,
+    /// listed with `envelope list -m @<name>` (global only).
+    #[serde(default, rename = "virtual", skip_serializing_if = "HashMap::is_empty")]
+    pub virtual_mailboxes: HashMap<String, VirtualMailboxConfig>,
+    pub accounts: HashMap<String, AccountConfig>,
+}
+
+impl TomlConfig for Config {
+    type Account = AccountConfig;
+
+    fn project_name() -> &'static str {
+        env!("CARGO_PKG_NAME")
+    }
+
+    fn take_named_account(&mut self, name: &str) -> Option<(String, Self::Account)> {
+        self.accounts.remove_entry(name)
+    }
+
+    /// Takes the default account (see [`Config::default_account`]),
+    /// `None` when there is no single one.
+    fn take_default_account(&mut self) -> Option<(String, Self::Account)> {
+        let name = self.default_account().ok()?;
+        self.take_named_account(&name)
+    }
+}
+
+impl Config {
+    /// Takes the account named `name`, or the default one when
+    /// `None`. Unlike [`TomlConfig::take_account`], explains why no
+    /// account could be picked.
+    pub fn select_account(&mut self, name: Option<&str>) -> Result<(String, AccountConfig)> {
+        let name = match name {
+            Some(name) => name.to_owned(),
+            None => self.default_account()?,
+        };
+
+        match self.take_named_account(&name) {
+            Some(account) => Ok(account),
+            None => bail!(
+                "Cannot find account {name}, expected one of: {}",
+                self.account_names().join(", ")
+            ),
+        }
+    }
+
+    /// Name of the default account: the one set as default, or the
+    /// only account when none is. Fails when several accounts are
+    /// set as default, or when there are several accounts and none
+    /// is, rather than picking one by hash order.
+    pub fn default_account(&self) -> Result<String> {
+        let defaults = self.default_accounts();
+
+        match defaults.as_slice() {
+            [name] => return Ok(name.to_string()),
+            [] => (),
+            names => bail!(
+                "Accounts {} are all set as default: keep only one, or pass --account",
+                names.join(", ")
+            ),
+        }
+
+        match self.account_names().as_slice() {
+            [] => bail!("No account configured"),
+            [name] => Ok(name.to_string()),
+            names => bail!(
+                "No default account among {}: set `default = true` on one, or pass --account",
+                names.join(", ")
+            ),
+        }
+    }
+
+    /// Output format of the account named `name`, or of the default
+    /// one when `None`. `None` when the account cannot be found or
+    /// sets no output format.
+    pub fn account_output(&self, name: Option<&str>) -> Option<OutputFormat> {
+        let name = match name {
+            Some(name) => name.to_owned(),
+            None => self.default_account().ok()?,
+        };
+
+        self.accounts.get(&name)?.output
+    }
+
+    /// Lifetime of the `mailbox list` cache of `account`, in seconds,
+    /// like [`Account::mailboxes_list_cache_ttl`] but read before
+    /// the account is built, so a fresh listing can be served without
+    /// connecting.
+    ///
+    /// [`Account::mailboxes_list_cache_ttl`]: crate::account::context::Account::mailboxes_list_cache_ttl
+    pub fn mailboxes_list_cache_ttl(&self, account: &AccountConfig) -> u64 {
+        account
+            .mailbox
+            .list
+            .cache_ttl
+            .or(self.mailbox.list.cache_ttl)
+            .unwrap_or(DEFAULT_MAILBOXES_LIST_CACHE_TTL)
+    }
+
+    /// Names of all the accounts, sorted.
+    pub fn account_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.accounts.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Names of the accounts set as default, sorted. More than one
+    /// is a configuration error.
+    pub fn default_accounts(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .accounts
+            .iter()
+            .filter(|(_, account)| account.default)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Loads the configuration from `paths`, like
+    /// [`TomlConfig::from_paths_or_default`], but turns serde's terse
+    /// unknown-field error into an actionable one: the offending key,
+    /// its location and the closest valid key are put on top of the
+    /// original error. Unknown keys are still rejected, and so are
+    /// accounts with a reserved name (see [`check_account_name`]).
+    pub fn load(paths: &[PathBuf]) -> Result<Option<Self>> {
+        let config =
+            Self::from_paths_or_default(paths).map_err(|err| explain_unknown_key(err.into()))?;
+
+        if let Some(config) = &config {
+            for name in config.accounts.keys() {
+                check_account_name(name)?;
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Configuration paths to load: the explicit `--config` `paths`
+    /// when given, otherwise the first configuration file found in
+    /// the `--config-dir` `dirs`, otherwise in the directories of
+    /// `HIMALAYA_CONFIG_DIRS`, otherwise the default one.
+    ///
+    /// The closest `.himalaya.toml` of the current directory or its
+    /// ancestors is then appended, to be deep-merged on top, unless
+    /// `HIMALAYA_NO_PROJECT_CONFIG` is set. It is skipped when it is
+    /// the configuration file itself, and ignored with a warning when
+    /// it sets a key running a command (see [`command_key`]) or the
+    /// server and authentication of an account (see
+    /// [`backend_key`]): such keys belong to the user configuration,
+    /// not to whatever directory himalaya happens to run in. Empty
+    /// when there is no configuration file, so the default locations
+    /// are looked at.
+    pub fn resolve_paths(paths: &[PathBuf], dirs: &[PathBuf]) -> Vec<PathBuf> {
+        if !paths.is_empty() {
+            return paths.to_vec();
+        }
+
+        let dirs = if dirs.is_empty() {
+            match env::var_os(CONFIG_DIRS_ENV) {
+                Some(dirs) => env::split_paths(&dirs).collect(),
+                None => Vec::new(),
+            }
+        } else {
+            dirs.to_vec()
+        };
+
+        let mut base = None;
+
+        if !dirs.is_empty() {
+            base = find_in_dirs(&dirs);
+
+            match &base {
+                Some(path) => debug!("using configuration file `{}`", path.display()),
+                None => debug!("no configuration file in {dirs:?}, using the default locations"),
+            }
+        }
+
+        let project = match env::var_os(NO_PROJECT_CONFIG_ENV) {
+            Some(_) => None,
+            None => env::current_dir()
+                .ok()
+                .and_then(|dir| find_project_config(&dir)),
+        };
+
+        let Some(project) = project else {
+            return base.into_iter().collect();
+        };
+
+        let base = base.or_else(Self::first_valid_default_path);
+
+        if base.as_ref().is_some_and(|base| same_file(base, &project)) {
+            return base.into_iter().collect();
+        }
+
+        let content = fs::read_to_string(&project).unwrap_or_default();
+        let table = content.parse::<toml::Table>().unwrap_or_default();
+
+        if let Some(key) = command_key(&table) {
+            warn!(
+                "ignoring project configuration `{}`: `{key}` runs a command, \
+                 which is only allowed in the user configuration",
+                project.display()
+            );
+            return base.into_iter().collect();
+        }
+
+        if let Some(key) = backend_key(&table) {
+            warn!(
+                "ignoring project configuration `{}`: `{key}` sets the server \
+                 or the authentication of an account, which is only allowed \
+                 in the user configuration",
+                project.display()
+            );
+            return base.into_iter().collect();
+        }
+
+        debug!("merging project configuration `{}`", project.display());
+        base.into_iter().chain([project]).collect()
+    }
+
+    /// Serializes `self` to TOML and writes it to `path`, creating
+    /// any missing parent directories. Used by the wizard to persist
+    /// a freshly-built configuration.
+    ///
+    /// The file may hold plaintext secrets (a `Secret::Raw` password or
+    /// token), so on unix it is created with `0600` permissions to keep
+    /// it readable by the owner only.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let toml = toml::to_string_pretty(self).context("Serialize TOML config error")?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Create TOML config parent `{}` error", parent.display())
+            })?;
+        }
+
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        options.mode(0o600);
+
+        let mut file = options
+            .open(path)
+            .with_context(|| format!("Open TOML config `{}` error", path.display()))?;
+        file.write_all(toml.as_bytes())
+            .with_context(|| format!("Write TOML config `{}` error", path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// First configuration file found in `dirs`, walked in order, each
+/// one for every name of [`CONFIG_FILE_NAMES`].
+fn find_in_dirs(dirs: &[PathBuf]) -> Option<PathBuf> {
+    dirs.iter()
+        .flat_map(|dir| CONFIG_FILE_NAMES.iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+}
+
+/// Closest project configuration file of `dir`: in `dir` itself or
+/// in one of its ancestors, up to the filesystem root.
+fn find_project_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Whether `a` and `b` are the same file, whatever the path they are
+/// reached through.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Dotted path of the first key of `table` whose value is a command
+/// himalaya runs: secrets read from a `command`, the `*-command`
+/// hooks (spell checker, spam filter) and the `pager`.
+fn command_key(table: &toml::Table) -> Option<String> {
+    for (key, value) in table {
+        if key == "command" || key == "pager" || key.ends_with("-command") {
+            return Some(key.clone());
+        }
+
+        let tables: Vec<&toml::Table> = match value {
+            toml::Value::Table(table) => vec![table],
+            toml::Value::Array(items) => items.iter().filter_map(|v| v.as_table()).collect(),
+            _ => Vec::new(),
+        };
+
+        if let Some(sub) = tables.into_iter().find_map(command_key) {
+            return Some(format!("{key}.{sub}"));
+        }
+    }
+
+    None
+}
+
+/// Backends of an account a project configuration cannot set: they
+/// hold the server the secrets of the user configuration are sent
+/// to (host, port, URL, TLS) and the authentication itself.
+const REMOTE_BACKENDS: &[&str] = &["imap", "jmap", "gmail", "msgraph", "smtp"];
+
+/// Dotted path of the first remote backend (see [`REMOTE_BACKENDS`])
+/// an account of `table` sets.
+fn backend_key(table: &toml::Table) -> Option<String> {
+    let accounts = table.get("accounts")?.as_table()?;
+
+    accounts.iter().find_map(|(name, account)| {
+        let account = account.as_table()?;
+        let backend = REMOTE_BACKENDS.iter().find(|b| account.contains_key(**b))?;
+        Some(format!("accounts.{name}.{backend}"))
+    })
+}
+
+/// Adds a friendly context to `err` when it is caused by an unknown
+/// config key, otherwise returns it untouched.
+fn explain_unknown_key(err: anyhow::Error) -> anyhow::Error {
+    let msg = format!("{err:#}");
+
+    let Some((key, expected)) = parse_unknown_field(&msg) else {
+        return err;
+    };
+
+    let mut hint = format!("Unknown config key `{key}`");
+
+    if let Some((line, column)) = parse_location(&msg) {
+        hint.push_str(&format!(" at line {line}, column {column}"));
+    }
+
+    match closest_key(&key, &expected) {
+        Some(suggestion) => hint.push_str(&format!(": did you mean `{suggestion}`?")),
+        None if !expected.is_empty() => {
+            let expected = expected.join("`, `");
+            hint.push_str(&format!(": expected one of `{expected}`"))
+        }
+        None => (),
+    }
+
+    err.context(hint)
+}
+
+/// Extracts the key and the valid keys from serde's `unknown field
+/// `key`, expected one of `a`, `b`` message (or its `expected `a``
+/// and `expected `a` or `b`` variants).
+fn parse_unknown_field(msg: &str) -> Option<(String, Vec<String>)> {
+    let (_, rest) = msg.split_once("unknown field `")?;
+    let (key, rest) = rest.split_once('`')?;
+
+    let expected = match rest.strip_prefix(", expected ") {
+        Some(rest) => {
+            let rest = rest.lines().next().unwrap_or_default();
+            rest.split('`')
+                .skip(1)
+                .step_by(2)
+                .map(String::from)
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    Some((key.to_owned(), expected))
+}
+
+/// Extracts the `(line, column)` from a `TOML parse error at line N,
+/// column M` message.
+fn parse_location(msg: &str) -> Option<(usize, usize)> {
+    let (_, rest) = msg.split_once("at line ")?;
+    let (line, rest) = rest.split_once(", column ")?;
+    let column: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    Some((line.trim().parse().ok()?, column.parse().ok()?))
+}
+
+/// Returns the candidate closest to `key`, as long as it is close
+/// enough (at most a third of the key's length, or 2 edits) to be a
+/// likely typo rather than a different option.
+fn closest_key<'a>(key: &str, candidates: &'a [String]) -> Option<&'a str> {
+    let max = (key.chars().count() / 3).max(2);
+
+    candidates
+        .iter()
+        .map(|candidate| (levenshtein(key, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+/// Account configuration.
+///
+/// `deny_unknown_fields` is omitted so per-account TUI-only fields
+/// (`signature`, `signature-delim`) coexist in the same
+/// `[accounts.<name>]` block when the file is shared.
+#[derive(Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AccountConfig {
+    #[serde(default)]
+    pub default: bool,
+
+    /// Address of the account and its display name, shared with the
+    /// TUI. They make the default sending identity.
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+    /// Other addresses of the account (aliases, old addresses),
+    /// counting as the account's own like `email`, and sendable as
+    /// with `--identity`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identities: Vec<IdentityConfig>,
+
+    /// Output format of the commands run on this account, when
+    /// `--json` is not passed.
+    pub output: Option<OutputFormat>,
+
+    pub downloads_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub table: TableConfig,
+    #[serde(default)]
+    pub tree: TreeConfig,
+    #[serde(default)]
+    pub envelope: EnvelopeConfig,
+    #[serde(default)]
+    pub mailbox: MailboxConfig,
+    #[serde(default)]
+    pub attachment: AttachmentConfig,
+    #[serde(default)]
+    pub compose: ComposeConfig,
+    #[serde(default)]
+    pub send: SendConfig,
+    #[serde(default)]
+    pub read: ReadConfig,
+    #[serde(default)]
+    pub junk: JunkConfig,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub templates: HashMap<String, TemplateConfig>,
+
+    #[allow(unused)]
+    pub imap: Option<ImapConfig>,
+    #[allow(unused)]
+    pub jmap: Option<JmapConfig>,
+    #[allow(unused)]
+    pub gmail: Option<GmailConfig>,
+    #[allow(unused)]
+    pub msgraph: Option<MsgraphConfig>,
+    #[allow(unused)]
+    pub maildir: Option<MaildirConfig>,
+    #[allow(unused)]
+    pub m2dir: Option<M2dirConfig>,
+    #[allow(unused)]
+    pub smtp: Option<SmtpConfig>,
+}
+
+/// Output format of an account, see [`AccountConfig::output`].
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    #[default]
+    Plain,
+    Json,
+}
+
+/// Sending identity of an account: a bare address, or a table with
+/// the address, its display name and signature.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(untagged)]
+pub enum IdentityConfig {
+    Address(String),
+    #[serde(rename_all = "kebab-case")]
+    Full {
+        address: String,
+        name: Option<String>,
+        /// Signature appended to the messages sent as this identity
+        /// when none is passed on the command line.
+        signature: Option<String>,
+    },
+}
+
+impl IdentityConfig {
+    pub fn address(&self) -> &str {
+        match self {
+            Self::Address(address) | Self::Full { address, .. } => address.trim(),
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Address(_) => None,
+            Self::Full { name, .. } => name.as_deref(),
+        }
+    }
+
+    pub fn signature(&self) -> Option<&str> {
+        match self {
+            Self::Address(_) => None,
+            Self::Full { signature, .. } => signature.as_deref(),
+        }
+    }
+}
+
+/// Virtual mailbox, declared under `[virtual.<name>]`.
+///
+/// Read-only: `envelope list -m @<name>` merges the envelopes of the
+/// listed mailboxes by date, labelled with their account and
+/// mailbox. Other commands refuse virtual mailboxes; act on the
+/// underlying account and mailbox instead.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct VirtualMailboxConfig {
+    #[serde(default)]
+    pub mailboxes: Vec<VirtualMailboxSource>,
+}
+
+/// One mailbox of a virtual mailbox. `mailbox` is resolved through
+/// the `[mailbox.alias]` map of `account`.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct VirtualMailboxSource {
+    pub account: String,
+    pub mailbox: String,
+}
+
+/// Named compose template, declared under `[templates.<name>]`.
+///
+/// Pre-fills `message compose --template <name>`; values passed on
+/// the command line take precedence. `subject` and `body` expand the
+/// `{date}`, `{time}` and `{datetime}` placeholders.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TemplateConfig {
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Vec<String>,
+    #[serde(default)]
+    pub cc: Vec<String>,
+    #[serde(default)]
+    pub bcc: Vec<String>,
+    pub subject: Option<String>,
+    pub body: Option<String>,
+}
+
+/// Envelope-level rendering options.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct EnvelopeConfig {
+    #[serde(default)]
+    pub list: EnvelopeListConfig,
+}
+
+/// Mailbox-level configuration.
+///
+/// Exposes user-defined aliases mapping a friendly name to a
+/// backend-native id (looked up case-insensitively at resolution
+/// time; the `inbox` alias acts as the implicit default mailbox when
+/// a shared command omits `-m/--mailbox`) and the `mailboxes list`
+/// rendering options.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct MailboxConfig {
+    #[serde(default, rename = "alias", alias = "aliases")]
+    pub aliases: HashMap<String, String>,
+
+    #[serde(default)]
+    pub list: MailboxListConfig,
+}
+
+/// `mailboxes list` rendering options under `mailbox.list.*`.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct MailboxListConfig {
+    #[serde(default)]
+    pub table: MailboxListTableConfig,
+
+    /// How long `mailboxes list` serves the mailboxes from its
+    /// cache, in seconds, `0` disabling the cache. Defaults to 300.
+    pub cache_ttl: Option<u64>,
+}
+
+/// Per-column color overrides for the `mailboxes list` table.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct MailboxListTableConfig {
+    #[schemars(with = "Option<ColorSchema>")]
+    pub id_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub name_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub total_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub unread_color: Option<Color>,
+}
+
+/// `message junk` / `message not-junk` options.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct JunkConfig {
+    /// Shell command training the spam filter with a junk message,
+    /// piped to its stdin (e.g. `sa-learn --spam`). Run once per
+    /// message by `message junk`.
+    pub spam_command: Option<String>,
+    /// Shell command training the spam filter with a legitimate
+    /// message, piped to its stdin (e.g. `sa-learn --ham`). Run once
+    /// per message by `message not-junk`.
+    pub ham_command: Option<String>,
+}
+
+/// `message compose` options.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ComposeConfig {
+    /// Allow `--header` to set the `From`, `Sender`, `Date` and
+    /// `Message-ID` headers. Refused by default, as a typo there
+    /// silently changes who the message claims to come from.
+    pub allow_header_override: Option<bool>,
+    /// Spell checker the text of a message is piped through before
+    /// sending, printing the misspelled words one per line (e.g.
+    /// `aspell list` or `hunspell -l`). When it prints any, sending
+    /// is confirmed first. Skipped without a terminal.
+    pub spellcheck_command: Option<String>,
+}
+
+/// Outgoing message options.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SendConfig {
+    /// Maximum size of an outgoing message, in bytes. Bigger messages
+    /// are refused before any upload unless `--force` is passed.
+    /// Defaults to the known limit of well-known providers (e.g. 25
+    /// MB for Gmail), unlimited otherwise. Set to `0` to disable.
+    pub max_size: Option<u64>,
+    /// Blind-copies every sent message to its own `From` address.
+    pub bcc_self: Option<bool>,
+    /// Address every sent message is blind-copied to. Like
+    /// `bcc-self`, it only adds a delivery recipient: saving a copy
+    /// to a Sent mailbox (`--save`) is a separate step.
+    pub always_bcc: Option<String>,
+    /// Mailbox a copy of every sent message is saved to (e.g.
+    /// `sent`), resolved through the mailbox aliases. Overridden by
+    /// `--save-to`, skipped with `--save-to -`. No copy is saved when
+    /// unset.
+    pub save_to: Option<String>,
+}
+
+/// `message read` options.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ReadConfig {
+    /// Pager command the rendered message is piped through when
+    /// stdout is a terminal (e.g. `less -R`). Defaults to `$PAGER`,
+    /// then `less -R`. Set to an empty string to disable.
+    pub pager: Option<String>,
+    /// Charsets forcing the decoding of text parts, keyed by sender
+    /// address or domain (e.g. `"legacy.example.org" = "latin1"`),
+    /// for senders mislabeling their messages. Overridden by
+    /// `message read --charset`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub charsets: HashMap<String, String>,
+    /// Whether `message read` answers the read receipt requests
+    /// (`Disposition-Notification-To`) of the messages it renders.
+    /// Defaults to `never`.
+    pub send_read_receipts: Option<ReadReceipts>,
+}
+
+/// Read receipt policy of `message read`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReadReceipts {
+    /// Ask before sending each receipt.
+    Ask,
+    /// Never send receipts.
+    #[default]
+    Never,
+    /// Send receipts without asking, unless they would not go back
+    /// to the `Return-Path` of the message.
+    Always,
+}
+
+/// `attachments list` rendering options.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AttachmentConfig {
+    #[serde(default)]
+    pub list: AttachmentListConfig,
+}
+
+/// `attachments list` rendering options under `attachment.list.*`.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AttachmentListConfig {
+    #[serde(default)]
+    pub table: AttachmentListTableConfig,
+}
+
+/// Per-column color overrides for the `attachments list` table.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AttachmentListTableConfig {
+    #[schemars(with = "Option<ColorSchema>")]
+    pub id_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub filename_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub type_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub size_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub inline_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub path_color: Option<Color>,
+}
+
+/// `account list` rendering options. Top-level only — there is no
+/// per-account override.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AccountListingConfig {
+    #[serde(default)]
+    pub list: AccountListingListConfig,
+}
+
+/// `account list` rendering options under `account.list.*`.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AccountListingListConfig {
+    #[serde(default)]
+    pub table: AccountListingTableConfig,
+}
+
+/// Per-column color overrides for the `account list` table.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AccountListingTableConfig {
+    #[schemars(with = "Option<ColorSchema>")]
+    pub name_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub backends_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub default_color: Option<Color>,
+}
+
+/// `envelopes list` rendering options under `envelope.list.*`.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct EnvelopeListConfig {
+    /// chrono `strftime` format used to render the DATE column.
+    /// Defaults to `"%F %R%:z"` (e.g. `2026-05-06 14:30+02:00`) when
+    /// neither the global nor the account config sets it.
+    pub datetime_fmt: Option<String>,
+
+    /// When `true`, the `Date:` header timezone offset is converted
+    /// to the system's local timezone before formatting. Defaults to
+    /// `false`, which preserves the wire offset. Superseded by
+    /// `timezone`.
+    pub datetime_local_tz: Option<bool>,
+
+    /// Timezone dates are rendered in: `"original"` (the `Date:`
+    /// header offset), `"local"`, `"utc"` or an IANA zone name such
+    /// as `"Europe/Paris"`. Applies to the DATE column, the date
+    /// sections of `--group-by date` and the `message read
+    /// --metadata` hops. Defaults to `"local"` when
+    /// `datetime-local-tz` is `true`, otherwise to `"original"`.
+    #[schemars(with = "Option<String>")]
+    pub timezone: Option<TimezoneConfig>,
+
+    /// Default `-s/--page-size` value for `envelopes list`. The CLI
+    /// flag wins when passed; otherwise the merged account/global
+    /// config wins; otherwise the hard fallback (25) is used.
+    pub page_size: Option<u32>,
+
+    /// Format of `envelope list --output count-only`, where `{unseen}`
+    /// and `{total}` are replaced by the message counts of the
+    /// mailbox. Defaults to `"{unseen}/{total}"`.
+    pub count_format: Option<String>,
+
+    /// Per-column color overrides + flag glyph customization for the
+    /// rendered envelopes table. Keys mirror the v1.2.0 layout
+    /// (`envelope.list.table.id-color`, `envelope.list.table.unseen-char`,
+    /// etc.). Color values accept either a named [crossterm color]
+    /// (`"red"`, `"dark-magenta"`, …) or an `{ Rgb = { r = .., g = ..,
+    /// b = .. } }`/`{ AnsiValue = N }` table.
+    ///
+    /// [crossterm color]: https://docs.rs/crossterm/latest/crossterm/style/enum.Color.html
+    #[serde(default)]
+    pub table: EnvelopeListTableConfig,
+}
+
+/// Per-column color and flag glyph overrides for the envelopes table.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct EnvelopeListTableConfig {
+    /// Columns of the table, in order, among `id`, `account`,
+    /// `mailbox` (or `folder`), `flags`, `att`, `subject`, `from`,
+    /// `to`, `date` and `size`. Defaults to `["id", "flags", "att",
+    /// "subject", "from", "date", "size"]`.
+    ///
+    /// `att` only shows with `--has-attachment`. `from` shows the
+    /// recipients instead with `--recipient`, and when listing the
+    /// sent mailbox (`mailbox.alias.sent`) unless `to` is
+    /// configured. The `account` and `mailbox` columns only show when
+    /// several accounts or mailboxes are listed, and are then added
+    /// after `id` when missing. `--header` columns always come last.
+    pub columns: Option<Vec<EnvelopeColumn>>,
+
+    /// How the DATE column renders dates: `absolute`, with
+    /// `envelope.list.datetime-fmt`, or `relative` to now (`5m ago`,
+    /// `3h ago`, `yesterday`, `2 weeks ago`). Relative dates fall
+    /// back to the absolute format past four weeks, or when in the
+    /// future. Defaults to `absolute`.
+    pub date_format: Option<EnvelopeDateFormat>,
+
+    /// Single character used in the FLAGS column for messages that
+    /// lack `\Seen`. Defaults to `*` (v1.2.0 default).
+    pub unseen_char: Option<char>,
+    /// Single character used in the FLAGS column for messages with
+    /// `\Answered`. Defaults to `R`.
+    pub replied_char: Option<char>,
+    /// Single character used in the FLAGS column for messages with
+    /// `\Flagged`. Defaults to `!`.
+    pub flagged_char: Option<char>,
+    /// Single character used in the ATT column for messages with at
+    /// least one attachment. Defaults to `@`.
+    pub attachment_char: Option<char>,
+
+    #[schemars(with = "Option<ColorSchema>")]
+    pub id_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub flags_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub att_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub subject_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub from_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub to_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub date_color: Option<Color>,
+    #[schemars(with = "Option<ColorSchema>")]
+    pub size_color: Option<Color>,
+    /// Background color of the subject and sender portions matching
+    /// `envelope list --highlight`. Defaults to `dark-grey`.
+    #[schemars(with = "Option<ColorSchema>")]
+    pub highlight_color: Option<Color>,
+
+    /// Sender addresses whose envelopes stand out, their whole row
+    /// rendered in bold. Compared case-insensitively with every
+    /// `From:` address.
+    pub highlight_senders: Option<Vec<String>>,
+    /// Foreground color of the whole row of envelopes sent by one of
+    /// `highlight-senders`, instead of the per-column colors. Unset by
+    /// default: rows are only made bold.
+    #[schemars(with = "Option<ColorSchema>")]
+    pub highlight_senders_color: Option<Color>,
+}
+
+/// Column of the envelopes table, see
+/// [`EnvelopeListTableConfig::columns`].
+#[derive(Clone, Copy, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EnvelopeColumn {
+    Id,
+    Account,
+    #[serde(alias = "folder")]
+    Mailbox,
+    Flags,
+    Att,
+    Subject,
+    From,
+    To,
+    Date,
+    Size,
+}
+
+/// Rendering of the envelopes table DATE column, see
+/// [`EnvelopeListTableConfig::date_format`].
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EnvelopeDateFormat {
+    #[default]
+    Absolute,
+    Relative,
+}
+
+/// Global / per-account table rendering quirks shared across every list
+/// command (envelopes, mailboxes, attachments). The per-column color
+/// blocks live under `*.list.table.*-color` (see [`EnvelopeListTableConfig`]
+/// & co.).
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TableConfig {
+    /// Table borders: one of the named presets `ascii`, `rounded`,
+    /// `minimal`, `borderless` or `markdown`, or a raw `comfy_table`
+    /// preset string (chars for borders / corners / separators).
+    /// Defaults to `UTF8_FULL_CONDENSED`. See
+    /// <https://docs.rs/comfy-table/latest/comfy_table/presets/>.
+    pub preset: Option<String>,
+    /// Column-arrangement strategy. Defaults to `Dynamic`.
+    pub arrangement: Option<TableArrangementConfig>,
+}
+
+/// Rendering of the trees drawn by the shared commands, such as the
+/// MIME structure of `message read --show-structure`.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TreeConfig {
+    /// Draw the tree branches with ASCII characters (`|`, `+-`,
+    /// `` `- ``) instead of box-drawing ones (`│`, `├─`, `└─`), for
+    /// terminals and fonts lacking them. Defaults to `false`.
+    pub ascii: Option<bool>,
+}
+
+/// `UTF8_FULL_CONDENSED` with rounded corners.
+const ROUNDED_PRESET: &str = "││──╞═╪╡┆    ┬┴╭╮╰╯";
+
+/// Resolves a `table.preset` value to a `comfy_table` preset string:
+/// named presets are matched case-insensitively, anything else is
+/// passed through as a raw preset string.
+pub fn table_preset(preset: &str) -> &str {
+    match preset.to_ascii_lowercase().as_str() {
+        "ascii" => presets::ASCII_FULL_CONDENSED,
+        "rounded" => ROUNDED_PRESET,
+        "minimal" => presets::UTF8_HORIZONTAL_ONLY,
+        "borderless" => presets::NOTHING,
+        "markdown" => presets::ASCII_MARKDOWN,
+        _ => preset,
+    }
+}
+
+/// Column-arrangement strategy for rendered tables.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum TableArrangementConfig {
+    #[default]
+    Dynamic,
+    DynamicFullWidth,
+    Disabled,
+}
+
+impl From<TableArrangementConfig> for ContentArrangement {
+    fn from(arrangement: TableArrangementConfig) -> Self {
+        match arrangement {
+            TableArrangementConfig::Dynamic => ContentArrangement::Dynamic,
+            TableArrangementConfig::DynamicFullWidth => ContentArrangement::DynamicFullWidth,
+            TableArrangementConfig::Disabled => ContentArrangement::Disabled,
+        }
+    }
+}
+
+/// Timezone dates are converted to before being rendered.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum TimezoneConfig {
+    /// The offset the date was written with.
+    #[default]
+    Original,
+    Local,
+    Utc,
+    Named(Tz),
+}
+
+impl TimezoneConfig {
+    /// Formats `date` with the chrono `strftime` format `fmt`, in
+    /// this timezone.
+    pub fn format(&self, date: DateTime<FixedOffset>, fmt: &str) -> String {
+        match self {
+            Self::Original => date.format(fmt).to_string(),
+            Self::Local => date.with_timezone(&Local).format(fmt).to_string(),
+            Self::Utc => date.with_timezone(&Utc).format(fmt).to_string(),
+            Self::Named(tz) => date.with_timezone(tz).format(fmt).to_string(),
+        }
+    }
+
+    /// Calendar day of `date` in this timezone, the local one for
+    /// [`Self::Original`] so that days compare with [`Self::today`].
+    pub fn date_naive(&self, date: DateTime<FixedOffset>) -> NaiveDate {
+        match self {
+            Self::Original | Self::Local => date.with_timezone(&Local).date_naive(),
+            Self::Utc => date.with_timezone(&Utc).date_naive(),
+            Self::Named(tz) => date.with_timezone(tz).date_naive(),
+        }
+    }
+
+    /// Current calendar day in this timezone, the local one for
+    /// [`Self::Original`].
+    pub fn today(&self) -> NaiveDate {
+        match self {
+            Self::Original | Self::Local => Local::now().date_naive(),
+            Self::Utc => Utc::now().date_naive(),
+            Self::Named(tz) => Utc::now().with_timezone(tz).date_naive(),
+        }
+    }
+}
+
+impl TryFrom<String> for TimezoneConfig {
+    type Error = String;
+
+    fn try_from(tz: String) -> Result<Self, Self::Error> {
+        match tz.to_ascii_lowercase().as_str() {
+            "original" => Ok(Self::Original),
+            "local" => Ok(Self::Local),
+            "utc" => Ok(Self::Utc),
+            _ => match tz.parse() {
+                Ok(tz) => Ok(Self::Named(tz)),
+                Err(_) => Err(format!(
+                    "invalid timezone `{tz}`: expected original, local, utc or an IANA zone name"
+                )),
+            },
+        }
+    }
+}
+
+impl From<TimezoneConfig> for String {
+    fn from(tz: TimezoneConfig) -> Self {
+        match tz {
+            TimezoneConfig::Original => String::from("original"),
+            TimezoneConfig::Local => String::from("local"),
+            TimezoneConfig::Utc => String::from("utc"),
+            TimezoneConfig::Named(tz) => tz.name().to_owned(),
+        }
+    }
+}
+
+/// Parses a backend `server` config string into a [`Url`], accepting
+/// three forms: a full `scheme://host[:port][/path]` URL, a bare
+/// authority `host:port`, or a bare `host`. The last two default to
+/// `default_scheme` (the protocol's secure scheme).
+///
+/// A bare `host:port` must be detected by the absence of `://`: the
+/// URL parser would otherwise read it as `scheme:path` (e.g.
+/// `mail.example.com:993` parses as scheme `mail.example.com`), so any
+/// string without an explicit `://` is treated as an authority. The
+/// resulting scheme is validated against `allowed`.
+pub fn parse_server(server: &str, default_scheme: &str, allowed: &[&str]) -> Result<Url> {
+    let url = if server.contains("://") {
+        Url::parse(server)?
+    } else {
+        Url::parse(&format!("{default_scheme}://{server}"))?
+    };
+
+    let scheme = url.scheme();
+
+    if !allowed.contains(&scheme) {
+        bail!("Invalid server scheme `{scheme}`: expected one of {allowed:?}");
+    }
+
+    Ok(url)
+}
+
+/// IMAP configuration.
+#[allow(unused)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ImapConfig {
+    /// IMAP server address. Either a bare authority
+    /// (`imap.example.com[:port]`, treated as `imaps://<authority>` by
+    /// default), or a full URL with `imap://` (cleartext, with
+    /// optional STARTTLS upgrade) or `imaps://` (implicit TLS) scheme
+    /// used verbatim. Mirrors [`JmapConfig::server`].
+    pub server: String,
+
+    #[serde(default)]
+    pub tls: TlsConfig,
+    #[serde(default)]
+    pub starttls: bool,
+
+    /// ALPN protocol identifiers offered during the TLS handshake.  Defaults to
+    /// `["imap"]` (RFC 7595, IANA registry). Set to `[]` to skip ALPN
+    /// negotiation entirely. Only relevant for the rustls provider;
+    /// `native-tls` ignores ALPN.
+    #[serde(default = "io_imap::client::default_alpn")]
+    pub alpn: Vec<String>,
+
+    /// Optional SASL credentials. When omitted, the connection skips
+    /// authentication entirely (no `AUTHENTICATE` command is sent); to
+    /// advertise the ANONYMOUS mechanism explicitly, set `sasl.anonymous = {}`.
+    pub sasl: Option<SaslConfig>,
+
+    /// RFC 2971 `ID` extension quirks. Some providers (notably mail.qq.com,
+    /// fastmail) require an `ID` exchange straight after authentication; set
+    /// `id.auto = true` to opt in.
+    #[serde(default)]
+    pub id: ImapIdConfig,
+
+    /// RFC 5256 `SORT` extension config.
+    #[serde(default)]
+    pub sort: ImapSortConfig,
+
+    /// Mailbox hierarchy delimiter (e.g. `/` or `.`). Left unset, it
+    /// is detected from the server's LIST response. Set it for
+    /// servers reporting an unusual delimiter, so hierarchical paths
+    /// built by `imap rename` nest correctly.
+    pub hierarchy_delimiter: Option<char>,
+}
+
+/// Per-account `imap.sort.*` options.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ImapSortConfig {
+    /// Forces the SORT fallback on or off. `Some(true)` always sorts
+    /// client-side via SEARCH + FETCH; `Some(false)` always issues a server
+    /// `SORT`. Left unset, the fallback is enabled only when the server lacks
+    /// the SORT capability.
+    pub fallback: Option<bool>,
+}
+
+/// Per-account `imap.id.*` quirks.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ImapIdConfig {
+    /// When `true`, the auth coroutine chains an `ID` round-trip
+    /// after the tagged auth response. Default `false` skips ID
+    /// entirely.
+    #[serde(default)]
+    pub auto: bool,
+
+    /// Parameters sent with the auto-ID command. Empty (default)
+    /// sends `ID NIL`. For each entry: `true` substitutes himalaya's
+    /// canned value for the well-known keys (`name`, `version`,
+    /// `vendor`, `support-url`) or `NIL` for unknown keys; `false`
+    /// always sends `NIL`. Keys absent from this map are not
+    /// transmitted.
+    #[serde(default)]
+    pub fields: HashMap<String, bool>,
+}
+
+/// Maildir configuration.
+#[allow(unused)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct MaildirConfig {
+    pub root: PathBuf,
+    /// Use the Maildir++ layout (Dovecot, Courier): the root is the
+    /// Inbox, and subfolders are dot-prefixed siblings such as
+    /// `.Work.Clients` for `Work/Clients`. Applies to the `maildir`
+    /// commands.
+    #[serde(default)]
+    pub maildirpp: bool,
+}
+
+/// m2dir configuration.
+#[allow(unused)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct M2dirConfig {
+    pub root: PathBuf,
+}
+
+/// SMTP configuration.
+#[allow(unused)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SmtpConfig {
+    /// SMTP server address. Either a bare authority
+    /// (`smtp.example.com[:port]`, treated as `smtps://<authority>`
+    /// by default), or a full URL with `smtp://` (cleartext, with
+    /// optional STARTTLS upgrade) or `smtps://` (implicit TLS) scheme
+    /// used verbatim. Mirrors [`JmapConfig::server`].
+    pub server: String,
+
+    #[serde(default)]
+    pub tls: TlsConfig,
+    #[serde(default)]
+    pub starttls: bool,
+
+    /// ALPN protocol identifiers offered during the TLS handshake.
+    /// Defaults to `["smtp"]` (RFC 7595, IANA registry). Set to `[]`
+    /// to skip ALPN negotiation entirely. Only relevant for the
+    /// rustls provider; `native-tls` ignores ALPN.
+    #[serde(default = "io_smtp::client::default_alpn")]
+    pub alpn: Vec<String>,
+
+    /// Optional SASL credentials. See [`ImapConfig::sasl`].
+    pub sasl: Option<SaslConfig>,
+}
+
+/// SSL/TLS configuration.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TlsConfig {
+    pub provider: Option<TlsProviderConfig>,
+    #[serde(default)]
+    pub rustls: RustlsConfig,
+    /// Extra root certificate (PEM) trusted on top of the system
+    /// ones, for servers using a self-signed certificate or a
+    /// private CA. Also accepted as `ca-cert`.
+    #[serde(alias = "ca-cert")]
+    pub cert: Option<PathBuf>,
+    /// Client certificate (PEM) presented to servers requiring one.
+    /// Goes with `client-key`.
+    pub client_cert: Option<PathBuf>,
+    /// Private key (PEM) of `client-cert`.
+    pub client_key: Option<PathBuf>,
+    /// Skip the verification of the server certificate. Insecure:
+    /// anyone on the path can read the credentials, prefer trusting
+    /// the server certificate with `ca-cert`.
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+}
+
+/// SSL/TLS provider configuration.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum TlsProviderConfig {
+    Rustls,
+    NativeTls,
+}
+
+/// Rustls configuration.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RustlsConfig {
+    pub crypto: Option<RustlsCryptoConfig>,
+}
+
+/// Rustls crypto provider configuration.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum RustlsCryptoConfig {
+    Aws,
+    Ring,
+}
+
+impl TlsConfig {
+    /// Builds the runtime [`Tls`] handle the connect helpers expect.
+    /// `alpn` is the protocol-level ALPN list (e.g. `["imap"]`,
+    /// `["smtp"]`, `["http/1.1"]`); pass an empty vec to skip ALPN.
+    /// The TOML schema never exposes `tls.rustls.alpn` directly: the
+    /// per-protocol `*.alpn` field is folded in here.
+    ///
+    /// Fails on the options the [`Tls`] handle cannot carry yet
+    /// (client certificates, skipped verification), rather than
+    /// connecting without them.
+    pub fn into_tls(self, alpn: Vec<String>) -> Result<Tls> {
+        if self.accept_invalid_certs {
+            warn!(
+                "tls.accept-invalid-certs is set: the server certificate would NOT be verified, \
+                 anyone on the network path could read your credentials and messages"
+            );
+            bail!(
+                "`tls.accept-invalid-certs` is not supported by the TLS layer, \
+                 trust the server certificate with `tls.ca-cert` instead"
+            );
+        }
+
+        match (&self.client_cert, &self.client_key) {
+            (None, None) => (),
+            (Some(_), None) => bail!("`tls.client-cert` is set without `tls.client-key`"),
+            (None, Some(_)) => bail!("`tls.client-key` is set without `tls.client-cert`"),
+            (Some(cert), Some(key)) => {
+                for path in [cert, key] {
+                    if !path.is_file() {
+                        bail!("Cannot find TLS client file `{}`", path.display());
+                    }
+                }
+
+                bail!("`tls.client-cert` and `tls.client-key` are not supported by the TLS layer");
+            }
+        }
+
+        Ok(Tls {
+            provider: self.provider.map(|p| match p {
+                TlsProviderConfig::Rustls => TlsProvider::Rustls,
+                TlsProviderConfig::NativeTls => TlsProvider::NativeTls,
+            }),
+            rustls: Rustls {
+                crypto: self.rustls.crypto.map(|c| match c {
+                    RustlsCryptoConfig::Aws => RustlsCrypto::Aws,
+                    RustlsCryptoConfig::Ring => RustlsCrypto::Ring,
+                }),
+                alpn,
+            },
+            cert: self.cert,
+        })
+    }
+}
+
+/// SASL configuration.
+///
+/// Exactly one mechanism per `[*.sasl]` block. Each variant carries
+/// only the bits its mechanism actually transmits; serde picks the
+/// variant from the field name (`plain`, `login`, `anonymous`,
+/// `oauthbearer`, `xoauth2`, `scram-sha-256`).
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum SaslConfig {
+    Anonymous(SaslAnonymousConfig),
+    Login(SaslLoginConfig),
+    Plain(SaslPlainConfig),
+    Oauthbearer(SaslOauthbearerConfig),
+    Xoauth2(SaslXoauth2Config),
+    #[serde(rename = "scram-sha-256")]
+    ScramSha256(SaslScramSha256Config),
+}
+
+/// SASL ANONYMOUS configuration <sup>[rfc4505]</sup>.
+///
+/// [rfc4505]: https://www.iana.org/go/rfc4505
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SaslAnonymousConfig {
+    pub message: Option<String>,
+}
+
+/// SASL LOGIN configuration <sup>[draft]</sup>.
+///
+/// [draft]: https://datatracker.ietf.org/doc/html/draft-murchison-sasl-login-00
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SaslLoginConfig {
+    #[serde(deserialize_with = "shell_expanded_string")]
+    pub username: String,
+    #[schemars(with = "SecretSchema")]
+    pub password: Secret,
+}
+
+/// SASL PLAIN configuration <sup>[rfc4616]</sup>.
+///
+/// [rfc4616]: https://www.iana.org/go/rfc4616
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SaslPlainConfig {
+    pub authzid: Option<String>,
+    #[serde(deserialize_with = "shell_expanded_string")]
+    #[serde(alias = "username")]
+    pub authcid: String,
+    #[serde(alias = "password")]
+    #[schemars(with = "SecretSchema")]
+    pub passwd: Secret,
+}
+
+/// SASL OAUTHBEARER configuration <sup>[rfc7628]</sup>.
+///
+/// The `host` and `port` echoed in the GS2 header are derived from
+/// the live IMAP/SMTP server URL at connect time, so they aren't part
+/// of the user-facing config.
+///
+/// [rfc7628]: https://www.iana.org/go/rfc7628
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SaslOauthbearerConfig {
+    #[serde(deserialize_with = "shell_expanded_string")]
+    pub username: String,
+    #[schemars(with = "SecretSchema")]
+    pub token: Secret,
+}
+
+/// SASL XOAUTH2 configuration. Google's pre-standard OAuth 2.0 SASL
+/// scheme; see <https://developers.google.com/gmail/imap/xoauth2-protocol>.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SaslXoauth2Config {
+    #[serde(deserialize_with = "shell_expanded_string")]
+    pub username: String,
+    #[schemars(with = "SecretSchema")]
+    pub token: Secret,
+}
+
+/// SASL SCRAM-SHA-256 configuration <sup>[rfc7677]</sup>.
+///
+/// [rfc7677]: https://www.iana.org/go/rfc7677
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SaslScramSha256Config {
+    #[serde(deserialize_with = "shell_expanded_string")]
+    pub username: String,
+    #[schemars(with = "SecretSchema")]
+    pub password: Secret,
+}
+
+impl SaslConfig {
+    /// Resolves the SASL config into a runtime [`Sasl`]. `host` and
+    /// `port` come from the live server URL; they are only used by
+    /// OAUTHBEARER (echoed in the GS2 header) and ignored by every
+    /// other mechanism.
+    pub fn try_into_sasl(self, host: impl ToString, port: u16) -> Result<Sasl> {
+        Ok(match self {
+            SaslConfig::Anonymous(c) => Sasl::Anonymous(SaslAnonymous { message: c.message }),
+            SaslConfig::Login(c) => Sasl::Login(SaslLogin {
+                username: c.username,
+                password: c.password.get()?,
+            }),
+            SaslConfig::Plain(c) => Sasl::Plain(SaslPlain {
+                authzid: c.authzid,
+                authcid: c.authcid,
+                passwd: c.passwd.get()?,
+            }),
+            SaslConfig::Oauthbearer(c) => Sasl::Oauthbearer(SaslOauthbearer {
+                username: c.username,
+                host: host.to_string(),
+                port,
+                token: c.token.get()?,
+            }),
+            SaslConfig::Xoauth2(c) => Sasl::Xoauth2(SaslXoauth2 {
+                username: c.username,
+                token: c.token.get()?,
+            }),
+            SaslConfig::ScramSha256(c) => Sasl::ScramSha256(SaslScramSha256 {
+                username: c.username,
+                password: c.password.get()?,
+            }),
+        })
+    }
+}
+
+/// JMAP configuration.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct JmapConfig {
+    /// The JMAP server address.
+    ///
+    /// Accepts either a bare authority (`fastmail.com`, `mail.example.com:8080`)
+    /// for automatic discovery via `GET /.well-known/jmap`, or a full URL
+    /// (`https://api.fastmail.com/jmap/api/`) to connect directly to the
+    /// session endpoint. Supported schemes: `http`, `https`, `jmap` (→ http),
+    /// `jmaps` (→ https).
+    pub server: String,
+
+    /// TLS configuration.
+    #[serde(default)]
+    pub tls: TlsConfig,
+
+    /// ALPN protocol identifiers offered during the TLS handshake.
+    /// Defaults to `["http/1.1"]` (JMAP rides on HTTP/1.1). Set to
+    /// `[]` to skip ALPN negotiation entirely. Only relevant for the
+    /// rustls provider; `native-tls` ignores ALPN.
+    #[serde(default = "io_jmap::client::default_alpn")]
+    pub alpn: Vec<String>,
+
+    /// Authentication configuration.
+    pub auth: JmapAuthConfig,
+
+    /// Identity id used by `messages send` to submit emails. Required
+    /// only for JMAP send; can be discovered with `himalaya jmap
+    /// identity get`.
+    pub identity_id: Option<String>,
+
+    /// Drafts mailbox id used by `messages send` to stage emails before
+    /// submission. Required only for JMAP send; can be discovered with
+    /// `himalaya jmap mailbox query --role drafts`.
+    pub drafts_mailbox_id: Option<String>,
+}
+
+/// JMAP authentication configuration.
+// https://www.iana.org/assignments/http-authschemes/http-authschemes.xhtml#authschemes
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum JmapAuthConfig {
+    /// Full raw Authorization header value, sent verbatim.
+    Header(#[schemars(with = "SecretSchema")] Secret),
+    /// Bearer token (OAuth 2.0 access token).
+    Bearer { token: Secret },
+    /// HTTP Basic authentication (username + password).
+    Basic {
+        #[serde(deserialize_with = "shell_expanded_string")]
+        username: String,
+        #[schemars(with = "SecretSchema")]
+        password: Secret,
+    },
+}
+
+/// Gmail REST API configuration.
+///
+/// Gmail has no per-account server address: the client always talks to
+/// `https://gmail.googleapis.com`. Only the mailbox owner, TLS and the
+/// OAuth 2.0 credential are configurable.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct GmailConfig {
+    /// Gmail user id (the mailbox owner). Defaults to `me`, the
+    /// authenticated user.
+    #[serde(default = "default_gmail_user_id")]
+    pub user_id: String,
+
+    /// TLS configuration.
+    #[serde(default)]
+    pub tls: TlsConfig,
+
+    /// ALPN protocol identifiers offered during the TLS handshake.
+    /// Defaults to `["http/1.1"]` (the Gmail REST API rides on
+    /// HTTP/1.1). Set to `[]` to skip ALPN negotiation entirely. Only
+    /// relevant for the rustls provider; `native-tls` ignores ALPN.
+    #[serde(default = "default_gmail_alpn")]
+    pub alpn: Vec<String>,
+
+    /// Authentication configuration.
+    pub auth: GmailAuthConfig,
+}
+
+fn default_gmail_user_id() -> String {
+    String::from("me")
+}
+
+fn default_gmail_alpn() -> Vec<String> {
+    vec![String::from("http/1.1")]
+}
+
+/// Gmail authentication configuration.
+///
+/// Gmail only accepts OAuth 2.0 bearer tokens; supply a short-lived
+/// access token (e.g. minted by an external helper such as `ortie`).
+/// Token refresh is the caller's responsibility.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct GmailAuthConfig {
+    /// OAuth 2.0 bearer access token; sent as `Bearer <token>`. It is
+    /// the only authorization Gmail's REST API accepts.
+    #[schemars(with = "SecretSchema")]
+    pub token: Secret,
+}
+
+/// Microsoft Graph API configuration.
+///
+/// Graph has no per-account server address: the client always talks to
+/// `https://graph.microsoft.com`. Only the mailbox owner, TLS and the
+/// OAuth 2.0 credential are configurable.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct MsgraphConfig {
+    /// Graph user id (the mailbox owner). Defaults to `me`, the
+    /// authenticated user; set it to a user id or principal name to
+    /// target another mailbox.
+    #[serde(default = "default_msgraph_user_id")]
+    pub user_id: String,
+
+    /// TLS configuration.
+    #[serde(default)]
+    pub tls: TlsConfig,
+
+    /// ALPN protocol identifiers offered during the TLS handshake.
+    /// Defaults to `["http/1.1"]` (the Graph API rides on HTTP/1.1). Set
+    /// to `[]` to skip ALPN negotiation entirely. Only relevant for the
+    /// rustls provider; `native-tls` ignores ALPN.
+    #[serde(default = "default_msgraph_alpn")]
+    pub alpn: Vec<String>,
+
+    /// Authentication configuration.
+    pub auth: MsgraphAuthConfig,
+}
+
+fn default_msgraph_user_id() -> String {
+    String::from("me")
+}
+
+fn default_msgraph_alpn() -> Vec<String> {
+    vec![String::from("http/1.1")]
+}
+
+/// Microsoft Graph authentication configuration.
+///
+/// Graph only accepts OAuth 2.0 bearer tokens; supply a short-lived
+/// access token (e.g. minted by an external helper such as `ortie`).
+/// Token refresh is the caller's responsibility.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct MsgraphAuthConfig {
+    /// OAuth 2.0 bearer access token; sent as `Bearer <token>`. It is
+    /// the only authorization the Graph API accepts.
+    #[schemars(with = "SecretSchema")]
+    pub token: Secret,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMAP: &[&str] = &["imap", "imaps"];
+
+    #[test]
+    fn bare_host_defaults_to_secure_scheme() {
+        let url = parse_server("mail.example.com", "imaps", IMAP).unwrap();
+        assert_eq!(url.scheme(), "imaps");
+        assert_eq!(url.host_str(), Some("mail.example.com"));
+        // No explicit port: the protocol's default (e.g. 993) is
+        // applied by the backend client, not by this parser.
+        assert_eq!(url.port(), None);
+    }
+
+    #[test]
+    fn bare_host_port_keeps_port_and_secure_scheme() {
+        let url = parse_server("mail.example.com:1993", "imaps", IMAP).unwrap();
+        assert_eq!(url.scheme(), "imaps");
+        assert_eq!(url.host_str(), Some("mail.example.com"));
+        assert_eq!(url.port(), Some(1993));
+    }
+
+    #[test]
+    fn full_url_scheme_host_port_is_kept_verbatim() {
+        let url = parse_server("imap://mail.example.com:143", "imaps", IMAP).unwrap();
+        assert_eq!(url.scheme(), "imap");
+        assert_eq!(url.host_str(), Some("mail.example.com"));
+        assert_eq!(url.port(), Some(143));
+    }
+
+    #[test]
+    fn unknown_scheme_is_rejected() {
+        let err = parse_server("ftp://mail.example.com", "imaps", IMAP).unwrap_err();
+        assert!(err.to_string().contains("Invalid server scheme `ftp`"));
+    }
+
+    #[test]
+    fn path_is_preserved_for_full_url() {
+        let url = parse_server("https://example.com/jmap/session", "https", &["https"]).unwrap();
+        assert_eq!(url.scheme(), "https");
+        assert_eq!(url.path(), "/jmap/session");
+    }
+
+    #[test]
+    fn unknown_field_suggests_closest_key() {
+        let msg = "TOML parse error at line 5, column 1\n  |\n5 | id-colr = \"red\"\n  | ^^^^^^^\n\
+                   unknown field `id-colr`, expected one of `id-color`, `name-color`";
+        let (key, expected) = parse_unknown_field(msg).unwrap();
+        assert_eq!(key, "id-colr");
+        assert_eq!(expected, ["id-color", "name-color"]);
+        assert_eq!(parse_location(msg), Some((5, 1)));
+        assert_eq!(closest_key(&key, &expected), Some("id-color"));
+    }
+
+    #[test]
+    fn unknown_field_without_close_key() {
+        let msg = "unknown field `foo`, expected `server` or `tls`";
+        let (key, expected) = parse_unknown_field(msg).unwrap();
+        assert_eq!(expected, ["server", "tls"]);
+        assert_eq!(closest_key(&key, &expected), None);
+    }
+
+    #[test]
+    fn config_dirs_are_walked_in_order() {
+        let root = env::temp_dir().join(format!("himalaya-config-dirs-{}", std::process::id()));
+        let (project, home) = (root.join("project"), root.join("home"));
+        fs::create_dir_all(&project).unwrap();
+        fs::create_dir_all(&home).unwrap();
+        fs::write(home.join("config.toml"), "").unwrap();
+
+        let dirs = [project.clone(), home.clone()];
+        assert_eq!(find_in_dirs(&dirs), Some(home.join("config.toml")));
+
+        fs::write(project.join(".himalaya.toml"), "").unwrap();
+        assert_eq!(find_in_dirs(&dirs), Some(project.join(".himalaya.toml")));
+
+        let explicit = [PathBuf::from("/etc/himalaya.toml")];
+        assert_eq!(Config::resolve_paths(&explicit, &dirs), explicit);
+
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(find_in_dirs(&dirs), None);
+    }
+
+    #[test]
+    fn project_config_is_found_in_ancestors() {
+        let root = env::temp_dir().join(format!("himalaya-project-{}", std::process::id()));
+        let nested = root.join("project").join("src").join("bin");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(".himalaya.toml"), "").unwrap();
+
+        assert_eq!(
+            find_project_config(&nested),
+            Some(root.join(".himalaya.toml"))
+        );
+
+        fs::write(root.join("project").join(".himalaya.toml"), "").unwrap();
+        let closest = root.join("project").join(".himalaya.toml");
+        assert_eq!(find_project_config(&nested), Some(closest));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn project_config_cannot_run_commands() {
+        let table = |toml: &str| toml.parse::<toml::Table>().unwrap();
+
+        let config = table("[accounts.work]\nimap.sasl.plain.password.command = \"pass work\"");
+        assert_eq!(
+            command_key(&config).as_deref(),
+            Some("accounts.work.imap.sasl.plain.password.command")
+        );
+
+        let config = table("[accounts.work.junk]\nspam-command = \"sa-learn --spam\"");
+        assert_eq!(
+            command_key(&config).as_deref(),
+            Some("accounts.work.junk.spam-command")
+        );
+
+        let config = table("[accounts.work]\nread.pager = \"less\"");
+        assert_eq!(
+            command_key(&config).as_deref(),
+            Some("accounts.work.read.pager")
+        );
+
+        let config = table("[accounts.work]\ndefault = true\nimap.sasl.plain.password.raw = \"x\"");
+        assert_eq!(command_key(&config), None);
+    }
+
+    #[test]
+    fn project_config_cannot_redirect_accounts() {
+        let table = |toml: &str| toml.parse::<toml::Table>().unwrap();
+
+        let config = table("[accounts.work]\nimap.server = \"imaps://evil.example.org\"");
+        assert_eq!(backend_key(&config).as_deref(), Some("accounts.work.imap"));
+
+        let config = table("[accounts.work.smtp]\nserver = \"smtp://evil.example.org:25\"");
+        assert_eq!(backend_key(&config).as_deref(), Some("accounts.work.smtp"));
+
+        let config = table("[accounts.work]\njmap.server = \"https://evil.example.org\"");
+        assert_eq!(backend_key(&config).as_deref(), Some("accounts.work.jmap"));
+
+        let config =
+            table("[accounts.work]\ndownloads-dir = \"~/work\"\nmailbox.alias.inbox = \"Work\"");
+        assert_eq!(backend_key(&config), None);
+    }
+
+    #[test]
+    fn timezone_renders_dates_in_the_configured_zone() {
+        let date = DateTime::parse_from_rfc3339("2026-05-06T23:30:00+02:00").unwrap();
+
+        let tz = TimezoneConfig::try_from(String::from("UTC")).unwrap();
+        assert_eq!(tz.format(date, "%F %R%:z"), "2026-05-06 21:30+00:00");
+
+        let tz = TimezoneConfig::try_from(String::from("America/New_York")).unwrap();
+        assert_eq!(tz.format(date, "%F %R"), "2026-05-06 17:30");
+        assert_eq!(String::from(tz), "America/New_York");
+
+        let tz = TimezoneConfig::Original;
+        assert_eq!(tz.format(date, "%F %R%:z"), "2026-05-06 23:30+02:00");
+
+        assert!(TimezoneConfig::try_from(String::from("Mars/Olympus")).is_err());
+    }
+
+    fn accounts(accounts: &[(&str, bool)]) -> Config {
+        let mut config = Config::default();
+
+        for (name, default) in accounts {
+            let account = AccountConfig {
+                default: *default,
+                ..Default::default()
+            };
+            config.accounts.insert(name.to_string(), account);
+        }
+
+        config
+    }
+
+    #[test]
+    fn default_account_selection() {
+        assert!(accounts(&[]).default_account().is_err());
+        assert_eq!(accounts(&[("a", false)]).default_account().unwrap(), "a");
+        assert_eq!(accounts(&[("a", true)]).default_account().unwrap(), "a");
+
+        let config = accounts(&[("a", false), ("b", true)]);
+        assert_eq!(config.default_account().unwrap(), "b");
+
+        let err = accounts(&[("b", false), ("a", false)]).default_account();
+        assert!(err.unwrap_err().to_string().contains("among a, b"));
+
+        let mut config = accounts(&[("b", true), ("a", true), ("c", false)]);
+        let err = config.default_account().unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Accounts a, b are all set as default")
+        );
+        assert!(config.take_default_account().is_none());
+        assert_eq!(config.select_account(Some("c")).unwrap().0, "c");
+    }
+}
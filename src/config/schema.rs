@@ -0,0 +1,90 @@
+use std::{borrow::Cow, fmt};
+
+use anyhow::Result;
+use clap::Parser;
+use pimalaya_cli::printer::Printer;
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema, schema_for};
+use serde::Serialize;
+
+use crate::config::Config;
+
+/// Print the JSON Schema of the TOML configuration file.
+///
+/// The schema describes every global and per-account option accepted
+/// by the configuration, and is written to stdout. Point your editor
+/// at it (e.g. via taplo or Even Better TOML) to get completion and
+/// validation while editing `config.toml`.
+#[derive(Debug, Parser)]
+pub struct ConfigSchemaCommand;
+
+impl ConfigSchemaCommand {
+    pub fn execute(self, printer: &mut impl Printer) -> Result<()> {
+        printer.out(ConfigSchema(schema_for!(Config)))
+    }
+}
+
+/// JSON Schema of [`Config`], rendered as pretty JSON in both text
+/// and JSON output modes.
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct ConfigSchema(Schema);
+
+impl fmt::Display for ConfigSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match serde_json::to_string_pretty(&self.0) {
+            Ok(s) => write!(f, "{s}"),
+            Err(e) => write!(f, "<serialization error: {e}>"),
+        }
+    }
+}
+
+/// Schema stand-in for [`pimalaya_config::secret::Secret`], which
+/// does not implement [`JsonSchema`].
+///
+/// A secret is either a `raw` value or a shell `command` (a single
+/// string or an argv array) whose output is the secret.
+pub struct SecretSchema;
+
+impl JsonSchema for SecretSchema {
+    fn schema_name() -> Cow<'static, str> {
+        "Secret".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Secret value, either given raw or read from the output of a shell command.",
+            "type": "object",
+            "properties": {
+                "raw": {
+                    "type": "string",
+                },
+                "command": {
+                    "anyOf": [
+                        { "type": "string" },
+                        { "type": "array", "items": { "type": "string" } },
+                    ],
+                },
+            },
+            "minProperties": 1,
+            "maxProperties": 1,
+            "additionalProperties": false,
+        })
+    }
+}
+
+/// Schema stand-in for [`crossterm::style::Color`], which does not
+/// implement [`JsonSchema`].
+pub struct ColorSchema;
+
+impl JsonSchema for ColorSchema {
+    fn schema_name() -> Cow<'static, str> {
+        "Color".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Terminal color: a named color (`reset`, `black`, `red`, `dark-yellow`, …), `ansi_(value)`, `rgb_(r,g,b)` or `#rrggbb`.",
+            "type": "string",
+        })
+    }
+}
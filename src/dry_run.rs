@@ -0,0 +1,68 @@
+//! `--dry-run`: the shared commands changing messages (flag add, set
+//! and remove, message move, junk and not-junk) and the deleting
+//! protocol-specific ones (`imap store` and `expunge`, `jmap email
+//! delete`, `maildir delete`, `m2dir delete`) resolve their mailboxes
+//! and messages as usual, then report what they would change instead
+//! of calling the backend. Read-only commands run as usual, other
+//! commands refuse it.
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use serde::Serialize;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turns dry-run on for the rest of the process.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether commands should only report their changes.
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Change a command would have made, printed instead of making it.
+#[derive(Debug, Serialize)]
+pub struct DryRun {
+    /// What would be done to the messages, e.g. `move to Archive`.
+    action: String,
+    /// Backend id of the mailbox the messages are in.
+    mailbox: String,
+    /// Ids of the messages, empty when the action applies to the
+    /// mailbox itself.
+    ids: Vec<String>,
+}
+
+impl DryRun {
+    pub fn new(action: impl Into<String>, mailbox: &str, ids: &[String]) -> Self {
+        Self {
+            action: action.into(),
+            mailbox: mailbox.to_owned(),
+            ids: ids.to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ids.is_empty() {
+            return write!(
+                f,
+                "Dry run, nothing changed: would {} mailbox {}",
+                self.action, self.mailbox
+            );
+        }
+
+        write!(
+            f,
+            "Dry run, nothing changed: would {}: message(s) {} of mailbox {}",
+            self.action,
+            self.ids.join(", "),
+            self.mailbox
+        )
+    }
+}
@@ -42,6 +42,19 @@ pub enum GmailCommand {
 }
 
 impl GmailCommand {
+    /// Whether the command creates, deletes or renames mailboxes,
+    /// which invalidates the `mailbox list` cache.
+    pub fn changes_mailboxes(&self) -> bool {
+        matches!(
+            self,
+            Self::Labels(
+                GmailLabelsCommand::Create(_)
+                    | GmailLabelsCommand::Update(_)
+                    | GmailLabelsCommand::Delete(_)
+            )
+        )
+    }
+
     pub fn execute(
         self,
         printer: &mut impl Printer,
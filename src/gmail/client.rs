@@ -11,18 +11,14 @@
 //!
 //! [`GmailClientStd::connect`]: io_gmail::v1::client::GmailClientStd::connect
 
-use std::{
-    ops::{Deref, DerefMut},
-    path::PathBuf,
-};
+use std::ops::{Deref, DerefMut};
 
 use anyhow::{Result, anyhow};
 use io_gmail::v1::client::{GmailClientStd as Inner, GmailClientStdConnectOptions};
-use pimalaya_config::toml::TomlConfig;
 use secrecy::{ExposeSecret, SecretString};
 
 use crate::{
-    account::context::Account, cli::load_or_wizard, config::GmailAuthConfig, config::GmailConfig,
+    account::context::Account, config::Config, config::GmailAuthConfig, config::GmailConfig,
 };
 
 /// Live Gmail client handed down to every `gmail` subcommand.
@@ -35,7 +31,7 @@ impl GmailClient {
     /// (`https://gmail.googleapis.com`) with the configured bearer
     /// credential and user id.
     pub fn new(config: GmailConfig) -> Result<Self> {
-        let tls = config.tls.into_tls(config.alpn);
+        let tls = config.tls.into_tls(config.alpn)?;
         let token = gmail_token(config.auth)?;
         let options = GmailClientStdConnectOptions {
             tls,
@@ -60,17 +56,14 @@ impl DerefMut for GmailClient {
     }
 }
 
-/// Loads the configuration, picks the active account, builds the
+/// Picks the active account of `config`, builds the
 /// merged [`Account`] then opens the Gmail client. Bails when the
 /// account has no `[gmail]` block.
 pub fn build_gmail_client(
-    config_paths: &[PathBuf],
+    mut config: Config,
     account_name: Option<&str>,
 ) -> Result<(Account, GmailClient)> {
-    let mut config = load_or_wizard(config_paths)?;
-    let (name, mut ac) = config
-        .take_account(account_name)?
-        .ok_or_else(|| anyhow!("Cannot find account"))?;
+    let (name, mut ac) = config.select_account(account_name)?;
     let gmail_config = ac
         .gmail
         .take()
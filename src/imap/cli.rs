@@ -18,10 +18,11 @@ use crate::imap::{
         list::ImapMailboxListCommand, rename::ImapMailboxRenameCommand,
         select::ImapMailboxSelectCommand, status::ImapMailboxStatusCommand,
         subscribe::ImapMailboxSubscribeCommand, unselect::ImapMailboxUnselectCommand,
-        unsubscribe::ImapMailboxUnsubscribeCommand,
+        unsubscribe::ImapMailboxUnsubscribeCommand, watch::ImapMailboxWatchCommand,
     },
     message::{
-        copy::ImapMessageCopyCommand, r#move::ImapMessageMoveCommand, save::ImapMessageSaveCommand,
+        copy::ImapMessageCopyCommand, export::ImapMessageExportCommand,
+        r#move::ImapMessageMoveCommand, save::ImapMessageSaveCommand,
     },
     raw::ImapRawCommand,
 };
@@ -48,6 +49,7 @@ pub enum ImapCommand {
     Close(ImapMailboxCloseCommand),
     Unselect(ImapMailboxUnselectCommand),
     Expunge(ImapMailboxExpungeCommand),
+    Watch(ImapMailboxWatchCommand),
 
     // Search and ordering.
     Search(ImapEnvelopeSearchCommand),
@@ -60,6 +62,7 @@ pub enum ImapCommand {
 
     // Message data.
     Fetch(ImapFetchCommand),
+    Export(ImapMessageExportCommand),
     Append(ImapMessageSaveCommand),
     Copy(ImapMessageCopyCommand),
     Move(ImapMessageMoveCommand),
@@ -69,6 +72,18 @@ pub enum ImapCommand {
 }
 
 impl ImapCommand {
+    /// Whether the command creates, deletes or renames mailboxes,
+    /// which invalidates the `mailbox list` cache.
+    pub fn changes_mailboxes(&self) -> bool {
+        matches!(self, Self::Create(_) | Self::Delete(_) | Self::Rename(_))
+    }
+
+    /// Whether the command reports the messages it would change
+    /// under `--dry-run`.
+    pub fn supports_dry_run(&self) -> bool {
+        matches!(self, Self::Store(_) | Self::Expunge(_))
+    }
+
     pub fn execute(
         self,
         printer: &mut impl Printer,
@@ -89,15 +104,17 @@ impl ImapCommand {
             Self::Close(cmd) => cmd.execute(printer, client),
             Self::Unselect(cmd) => cmd.execute(printer, client),
             Self::Expunge(cmd) => cmd.execute(printer, client),
+            Self::Watch(cmd) => cmd.execute(printer, client),
 
             Self::Search(cmd) => cmd.execute(printer, account, client),
             Self::Sort(cmd) => cmd.execute(printer, account, client),
-            Self::Thread(cmd) => cmd.execute(printer, client),
+            Self::Thread(cmd) => cmd.execute(printer, account, client),
 
             Self::Store(cmd) => cmd.execute(printer, client),
             Self::Flags(cmd) => cmd.execute(printer, account, client),
 
             Self::Fetch(cmd) => cmd.execute(printer, client),
+            Self::Export(cmd) => cmd.execute(printer, client),
             Self::Append(cmd) => cmd.execute(printer, client),
             Self::Copy(cmd) => cmd.execute(printer, client),
             Self::Move(cmd) => cmd.execute(printer, client),
@@ -5,21 +5,16 @@
 //! [`build_imap_client`] and hands the ready-to-use wrapper down,
 //! together with the merged [`Account`] as a sibling argument.
 
-use std::{
-    ops::{Deref, DerefMut},
-    path::PathBuf,
-};
+use std::ops::{Deref, DerefMut};
 
 use anyhow::{Result, anyhow};
 use io_imap::{client::ImapClientStd as Inner, has_imap_capability, types::response::Capability};
-use pimalaya_config::toml::TomlConfig;
 use pimalaya_stream::sasl::Sasl;
 use url::Url;
 
 use crate::{
     account::context::Account,
-    cli::load_or_wizard,
-    config::{ImapConfig, parse_server},
+    config::{Config, ImapConfig, parse_server},
     imap::id::resolve_auto_id_params,
 };
 
@@ -29,6 +24,9 @@ pub struct ImapClient {
     inner: Inner,
     capabilities: Vec<Capability<'static>>,
     sort_fallback: Option<bool>,
+    hierarchy_delimiter: Option<char>,
+    /// Kept to connect again with [`ImapClient::reconnect`].
+    config: ImapConfig,
 }
 
 impl ImapClient {
@@ -36,8 +34,10 @@ impl ImapClient {
     /// caching the capability list reported by the handshake and the
     /// `imap.sort.fallback` config override for later policy checks.
     pub fn new(config: ImapConfig) -> Result<Self> {
+        let kept_config = config.clone();
         let sort_fallback = config.sort.fallback;
-        let tls = config.tls.into_tls(config.alpn);
+        let hierarchy_delimiter = config.hierarchy_delimiter;
+        let tls = config.tls.into_tls(config.alpn)?;
         let auto_id = resolve_auto_id_params(&config.id)?;
         let server = parse_imap_server(&config.server)?;
         let sasl: Option<Sasl> = match config.sasl {
@@ -60,9 +60,18 @@ impl ImapClient {
             inner,
             capabilities,
             sort_fallback,
+            hierarchy_delimiter,
+            config: kept_config,
         })
     }
 
+    /// Replaces the session with a fresh one, opened from the same
+    /// config, e.g. after the server dropped the connection.
+    pub fn reconnect(&mut self) -> Result<()> {
+        *self = Self::new(self.config.clone())?;
+        Ok(())
+    }
+
     /// Resolves the SORT fallback policy: the `imap.sort.fallback`
     /// config override when set, otherwise on only when the server
     /// lacks the SORT capability. When `true`, sort client-side via
@@ -71,6 +80,34 @@ impl ImapClient {
         self.sort_fallback
             .unwrap_or_else(|| !has_imap_capability!(self.capabilities, Sort(_)))
     }
+
+    /// Whether the server advertised the IDLE capability (RFC 2177).
+    pub fn has_idle(&self) -> bool {
+        has_imap_capability!(self.capabilities, Idle)
+    }
+
+    /// Resolves the hierarchy delimiter of `mailbox` (of the root
+    /// when empty): the `imap.hierarchy-delimiter` config override
+    /// when set, otherwise the one reported by LIST. `None` for flat
+    /// namespaces.
+    pub fn hierarchy_delimiter(&mut self, mailbox: &str) -> Result<Option<char>> {
+        if let Some(delim) = self.hierarchy_delimiter {
+            return Ok(Some(delim));
+        }
+
+        detect_hierarchy_delimiter(&mut self.inner, mailbox)
+    }
+}
+
+/// Detects the hierarchy delimiter of `mailbox` from the server's
+/// LIST response. An empty `mailbox` queries the root (`LIST ""
+/// ""`, RFC 3501 §6.3.8).
+pub fn detect_hierarchy_delimiter(client: &mut Inner, mailbox: &str) -> Result<Option<char>> {
+    let mailboxes = client.list(String::new().try_into()?, mailbox.to_owned().try_into()?)?;
+    let delimiter = mailboxes
+        .into_iter()
+        .find_map(|(_, delim, _)| delim.map(|delim| delim.inner()));
+    Ok(delimiter)
 }
 
 /// Parses an IMAP server string into a URL.
@@ -96,19 +133,16 @@ impl DerefMut for ImapClient {
     }
 }
 
-/// Loads the configuration, picks the active account, builds the
+/// Picks the active account of `config`, builds the
 /// merged [`Account`] then opens the IMAP session. Bails when the
 /// account has no `[imap]` block. Returns the live client paired
 /// with the merged account so subcommands receive both as sibling
 /// arguments.
 pub fn build_imap_client(
-    config_paths: &[PathBuf],
+    mut config: Config,
     account_name: Option<&str>,
 ) -> Result<(Account, ImapClient)> {
-    let mut config = load_or_wizard(config_paths)?;
-    let (name, mut ac) = config
-        .take_account(account_name)?
-        .ok_or_else(|| anyhow!("Cannot find account"))?;
+    let (name, mut ac) = config.select_account(account_name)?;
     let imap_config = ac
         .imap
         .take()
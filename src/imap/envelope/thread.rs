@@ -2,6 +2,7 @@ use std::{collections::HashMap, fmt, num::NonZeroU32};
 
 use anyhow::Result;
 use clap::{Parser, ValueEnum};
+use comfy_table::{Cell, Color, ContentArrangement, Row, Table};
 use io_imap::{
     rfc3501::{fetch::ImapMessageFetchOptions, select::ImapMailboxSelectOptions},
     rfc5256::thread::ImapMessageThreadOptions,
@@ -14,11 +15,14 @@ use io_imap::{
 use pimalaya_cli::printer::Printer;
 use serde::{Serialize, Serializer, ser::SerializeStruct};
 
-use crate::imap::{
-    client::ImapClient,
-    envelope::search::SearchCriteriaArgs,
-    mailbox::arg::{MailboxNameOptionalFlag, MailboxNoSelectFlag},
-    utils::decode_mime,
+use crate::{
+    account::context::Account,
+    imap::{
+        client::ImapClient,
+        envelope::search::SearchCriteriaArgs,
+        mailbox::arg::{MailboxNameOptionalFlag, MailboxNoSelectFlag},
+        utils::decode_mime,
+    },
 };
 
 /// Thread IMAP messages (THREAD, RFC 5256).
@@ -47,10 +51,22 @@ pub struct ImapEnvelopeThreadCommand {
     /// Use sequence numbers instead of UIDs.
     #[arg(long)]
     pub seq: bool,
+
+    /// Print a flat table instead of a tree.
+    ///
+    /// Messages keep the thread order, each reply right after the
+    /// message it answers, with its depth in the thread as a column.
+    #[arg(long)]
+    pub flat: bool,
 }
 
 impl ImapEnvelopeThreadCommand {
-    pub fn execute(self, printer: &mut impl Printer, client: &mut ImapClient) -> Result<()> {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        account: &mut Account,
+        client: &mut ImapClient,
+    ) -> Result<()> {
         let mailbox = self.mailbox_name.inner.try_into()?;
 
         if !self.mailbox_no_select.inner {
@@ -74,6 +90,18 @@ impl ImapEnvelopeThreadCommand {
 
         let table = ThreadResultsTable::new(threads, subjects);
 
+        if self.flat {
+            let table = ThreadFlatTable {
+                preset: account.table_preset().to_string(),
+                arrangement: account.table_arrangement(),
+                id_color: account.envelopes_list_table_id_color(),
+                uid_mode: !self.seq,
+                threads: table.build_entries(),
+            };
+
+            return printer.out(table);
+        }
+
         printer.out(table)
     }
 }
@@ -309,3 +337,47 @@ impl Serialize for ThreadResultsTable {
         s.end()
     }
 }
+
+/// Renderable table of THREAD results, in thread order.
+#[derive(Clone, Debug, Serialize)]
+pub struct ThreadFlatTable {
+    #[serde(skip)]
+    preset: String,
+    #[serde(skip)]
+    arrangement: ContentArrangement,
+    #[serde(skip)]
+    id_color: Color,
+    #[serde(skip)]
+    uid_mode: bool,
+    threads: Vec<ThreadEntry>,
+}
+
+impl fmt::Display for ThreadFlatTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut table = Table::new();
+
+        let id_header = if self.uid_mode { "UID" } else { "SEQ" };
+
+        table
+            .load_preset(&self.preset)
+            .set_content_arrangement(self.arrangement.clone())
+            .set_header(Row::from([
+                Cell::new(id_header),
+                Cell::new("DEPTH"),
+                Cell::new("SUBJECT"),
+            ]));
+
+        for entry in &self.threads {
+            table.add_row(Row::from([
+                Cell::new(entry.id).fg(self.id_color),
+                Cell::new(entry.depth),
+                Cell::new(&entry.subject),
+            ]));
+        }
+
+        writeln!(f)?;
+        write!(f, "{table}")?;
+        writeln!(f)?;
+        writeln!(f, "Found {} message(s)", self.threads.len())
+    }
+}
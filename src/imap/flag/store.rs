@@ -1,14 +1,20 @@
 use anyhow::Result;
 use clap::{Parser, ValueEnum};
 use io_imap::{
-    rfc3501::{select::ImapMailboxSelectOptions, store::ImapMessageStoreOptions},
+    rfc3501::{
+        search::ImapMessageSearchOptions, select::ImapMailboxSelectOptions,
+        store::ImapMessageStoreOptions,
+    },
     types::{
         IntoStatic,
+        core::Vec1,
         flag::{Flag, StoreType},
+        search::SearchKey,
     },
 };
 use pimalaya_cli::printer::{Message, Printer};
 
+use crate::dry_run::{self, DryRun};
 use crate::imap::{
     client::ImapClient,
     mailbox::arg::{MailboxNameOptionalFlag, MailboxNoSelectFlag},
@@ -18,6 +24,12 @@ use crate::imap::{
 ///
 /// Adds (`+FLAGS`), removes (`-FLAGS`) or replaces (`FLAGS`) the given
 /// flags on every message in the sequence set, depending on --action.
+///
+/// Storing `\Deleted` only marks messages for deletion: they stay in
+/// the mailbox, and `--action remove` restores them, until `expunge`
+/// (or `close`) removes them for good. This is the mark-only
+/// counterpart of a delete. With `--dry-run`, reports the UIDs of the
+/// messages of the sequence set instead.
 #[derive(Debug, Parser)]
 pub struct ImapStoreCommand {
     #[command(flatten)]
@@ -52,6 +64,16 @@ pub enum StoreActionArg {
     Set,
 }
 
+impl StoreActionArg {
+    fn verb(self) -> &'static str {
+        match self {
+            Self::Add => "add",
+            Self::Remove => "remove",
+            Self::Set => "set",
+        }
+    }
+}
+
 impl From<StoreActionArg> for StoreType {
     fn from(action: StoreActionArg) -> Self {
         match action {
@@ -64,6 +86,7 @@ impl From<StoreActionArg> for StoreType {
 
 impl ImapStoreCommand {
     pub fn execute(self, printer: &mut impl Printer, client: &mut ImapClient) -> Result<()> {
+        let name = self.mailbox_name.inner.clone();
         let mailbox = self.mailbox_name.inner.try_into()?;
 
         if !self.mailbox_no_select.inner {
@@ -77,6 +100,24 @@ impl ImapStoreCommand {
             .map(|f| Flag::try_from(f.as_str()).map(|flag| flag.into_static()))
             .collect::<Result<_, _>>()?;
 
+        if dry_run::enabled() {
+            let set = self.sequence_set.as_str().try_into()?;
+            let key = match self.seq {
+                true => SearchKey::SequenceSet(set),
+                false => SearchKey::Uid(set),
+            };
+
+            let uids: Vec<String> = client
+                .search(Vec1::from(key), ImapMessageSearchOptions { uid: true })?
+                .into_iter()
+                .map(|uid| uid.to_string())
+                .collect();
+
+            let flags = self.flag.join(" ");
+            let action = format!("{} flags {flags}", self.action.verb());
+            return printer.out(DryRun::new(action, &name, &uids));
+        }
+
         client.store(
             sequence_set,
             self.action.into(),
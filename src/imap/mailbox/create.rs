@@ -2,21 +2,52 @@ use anyhow::Result;
 use clap::Parser;
 use pimalaya_cli::printer::{Message, Printer};
 
-use crate::imap::{client::ImapClient, mailbox::arg::MailboxNameArg};
+use crate::imap::{
+    client::ImapClient,
+    mailbox::{arg::MailboxNameArg, rename::create_parents},
+};
 
 /// Create the given mailbox (CREATE, RFC 3501).
 ///
-/// Creates a new mailbox with the given name.
+/// Creates a new mailbox with the given name. Missing parents of a
+/// hierarchical name (e.g. `Archive/2024/Receipts`) are created
+/// first, for servers that require them to exist. Pass `--subscribe`
+/// to subscribe to the new mailbox right away.
 #[derive(Debug, Parser)]
 pub struct ImapMailboxCreateCommand {
     #[command(flatten)]
     pub mailbox_name: MailboxNameArg,
+
+    /// Subscribe to the mailbox once created (SUBSCRIBE).
+    #[arg(long)]
+    pub subscribe: bool,
 }
 
 impl ImapMailboxCreateCommand {
     pub fn execute(self, printer: &mut impl Printer, client: &mut ImapClient) -> Result<()> {
-        let mailbox = self.mailbox_name.inner.try_into()?;
-        client.create(mailbox)?;
-        printer.out(Message::new("Mailbox successfully created"))
+        let mailbox = self.mailbox_name.inner;
+
+        let mut created = match client.hierarchy_delimiter("")? {
+            Some(delim) => create_parents(client, &mailbox, delim)?,
+            None => Vec::new(),
+        };
+
+        client.create(mailbox.clone().try_into()?)?;
+
+        if self.subscribe {
+            client.subscribe(mailbox.clone().try_into()?)?;
+        }
+
+        created.push(mailbox);
+
+        let mut msg = String::from("Mailbox(es) successfully created:");
+        for name in &created {
+            msg.push_str(&format!("\n - {name}"));
+        }
+        if self.subscribe {
+            msg.push_str("\nMailbox successfully subscribed");
+        }
+
+        printer.out(Message::new(msg))
     }
 }
@@ -1,8 +1,12 @@
 use anyhow::Result;
 use clap::Parser;
-use io_imap::rfc3501::select::ImapMailboxSelectOptions;
+use io_imap::{
+    rfc3501::{search::ImapMessageSearchOptions, select::ImapMailboxSelectOptions},
+    types::{core::Vec1, search::SearchKey},
+};
 use pimalaya_cli::printer::{Message, Printer};
 
+use crate::dry_run::{self, DryRun};
 use crate::imap::{
     client::ImapClient,
     mailbox::arg::{MailboxNameArg, MailboxNoSelectFlag},
@@ -11,6 +15,7 @@ use crate::imap::{
 /// Expunge the given mailbox (EXPUNGE, RFC 3501).
 ///
 /// Permanently removes every message flagged \Deleted from the mailbox.
+/// With `--dry-run`, reports the UIDs of these messages instead.
 #[derive(Debug, Parser)]
 pub struct ImapMailboxExpungeCommand {
     #[command(flatten)]
@@ -21,12 +26,26 @@ pub struct ImapMailboxExpungeCommand {
 
 impl ImapMailboxExpungeCommand {
     pub fn execute(self, printer: &mut impl Printer, client: &mut ImapClient) -> Result<()> {
+        let name = self.mailbox_name.inner.clone();
         let mailbox = self.mailbox_name.inner.try_into()?;
 
         if !self.mailbox_no_select.inner {
             client.select(mailbox, ImapMailboxSelectOptions::default())?;
         }
 
+        if dry_run::enabled() {
+            let uids: Vec<String> = client
+                .search(
+                    Vec1::from(SearchKey::Deleted),
+                    ImapMessageSearchOptions { uid: true },
+                )?
+                .into_iter()
+                .map(|uid| uid.to_string())
+                .collect();
+
+            return printer.out(DryRun::new("expunge", &name, &uids));
+        }
+
         client.expunge()?;
 
         printer.out(Message::new("Mailbox successfully expunged"))
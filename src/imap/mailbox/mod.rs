@@ -10,3 +10,4 @@ pub mod status;
 pub mod subscribe;
 pub mod unselect;
 pub mod unsubscribe;
+pub mod watch;
@@ -1,15 +1,22 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
 use clap::Parser;
 use pimalaya_cli::printer::{Message, Printer};
 
 use crate::imap::{
     client::ImapClient,
-    mailbox::arg::{MailboxNameArg, TargetMailboxNameArg},
+    mailbox::{
+        arg::{MailboxNameArg, TargetMailboxNameArg},
+        list::MailboxRow,
+    },
 };
 
 /// Rename the given mailbox (RENAME, RFC 3501).
 ///
-/// Renames an existing mailbox to a new name.
+/// Renames an existing mailbox to a new name. A hierarchical target
+/// (e.g. `Archive/Clients/OldClient`) moves the mailbox under a
+/// different parent: missing parents are created first, and the
+/// subfolders that the server moves along with the mailbox are
+/// checked to be reachable under the new name afterward.
 #[derive(Debug, Parser)]
 pub struct ImapMailboxRenameCommand {
     #[command(flatten)]
@@ -20,9 +27,133 @@ pub struct ImapMailboxRenameCommand {
 
 impl ImapMailboxRenameCommand {
     pub fn execute(self, printer: &mut impl Printer, client: &mut ImapClient) -> Result<()> {
-        let from = self.mailbox_source_name.inner.try_into()?;
-        let to = self.mailbox_dest_name.inner.try_into()?;
-        client.rename(from, to)?;
+        let source = self.mailbox_source_name.inner;
+        let target = self.mailbox_dest_name.inner;
+
+        let delimiter = client.hierarchy_delimiter(&source)?;
+        let children = match delimiter {
+            Some(delim) => list_children(client, &source, delim)?,
+            None => Vec::new(),
+        };
+
+        if let Some(delim) = delimiter {
+            create_parents(client, &target, delim)?;
+        }
+
+        client.rename(source.clone().try_into()?, target.clone().try_into()?)?;
+
+        if let Some(delim) = delimiter {
+            let moved = list_children(client, &target, delim)?;
+            let missing = missing_children(&children, &moved);
+
+            if !missing.is_empty() {
+                bail!(
+                    "Mailbox renamed, but subfolder(s) not found under `{target}`: {}",
+                    missing.join(", ")
+                );
+            }
+        }
+
         printer.out(Message::new("Mailbox successfully renamed"))
     }
 }
+
+/// Names of every descendant of `mailbox`, relative to it.
+fn list_children(client: &mut ImapClient, mailbox: &str, delim: char) -> Result<Vec<String>> {
+    let pattern = format!("{mailbox}{delim}*");
+    let mailboxes = client.list(String::new().try_into()?, pattern.try_into()?)?;
+
+    let children = mailboxes
+        .into_iter()
+        .map(MailboxRow::from)
+        .filter_map(|row| child_name(&row.name, mailbox, delim).map(str::to_owned))
+        .collect();
+
+    Ok(children)
+}
+
+/// Name of the mailbox `name` relative to its ancestor `mailbox`,
+/// `None` when it is not a descendant of it.
+fn child_name<'a>(name: &'a str, mailbox: &str, delim: char) -> Option<&'a str> {
+    name.strip_prefix(mailbox)?
+        .strip_prefix(delim)
+        .filter(|child| !child.is_empty())
+}
+
+/// Children found before the rename that are not among the `moved`
+/// ones listed under the new name.
+fn missing_children<'a>(children: &'a [String], moved: &[String]) -> Vec<&'a str> {
+    children
+        .iter()
+        .filter(|child| !moved.contains(child))
+        .map(String::as_str)
+        .collect()
+}
+
+/// Creates the missing ancestors of `mailbox`, for servers that do
+/// not create them on CREATE or RENAME (RFC 3501 §6.3.3 and §6.3.5
+/// only say SHOULD). Returns the names of the created mailboxes.
+pub(super) fn create_parents(
+    client: &mut ImapClient,
+    mailbox: &str,
+    delim: char,
+) -> Result<Vec<String>> {
+    let mut created = Vec::new();
+
+    for parent in ancestors(mailbox, delim) {
+        let existing = client.list(String::new().try_into()?, parent.clone().try_into()?)?;
+
+        if existing.is_empty() {
+            client.create(parent.clone().try_into()?)?;
+            created.push(parent);
+        }
+    }
+
+    Ok(created)
+}
+
+/// Ancestors of `mailbox`, outermost first: `Archive` then
+/// `Archive/Clients` for `Archive/Clients/OldClient`.
+fn ancestors(mailbox: &str, delim: char) -> Vec<String> {
+    mailbox
+        .match_indices(delim)
+        .map(|(i, _)| mailbox[..i].to_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_target_parents() {
+        assert_eq!(
+            ancestors("Archive/Clients/OldClient", '/'),
+            ["Archive", "Archive/Clients"]
+        );
+        assert_eq!(
+            ancestors("INBOX.Archive.2024", '.'),
+            ["INBOX", "INBOX.Archive"]
+        );
+        assert!(ancestors("Archive", '/').is_empty());
+    }
+
+    #[test]
+    fn checks_children_under_the_target() {
+        assert_eq!(
+            child_name("Work/OldClient/2024", "Work/OldClient", '/'),
+            Some("2024")
+        );
+        assert_eq!(
+            child_name("Work/OldClient/2024/Q1", "Work/OldClient", '/'),
+            Some("2024/Q1")
+        );
+        assert_eq!(child_name("Work/OldClientX", "Work/OldClient", '/'), None);
+        assert_eq!(child_name("Work/OldClient", "Work/OldClient", '/'), None);
+
+        let children = [String::from("2024"), String::from("2024/Q1")];
+        let moved = [String::from("2024")];
+        assert_eq!(missing_children(&children, &moved), ["2024/Q1"]);
+        assert!(missing_children(&children, &children).is_empty());
+    }
+}
@@ -0,0 +1,128 @@
+use std::{thread, time::Duration};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use io_imap::types::status::{StatusDataItem, StatusDataItemName};
+use log::{debug, warn};
+use pimalaya_cli::printer::{Message, Printer};
+
+use crate::imap::{client::ImapClient, mailbox::arg::MailboxNameArg};
+
+/// Watch the given mailbox for new messages (STATUS polling).
+///
+/// Polls the mailbox UIDNEXT every `--interval` seconds without
+/// selecting it, and prints a line each time new messages arrive.
+/// Runs until interrupted.
+///
+/// IMAP IDLE (RFC 2177) is not used yet, even on servers advertising
+/// it: the IMAP client does not expose an IDLE session, so
+/// notifications are delayed by up to one interval.
+///
+/// When the connection drops, it is opened again up to 5 times in a
+/// row, waiting longer before each attempt, before giving up.
+#[derive(Debug, Parser)]
+pub struct ImapMailboxWatchCommand {
+    #[command(flatten)]
+    pub mailbox_name: MailboxNameArg,
+
+    /// Seconds between two polls.
+    #[arg(long, short, value_name = "SECS", default_value = "60")]
+    #[arg(value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+}
+
+impl ImapMailboxWatchCommand {
+    pub fn execute(self, printer: &mut impl Printer, client: &mut ImapClient) -> Result<()> {
+        let name = self.mailbox_name.inner;
+        let interval = Duration::from_secs(self.interval);
+
+        if client.has_idle() {
+            debug!("server supports IDLE, but the client cannot use it yet: polling");
+        }
+
+        let mut last = poll(client, &name)?;
+        debug!("watching mailbox {name} every {}s", self.interval);
+
+        loop {
+            thread::sleep(interval);
+
+            let next = poll_or_reconnect(client, &name)?;
+
+            // NOTE: a new UIDVALIDITY invalidates every known UID, so
+            // only report arrivals within the same validity
+            if next.uid_validity == last.uid_validity && next.uid_next > last.uid_next {
+                let count = next.uid_next - last.uid_next;
+                printer.out(Message::new(format!(
+                    "{count} new message(s) in mailbox {name}"
+                )))?;
+            }
+
+            last = next;
+        }
+    }
+}
+
+/// UID values of a mailbox, compared between two polls.
+#[derive(Clone, Copy, Debug, Default)]
+struct Snapshot {
+    uid_next: u32,
+    uid_validity: u32,
+}
+
+/// Reconnection attempts in a row before the watch gives up.
+const RECONNECT_ATTEMPTS: u32 = 5;
+
+/// Polls `name`, reconnecting when the poll fails, which is how a
+/// dropped connection shows.
+fn poll_or_reconnect(client: &mut ImapClient, name: &str) -> Result<Snapshot> {
+    let mut attempt = 0;
+
+    loop {
+        let err = match poll(client, name) {
+            Ok(snapshot) => return Ok(snapshot),
+            Err(err) => err,
+        };
+
+        if attempt == RECONNECT_ATTEMPTS {
+            return Err(err).context(format!(
+                "Watch mailbox {name} error, giving up after {RECONNECT_ATTEMPTS} reconnections"
+            ));
+        }
+
+        attempt += 1;
+
+        // NOTE: 2, 4, 8… seconds, so a server restarting has time to
+        // come back
+        let delay = Duration::from_secs(1 << attempt);
+        warn!("cannot poll mailbox {name}: {err:#}");
+        warn!(
+            "reconnecting in {}s ({attempt}/{RECONNECT_ATTEMPTS})",
+            delay.as_secs()
+        );
+        thread::sleep(delay);
+
+        if let Err(err) = client.reconnect() {
+            warn!("cannot reconnect: {err:#}");
+        }
+    }
+}
+
+fn poll(client: &mut ImapClient, name: &str) -> Result<Snapshot> {
+    let mailbox = name.to_owned().try_into()?;
+    let items = client.status(
+        mailbox,
+        vec![StatusDataItemName::UidNext, StatusDataItemName::UidValidity],
+    )?;
+
+    let mut snapshot = Snapshot::default();
+
+    for item in items {
+        match item {
+            StatusDataItem::UidNext(n) => snapshot.uid_next = n.get(),
+            StatusDataItem::UidValidity(n) => snapshot.uid_validity = n.get(),
+            _ => (),
+        }
+    }
+
+    Ok(snapshot)
+}
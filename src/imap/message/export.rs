@@ -0,0 +1,156 @@
+use std::{
+    io::{self, BufWriter, Write},
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use clap::Parser;
+use io_imap::{
+    rfc3501::{fetch::ImapMessageFetchOptions, select::ImapMailboxSelectOptions},
+    types::fetch::{MacroOrMessageDataItemNames, MessageDataItem, MessageDataItemName},
+};
+use pimalaya_cli::printer::{Message, Printer};
+use tempfile::NamedTempFile;
+
+use crate::imap::{
+    client::ImapClient,
+    mailbox::arg::{MailboxNameOptionalFlag, MailboxNoSelectFlag},
+};
+
+/// Export a raw message chunk by chunk (partial FETCH, RFC 3501).
+///
+/// Downloads the RFC 5322 bytes with successive `BODY.PEEK[]<offset.size>`
+/// fetches and writes each chunk as soon as it arrives, so memory use
+/// is bounded by the chunk size instead of the message size (useful
+/// for messages with big attachments on small devices). Writes to
+/// `--output` when given, otherwise to stdout. Does not set `\Seen`.
+///
+/// The `--output` file is only replaced once the whole message is
+/// downloaded: an unknown UID or a failed fetch leaves it untouched.
+#[derive(Debug, Parser)]
+pub struct ImapMessageExportCommand {
+    #[command(flatten)]
+    pub mailbox_name: MailboxNameOptionalFlag,
+    #[command(flatten)]
+    pub mailbox_no_select: MailboxNoSelectFlag,
+
+    /// The UID of the message.
+    #[arg(value_name = "UID")]
+    pub uid: NonZeroU32,
+
+    /// Write the message to this file instead of stdout.
+    #[arg(long, short, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// Size of each fetched chunk, in bytes.
+    #[arg(long, value_name = "BYTES", default_value = "1048576")]
+    pub chunk_size: NonZeroU32,
+}
+
+impl ImapMessageExportCommand {
+    pub fn execute(self, printer: &mut impl Printer, client: &mut ImapClient) -> Result<()> {
+        let mailbox = self.mailbox_name.inner.try_into()?;
+
+        if !self.mailbox_no_select.inner {
+            client.select(mailbox, ImapMailboxSelectOptions::default())?;
+        }
+
+        let tmp = match &self.output {
+            Some(path) => Some(temp_file(path)?),
+            None => None,
+        };
+
+        let mut out: Box<dyn Write> = match &tmp {
+            Some(tmp) => Box::new(BufWriter::new(tmp.as_file())),
+            None => Box::new(io::stdout().lock()),
+        };
+
+        let uid = self.uid.to_string();
+        let mut offset = 0;
+
+        loop {
+            let chunk = fetch_chunk(client, &uid, offset, self.chunk_size)?;
+
+            let Some(chunk) = chunk else {
+                if offset == 0 {
+                    bail!("Cannot find message with UID {uid}");
+                }
+                break;
+            };
+
+            out.write_all(&chunk).context("Write message chunk error")?;
+            offset += chunk.len() as u32;
+
+            if chunk.len() < self.chunk_size.get() as usize {
+                break;
+            }
+        }
+
+        out.flush().context("Flush message error")?;
+        drop(out);
+
+        let (Some(path), Some(tmp)) = (self.output, tmp) else {
+            return Ok(());
+        };
+
+        tmp.persist(&path)
+            .with_context(|| format!("Write `{}` error", path.display()))?;
+
+        printer.out(Message::new(format!(
+            "Exported {offset} bytes to {}",
+            path.display()
+        )))
+    }
+}
+
+/// Temporary file in the directory of `path`, so that it can be
+/// renamed over it once the message is written. Removed when dropped
+/// before that.
+fn temp_file(path: &Path) -> Result<NamedTempFile> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+
+    tempfile::Builder::new()
+        .prefix(".himalaya-export-")
+        .tempfile_in(dir)
+        .with_context(|| format!("Create temporary file in `{}` error", dir.display()))
+}
+
+/// Fetches `size` bytes of the message `uid` starting at `offset`.
+/// Returns `None` when the server sent no data (unknown UID, or
+/// offset past the end of the message).
+fn fetch_chunk(
+    client: &mut ImapClient,
+    uid: &str,
+    offset: u32,
+    size: NonZeroU32,
+) -> Result<Option<Vec<u8>>> {
+    let item = MessageDataItemName::BodyExt {
+        section: None,
+        partial: Some((offset, size)),
+        peek: true,
+    };
+
+    let data = client.fetch(
+        uid.try_into()?,
+        MacroOrMessageDataItemNames::MessageDataItemNames(vec![item]),
+        ImapMessageFetchOptions {
+            uid: true,
+            modifiers: Vec::new(),
+        },
+    )?;
+
+    let chunk = data
+        .into_iter()
+        .flat_map(|(_, items)| items.into_iter())
+        .find_map(|item| match item {
+            MessageDataItem::BodyExt { data, .. } => data.0.map(|data| data.as_ref().to_vec()),
+            _ => None,
+        })
+        .filter(|chunk| !chunk.is_empty());
+
+    Ok(chunk)
+}
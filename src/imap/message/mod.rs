@@ -1,3 +1,4 @@
 pub mod copy;
+pub mod export;
 pub mod r#move;
 pub mod save;
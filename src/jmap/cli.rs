@@ -42,6 +42,25 @@ pub enum JmapCommand {
 }
 
 impl JmapCommand {
+    /// Whether the command creates, deletes or renames mailboxes,
+    /// which invalidates the `mailbox list` cache.
+    pub fn changes_mailboxes(&self) -> bool {
+        matches!(
+            self,
+            Self::Mailbox(
+                JmapMailboxCommand::Create(_)
+                    | JmapMailboxCommand::Update(_)
+                    | JmapMailboxCommand::Destroy(_)
+            )
+        )
+    }
+
+    /// Whether the command reports the emails it would change under
+    /// `--dry-run`.
+    pub fn supports_dry_run(&self) -> bool {
+        matches!(self, Self::Email(JmapEmailCommand::Delete(_)))
+    }
+
     pub fn execute(
         self,
         printer: &mut impl Printer,
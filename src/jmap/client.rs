@@ -5,22 +5,17 @@
 //! [`build_jmap_client`] and handed down to every JMAP-specific
 //! subcommand.
 
-use std::{
-    ops::{Deref, DerefMut},
-    path::PathBuf,
-};
+use std::ops::{Deref, DerefMut};
 
 use anyhow::{Result, anyhow};
 use base64::{Engine, prelude::BASE64_STANDARD};
 use io_jmap::client::JmapClientStd as Inner;
-use pimalaya_config::toml::TomlConfig;
 use secrecy::{ExposeSecret, SecretString};
 use url::Url;
 
 use crate::{
     account::context::Account,
-    cli::load_or_wizard,
-    config::{JmapAuthConfig, JmapConfig, parse_server},
+    config::{Config, JmapAuthConfig, JmapConfig, parse_server},
 };
 
 /// Live JMAP session paired with the merged account configuration.
@@ -63,19 +58,16 @@ impl DerefMut for JmapClient {
     }
 }
 
-/// Loads the configuration, picks the active account, builds the
+/// Picks the active account of `config`, builds the
 /// merged [`Account`] then opens the JMAP session. Bails when the
 /// account has no `[jmap]` block. Returns the live client paired
 /// with the merged account so subcommands receive both as sibling
 /// arguments.
 pub fn build_jmap_client(
-    config_paths: &[PathBuf],
+    mut config: Config,
     account_name: Option<&str>,
 ) -> Result<(Account, JmapClient)> {
-    let mut config = load_or_wizard(config_paths)?;
-    let (name, mut ac) = config
-        .take_account(account_name)?
-        .ok_or_else(|| anyhow!("Cannot find account"))?;
+    let (name, mut ac) = config.select_account(account_name)?;
     let jmap_config = ac
         .jmap
         .take()
@@ -1,11 +1,15 @@
 use anyhow::{Result, bail};
 use clap::Parser;
 use io_jmap::rfc8621::email::set::JmapEmailSetArgs;
+use log::warn;
 use pimalaya_cli::printer::{Message, Printer};
 
+use crate::dry_run::{self, DryRun};
 use crate::jmap::{client::JmapClient, error::format_set_error};
 
 /// Delete JMAP emails (Email/set destroy).
+///
+/// With `--dry-run`, reports the ids of the emails found instead.
 #[derive(Debug, Parser)]
 pub struct JmapEmailDestroyCommand {
     /// Email ID(s) to delete.
@@ -15,6 +19,19 @@ pub struct JmapEmailDestroyCommand {
 
 impl JmapEmailDestroyCommand {
     pub fn execute(self, printer: &mut impl Printer, client: &mut JmapClient) -> Result<()> {
+        // NOTE: JMAP email ids are global to the account, hence the
+        // `*` mailbox of the report
+        if dry_run::enabled() {
+            let output = client.email_get(self.ids, Default::default())?;
+
+            for id in output.not_found {
+                warn!("email `{id}` not found, ignoring it");
+            }
+
+            let ids: Vec<String> = output.emails.into_iter().filter_map(|e| e.id).collect();
+            return printer.out(DryRun::new("delete", "*", &ids));
+        }
+
         let mut args = JmapEmailSetArgs::default();
 
         for id in self.ids {
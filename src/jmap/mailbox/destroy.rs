@@ -1,11 +1,21 @@
+use std::io::{IsTerminal, stdin};
+
 use anyhow::{Result, bail};
 use clap::Parser;
 use io_jmap::rfc8621::mailbox::set::JmapMailboxSetArgs;
-use pimalaya_cli::printer::{Message, Printer};
+use pimalaya_cli::{
+    printer::{Message, Printer},
+    prompt,
+};
 
 use crate::jmap::{client::JmapClient, error::format_set_error};
 
 /// Delete a JMAP mailbox.
+///
+/// With `--purge`, the emails of the mailbox are destroyed too: the
+/// id of each mailbox must then be typed back to confirm, unless
+/// `--yes` is given. Without a terminal to confirm on, the command
+/// refuses to purge.
 #[derive(Debug, Parser)]
 pub struct JmapMailboxDestroyCommand {
     /// The ID of the mailbox to delete.
@@ -15,10 +25,18 @@ pub struct JmapMailboxDestroyCommand {
     /// Destroy all emails in the mailbox when deleting.
     #[arg(long, default_value_t)]
     pub purge: bool,
+
+    /// Purge without asking for confirmation.
+    #[arg(long, short = 'y', alias = "force", requires = "purge")]
+    pub yes: bool,
 }
 
 impl JmapMailboxDestroyCommand {
     pub fn execute(self, printer: &mut impl Printer, client: &mut JmapClient) -> Result<()> {
+        if self.purge && !self.yes {
+            confirm_purge(&self.ids)?;
+        }
+
         let args = JmapMailboxSetArgs {
             destroy: Some(self.ids.clone()),
             on_destroy_remove_emails: if self.purge { Some(true) } else { None },
@@ -41,3 +59,22 @@ impl JmapMailboxDestroyCommand {
         printer.out(Message::new("Mailbox successfully deleted"))
     }
 }
+
+/// Asks to type back each mailbox id before purging it, and bails
+/// when one does not match or when there is no terminal to ask on.
+fn confirm_purge(ids: &[String]) -> Result<()> {
+    if !stdin().is_terminal() {
+        bail!("Refusing to purge mailbox(es) without a terminal to confirm on, pass --yes");
+    }
+
+    for id in ids {
+        let question =
+            format!("All emails of mailbox {id} will be destroyed, type its id to confirm:");
+
+        if prompt::text::<&str>(&question, None)?.trim() != id.as_str() {
+            bail!("Purge of mailbox {id} not confirmed, nothing deleted");
+        }
+    }
+
+    Ok(())
+}
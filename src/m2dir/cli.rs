@@ -26,6 +26,18 @@ pub enum M2dirCommand {
 }
 
 impl M2dirCommand {
+    /// Whether the command creates, deletes or renames mailboxes,
+    /// which invalidates the `mailbox list` cache.
+    pub fn changes_mailboxes(&self) -> bool {
+        matches!(self, Self::Create(_) | Self::Delete(_))
+    }
+
+    /// Whether the command reports the folders it would change under
+    /// `--dry-run`.
+    pub fn supports_dry_run(&self) -> bool {
+        matches!(self, Self::Delete(_))
+    }
+
     pub fn execute(
         self,
         printer: &mut impl Printer,
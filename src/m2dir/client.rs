@@ -1,15 +1,11 @@
 //! Himalaya wrapper around [`io_m2dir::client::M2dirClient`].
 
-use std::{
-    ops::{Deref, DerefMut},
-    path::PathBuf,
-};
+use std::ops::{Deref, DerefMut};
 
 use anyhow::{Result, anyhow};
 use io_m2dir::client::M2dirClient as Inner;
-use pimalaya_config::toml::TomlConfig;
 
-use crate::{account::context::Account, cli::load_or_wizard, config::M2dirConfig};
+use crate::{account::context::Account, config::Config, config::M2dirConfig};
 
 /// Live m2dir client wrapping io_m2dir with the configured store root.
 pub struct M2dirClient {
@@ -39,19 +35,16 @@ impl DerefMut for M2dirClient {
     }
 }
 
-/// Loads the configuration, picks the active account, builds the
+/// Picks the active account of `config`, builds the
 /// merged [`Account`] then opens the m2dir client. Bails when the
 /// account has no `[m2dir]` block. Returns the client paired with
 /// the merged account so subcommands receive both as sibling
 /// arguments.
 pub fn build_m2dir_client(
-    config_paths: &[PathBuf],
+    mut config: Config,
     account_name: Option<&str>,
 ) -> Result<(Account, M2dirClient)> {
-    let mut config = load_or_wizard(config_paths)?;
-    let (name, mut ac) = config
-        .take_account(account_name)?
-        .ok_or_else(|| anyhow!("Cannot find account"))?;
+    let (name, mut ac) = config.select_account(account_name)?;
     let m2dir_config = ac
         .m2dir
         .take()
@@ -2,9 +2,12 @@ use anyhow::Result;
 use clap::Parser;
 use pimalaya_cli::printer::{Message, Printer};
 
+use crate::dry_run::{self, DryRun};
 use crate::m2dir::{arg::M2dirNameArg, client::M2dirClient};
 
 /// Delete the given m2dir folder.
+///
+/// With `--dry-run`, reports the folder it would delete instead.
 #[derive(Debug, Parser)]
 pub struct M2dirMailboxDeleteCommand {
     #[command(flatten)]
@@ -15,6 +18,11 @@ impl M2dirMailboxDeleteCommand {
     pub fn execute(self, printer: &mut impl Printer, client: &mut M2dirClient) -> Result<()> {
         let store = client.open_store()?;
         let path = store.resolve_folder_path(&self.m2dir_name.inner)?;
+
+        if dry_run::enabled() {
+            return printer.out(DryRun::new("delete", &self.m2dir_name.inner, &[]));
+        }
+
         client.delete_m2dir(path)?;
         printer.out(Message::new("m2dir folder successfully deleted"))
     }
@@ -7,7 +7,7 @@ use crate::maildir::{
     client::MaildirClient, create::MaildirMailboxCreateCommand,
     delete::MaildirMailboxDeleteCommand, flag::cli::MaildirFlagCommand,
     list::MaildirMailboxListCommand, message::cli::MaildirMessageCommand,
-    rename::MaildirMailboxRenameCommand,
+    r#move::MaildirMailboxMoveCommand, rename::MaildirMailboxRenameCommand,
 };
 
 /// Maildir-specific API.
@@ -18,6 +18,7 @@ use crate::maildir::{
 pub enum MaildirCommand {
     Create(MaildirMailboxCreateCommand),
     Rename(MaildirMailboxRenameCommand),
+    Move(MaildirMailboxMoveCommand),
     Delete(MaildirMailboxDeleteCommand),
     List(MaildirMailboxListCommand),
 
@@ -29,6 +30,21 @@ pub enum MaildirCommand {
 }
 
 impl MaildirCommand {
+    /// Whether the command creates, deletes or renames mailboxes,
+    /// which invalidates the `mailbox list` cache.
+    pub fn changes_mailboxes(&self) -> bool {
+        matches!(
+            self,
+            Self::Create(_) | Self::Rename(_) | Self::Move(_) | Self::Delete(_)
+        )
+    }
+
+    /// Whether the command reports the folders it would change under
+    /// `--dry-run`.
+    pub fn supports_dry_run(&self) -> bool {
+        matches!(self, Self::Delete(_))
+    }
+
     pub fn execute(
         self,
         printer: &mut impl Printer,
@@ -38,6 +54,7 @@ impl MaildirCommand {
         match self {
             Self::Create(cmd) => cmd.execute(printer, client),
             Self::Rename(cmd) => cmd.execute(printer, client),
+            Self::Move(cmd) => cmd.execute(printer, client),
             Self::Delete(cmd) => cmd.execute(printer, client),
             Self::List(cmd) => cmd.execute(printer, account, client),
 
@@ -12,9 +12,8 @@ use std::{
 
 use anyhow::{Result, anyhow, bail};
 use io_maildir::{client::MaildirClient as Inner, maildir::types::Maildir};
-use pimalaya_config::toml::TomlConfig;
 
-use crate::{account::context::Account, cli::load_or_wizard, config::MaildirConfig};
+use crate::{account::context::Account, config::Config, config::MaildirConfig, maildir::maildirpp};
 
 /// Live Maildir client wrapping io_maildir with the configured root.
 pub struct MaildirClient {
@@ -23,6 +22,8 @@ pub struct MaildirClient {
     /// so commands can join sub-paths (per-mailbox) without needing
     /// the original [`MaildirConfig`].
     pub root: PathBuf,
+    /// Whether folders follow the Maildir++ layout.
+    pub maildirpp: bool,
 }
 
 impl MaildirClient {
@@ -31,18 +32,37 @@ impl MaildirClient {
     pub fn new(config: MaildirConfig) -> Self {
         let root = config.root.clone();
         let inner = Inner::new(root.to_string_lossy().into_owned());
-        Self { inner, root }
+        Self {
+            inner,
+            root,
+            maildirpp: config.maildirpp,
+        }
+    }
+
+    /// Directory of the folder `name`, relative to the account root:
+    /// the nested `root/name` path, or its dotted Maildir++ sibling.
+    /// Validates `name` first (see [`validate_maildir_name`]).
+    pub fn folder_path(&self, name: &Path) -> Result<PathBuf> {
+        validate_maildir_name(name)?;
+
+        if self.maildirpp {
+            maildirpp::folder_path(&self.root, name)
+        } else {
+            Ok(self.root.join(name))
+        }
     }
 
     /// Resolves a maildir CLI argument: tries `path` as-is first, then
-    /// falls back to `self.root.join(path)`. Both attempts go through
+    /// falls back to the folder of that name under the root (see
+    /// [`MaildirClient::folder_path`]). Both attempts go through
     /// [`io_maildir::client::MaildirClient::load_maildir`] so the
     /// `cur` / `new` / `tmp` markers are validated.
     pub fn resolve_maildir(&self, path: &Path) -> Result<Maildir> {
         if let Ok(maildir) = self.load_maildir(path.to_string_lossy().into_owned()) {
             return Ok(maildir);
         }
-        Ok(self.load_maildir(self.root.join(path).to_string_lossy().into_owned())?)
+        let path = self.folder_path(path)?;
+        Ok(self.load_maildir(path.to_string_lossy().into_owned())?)
     }
 }
 
@@ -60,19 +80,16 @@ impl DerefMut for MaildirClient {
     }
 }
 
-/// Loads the configuration, picks the active account, builds the
+/// Picks the active account of `config`, builds the
 /// merged [`Account`] then opens the maildir client. Bails when the
 /// account has no `[maildir]` block. Returns the client paired with
 /// the merged account so subcommands receive both as sibling
 /// arguments.
 pub fn build_maildir_client(
-    config_paths: &[PathBuf],
+    mut config: Config,
     account_name: Option<&str>,
 ) -> Result<(Account, MaildirClient)> {
-    let mut config = load_or_wizard(config_paths)?;
-    let (name, mut ac) = config
-        .take_account(account_name)?
-        .ok_or_else(|| anyhow!("Cannot find account"))?;
+    let (name, mut ac) = config.select_account(account_name)?;
     let maildir_config = ac
         .maildir
         .take()
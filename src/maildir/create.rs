@@ -1,18 +1,17 @@
 use std::path::Path;
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 use clap::Parser;
 use pimalaya_cli::printer::{Message, Printer};
 
-use crate::maildir::{
-    arg::MaildirNameArg,
-    client::{MaildirClient, validate_maildir_name},
-};
+use crate::maildir::{arg::MaildirNameArg, client::MaildirClient};
 
 /// Create a Maildir folder.
 ///
 /// Creates the new, cur and tmp subdirectories for a new folder under
-/// the account root.
+/// the account root (a dotted `.Name` directory with the Maildir++
+/// layout). Missing parents of a nested name (e.g.
+/// `Archive/2024/Receipts`) are created as Maildirs as well.
 #[derive(Debug, Parser)]
 pub struct MaildirMailboxCreateCommand {
     #[command(flatten)]
@@ -21,15 +20,45 @@ pub struct MaildirMailboxCreateCommand {
 
 impl MaildirMailboxCreateCommand {
     pub fn execute(self, printer: &mut impl Printer, client: &mut MaildirClient) -> Result<()> {
-        validate_maildir_name(Path::new(&self.maildir_name.inner))?;
+        let name = Path::new(&self.maildir_name.inner);
+        let path = client.folder_path(name)?;
 
-        let path = client
-            .root
-            .join(&self.maildir_name.inner)
-            .to_string_lossy()
-            .into_owned();
+        if is_maildir(&path) {
+            bail!("Maildir `{}` already exists", path.display());
+        }
 
-        client.create_maildir(path)?;
-        printer.out(Message::new("Maildir successfully created"))
+        let mut created = Vec::new();
+
+        // NOTE: ancestors() yields the name itself first and ends with
+        // the empty path, which is the root
+        let mut names: Vec<&Path> = name
+            .ancestors()
+            .filter(|name| !name.as_os_str().is_empty())
+            .collect();
+        names.reverse();
+
+        for name in names {
+            let path = client.folder_path(name)?;
+
+            if is_maildir(&path) {
+                continue;
+            }
+
+            client.create_maildir(path.to_string_lossy().into_owned())?;
+            created.push(name.display().to_string());
+        }
+
+        let mut msg = String::from("Maildir(s) successfully created:");
+        for name in &created {
+            msg.push_str(&format!("\n - {name}"));
+        }
+
+        printer.out(Message::new(msg))
     }
 }
+
+fn is_maildir(path: &Path) -> bool {
+    ["cur", "new", "tmp"]
+        .iter()
+        .all(|subdir| path.join(subdir).is_dir())
+}
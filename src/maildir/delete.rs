@@ -1,17 +1,18 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
 use clap::Parser;
 use pimalaya_cli::printer::{Message, Printer};
 
-use crate::maildir::{
-    arg::RequiredMaildirPathFlag,
-    client::{MaildirClient, validate_maildir_name},
-};
+use crate::dry_run::{self, DryRun};
+use crate::maildir::{arg::RequiredMaildirPathFlag, client::MaildirClient, maildirpp};
 
 /// Delete a Maildir folder.
 ///
 /// Removes the folder directory and every message it contains. The
 /// target must be given explicitly (no default), since deletion is
-/// destructive.
+/// destructive. With the Maildir++ layout, the subfolders (stored as
+/// dotted siblings) are deleted too, and the Inbox, which is the
+/// account root, cannot be deleted. With `--dry-run`, reports the
+/// directories it would delete instead.
 #[derive(Debug, Parser)]
 pub struct MaildirMailboxDeleteCommand {
     #[command(flatten)]
@@ -20,15 +21,28 @@ pub struct MaildirMailboxDeleteCommand {
 
 impl MaildirMailboxDeleteCommand {
     pub fn execute(self, printer: &mut impl Printer, client: &mut MaildirClient) -> Result<()> {
-        validate_maildir_name(&self.maildir_path.inner)?;
+        if client.maildirpp {
+            if dry_run::enabled() {
+                let dirs = maildirpp::deleted_dirs(&client.root, &self.maildir_path.inner)?;
+                let dirs: Vec<String> = dirs.iter().map(|d| d.display().to_string()).collect();
+                return printer.out(DryRun::new("delete", &dirs.join(", "), &[]));
+            }
 
-        let path = client
-            .root
-            .join(&self.maildir_path.inner)
-            .to_string_lossy()
-            .into_owned();
+            maildirpp::delete(&client.root, &self.maildir_path.inner)?;
+            return printer.out(Message::new("Maildir successfully deleted"));
+        }
 
-        client.delete_maildir(path)?;
+        let path = client.folder_path(&self.maildir_path.inner)?;
+
+        if path.components().eq(client.root.components()) {
+            bail!("Cannot delete the Maildir root, it holds the whole account");
+        }
+
+        if dry_run::enabled() {
+            return printer.out(DryRun::new("delete", &path.display().to_string(), &[]));
+        }
+
+        client.delete_maildir(path.to_string_lossy().into_owned())?;
         printer.out(Message::new("Maildir successfully deleted"))
     }
 }
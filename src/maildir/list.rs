@@ -8,12 +8,14 @@ use pimalaya_cli::printer::Printer;
 use serde::Serialize;
 
 use crate::account::context::Account;
-use crate::maildir::client::MaildirClient;
+use crate::maildir::{client::MaildirClient, maildirpp};
 
 /// List Maildir folders.
 ///
 /// Scans the account root and lists every folder found, with its name
-/// and filesystem path.
+/// and filesystem path. With the Maildir++ layout, dotted directory
+/// names are shown as their logical hierarchy (`.Work.Clients` as
+/// `Work/Clients`).
 #[derive(Debug, Parser)]
 pub struct MaildirMailboxListCommand;
 
@@ -24,12 +26,23 @@ impl MaildirMailboxListCommand {
         account: &mut Account,
         client: &mut MaildirClient,
     ) -> Result<()> {
-        let maildirs = client.list_maildirs()?;
+        let rows = if client.maildirpp {
+            maildirpp::list(&client.root)?
+                .into_iter()
+                .map(|(name, path)| MaildirRow { name, path })
+                .collect()
+        } else {
+            client
+                .list_maildirs()?
+                .into_iter()
+                .map(From::from)
+                .collect()
+        };
 
         let table = MaildirsTable {
             preset: account.table_preset().to_string(),
             name_color: account.mailboxes_list_table_name_color(),
-            rows: maildirs.into_iter().map(From::from).collect(),
+            rows,
         };
 
         printer.out(table)
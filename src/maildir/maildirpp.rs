@@ -0,0 +1,297 @@
+//! Maildir++ layout (as used by Dovecot and Courier).
+//!
+//! The account root is the Inbox itself, and every other folder is a
+//! dot-prefixed sibling directory whose name spells its hierarchy
+//! with dots: the logical folder `Work/Clients` lives in
+//! `<root>/.Work.Clients`.
+
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+
+const INBOX: &str = "Inbox";
+
+/// Directory of the logical folder `name` (`/`-separated) under
+/// `root`. The Inbox maps to the root itself.
+pub fn folder_path(root: &Path, name: &Path) -> Result<PathBuf> {
+    if name.as_os_str().eq_ignore_ascii_case(INBOX) {
+        return Ok(root.to_owned());
+    }
+
+    let mut dir_name = String::new();
+
+    for component in name.components() {
+        let Component::Normal(part) = component else {
+            bail!("Invalid Maildir++ folder `{}`", name.display());
+        };
+
+        let part = part.to_string_lossy();
+
+        if part.contains('.') {
+            bail!("Maildir++ folder `{}` must not contain `.`", name.display());
+        }
+
+        dir_name.push('.');
+        dir_name.push_str(&part);
+    }
+
+    Ok(root.join(dir_name))
+}
+
+/// Logical folder name (`/`-separated) of a Maildir++ directory
+/// name, `None` when it is not dot-prefixed.
+pub fn folder_name(dir_name: &str) -> Option<String> {
+    let name = dir_name.strip_prefix('.')?;
+
+    if name.is_empty() || name.starts_with('.') {
+        return None;
+    }
+
+    Some(name.replace('.', "/"))
+}
+
+/// Logical name and path of every folder found under `root`, Inbox
+/// first then sorted by name.
+pub fn list(root: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut folders = Vec::new();
+
+    let entries =
+        fs::read_dir(root).with_context(|| format!("Read Maildir `{}` error", root.display()))?;
+
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+
+        if !is_maildir(&path) {
+            continue;
+        }
+
+        if let Some(name) = entry.file_name().to_str().and_then(folder_name) {
+            folders.push((name, path));
+        }
+    }
+
+    folders.sort();
+
+    if is_maildir(root) {
+        folders.insert(0, (INBOX.to_owned(), root.to_owned()));
+    }
+
+    Ok(folders)
+}
+
+/// Renames the folder `from` to `to`, together with its subfolders
+/// (which Maildir++ stores as siblings).
+pub fn rename(root: &Path, from: &Path, to: &Path) -> Result<()> {
+    let source = folder_path(root, from)?;
+    let target = folder_path(root, to)?;
+
+    if source == root || target == root {
+        bail!("Cannot rename the Maildir++ Inbox");
+    }
+
+    if !source.is_dir() {
+        bail!("Maildir `{}` does not exist", source.display());
+    }
+
+    if target.exists() {
+        bail!("Maildir `{}` already exists", target.display());
+    }
+
+    let source_name = dir_name(&source);
+    let target_name = dir_name(&target);
+
+    if target_name.starts_with(&format!("{source_name}.")) {
+        bail!("Cannot move a Maildir into itself");
+    }
+
+    let entries =
+        fs::read_dir(root).with_context(|| format!("Read Maildir `{}` error", root.display()))?;
+
+    for entry in entries {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+
+        let suffix = match name.strip_prefix(&source_name) {
+            Some(suffix) if suffix.is_empty() || suffix.starts_with('.') => suffix,
+            _ => continue,
+        };
+
+        let from = root.join(&name);
+        let to = root.join(format!("{target_name}{suffix}"));
+
+        fs::rename(&from, &to).with_context(|| {
+            format!(
+                "Move Maildir `{}` to `{}` error",
+                from.display(),
+                to.display()
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Deletes the folder `name` together with its subfolders (which
+/// Maildir++ stores as siblings) and every message they contain. The
+/// Inbox cannot be deleted, it is the account root itself.
+pub fn delete(root: &Path, name: &Path) -> Result<()> {
+    for path in deleted_dirs(root, name)? {
+        fs::remove_dir_all(&path)
+            .with_context(|| format!("Delete Maildir `{}` error", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Directories [`delete`] removes for the folder `name`: its own and
+/// the ones of its subfolders.
+pub fn deleted_dirs(root: &Path, name: &Path) -> Result<Vec<PathBuf>> {
+    let path = folder_path(root, name)?;
+
+    if path == root {
+        bail!("Cannot delete the Maildir++ Inbox, it holds the whole account");
+    }
+
+    if !path.is_dir() {
+        bail!("Maildir `{}` does not exist", path.display());
+    }
+
+    let folder_name = dir_name(&path);
+    let mut dirs = Vec::new();
+
+    let entries =
+        fs::read_dir(root).with_context(|| format!("Read Maildir `{}` error", root.display()))?;
+
+    for entry in entries {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+
+        match name.strip_prefix(&folder_name) {
+            Some(suffix) if suffix.is_empty() || suffix.starts_with('.') => (),
+            _ => continue,
+        }
+
+        dirs.push(root.join(&name));
+    }
+
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn is_maildir(path: &Path) -> bool {
+    ["cur", "new", "tmp"]
+        .iter()
+        .all(|subdir| path.join(subdir).is_dir())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    fn tmp_root(name: &str) -> PathBuf {
+        let root = env::temp_dir().join(format!("himalaya-maildirpp-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        root
+    }
+
+    fn create(root: &Path, dir: &str) {
+        for subdir in ["cur", "new", "tmp"] {
+            fs::create_dir_all(root.join(dir).join(subdir)).unwrap();
+        }
+    }
+
+    #[test]
+    fn maps_names_to_dotted_dirs() {
+        let root = Path::new("/mail");
+
+        assert_eq!(folder_path(root, Path::new("INBOX")).unwrap(), root);
+        assert_eq!(
+            folder_path(root, Path::new("Work/Clients")).unwrap(),
+            root.join(".Work.Clients")
+        );
+        assert!(folder_path(root, Path::new("Dotted.Name")).is_err());
+        assert!(folder_path(root, Path::new("../escape")).is_err());
+
+        assert_eq!(
+            folder_name(".Work.Clients").as_deref(),
+            Some("Work/Clients")
+        );
+        assert_eq!(folder_name("cur"), None);
+        assert_eq!(folder_name(".."), None);
+    }
+
+    #[test]
+    fn lists_nested_layout() {
+        let root = tmp_root("list");
+        create(&root, "");
+        create(&root, ".Work");
+        create(&root, ".Work.Clients");
+        create(&root, ".Archive");
+        fs::create_dir_all(root.join(".NotAMaildir")).unwrap();
+
+        let names: Vec<String> = list(&root).unwrap().into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, ["Inbox", "Archive", "Work", "Work/Clients"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn renames_with_subfolders() {
+        let root = tmp_root("rename");
+        create(&root, "");
+        create(&root, ".Work");
+        create(&root, ".Work.Clients");
+        create(&root, ".Workshop");
+
+        rename(&root, Path::new("Work"), Path::new("Archive/Work")).unwrap();
+
+        let names: Vec<String> = list(&root).unwrap().into_iter().map(|(n, _)| n).collect();
+        assert_eq!(
+            names,
+            ["Inbox", "Archive/Work", "Archive/Work/Clients", "Workshop"]
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn deletes_with_subfolders() {
+        let root = tmp_root("delete");
+        create(&root, "");
+        create(&root, ".Work");
+        create(&root, ".Work.Clients");
+        create(&root, ".Workshop");
+
+        delete(&root, Path::new("Work")).unwrap();
+
+        let names: Vec<String> = list(&root).unwrap().into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, ["Inbox", "Workshop"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn refuses_to_delete_inbox() {
+        let root = tmp_root("delete-inbox");
+        create(&root, "");
+        create(&root, ".Work");
+
+        assert!(delete(&root, Path::new("Inbox")).is_err());
+        assert!(delete(&root, Path::new("INBOX")).is_err());
+
+        let names: Vec<String> = list(&root).unwrap().into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, ["Inbox", "Work"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
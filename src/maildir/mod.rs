@@ -5,5 +5,7 @@ pub mod create;
 pub mod delete;
 pub mod flag;
 pub mod list;
+pub mod maildirpp;
 pub mod message;
+pub mod r#move;
 pub mod rename;
@@ -0,0 +1,196 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use clap::Parser;
+use pimalaya_cli::printer::{Message, Printer};
+
+use crate::maildir::{
+    arg::{RequiredMaildirPathFlag, TargetMaildirPathFlag},
+    client::{MaildirClient, validate_maildir_name},
+    maildirpp,
+};
+
+/// Move a Maildir folder under a different parent.
+///
+/// Moves the folder directory, subfolders included, to the target
+/// path relative to the account root (e.g. `-m Work/OldClient -t
+/// Archive/Clients/OldClient`). Missing intermediate parents are
+/// created, and subfolders are checked to be reachable under the new
+/// path afterward. With the Maildir++ layout, where the hierarchy is
+/// spelled in the dotted directory names, this renames the folder and
+/// its `.Name.Sub` siblings.
+#[derive(Debug, Parser)]
+pub struct MaildirMailboxMoveCommand {
+    #[command(flatten)]
+    pub maildir_path: RequiredMaildirPathFlag,
+    #[command(flatten)]
+    pub target_path: TargetMaildirPathFlag,
+}
+
+impl MaildirMailboxMoveCommand {
+    pub fn execute(self, printer: &mut impl Printer, client: &mut MaildirClient) -> Result<()> {
+        validate_maildir_name(&self.maildir_path.inner)?;
+        validate_maildir_name(&self.target_path.inner)?;
+
+        if client.maildirpp {
+            maildirpp::rename(
+                &client.root,
+                &self.maildir_path.inner,
+                &self.target_path.inner,
+            )?;
+        } else {
+            move_maildir(
+                &client.root,
+                &self.maildir_path.inner,
+                &self.target_path.inner,
+            )?;
+        }
+
+        printer.out(Message::new("Maildir successfully moved"))
+    }
+}
+
+/// Moves the folder `from` to `to` (both relative to `root`),
+/// subfolders included, creating the missing parents of `to`. Fails
+/// when a subfolder cannot be found under `to` afterward.
+fn move_maildir(root: &Path, from: &Path, to: &Path) -> Result<()> {
+    let source = root.join(from);
+    let target = root.join(to);
+
+    if !source.is_dir() {
+        bail!("Maildir `{}` does not exist", source.display());
+    }
+
+    if target.exists() {
+        bail!("Maildir `{}` already exists", target.display());
+    }
+
+    if target.starts_with(&source) {
+        bail!("Cannot move a Maildir into itself");
+    }
+
+    let children = subfolders(&source)?;
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Create parent `{}` error", parent.display()))?;
+    }
+
+    fs::rename(&source, &target).with_context(|| {
+        format!(
+            "Move Maildir `{}` to `{}` error",
+            source.display(),
+            target.display()
+        )
+    })?;
+
+    let missing: Vec<String> = children
+        .iter()
+        .filter(|child| !target.join(child).is_dir())
+        .map(|child| child.display().to_string())
+        .collect();
+
+    if !missing.is_empty() {
+        bail!(
+            "Maildir moved, but subfolder(s) not found under `{}`: {}",
+            target.display(),
+            missing.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Relative paths of the nested Maildir folders of `dir`, skipping
+/// the `cur`, `new` and `tmp` subdirectories.
+fn subfolders(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut folders = Vec::new();
+    let mut stack = vec![PathBuf::new()];
+
+    while let Some(rel) = stack.pop() {
+        let entries = fs::read_dir(dir.join(&rel))
+            .with_context(|| format!("Read Maildir `{}` error", dir.join(&rel).display()))?;
+
+        for entry in entries {
+            let entry = entry?;
+
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            let name = entry.file_name();
+
+            if matches!(name.to_str(), Some("cur" | "new" | "tmp")) {
+                continue;
+            }
+
+            let child = rel.join(name);
+            folders.push(child.clone());
+            stack.push(child);
+        }
+    }
+
+    Ok(folders)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    fn tmp_root(name: &str) -> PathBuf {
+        let root = env::temp_dir().join(format!("himalaya-maildir-move-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        root
+    }
+
+    fn create(root: &Path, dir: &str) {
+        for subdir in ["cur", "new", "tmp"] {
+            fs::create_dir_all(root.join(dir).join(subdir)).unwrap();
+        }
+    }
+
+    #[test]
+    fn moves_with_nested_subfolders() {
+        let root = tmp_root("nested");
+        create(&root, "Work/OldClient");
+        create(&root, "Work/OldClient/2023");
+        create(&root, "Work/OldClient/2023/Invoices");
+        create(&root, "Work/Current");
+
+        move_maildir(
+            &root,
+            Path::new("Work/OldClient"),
+            Path::new("Archive/Clients/OldClient"),
+        )
+        .unwrap();
+
+        let target = root.join("Archive/Clients/OldClient");
+        let mut children = subfolders(&target).unwrap();
+        children.sort();
+        assert_eq!(
+            children,
+            [PathBuf::from("2023"), PathBuf::from("2023/Invoices")]
+        );
+        assert!(target.join("2023/Invoices/cur").is_dir());
+        assert!(!root.join("Work/OldClient").exists());
+        assert!(root.join("Work/Current/cur").is_dir());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn refuses_to_move_into_itself() {
+        let root = tmp_root("itself");
+        create(&root, "Work");
+
+        assert!(move_maildir(&root, Path::new("Work"), Path::new("Work/Sub")).is_err());
+        assert!(root.join("Work/cur").is_dir());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
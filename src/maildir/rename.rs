@@ -7,13 +7,15 @@ use std::path::Path;
 use crate::maildir::{
     arg::{MaildirNameArg, RequiredMaildirPathFlag},
     client::{MaildirClient, validate_maildir_name},
+    maildirpp,
 };
 
 /// Rename a Maildir folder.
 ///
 /// Renames the folder directory from its current path to the new name.
 /// The source must be given explicitly (no default), since renaming is
-/// destructive.
+/// destructive. With the Maildir++ layout, the subfolders (stored as
+/// `.Name.Sub` siblings) are renamed along.
 #[derive(Debug, Parser)]
 pub struct MaildirMailboxRenameCommand {
     #[command(flatten)]
@@ -27,6 +29,12 @@ impl MaildirMailboxRenameCommand {
         validate_maildir_name(&self.maildir_path.inner)?;
         validate_maildir_name(Path::new(&self.maildir_name.inner))?;
 
+        if client.maildirpp {
+            let target = Path::new(&self.maildir_name.inner);
+            maildirpp::rename(&client.root, &self.maildir_path.inner, target)?;
+            return printer.out(Message::new("Maildir successfully renamed"));
+        }
+
         let path = client
             .root
             .join(&self.maildir_path.inner)
@@ -1,7 +1,9 @@
 mod account;
+mod alias;
 mod backend;
 mod cli;
 mod config;
+mod dry_run;
 #[cfg(feature = "gmail")]
 mod gmail;
 #[cfg(feature = "imap")]
@@ -14,28 +16,83 @@ mod m2dir;
 mod maildir;
 #[cfg(feature = "msgraph")]
 mod msgraph;
+mod outbox;
+mod profile;
 mod shared;
 #[cfg(feature = "smtp")]
 mod smtp;
 mod wizard;
 
+use std::{env, path::PathBuf};
+
 use anyhow::Result;
-use clap::Parser;
+use clap::{CommandFactory, Parser, error::ErrorKind};
 use pimalaya_cli::{error::ErrorReport, log::Logger, printer::StdoutPrinter};
 
-use crate::cli::Cli;
+use crate::{
+    cli::Cli,
+    config::{Config, OutputFormat},
+};
 
 fn main() {
-    let cli = Cli::parse();
+    let args = match alias::expand(env::args_os().collect()) {
+        Ok(args) => args,
+        Err(err) => Cli::command()
+            .error(ErrorKind::InvalidValue, format!("{err:#}"))
+            .exit(),
+    };
+
+    let mut cli = Cli::parse_from(args);
+
+    if cli.plain && cli.json.json {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "`--plain` cannot be combined with `--json`",
+            )
+            .exit()
+    }
+
+    // NOTE: initialized first, so the warnings of the configuration
+    // lookup are not lost
+    let logger = Logger::try_init(&cli.log).map_err(anyhow::Error::from);
+
+    let paths = Config::resolve_paths(cli.config.paths.as_ref(), &cli.config_dirs);
+    let config = Config::load(&paths).ok().flatten();
+
+    if !cli.json.json && !cli.plain {
+        cli.json.json = account_output(&cli, config.as_ref()) == Some(OutputFormat::Json);
+    }
+
     let mut printer = StdoutPrinter::new(&cli.json);
-    let result = execute(cli, &mut printer);
+    let result = logger.and_then(|()| execute(cli, &paths, config, &mut printer));
     ErrorReport::eval(&mut printer, result);
 }
 
-fn execute(cli: Cli, printer: &mut StdoutPrinter) -> Result<()> {
-    Logger::try_init(&cli.log)?;
-    let config = cli.config.paths.as_ref();
+/// Output format set by the selected account, if the configuration
+/// could be loaded. Errors are left to the command to report, the
+/// configuration being loaded again when `None`.
+fn account_output(cli: &Cli, config: Option<&Config>) -> Option<OutputFormat> {
+    config?.account_output(cli.account.name.as_deref())
+}
+
+fn execute(
+    cli: Cli,
+    paths: &[PathBuf],
+    config: Option<Config>,
+    printer: &mut StdoutPrinter,
+) -> Result<()> {
     let account = cli.account.name.as_deref();
     let backend = cli.backend;
-    cli.cmd.execute(printer, config, account, backend)
+
+    if cli.profile {
+        profile::enable();
+    }
+
+    if cli.dry_run {
+        dry_run::enable();
+    }
+
+    let _span = profile::span("total");
+    cli.cmd.execute(printer, paths, config, account, backend)
 }
@@ -28,6 +28,21 @@ pub enum MsgraphCommand {
 }
 
 impl MsgraphCommand {
+    /// Whether the command creates, deletes or renames mailboxes,
+    /// which invalidates the `mailbox list` cache.
+    pub fn changes_mailboxes(&self) -> bool {
+        matches!(
+            self,
+            Self::MailFolder(
+                MsgraphMailFolderCommand::Create(_)
+                    | MsgraphMailFolderCommand::Rename(_)
+                    | MsgraphMailFolderCommand::Copy(_)
+                    | MsgraphMailFolderCommand::Move(_)
+                    | MsgraphMailFolderCommand::Delete(_)
+            )
+        )
+    }
+
     pub fn execute(
         self,
         printer: &mut impl Printer,
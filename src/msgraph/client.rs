@@ -11,19 +11,14 @@
 //!
 //! [`MsgraphClientStd::connect`]: io_msgraph::v1::client::MsgraphClientStd::connect
 
-use std::{
-    ops::{Deref, DerefMut},
-    path::PathBuf,
-};
+use std::ops::{Deref, DerefMut};
 
 use anyhow::{Result, anyhow};
 use io_msgraph::v1::client::{MsgraphClientStd as Inner, MsgraphClientStdConnectOptions};
-use pimalaya_config::toml::TomlConfig;
 use secrecy::{ExposeSecret, SecretString};
 
 use crate::{
-    account::context::Account, cli::load_or_wizard, config::MsgraphAuthConfig,
-    config::MsgraphConfig,
+    account::context::Account, config::Config, config::MsgraphAuthConfig, config::MsgraphConfig,
 };
 
 /// Live Microsoft Graph client handed down to every `msgraph` subcommand.
@@ -61,17 +56,14 @@ impl DerefMut for MsgraphClient {
     }
 }
 
-/// Loads the configuration, picks the active account, builds the merged
+/// Picks the active account of `config`, builds the merged
 /// [`Account`] then opens the Microsoft Graph client. Bails when the
 /// account has no `[msgraph]` block.
 pub fn build_msgraph_client(
-    config_paths: &[PathBuf],
+    mut config: Config,
     account_name: Option<&str>,
 ) -> Result<(Account, MsgraphClient)> {
-    let mut config = load_or_wizard(config_paths)?;
-    let (name, mut ac) = config
-        .take_account(account_name)?
-        .ok_or_else(|| anyhow!("Cannot find account"))?;
+    let (name, mut ac) = config.select_account(account_name)?;
     let msgraph_config = ac
         .msgraph
         .take()
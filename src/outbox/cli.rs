@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Subcommand;
+use pimalaya_cli::printer::Printer;
+
+use crate::{
+    backend::Backend,
+    outbox::{
+        flush::OutboxFlushCommand, list::OutboxListCommand, remove::OutboxRemoveCommand,
+        retry::OutboxRetryCommand,
+    },
+};
+
+/// Manage the local outbox.
+///
+/// The outbox holds messages queued with `message send --schedule`,
+/// or after a failed `message send`, waiting on disk until an `outbox
+/// flush` finds them due. There is no daemon: run `outbox flush`
+/// periodically, for example from cron.
+#[derive(Debug, Subcommand)]
+pub enum OutboxCommand {
+    #[command(visible_alias = "ls")]
+    List(OutboxListCommand),
+    Flush(OutboxFlushCommand),
+    Retry(OutboxRetryCommand),
+    #[command(visible_alias = "rm")]
+    Remove(OutboxRemoveCommand),
+}
+
+impl OutboxCommand {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        config_paths: &[PathBuf],
+        account_name: Option<&str>,
+        backend: Backend,
+    ) -> Result<()> {
+        match self {
+            Self::List(cmd) => cmd.execute(printer, account_name),
+            Self::Flush(cmd) => cmd.execute(printer, config_paths, account_name, backend),
+            Self::Retry(cmd) => cmd.execute(printer, config_paths, account_name, backend),
+            Self::Remove(cmd) => cmd.execute(printer),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::{collections::HashMap, fmt, path::PathBuf};
+
+use anyhow::{Result, bail};
+use chrono::Local;
+use clap::Parser;
+use io_email::flag::types::{Flag, IanaFlag};
+use log::{debug, warn};
+use pimalaya_cli::printer::Printer;
+use serde::Serialize;
+
+use crate::{
+    account::context::Account,
+    backend::Backend,
+    outbox::queue::{Outbox, QueuedMessage},
+    shared::{
+        client::{EmailClient, build_email_client},
+        message::handler,
+    },
+};
+
+/// Send the outbox messages that are due.
+///
+/// Meant to be run periodically (e.g. from cron). Each due message is
+/// sent with the account it was queued for, then removed from the
+/// outbox. A message that fails to send stays queued for the next
+/// flush, and the command exits with an error after reporting it.
+/// Pass the global `--account` flag to only flush one account.
+#[derive(Debug, Parser)]
+pub struct OutboxFlushCommand {
+    /// Also send the messages that are not due yet.
+    #[arg(long)]
+    pub all: bool,
+}
+
+impl OutboxFlushCommand {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        config_paths: &[PathBuf],
+        account_name: Option<&str>,
+        backend: Backend,
+    ) -> Result<()> {
+        let outbox = Outbox::open()?;
+        let now = Local::now().fixed_offset();
+
+        let messages: Vec<QueuedMessage> = outbox
+            .list()?
+            .into_iter()
+            .filter(|msg| account_name.is_none_or(|name| msg.account == name))
+            .filter(|msg| self.all || msg.is_due(now))
+            .collect();
+
+        let report = send_all(&outbox, config_paths, backend, messages);
+        let failed = report.failed.len();
+
+        printer.out(report)?;
+
+        if failed > 0 {
+            bail!("{failed} queued message(s) could not be sent and stay in the outbox");
+        }
+
+        Ok(())
+    }
+}
+
+/// Sends `messages`, removing each one from `outbox` once sent, or
+/// recording the attempt and its error when the send fails. One
+/// client is built per account, on first use.
+pub fn send_all(
+    outbox: &Outbox,
+    config_paths: &[PathBuf],
+    backend: Backend,
+    messages: Vec<QueuedMessage>,
+) -> SendReport {
+    let mut clients: HashMap<String, Result<(Account, EmailClient), String>> = HashMap::new();
+    let mut report = SendReport::default();
+
+    for mut msg in messages {
+        let client = clients.entry(msg.account.clone()).or_insert_with(|| {
+            build_email_client(config_paths, Some(&msg.account), backend)
+                .map_err(|err| format!("{err:#}"))
+        });
+
+        let result = match client {
+            Ok((account, client)) => send(outbox, account, client, &msg),
+            Err(err) => Err(err.clone()),
+        };
+
+        match result {
+            Ok(()) => report.sent.push(msg.id),
+            Err(error) => {
+                warn!("cannot send queued message {}: {error}", msg.id);
+
+                msg.attempts += 1;
+                msg.last_error = Some(error.clone());
+
+                if let Err(err) = outbox.write(&msg.id, &msg) {
+                    warn!("cannot record failed attempt of {}: {err}", msg.id);
+                }
+
+                report.failed.push(SendFailure { id: msg.id, error });
+            }
+        }
+    }
+
+    report
+}
+
+fn send(
+    outbox: &Outbox,
+    account: &Account,
+    client: &mut EmailClient,
+    msg: &QueuedMessage,
+) -> Result<(), String> {
+    let raw = outbox.raw(&msg.id).map_err(|err| format!("{err:#}"))?;
+
+    handler::apply(
+        account,
+        client,
+        raw,
+        &[Flag::from_iana(IanaFlag::Seen)],
+        handler::save_to(account, msg.save.as_deref(), true),
+        true,
+        msg.force,
+    )
+    .map_err(|err| {
+        debug!("{err:?}");
+        format!("{err:#}")
+    })?;
+
+    // NOTE: the message is gone at this point, failing to remove it
+    // only risks sending it twice
+    if let Err(err) = outbox.remove(&msg.id) {
+        warn!(
+            "cannot remove sent message {} from the outbox: {err}",
+            msg.id
+        );
+    }
+
+    Ok(())
+}
+
+/// What a flush did with each queued message.
+#[derive(Debug, Default, Serialize)]
+pub struct SendReport {
+    pub sent: Vec<String>,
+    pub failed: Vec<SendFailure>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SendFailure {
+    pub id: String,
+    pub error: String,
+}
+
+impl fmt::Display for SendReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.sent.is_empty() && self.failed.is_empty() {
+            return writeln!(f, "No queued message to send");
+        }
+
+        for id in &self.sent {
+            writeln!(f, "Sent {id}")?;
+        }
+
+        for failure in &self.failed {
+            writeln!(f, "Failed {}: {}", failure.id, failure.error)?;
+        }
+
+        Ok(())
+    }
+}
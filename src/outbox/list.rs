@@ -0,0 +1,77 @@
+use std::fmt;
+
+use anyhow::Result;
+use chrono::DateTime;
+use clap::Parser;
+use comfy_table::{Cell, ContentArrangement, Row, Table, presets::UTF8_FULL_CONDENSED};
+use pimalaya_cli::printer::Printer;
+use serde::Serialize;
+
+use crate::outbox::queue::{Outbox, QueuedMessage};
+
+/// List the messages waiting in the outbox.
+///
+/// Messages are ordered by due date. Pass the global `--account`
+/// flag to only show the messages of one account.
+#[derive(Debug, Parser)]
+pub struct OutboxListCommand;
+
+impl OutboxListCommand {
+    pub fn execute(self, printer: &mut impl Printer, account_name: Option<&str>) -> Result<()> {
+        let messages = Outbox::open()?
+            .list()?
+            .into_iter()
+            .filter(|msg| account_name.is_none_or(|name| msg.account == name))
+            .collect();
+
+        printer.out(QueuedMessages { messages })
+    }
+}
+
+/// Renderable table for the outbox list command.
+#[derive(Clone, Debug, Serialize)]
+pub struct QueuedMessages {
+    pub messages: Vec<QueuedMessage>,
+}
+
+impl fmt::Display for QueuedMessages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut table = Table::new();
+
+        table
+            .load_preset(UTF8_FULL_CONDENSED)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_header(Row::from(vec![
+                Cell::new("ID"),
+                Cell::new("ACCOUNT"),
+                Cell::new("SEND AT"),
+                Cell::new("TO"),
+                Cell::new("SUBJECT"),
+                Cell::new("ATTEMPTS"),
+                Cell::new("LAST ERROR"),
+            ]))
+            .add_rows(self.messages.iter().map(|msg| {
+                let send_at = match msg.send_at.as_deref() {
+                    Some(date) => match DateTime::parse_from_rfc3339(date) {
+                        Ok(date) => date.format("%F %R").to_string(),
+                        Err(_) => date.to_owned(),
+                    },
+                    None => String::from("now"),
+                };
+
+                let mut row = Row::new();
+                row.max_height(1);
+                row.add_cell(Cell::new(&msg.id));
+                row.add_cell(Cell::new(&msg.account));
+                row.add_cell(Cell::new(send_at));
+                row.add_cell(Cell::new(msg.to.join(", ")));
+                row.add_cell(Cell::new(&msg.subject));
+                row.add_cell(Cell::new(msg.attempts));
+                row.add_cell(Cell::new(msg.last_error.as_deref().unwrap_or_default()));
+                row
+            }));
+
+        writeln!(f)?;
+        writeln!(f, "{table}")
+    }
+}
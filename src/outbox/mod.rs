@@ -0,0 +1,6 @@
+pub mod cli;
+pub mod flush;
+pub mod list;
+pub mod queue;
+pub mod remove;
+pub mod retry;
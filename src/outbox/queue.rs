@@ -0,0 +1,286 @@
+//! On-disk outbox: messages waiting to be sent.
+//!
+//! Each queued message is a pair of files in the outbox directory
+//! (`$XDG_DATA_HOME/himalaya/outbox` on Linux): `<id>.eml` holds the
+//! raw message as it will be sent, `<id>.toml` its [`QueuedMessage`]
+//! metadata. The metadata file is written last, so a message
+//! interrupted while being queued is never picked up half-written.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone};
+use mail_parser::MessageParser;
+use serde::{Deserialize, Serialize};
+
+/// Metadata of a queued message.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct QueuedMessage {
+    /// File stem shared by the `.eml` and `.toml` files, always
+    /// taken from the file name when reading.
+    #[serde(default, skip_deserializing)]
+    pub id: String,
+    /// Name of the account to send the message with.
+    pub account: String,
+    /// RFC 3339 date the message was queued at.
+    pub queued_at: String,
+    /// RFC 3339 date the message is due at, `None` meaning as soon
+    /// as possible.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub send_at: Option<String>,
+    /// Mailbox to append a copy of the message to once sent, as
+    /// given to `--save-to`: `-` skips the `send.save-to` copy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub save: Option<String>,
+    /// Bypasses the `send.max-size` limit.
+    #[serde(default)]
+    pub force: bool,
+    #[serde(default)]
+    pub subject: String,
+    #[serde(default)]
+    pub to: Vec<String>,
+    /// Number of failed send attempts.
+    #[serde(default)]
+    pub attempts: u32,
+    /// Error of the last failed send attempt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+impl QueuedMessage {
+    /// Metadata for `raw`, to be sent with `account` at `send_at`.
+    /// The subject and recipients are read from `raw` for `outbox
+    /// list`.
+    pub fn new(account: &str, raw: &[u8], send_at: Option<DateTime<FixedOffset>>) -> Self {
+        let parsed = MessageParser::new().parse(raw);
+
+        let subject = parsed
+            .as_ref()
+            .and_then(|msg| msg.subject())
+            .unwrap_or_default()
+            .to_owned();
+
+        let to = parsed
+            .as_ref()
+            .and_then(|msg| msg.to())
+            .map(|to| {
+                to.iter()
+                    .filter_map(|addr| addr.address())
+                    .map(str::to_owned)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            id: String::new(),
+            account: account.to_owned(),
+            queued_at: Local::now().to_rfc3339(),
+            send_at: send_at.map(|date| date.to_rfc3339()),
+            save: None,
+            force: false,
+            subject,
+            to,
+            attempts: 0,
+            last_error: None,
+        }
+    }
+
+    /// Whether the message is due at `now`. An unparseable date is
+    /// considered due, so the message does not stay stuck forever.
+    pub fn is_due(&self, now: DateTime<FixedOffset>) -> bool {
+        match self.send_at.as_deref().map(DateTime::parse_from_rfc3339) {
+            Some(Ok(send_at)) => send_at <= now,
+            _ => true,
+        }
+    }
+}
+
+/// The outbox directory.
+pub struct Outbox {
+    dir: PathBuf,
+}
+
+impl Outbox {
+    /// Opens the outbox directory, creating it when missing.
+    pub fn open() -> Result<Self> {
+        let Some(data_dir) = dirs::data_dir() else {
+            bail!("Cannot find the user data directory for the outbox");
+        };
+
+        let dir = data_dir.join("himalaya").join("outbox");
+
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Create outbox `{}` error", dir.display()))?;
+
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Queues `raw` with the metadata `message` under a fresh id,
+    /// which is returned.
+    pub fn push(&self, message: &QueuedMessage, raw: &[u8]) -> Result<String> {
+        let stem = Local::now().format("%Y%m%dT%H%M%S").to_string();
+        let mut id = stem.clone();
+        let mut n = 1;
+
+        while self.eml_path(&id)?.exists() || self.meta_path(&id)?.exists() {
+            n += 1;
+            id = format!("{stem}-{n}");
+        }
+
+        let path = self.eml_path(&id)?;
+        fs::write(&path, raw).with_context(|| format!("Write `{}` error", path.display()))?;
+
+        self.write(&id, message)?;
+
+        Ok(id)
+    }
+
+    /// Every queued message, sorted by due date then id.
+    pub fn list(&self) -> Result<Vec<QueuedMessage>> {
+        let entries = fs::read_dir(&self.dir)
+            .with_context(|| format!("Read outbox `{}` error", self.dir.display()))?;
+
+        let mut messages = Vec::new();
+
+        for entry in entries {
+            let path = entry?.path();
+
+            if path.extension().is_none_or(|ext| ext != "toml") {
+                continue;
+            }
+
+            let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+
+            if !is_valid_id(id) {
+                continue;
+            }
+
+            messages.push(self.get(id)?);
+        }
+
+        messages.sort_by(|a, b| {
+            let a_date = a.send_at.as_deref().unwrap_or(&a.queued_at);
+            let b_date = b.send_at.as_deref().unwrap_or(&b.queued_at);
+            a_date.cmp(b_date).then_with(|| a.id.cmp(&b.id))
+        });
+
+        Ok(messages)
+    }
+
+    /// Metadata of the queued message `id`.
+    pub fn get(&self, id: &str) -> Result<QueuedMessage> {
+        let path = self.meta_path(id)?;
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Read queued message `{id}` error"))?;
+
+        let mut message: QueuedMessage = toml::from_str(&content)
+            .with_context(|| format!("Parse `{}` error", path.display()))?;
+        message.id = id.to_owned();
+
+        Ok(message)
+    }
+
+    /// Raw bytes of the queued message `id`.
+    pub fn raw(&self, id: &str) -> Result<Vec<u8>> {
+        let path = self.eml_path(id)?;
+        fs::read(&path).with_context(|| format!("Read `{}` error", path.display()))
+    }
+
+    /// Overwrites the metadata of the queued message `id`.
+    pub fn write(&self, id: &str, message: &QueuedMessage) -> Result<()> {
+        let path = self.meta_path(id)?;
+        let content = toml::to_string(message).context("Serialize queued message error")?;
+        fs::write(&path, content).with_context(|| format!("Write `{}` error", path.display()))
+    }
+
+    /// Removes the queued message `id`.
+    pub fn remove(&self, id: &str) -> Result<()> {
+        let path = self.meta_path(id)?;
+
+        if !path.exists() {
+            bail!("Cannot find queued message `{id}`");
+        }
+
+        fs::remove_file(&path).with_context(|| format!("Remove `{}` error", path.display()))?;
+
+        let path = self.eml_path(id)?;
+        fs::remove_file(&path).with_context(|| format!("Remove `{}` error", path.display()))
+    }
+
+    fn eml_path(&self, id: &str) -> Result<PathBuf> {
+        self.path(id, "eml")
+    }
+
+    fn meta_path(&self, id: &str) -> Result<PathBuf> {
+        self.path(id, "toml")
+    }
+
+    /// Path of the `ext` file of the queued message `id`, refusing
+    /// ids [`Self::push`] cannot have generated: one like `../x`
+    /// would reach files outside the outbox.
+    fn path(&self, id: &str, ext: &str) -> Result<PathBuf> {
+        if !is_valid_id(id) {
+            bail!("Invalid queued message id `{id}`");
+        }
+
+        Ok(self.dir.join(format!("{id}.{ext}")))
+    }
+}
+
+/// Whether `id` is made of the digits, `T` and `-` of the ids
+/// [`Outbox::push`] generates (e.g. `20240506T090700-2`).
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == 'T' || c == '-')
+}
+
+/// Parses a `--schedule` date: RFC 3339, or `YYYY-MM-DD HH:MM[:SS]`
+/// in the local time zone.
+pub fn parse_send_at(value: &str) -> Result<DateTime<FixedOffset>, String> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Ok(date);
+    }
+
+    let naive = ["%Y-%m-%d %H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(value, fmt).ok())
+        .ok_or_else(|| format!("invalid date `{value}`, expected `YYYY-MM-DD HH:MM`"))?;
+
+    match Local.from_local_datetime(&naive).earliest() {
+        Some(date) => Ok(date.fixed_offset()),
+        None => Err(format!("`{value}` does not exist in the local time zone")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_ids_escaping_the_outbox() {
+        let outbox = Outbox {
+            dir: PathBuf::from("/outbox"),
+        };
+
+        assert_eq!(
+            outbox.eml_path("20240506T090700-2").unwrap(),
+            Path::new("/outbox/20240506T090700-2.eml")
+        );
+        assert!(outbox.meta_path("../../x").is_err());
+        assert!(outbox.eml_path("/etc/passwd").is_err());
+        assert!(outbox.meta_path("").is_err());
+    }
+}
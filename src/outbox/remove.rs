@@ -0,0 +1,27 @@
+use anyhow::Result;
+use clap::Parser;
+use pimalaya_cli::printer::{Message, Printer};
+
+use crate::outbox::queue::Outbox;
+
+/// Remove messages from the outbox without sending them.
+#[derive(Debug, Parser)]
+pub struct OutboxRemoveCommand {
+    /// Ids of the queued messages, as shown by `outbox list`.
+    #[arg(value_name = "ID", required = true)]
+    pub ids: Vec<String>,
+}
+
+impl OutboxRemoveCommand {
+    pub fn execute(self, printer: &mut impl Printer) -> Result<()> {
+        let outbox = Outbox::open()?;
+
+        for id in &self.ids {
+            outbox.remove(id)?;
+        }
+
+        printer.out(Message::new(
+            "Message(s) successfully removed from the outbox",
+        ))
+    }
+}
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use anyhow::{Result, bail};
+use clap::Parser;
+use pimalaya_cli::printer::Printer;
+
+use crate::{
+    backend::Backend,
+    outbox::{flush::send_all, queue::Outbox},
+};
+
+/// Attempt to send queued messages right away.
+///
+/// Without ids, every queued message is attempted, scheduled ones
+/// included. Messages that fail again stay queued, with their attempt
+/// count and last error updated. Pass the global `--account` flag to
+/// only retry the messages of one account.
+#[derive(Debug, Parser)]
+pub struct OutboxRetryCommand {
+    /// Ids of the queued messages, as shown by `outbox list`.
+    #[arg(value_name = "ID")]
+    pub ids: Vec<String>,
+}
+
+impl OutboxRetryCommand {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        config_paths: &[PathBuf],
+        account_name: Option<&str>,
+        backend: Backend,
+    ) -> Result<()> {
+        let outbox = Outbox::open()?;
+
+        let messages = if self.ids.is_empty() {
+            outbox
+                .list()?
+                .into_iter()
+                .filter(|msg| account_name.is_none_or(|name| msg.account == name))
+                .collect()
+        } else {
+            self.ids
+                .iter()
+                .map(|id| outbox.get(id))
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let report = send_all(&outbox, config_paths, backend, messages);
+        let failed = report.failed.len();
+
+        printer.out(report)?;
+
+        if failed > 0 {
+            bail!("{failed} queued message(s) could not be sent and stay in the outbox");
+        }
+
+        Ok(())
+    }
+}
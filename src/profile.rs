@@ -0,0 +1,42 @@
+//! `--profile`: wall-clock timing of the main phases of a command
+//! (config load, backend connection, fetch, render), printed to
+//! stderr so it never mixes with the command output.
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turns timing output on for the rest of the process.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Running phase, reported when dropped. Inert when profiling is
+/// disabled.
+pub struct Span {
+    name: &'static str,
+    start: Option<Instant>,
+}
+
+/// Starts timing the phase `name`.
+pub fn span(name: &'static str) -> Span {
+    let start = ENABLED.load(Ordering::Relaxed).then(Instant::now);
+    Span { name, start }
+}
+
+/// Times `f` as the phase `name`.
+pub fn time<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    let _span = span(name);
+    f()
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            eprintln!("[profile] {:<8} {:>10.2?}", self.name, start.elapsed());
+        }
+    }
+}
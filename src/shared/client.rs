@@ -15,23 +15,63 @@
 //! routes each shared call to the appropriate one by priority. When
 //! the account also has SMTP configured, an SMTP slot is registered
 //! too so `send_message` works for IMAP/Maildir accounts; JMAP
-//! accounts send via JMAP submission. A connection failure on any
-//! registered backend (SMTP included) aborts construction.
+//! accounts send via JMAP submission. SMTP is only connected by the
+//! first [`EmailClient::send_message`], so commands that never send
+//! do not open it. A connection failure on any storage backend aborts
+//! construction.
 
-use std::ops::{Deref, DerefMut};
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    path::PathBuf,
+};
 
 use anyhow::Result;
-use io_email::client::EmailClientStd;
+use io_email::{
+    client::EmailClientStd,
+    flag::types::{Flag, IanaFlag},
+};
 
+#[cfg(feature = "jmap")]
+use crate::config::JmapConfig;
+#[cfg(feature = "smtp")]
+use crate::config::SmtpConfig;
 use crate::{
     account::context::Account,
     backend::Backend,
+    cli::load_or_wizard,
     config::{AccountConfig, Config},
 };
+#[cfg(feature = "imap")]
+use crate::{config::ImapConfig, imap::client::ImapClient};
 
 /// Cross-protocol email client backing the shared subcommands.
 pub struct EmailClient {
     inner: EmailClientStd,
+    /// Config of the IMAP backend, kept when it is the storage
+    /// backend shared calls are routed to, for the few operations
+    /// the shared API cannot express (see [`EmailClient::imap_page_uids`]).
+    #[cfg(feature = "imap")]
+    imap_config: Option<ImapConfig>,
+    /// Raw IMAP session backing those operations, see
+    /// [`EmailClient::imap_session`].
+    #[cfg(feature = "imap")]
+    imap_session: Option<ImapClient>,
+    /// Config of the JMAP backend, kept to read the addresses the
+    /// account may send as (see [`EmailClient::sender_addresses`]).
+    #[cfg(feature = "jmap")]
+    jmap_config: Option<JmapConfig>,
+    /// Config of the SMTP transport, taken by the first
+    /// [`EmailClient::send_message`] to connect it.
+    #[cfg(feature = "smtp")]
+    smtp_config: Option<SmtpConfig>,
+    /// Whether shared calls are routed to Maildir or m2dir, see
+    /// [`EmailClient::keeps_flags_on_copy`].
+    local_storage: bool,
+    /// Whether shared calls are routed to a backend whose message ids
+    /// do not change across mailboxes, see
+    /// [`EmailClient::move_messages_tracked`].
+    stable_ids: bool,
 }
 
 impl EmailClient {
@@ -42,15 +82,61 @@ impl EmailClient {
     ) -> Result<(Account, Self)> {
         let mut inner = EmailClientStd::new();
 
+        // IMAP is routed to only when no higher priority storage
+        // backend is registered.
+        #[cfg(feature = "imap")]
+        let imap_is_primary = !(backend.allows_jmap() && account_config.jmap.is_some()
+            || backend.allows_gmail() && account_config.gmail.is_some()
+            || backend.allows_msgraph() && account_config.msgraph.is_some());
+        #[cfg(feature = "imap")]
+        let mut kept_imap_config = None;
+        #[cfg(feature = "jmap")]
+        let mut kept_jmap_config = None;
+
+        // Maildir and m2dir are routed to only when no remote storage
+        // backend is registered.
+        let remote = (cfg!(feature = "jmap")
+            && backend.allows_jmap()
+            && account_config.jmap.is_some())
+            || (cfg!(feature = "gmail")
+                && backend.allows_gmail()
+                && account_config.gmail.is_some())
+            || (cfg!(feature = "msgraph")
+                && backend.allows_msgraph()
+                && account_config.msgraph.is_some())
+            || (cfg!(feature = "imap") && backend.allows_imap() && account_config.imap.is_some());
+        let local = (cfg!(feature = "maildir")
+            && backend.allows_maildir()
+            && account_config.maildir.is_some())
+            || (cfg!(feature = "m2dir")
+                && backend.allows_m2dir()
+                && account_config.m2dir.is_some());
+        let local_storage = local && !remote;
+
+        // JMAP and Gmail ids identify a message wherever it is filed,
+        // and take priority over the other remote backends.
+        let stable_ids =
+            (cfg!(feature = "jmap") && backend.allows_jmap() && account_config.jmap.is_some())
+                || (cfg!(feature = "gmail")
+                    && backend.allows_gmail()
+                    && account_config.gmail.is_some());
+
+        // Read before the SMTP block below takes the config.
+        let provider_max_size = account_config
+            .smtp
+            .as_ref()
+            .and_then(|smtp| provider_max_size(&smtp.server));
+
         #[cfg(feature = "jmap")]
         if backend.allows_jmap() {
             if let Some(jmap_config) = account_config.jmap.take() {
                 use crate::jmap::client::{jmap_http_auth, parse_server_url};
 
-                let tls = jmap_config.tls.clone().into_tls(jmap_config.alpn.clone());
+                let tls = jmap_config.tls.clone().into_tls(jmap_config.alpn.clone())?;
                 let http_auth = jmap_http_auth(jmap_config.auth.clone())?;
                 let url = parse_server_url(&jmap_config.server)?;
                 inner = inner.connect_jmap(&url, &tls, http_auth)?;
+                kept_jmap_config = Some(jmap_config);
             }
         }
 
@@ -61,7 +147,10 @@ impl EmailClient {
 
                 use crate::gmail::client::gmail_token;
 
-                let tls = gmail_config.tls.clone().into_tls(gmail_config.alpn.clone());
+                let tls = gmail_config
+                    .tls
+                    .clone()
+                    .into_tls(gmail_config.alpn.clone())?;
                 let token = gmail_token(gmail_config.auth.clone())?;
                 inner = inner.connect_gmail(
                     &tls,
@@ -81,7 +170,7 @@ impl EmailClient {
                 let tls = msgraph_config
                     .tls
                     .clone()
-                    .into_tls(msgraph_config.alpn.clone());
+                    .into_tls(msgraph_config.alpn.clone())?;
                 let token = msgraph_token(msgraph_config.auth.clone())?;
                 inner = inner.connect_msgraph(
                     &tls,
@@ -99,7 +188,11 @@ impl EmailClient {
 
                 use crate::imap::id::resolve_auto_id_params;
 
-                let tls = imap_config.tls.into_tls(imap_config.alpn);
+                if imap_is_primary {
+                    kept_imap_config = Some(imap_config.clone());
+                }
+
+                let tls = imap_config.tls.into_tls(imap_config.alpn)?;
                 let auto_id = resolve_auto_id_params(&imap_config.id)?;
                 let server = crate::imap::client::parse_imap_server(&imap_config.server)?;
                 let sasl: Option<Sasl> = imap_config
@@ -136,51 +229,670 @@ impl EmailClient {
             }
         }
 
-        // Register SMTP alongside the storage backend so shared
+        // SMTP is registered alongside the storage backend so shared
         // `send_message` works for IMAP/Maildir accounts. JMAP already
         // sends via submission; the dispatch priority (JMAP → SMTP in
         // the send path) keeps that working when both are present.
         // SMTP also counts as a configured backend on its own, so
         // accounts with only `[smtp]` populated still construct and
-        // can run `message send`. SMTP is initialized regardless of
-        // the `--backend` flag so an explicit storage pin (e.g.
-        // `--backend imap`) does not drop the send transport.
+        // can run `message send`. SMTP is kept regardless of the
+        // `--backend` flag so an explicit storage pin (e.g. `--backend
+        // imap`) does not drop the send transport.
         #[cfg(feature = "smtp")]
-        if backend.allows_smtp() {
-            if let Some(smtp_config) = account_config.smtp.take() {
-                use std::net::Ipv4Addr;
+        let smtp_config = match backend.allows_smtp() {
+            true => account_config.smtp.take(),
+            false => None,
+        };
 
-                use io_email::smtp::client::SmtpClientStd;
-                use io_smtp::rfc5321::types::ehlo_domain::EhloDomain;
-                use pimalaya_stream::sasl::Sasl;
+        let mut account = Account::from(config).merge(Account::from(account_config));
+        account.send_max_size = account.send_max_size.or(provider_max_size);
 
-                let tls = smtp_config.tls.into_tls(smtp_config.alpn);
-                let domain: EhloDomain<'static> = Ipv4Addr::new(127, 0, 0, 1).into();
-                let server = crate::smtp::client::parse_smtp_server(&smtp_config.server)?;
-                let sasl: Option<Sasl> = smtp_config
-                    .sasl
-                    .and_then(|cfg| {
-                        let host = server.host_str()?;
-                        let port = server.port().unwrap_or(587);
-                        Some(cfg.try_into_sasl(host, port))
-                    })
-                    .transpose()?;
-                inner = inner.with_smtp(SmtpClientStd::connect(
-                    &server,
-                    &tls,
-                    smtp_config.starttls,
-                    domain,
-                    sasl,
-                )?);
+        let client = Self {
+            inner,
+            #[cfg(feature = "imap")]
+            imap_config: kept_imap_config,
+            #[cfg(feature = "imap")]
+            imap_session: None,
+            #[cfg(feature = "jmap")]
+            jmap_config: kept_jmap_config,
+            #[cfg(feature = "smtp")]
+            smtp_config,
+            local_storage,
+            stable_ids,
+        };
+
+        Ok((account, client))
+    }
+
+    /// Sends `raw` through the shared send path, connecting the SMTP
+    /// transport first when it is configured and not connected yet.
+    /// A failed connection is tried again by the next call.
+    pub fn send_message(&mut self, raw: Vec<u8>) -> Result<()> {
+        #[cfg(feature = "smtp")]
+        if let Some(smtp_config) = &self.smtp_config {
+            use std::{mem, net::Ipv4Addr};
+
+            use io_email::smtp::client::SmtpClientStd;
+            use io_smtp::rfc5321::types::ehlo_domain::EhloDomain;
+            use pimalaya_stream::sasl::Sasl;
+
+            let tls = smtp_config.tls.clone().into_tls(smtp_config.alpn.clone())?;
+            let domain: EhloDomain<'static> = Ipv4Addr::new(127, 0, 0, 1).into();
+            let server = crate::smtp::client::parse_smtp_server(&smtp_config.server)?;
+            let sasl: Option<Sasl> = smtp_config
+                .sasl
+                .clone()
+                .and_then(|cfg| {
+                    let host = server.host_str()?;
+                    let port = server.port().unwrap_or(587);
+                    Some(cfg.try_into_sasl(host, port))
+                })
+                .transpose()?;
+            let smtp = SmtpClientStd::connect(&server, &tls, smtp_config.starttls, domain, sasl)?;
+
+            let inner = mem::replace(&mut self.inner, EmailClientStd::new());
+            self.inner = inner.with_smtp(smtp);
+            self.smtp_config = None;
+        }
+
+        self.inner.send_message(raw)?;
+        Ok(())
+    }
+
+    /// Whether IMAP is the storage backend shared calls are routed
+    /// to, see [`Self::imap_session`].
+    #[cfg(feature = "imap")]
+    pub fn uses_imap(&self) -> bool {
+        self.imap_config.is_some()
+    }
+
+    /// Raw IMAP session for the operations the shared API cannot
+    /// express, opened on first use and reused by the following ones.
+    /// The session of the shared client is not reachable from here,
+    /// so this is a second connection (and login) next to it: callers
+    /// on common paths should only ask for it when they need to.
+    /// `None` when IMAP is not the storage backend in use.
+    #[cfg(feature = "imap")]
+    fn imap_session(&mut self) -> Result<Option<&mut ImapClient>> {
+        let Some(config) = &self.imap_config else {
+            return Ok(None);
+        };
+
+        if self.imap_session.is_none() {
+            self.imap_session = Some(ImapClient::new(config.clone())?);
+        }
+
+        Ok(self.imap_session.as_mut())
+    }
+
+    /// UIDs of the messages an envelope listing page covers, skipped
+    /// messages included, highest first, searched over the raw IMAP
+    /// session. Returns `None` when IMAP is not the storage backend in
+    /// use.
+    ///
+    /// Used to spot the messages a listing skipped because their
+    /// FETCH response could not be parsed. The page is bounded by the
+    /// `listed` UIDs rather than recomputed, the order of the listing
+    /// being the backend's: the first page extends up to the newest
+    /// message, and the whole mailbox is covered without paging.
+    /// Messages skipped at the lower edge of a later page cannot be
+    /// told apart from the next page's, so they are left out.
+    #[cfg(feature = "imap")]
+    pub fn imap_page_uids(
+        &mut self,
+        mailbox: &str,
+        page: Option<u32>,
+        page_size: Option<u32>,
+        listed: &[&str],
+    ) -> Result<Option<Vec<String>>> {
+        use io_imap::{
+            rfc3501::{search::ImapMessageSearchOptions, select::ImapMailboxSelectOptions},
+            types::{core::Vec1, search::SearchKey},
+        };
+
+        let Some(client) = self.imap_session()? else {
+            return Ok(None);
+        };
+
+        let listed: Vec<u32> = listed.iter().filter_map(|id| id.parse().ok()).collect();
+        let first_page = page.unwrap_or(1) <= 1;
+
+        let range = match (listed.iter().min(), listed.iter().max()) {
+            _ if page_size.is_none() => String::from("1:*"),
+            (Some(lo), _) if first_page => format!("{lo}:*"),
+            (Some(lo), Some(hi)) => format!("{lo}:{hi}"),
+            _ if first_page => String::from("1:*"),
+            _ => return Ok(Some(Vec::new())),
+        };
+
+        client.select(
+            mailbox.to_owned().try_into()?,
+            ImapMailboxSelectOptions::default(),
+        )?;
+
+        let mut uids: Vec<_> = client
+            .search(
+                Vec1::from(SearchKey::Uid(range.parse()?)),
+                ImapMessageSearchOptions { uid: true },
+            )?
+            .into_iter()
+            .collect();
+
+        uids.sort_unstable_by(|a, b| b.cmp(a));
+
+        // NOTE: without any listed UID the first page is searched
+        // over the whole mailbox, the oldest messages of which belong
+        // to the next pages
+        if let (true, Some(size)) = (listed.is_empty(), page_size) {
+            uids.truncate(size as usize);
+        }
+
+        Ok(Some(uids.into_iter().map(|uid| uid.to_string()).collect()))
+    }
+
+    /// Header section of the message `id` of `mailbox`, fetched with
+    /// `BODY.PEEK[HEADER]` over the raw IMAP session so the body is
+    /// never downloaded. `None` when IMAP is not the storage backend,
+    /// `Some(None)` when the server returned no header for `id`.
+    #[cfg(feature = "imap")]
+    pub fn imap_get_headers(&mut self, mailbox: &str, id: &str) -> Result<Option<Option<Vec<u8>>>> {
+        let Some(mut headers) = self.imap_fetch_headers(mailbox, &[id.to_owned()])? else {
+            return Ok(None);
+        };
+
+        Ok(Some(headers.remove(id)))
+    }
+
+    /// Header sections of the messages of the given page of
+    /// `mailbox`, keyed by UID in the server order, fetched with one
+    /// `UID FETCH (UID BODY.PEEK[HEADER])` over the raw IMAP session.
+    /// Meant for bug reports: the page is the one
+    /// [`Self::imap_page_uids`] computes, skipped messages included.
+    /// `None` when IMAP is not the storage backend.
+    #[cfg(feature = "imap")]
+    pub fn imap_page_headers(
+        &mut self,
+        mailbox: &str,
+        page: Option<u32>,
+        page_size: Option<u32>,
+        listed: &[&str],
+    ) -> Result<Option<Vec<(String, Option<Vec<u8>>)>>> {
+        let Some(uids) = self.imap_page_uids(mailbox, page, page_size, listed)? else {
+            return Ok(None);
+        };
+
+        let Some(mut headers) = self.imap_fetch_headers(mailbox, &uids)? else {
+            return Ok(None);
+        };
+
+        let page = uids
+            .into_iter()
+            .map(|uid| {
+                let header = headers.remove(&uid);
+                (uid, header)
+            })
+            .collect();
+
+        Ok(Some(page))
+    }
+
+    /// Header sections of the messages `uids` of `mailbox`, keyed by
+    /// UID, fetched with one `UID FETCH (UID BODY.PEEK[HEADER])` over
+    /// the raw IMAP session. `None` when IMAP is not the storage
+    /// backend.
+    #[cfg(feature = "imap")]
+    fn imap_fetch_headers(
+        &mut self,
+        mailbox: &str,
+        uids: &[String],
+    ) -> Result<Option<HashMap<String, Vec<u8>>>> {
+        use io_imap::{
+            rfc3501::{fetch::ImapMessageFetchOptions, select::ImapMailboxSelectOptions},
+            types::fetch::{
+                MacroOrMessageDataItemNames, MessageDataItem, MessageDataItemName, Section,
+            },
+        };
+
+        if self.imap_config.is_none() {
+            return Ok(None);
+        }
+
+        let mut headers = HashMap::new();
+
+        if uids.is_empty() {
+            return Ok(Some(headers));
+        }
+
+        let Some(client) = self.imap_session()? else {
+            return Ok(None);
+        };
+
+        client.select(
+            mailbox.to_owned().try_into()?,
+            ImapMailboxSelectOptions::default(),
+        )?;
+
+        let names = vec![
+            MessageDataItemName::Uid,
+            MessageDataItemName::BodyExt {
+                section: Some(Section::Header(None)),
+                partial: None,
+                peek: true,
+            },
+        ];
+
+        let data = client.fetch(
+            uids.join(",").parse()?,
+            MacroOrMessageDataItemNames::MessageDataItemNames(names),
+            ImapMessageFetchOptions {
+                uid: true,
+                modifiers: Vec::new(),
+            },
+        )?;
+
+        for (_, items) in data {
+            let mut uid = None;
+            let mut header = None;
+
+            for item in items {
+                match item {
+                    MessageDataItem::Uid(n) => uid = Some(n.get().to_string()),
+                    MessageDataItem::BodyExt { data, .. } => {
+                        header = data.0.map(|data| data.as_ref().to_vec())
+                    }
+                    _ => (),
+                }
+            }
+
+            if let (Some(uid), Some(header)) = (uid, header) {
+                headers.insert(uid, header);
             }
         }
 
-        let account = Account::from(config).merge(Account::from(account_config));
+        Ok(Some(headers))
+    }
+
+    /// Flags of the messages `uids` of `mailbox`, keyed by UID,
+    /// fetched with one `UID FETCH (UID FLAGS)` over the raw IMAP
+    /// session. Only the flags the shared flag model names are kept.
+    /// `None` when IMAP is not the storage backend.
+    #[cfg(feature = "imap")]
+    fn imap_fetch_flags(
+        &mut self,
+        mailbox: &str,
+        uids: &[&str],
+    ) -> Result<Option<HashMap<String, Vec<Flag>>>> {
+        use io_imap::{
+            rfc3501::{fetch::ImapMessageFetchOptions, select::ImapMailboxSelectOptions},
+            types::{
+                fetch::{MacroOrMessageDataItemNames, MessageDataItem, MessageDataItemName},
+                flag::{Flag as ImapFlag, FlagFetch},
+            },
+        };
 
-        Ok((account, Self { inner }))
+        use crate::shared::flag::arg::FlagArg;
+
+        if self.imap_config.is_none() {
+            return Ok(None);
+        }
+
+        let mut flags = HashMap::new();
+
+        if uids.is_empty() {
+            return Ok(Some(flags));
+        }
+
+        let Some(client) = self.imap_session()? else {
+            return Ok(None);
+        };
+
+        client.select(
+            mailbox.to_owned().try_into()?,
+            ImapMailboxSelectOptions::default(),
+        )?;
+
+        let names = vec![MessageDataItemName::Uid, MessageDataItemName::Flags];
+
+        let data = client.fetch(
+            uids.join(",").parse()?,
+            MacroOrMessageDataItemNames::MessageDataItemNames(names),
+            ImapMessageFetchOptions {
+                uid: true,
+                modifiers: Vec::new(),
+            },
+        )?;
+
+        for (_, items) in data {
+            let mut uid = None;
+            let mut message_flags = Vec::new();
+
+            for item in items {
+                match item {
+                    MessageDataItem::Uid(n) => uid = Some(n.get().to_string()),
+                    MessageDataItem::Flags(fetched) => {
+                        message_flags = fetched
+                            .iter()
+                            .filter_map(|flag| match flag {
+                                FlagFetch::Flag(ImapFlag::Seen) => Some(FlagArg::Seen),
+                                FlagFetch::Flag(ImapFlag::Answered) => Some(FlagArg::Answered),
+                                FlagFetch::Flag(ImapFlag::Flagged) => Some(FlagArg::Flagged),
+                                FlagFetch::Flag(ImapFlag::Draft) => Some(FlagArg::Draft),
+                                _ => None,
+                            })
+                            .map(|flag| Flag::from(&flag))
+                            .collect();
+                    }
+                    _ => (),
+                }
+            }
+
+            if let Some(uid) = uid {
+                flags.insert(uid, message_flags);
+            }
+        }
+
+        Ok(Some(flags))
+    }
+
+    /// `UIDNEXT` of `mailbox`, the UID its next message will get, read
+    /// with one `STATUS` over the raw IMAP session. `None` when IMAP
+    /// is not the storage backend.
+    #[cfg(feature = "imap")]
+    fn imap_uid_next(&mut self, mailbox: &str) -> Result<Option<u32>> {
+        use io_imap::types::status::{StatusDataItem, StatusDataItemName};
+
+        let Some(client) = self.imap_session()? else {
+            return Ok(None);
+        };
+
+        let items = client.status(
+            mailbox.to_owned().try_into()?,
+            vec![StatusDataItemName::UidNext],
+        )?;
+
+        let uid_next = items.into_iter().find_map(|item| match item {
+            StatusDataItem::UidNext(n) => Some(n.get()),
+            _ => None,
+        });
+
+        Ok(uid_next)
+    }
+
+    /// UIDs of the messages of `mailbox` from `first` on, lowest
+    /// first, searched over the raw IMAP session. `None` when IMAP is
+    /// not the storage backend.
+    #[cfg(feature = "imap")]
+    fn imap_uids_from(&mut self, mailbox: &str, first: u32) -> Result<Option<Vec<String>>> {
+        use io_imap::{
+            rfc3501::{search::ImapMessageSearchOptions, select::ImapMailboxSelectOptions},
+            types::{core::Vec1, search::SearchKey},
+        };
+
+        let Some(client) = self.imap_session()? else {
+            return Ok(None);
+        };
+
+        client.select(
+            mailbox.to_owned().try_into()?,
+            ImapMailboxSelectOptions::default(),
+        )?;
+
+        let mut uids: Vec<u32> = client
+            .search(
+                Vec1::from(SearchKey::Uid(format!("{first}:*").parse()?)),
+                ImapMessageSearchOptions { uid: true },
+            )?
+            .into_iter()
+            .map(u32::from)
+            // NOTE: `n:*` matches the last message even when its UID
+            // is lower than `n`
+            .filter(|uid| *uid >= first)
+            .collect();
+
+        uids.sort_unstable();
+
+        Ok(Some(uids.into_iter().map(|uid| uid.to_string()).collect()))
+    }
+
+    /// Moves the messages `ids` of `from` to `to`, returning the ids
+    /// they got in `to` when the backend lets them be told: the same
+    /// ids on JMAP and Gmail, the UIDs above the `UIDNEXT` read before
+    /// the move on IMAP, unless messages arrived in `to` meanwhile.
+    /// `None` otherwise.
+    pub fn move_messages_tracked(
+        &mut self,
+        from: &str,
+        to: &str,
+        ids: &[&str],
+    ) -> Result<Option<Vec<String>>> {
+        if self.stable_ids {
+            self.move_messages(from, to, ids)?;
+            return Ok(Some(ids.iter().map(|id| id.to_string()).collect()));
+        }
+
+        #[cfg(feature = "imap")]
+        if let Some(uid_next) = self.imap_uid_next(to)? {
+            self.move_messages(from, to, ids)?;
+
+            let uids = self.imap_uids_from(to, uid_next)?.unwrap_or_default();
+            return Ok((uids.len() == ids.len()).then_some(uids));
+        }
+
+        self.move_messages(from, to, ids)?;
+        Ok(None)
+    }
+
+    /// Whether [`Self::move_messages_tracked`] can tell the ids of
+    /// the moved messages, barring concurrent deliveries.
+    pub fn tracks_moved_ids(&self) -> bool {
+        #[cfg(feature = "imap")]
+        if self.imap_config.is_some() {
+            return true;
+        }
+
+        self.stable_ids
+    }
+
+    /// Hierarchy delimiter of the mailbox names: the
+    /// `imap.hierarchy-delimiter` override, otherwise the one reported
+    /// by LIST over the raw IMAP session. `None` when IMAP is not the
+    /// storage backend or its namespace is flat.
+    pub fn hierarchy_delimiter(&mut self) -> Result<Option<char>> {
+        #[cfg(feature = "imap")]
+        {
+            // NOTE: the override needs no session
+            let config = self.imap_config.as_ref();
+            if let Some(delim) = config.and_then(|config| config.hierarchy_delimiter) {
+                return Ok(Some(delim));
+            }
+
+            if let Some(client) = self.imap_session()? {
+                return client.hierarchy_delimiter("");
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Unseen and total message counts of `mailbox`. IMAP asks for
+    /// them with one `STATUS` over the raw session, so counting
+    /// several mailboxes logs in once. Other backends read them from
+    /// the mailbox listing, falling back to counting the envelopes of
+    /// the mailbox when it has no counts (Maildir).
+    pub fn message_counts(&mut self, mailbox: &str) -> Result<(u64, u64)> {
+        #[cfg(feature = "imap")]
+        if let Some(client) = self.imap_session()? {
+            use io_imap::types::status::{StatusDataItem, StatusDataItemName};
+
+            let items = client.status(
+                mailbox.to_owned().try_into()?,
+                vec![StatusDataItemName::Unseen, StatusDataItemName::Messages],
+            )?;
+
+            let (mut unseen, mut total) = (0, 0);
+
+            for item in items {
+                match item {
+                    StatusDataItem::Unseen(n) => unseen = n as u64,
+                    StatusDataItem::Messages(n) => total = n as u64,
+                    _ => (),
+                }
+            }
+
+            return Ok((unseen, total));
+        }
+
+        let counts = self
+            .list_mailboxes(true)?
+            .into_iter()
+            .find(|m| m.name == mailbox)
+            .and_then(|m| Some((m.unread?, m.total?)));
+
+        if let Some(counts) = counts {
+            return Ok(counts);
+        }
+
+        let seen = Flag::from_iana(IanaFlag::Seen);
+        let envelopes = self.list_envelopes(mailbox, None, None, false)?;
+        let unseen = envelopes
+            .iter()
+            .filter(|env| !env.flags.contains(&seen))
+            .count();
+
+        Ok((unseen as u64, envelopes.len() as u64))
+    }
+
+    /// Addresses the server lets the account send as, `None` when it
+    /// cannot tell. Only JMAP exposes them, as `Identity` objects
+    /// (RFC 8621), read over an extra session.
+    pub fn sender_addresses(&self) -> Result<Option<Vec<String>>> {
+        #[cfg(feature = "jmap")]
+        if let Some(config) = &self.jmap_config {
+            use io_jmap::rfc8621::identity::get::JmapIdentityGetOptions;
+
+            let mut client = crate::jmap::client::JmapClient::new(config.clone())?;
+            let output = client.identity_get(JmapIdentityGetOptions { ids: None })?;
+            let addresses = output.identities.into_iter().map(|id| id.email).collect();
+            return Ok(Some(addresses));
+        }
+
+        Ok(None)
+    }
+
+    /// Whether copying messages within the account keeps their
+    /// flags. IMAP `COPY` and JMAP `mailboxIds` patches do; Maildir
+    /// and m2dir store the copy as a new message, so its flags have
+    /// to be set again. Moves keep them on every backend.
+    pub fn keeps_flags_on_copy(&self) -> bool {
+        !self.local_storage
+    }
+
+    /// Flags of the messages `ids` of `mailbox`, keyed by id. Fetched
+    /// in one request over the raw session when IMAP is the storage
+    /// backend; other backends read them from the envelopes of the
+    /// whole mailbox, the shared API having no per-message flag
+    /// lookup.
+    pub fn get_flags(&mut self, mailbox: &str, ids: &[&str]) -> Result<HashMap<String, Vec<Flag>>> {
+        #[cfg(feature = "imap")]
+        if let Some(flags) = self.imap_fetch_flags(mailbox, ids)? {
+            return Ok(flags);
+        }
+
+        let flags = self
+            .list_envelopes(mailbox, None, None, false)?
+            .into_iter()
+            .filter(|envelope| ids.contains(&envelope.id.as_str()))
+            .map(|envelope| (envelope.id, envelope.flags.into_iter().collect()))
+            .collect();
+
+        Ok(flags)
+    }
+
+    /// Header sections of the messages `ids` of `mailbox`, keyed by
+    /// id. Fetched in one request over the raw session when IMAP
+    /// is the storage backend, which leaves out the messages the
+    /// server returns no header for; other backends fetch each full
+    /// message.
+    pub fn get_headers(&mut self, mailbox: &str, ids: &[&str]) -> Result<HashMap<String, Vec<u8>>> {
+        #[cfg(feature = "imap")]
+        {
+            let uids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+
+            if let Some(headers) = self.imap_fetch_headers(mailbox, &uids)? {
+                return Ok(headers);
+            }
+        }
+
+        let mut headers = HashMap::new();
+
+        for id in ids {
+            headers.insert(id.to_string(), self.get_message(mailbox, id)?);
+        }
+
+        Ok(headers)
     }
 }
 
+/// Loads the configuration, picks the account named `account_name`
+/// (the default one when `None`) and builds the shared client for it.
+/// Returns the live client paired with the merged account.
+pub fn build_email_client(
+    config_paths: &[PathBuf],
+    account_name: Option<&str>,
+    backend: Backend,
+) -> Result<(Account, EmailClient)> {
+    let mut config = load_or_wizard(config_paths)?;
+    let (_, account_config) = config.select_account(account_name)?;
+    EmailClient::new(config, account_config, backend)
+}
+
+/// Merged account of `account_config`, for the commands that do not
+/// connect to a backend. The send size limit falls back to the one
+/// of the SMTP provider, like [`EmailClient::new`] does.
+pub fn merged_account(config: Config, account_config: AccountConfig) -> Account {
+    let provider_max_size = account_config
+        .smtp
+        .as_ref()
+        .and_then(|smtp| provider_max_size(&smtp.server));
+
+    let mut account = Account::from(config).merge(Account::from(account_config));
+    account.send_max_size = account.send_max_size.or(provider_max_size);
+    account
+}
+
+/// Known maximum message size of well-known SMTP providers, matched
+/// on the domain suffix of the configured server host.
+fn provider_max_size(server: &str) -> Option<u64> {
+    const MB: u64 = 1000 * 1000;
+    const LIMITS: &[(&str, u64)] = &[
+        ("gmail.com", 25 * MB),
+        ("googlemail.com", 25 * MB),
+        ("office365.com", 25 * MB),
+        ("outlook.com", 25 * MB),
+        ("yahoo.com", 25 * MB),
+        ("mail.me.com", 20 * MB),
+        ("fastmail.com", 70 * MB),
+    ];
+
+    let server = server.to_lowercase();
+    let authority = server.split_once("://").map_or(&*server, |(_, rest)| rest);
+    let authority = authority.split(['/', '?']).next().unwrap_or_default();
+    let host = authority
+        .rsplit_once(':')
+        .map_or(authority, |(host, _)| host);
+    let host = host.trim_end_matches('.');
+
+    LIMITS
+        .iter()
+        .find(|(domain, _)| {
+            host == *domain
+                || host
+                    .strip_suffix(domain)
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
+        .map(|(_, limit)| *limit)
+}
+
 impl Deref for EmailClient {
     type Target = EmailClientStd;
 
@@ -194,3 +906,20 @@ impl DerefMut for EmailClient {
         &mut self.inner
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_provider_domain_suffix() {
+        assert_eq!(provider_max_size("smtp.gmail.com"), Some(25_000_000));
+        assert_eq!(
+            provider_max_size("smtps://smtp.fastmail.com:465"),
+            Some(70_000_000)
+        );
+        assert_eq!(provider_max_size("mail.me.com."), Some(20_000_000));
+        assert_eq!(provider_max_size("smtp.notgmail.com"), None);
+        assert_eq!(provider_max_size("gmail.com.example.org"), None);
+    }
+}
@@ -5,7 +5,9 @@ use pimalaya_cli::printer::Printer;
 use crate::account::context::Account;
 use crate::shared::{
     client::EmailClient,
-    envelope::{list::EnvelopeListCommand, search::EnvelopeSearchCommand},
+    envelope::{
+        count::EnvelopeCountCommand, list::EnvelopeListCommand, search::EnvelopeSearchCommand,
+    },
 };
 
 /// Manage envelopes using the shared API.
@@ -19,6 +21,7 @@ pub enum EnvelopeCommand {
     List(EnvelopeListCommand),
     #[command(visible_alias = "sr")]
     Search(EnvelopeSearchCommand),
+    Count(EnvelopeCountCommand),
 }
 
 impl EnvelopeCommand {
@@ -27,10 +30,12 @@ impl EnvelopeCommand {
         printer: &mut impl Printer,
         account: &mut Account,
         client: &mut EmailClient,
+        account_name: &str,
     ) -> Result<()> {
         match self {
-            Self::List(cmd) => cmd.execute(printer, account, client),
+            Self::List(cmd) => cmd.execute(printer, account, client, account_name),
             Self::Search(cmd) => cmd.execute(printer, account, client),
+            Self::Count(cmd) => cmd.execute(printer, account, client),
         }
     }
 }
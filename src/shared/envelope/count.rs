@@ -0,0 +1,62 @@
+use std::fmt;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use pimalaya_cli::printer::Printer;
+use serde::Serialize;
+
+use crate::account::context::Account;
+use crate::shared::{client::EmailClient, mailbox::arg::MailboxArg};
+
+/// Count the envelopes of a mailbox.
+///
+/// Prints a bare number, or `{"count": N}` with `--json`, for status
+/// bars and scripts. Nothing is listed when the server keeps the
+/// counts (IMAP `STATUS`, mailbox counters of the other remote
+/// backends); otherwise the envelopes of the mailbox are counted.
+#[derive(Debug, Parser)]
+pub struct EnvelopeCountCommand {
+    #[command(flatten)]
+    pub mailbox: MailboxArg,
+
+    /// Only count the envelopes of unread messages, lacking the
+    /// `seen` flag.
+    #[arg(long)]
+    pub unseen: bool,
+}
+
+impl EnvelopeCountCommand {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        account: &Account,
+        client: &mut EmailClient,
+    ) -> Result<()> {
+        let count = self.count(account, client)?;
+        printer.out(count)
+    }
+
+    /// Counts the envelopes of the mailbox, without printing them.
+    pub fn count(&self, account: &Account, client: &mut EmailClient) -> Result<EnvelopesCount> {
+        let mailbox = self.mailbox.resolve(account)?;
+
+        let (unseen, total) = client
+            .message_counts(&mailbox)
+            .with_context(|| format!("Count messages of mailbox {mailbox} error"))?;
+
+        let count = if self.unseen { unseen } else { total };
+        Ok(EnvelopesCount { count })
+    }
+}
+
+/// Number of envelopes printed by `envelope count`.
+#[derive(Debug, Serialize)]
+pub struct EnvelopesCount {
+    pub count: u64,
+}
+
+impl fmt::Display for EnvelopesCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.count)
+    }
+}
@@ -0,0 +1,69 @@
+//! RFC 2047 decoding of the envelope fields shown to the user.
+//!
+//! Backends do not all decode encoded-words (`=?UTF-8?B?…?=`) the
+//! same way, so the subject and address names are decoded once more
+//! here, before reaching the table or the JSON output. Already
+//! decoded values are left untouched.
+
+use io_email::{address::Address, envelope::types::Envelope};
+use mail_parser::MessageParser;
+
+/// Decodes the subject and the sender and recipient names of
+/// `envelopes` in place.
+pub fn decode_envelopes(envelopes: &mut [Envelope]) {
+    for envelope in envelopes {
+        if let Some(subject) = decode_words(&envelope.subject) {
+            envelope.subject = subject;
+        }
+
+        decode_names(&mut envelope.from);
+        decode_names(&mut envelope.to);
+    }
+}
+
+fn decode_names(addrs: &mut [Address]) {
+    for addr in addrs {
+        if let Some(name) = addr.name.as_deref().and_then(decode_words) {
+            addr.name = Some(name);
+        }
+    }
+}
+
+/// Decodes the encoded-words of `value`, `None` when it has none.
+///
+/// The value is parsed as an unstructured header by `mail_parser`,
+/// which joins adjacent encoded-words (with or without whitespace
+/// between them, RFC 2047 §6.2), accepts both the `B` and the `Q`
+/// encodings and converts each word from its own charset.
+pub fn decode_words(value: &str) -> Option<String> {
+    if !value.contains("=?") {
+        return None;
+    }
+
+    let header = format!("Subject: {value}\r\n\r\n");
+    let message = MessageParser::new().parse_headers(header.as_bytes())?;
+    let decoded = message.subject()?;
+
+    if decoded == value {
+        None
+    } else {
+        Some(decoded.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_adjacent_words_in_different_charsets() {
+        let subject = "=?UTF-8?B?w6l0w6k=?==?ISO-8859-1?Q?_=E0_Paris?=";
+        assert_eq!(decode_words(subject).as_deref(), Some("été à Paris"));
+    }
+
+    #[test]
+    fn leaves_plain_values_untouched() {
+        assert_eq!(decode_words("Hello =? world"), None);
+        assert_eq!(decode_words("Hello"), None);
+    }
+}
@@ -0,0 +1,179 @@
+//! `envelope list --dump-raw-fetch`: a bug report artifact for
+//! listings skipping messages.
+//!
+//! The raw FETCH responses the listing failed to parse never reach
+//! himalaya (the IMAP library logs them at debug level, then drops
+//! them), so the page is fetched once more over an extra connection:
+//! the raw header section of each message of the page is written to
+//! a file, marked as listed or skipped. Servers build envelopes from
+//! these headers, so they are what reproduces a parsing failure.
+
+use std::{collections::HashSet, fs, path::Path};
+
+use anyhow::{Context, Result};
+use io_email::envelope::types::Envelope;
+use log::warn;
+
+use crate::shared::client::EmailClient;
+
+/// Headers whose values identify people, their hosts or their
+/// conversations, masked by `--redact`.
+const REDACTED_HEADERS: [&str; 14] = [
+    "from",
+    "to",
+    "cc",
+    "bcc",
+    "reply-to",
+    "sender",
+    "subject",
+    "return-path",
+    "delivered-to",
+    "x-original-to",
+    "in-reply-to",
+    "references",
+    "message-id",
+    "received",
+];
+
+/// Writes the raw header sections of the listed page of `mailbox` to
+/// `path`, each under a `* UID <uid> listed|skipped` line.
+#[cfg(feature = "imap")]
+pub fn dump_raw_fetch(
+    client: &mut EmailClient,
+    mailbox: &str,
+    page: Option<u32>,
+    page_size: Option<u32>,
+    envelopes: &[Envelope],
+    path: &Path,
+    redact: bool,
+) -> Result<()> {
+    let listed: Vec<&str> = envelopes.iter().map(|env| env.id.as_str()).collect();
+
+    let Some(headers) = client.imap_page_headers(mailbox, page, page_size, &listed)? else {
+        warn!("--dump-raw-fetch is only supported by the IMAP backend, ignoring it");
+        return Ok(());
+    };
+
+    let listed: HashSet<&str> = listed.into_iter().collect();
+
+    let mut dump = format!(
+        "# himalaya {} envelope list --dump-raw-fetch\r\n# mailbox: {mailbox}\r\n",
+        env!("CARGO_PKG_VERSION"),
+    );
+
+    if let (Some(page), Some(size)) = (page, page_size) {
+        dump.push_str(&format!("# page: {page}, page size: {size}\r\n"));
+    }
+
+    let mut dump = dump.into_bytes();
+
+    for (uid, header) in headers {
+        let status = if listed.contains(uid.as_str()) {
+            "listed"
+        } else {
+            "skipped"
+        };
+
+        dump.extend_from_slice(format!("\r\n* UID {uid} {status}\r\n").as_bytes());
+
+        match header {
+            Some(header) if redact => dump.extend(redact_headers(&header)),
+            Some(header) => dump.extend(header),
+            None => dump.extend_from_slice(b"(no header returned)\r\n"),
+        }
+    }
+
+    fs::write(path, dump).with_context(|| format!("Write dump `{}` error", path.display()))?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "imap"))]
+pub fn dump_raw_fetch(
+    _client: &mut EmailClient,
+    _mailbox: &str,
+    _page: Option<u32>,
+    _page_size: Option<u32>,
+    _envelopes: &[Envelope],
+    _path: &Path,
+    _redact: bool,
+) -> Result<()> {
+    warn!("--dump-raw-fetch is only supported by the IMAP backend, ignoring it");
+    Ok(())
+}
+
+/// Masks the values of the [`REDACTED_HEADERS`], continuation lines
+/// included: letters and digits become `x`, other bytes are kept, so
+/// the structure a parser trips on (quotes, brackets, encoded-word
+/// delimiters, folding) survives.
+fn redact_headers(raw: &[u8]) -> Vec<u8> {
+    let mut redacted = Vec::with_capacity(raw.len());
+    let mut masking = false;
+
+    for line in raw.split_inclusive(|b| *b == b'\n') {
+        let folded = line.first().is_some_and(|b| *b == b' ' || *b == b'\t');
+
+        if folded {
+            if masking {
+                redacted.extend(line.iter().map(mask));
+            } else {
+                redacted.extend_from_slice(line);
+            }
+            continue;
+        }
+
+        let Some(colon) = line.iter().position(|b| *b == b':') else {
+            masking = false;
+            redacted.extend_from_slice(line);
+            continue;
+        };
+
+        let name = String::from_utf8_lossy(&line[..colon])
+            .trim()
+            .to_lowercase();
+        masking = REDACTED_HEADERS.contains(&name.as_str());
+
+        redacted.extend_from_slice(&line[..=colon]);
+
+        if masking {
+            redacted.extend(line[colon + 1..].iter().map(mask));
+        } else {
+            redacted.extend_from_slice(&line[colon + 1..]);
+        }
+    }
+
+    redacted
+}
+
+fn mask(byte: &u8) -> u8 {
+    if byte.is_ascii_alphanumeric() || !byte.is_ascii() {
+        b'x'
+    } else {
+        *byte
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::redact_headers;
+
+    #[test]
+    fn masks_hosts_and_message_ids() {
+        let raw = b"Received: from mx.x.org (1.2.3.4)\r\nMessage-ID: <a1@h.io>\r\nReferences: <b2@h.io>\r\n\r\n";
+
+        assert_eq!(
+            String::from_utf8(redact_headers(raw)).unwrap(),
+            "Received: xxxx xx.x.xxx (x.x.x.x)\r\nMessage-ID: <xx@x.xx>\r\nReferences: <xx@x.xx>\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn masks_people_and_subjects_only() {
+        let raw = b"From: \"Doe, J.\" <jd@x.org>\r\nSubject: =?UTF-8?Q?Caf=C3=A9?=\r\n \tnext\r\nDate: Mon, 1 Jan 2024\r\n\r\n";
+
+        assert_eq!(
+            String::from_utf8(redact_headers(raw)).unwrap(),
+            "From: \"xxx, x.\" <xx@x.xxx>\r\nSubject: =?xxx-x?x?xxx=xx=xx?=\r\n \txxxx\r\nDate: Mon, 1 Jan 2024\r\n\r\n"
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! `~subject <text>` clause of `envelope search`: approximate subject
+//! matching, applied client-side to the envelopes the server query
+//! returned.
+//!
+//! Subjects are compared word by word: each word of the clause is
+//! paired with the closest subject word, by Levenshtein distance
+//! relative to the longer of both, and the clause matches when the
+//! average similarity reaches the threshold. A subject containing the
+//! clause text as is always matches.
+
+use anyhow::{Result, bail};
+
+use crate::shared::levenshtein::levenshtein;
+
+/// Keyword introducing the fuzzy subject clause.
+const KEYWORD: &str = "~subject";
+
+/// Query keywords ending an unquoted clause text.
+const STOP_WORDS: [&str; 3] = ["and", "or", "order"];
+
+/// Approximate subject text of a `~subject` clause.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FuzzySubject {
+    text: String,
+    words: Vec<String>,
+    /// Whether the clause is negated (`not ~subject <text>`).
+    negated: bool,
+}
+
+impl FuzzySubject {
+    /// Pulls the `~subject` clause out of `query`, with the `not`
+    /// negating it and the `and` joining it to the rest, and returns
+    /// the remaining query.
+    ///
+    /// The clause text is either quoted (`~subject "weekly report"`)
+    /// or runs up to the next `and`, `or` or `order by`. Joining the
+    /// clause with `or` fails: it is applied to the envelopes the
+    /// server returned, so it can only narrow them down.
+    pub fn take(query: &str) -> Result<(String, Option<Self>)> {
+        let Some(start) = find_keyword(query) else {
+            return Ok((query.to_owned(), None));
+        };
+
+        let mut before = query[..start].trim_end();
+
+        let negated = match strip_last_word(before, "not") {
+            Some(rest) => {
+                before = rest;
+                true
+            }
+            None => false,
+        };
+
+        let rest = query[start + KEYWORD.len()..].trim_start();
+
+        let (text, after) = match rest.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once('"') {
+                Some((text, after)) => (text, after),
+                None => (quoted, ""),
+            },
+            None => {
+                let end = rest
+                    .match_indices(' ')
+                    .map(|(i, _)| i)
+                    .find(|i| {
+                        let next = rest[i + 1..].split_whitespace().next();
+                        next.is_some_and(|word| STOP_WORDS.contains(&word))
+                    })
+                    .unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+
+        let after = after.trim_start();
+
+        if strip_last_word(before, "or").is_some() || after == "or" || after.starts_with("or ") {
+            bail!("`~subject` can only be combined with `and`, not `or`");
+        }
+
+        let remaining = match (strip_last_word(before, "and"), after.strip_prefix("and ")) {
+            (Some(before), _) => format!("{before} {after}"),
+            (_, Some(after)) => format!("{before} {}", after.trim_start()),
+            _ => format!("{before} {after}"),
+        };
+
+        let fuzzy = Self {
+            text: text.trim().to_lowercase(),
+            words: words(text),
+            negated,
+        };
+
+        Ok((remaining.trim().to_owned(), Some(fuzzy)))
+    }
+
+    /// Whether `subject` approximately matches the clause text, with
+    /// a similarity of at least `threshold` (between 0 and 1), or
+    /// does not when the clause is negated.
+    pub fn matches(&self, subject: &str, threshold: f64) -> bool {
+        self.is_similar(subject, threshold) != self.negated
+    }
+
+    fn is_similar(&self, subject: &str, threshold: f64) -> bool {
+        if self.words.is_empty() || subject.to_lowercase().contains(&self.text) {
+            return true;
+        }
+
+        let subject = words(subject);
+
+        let total: f64 = self
+            .words
+            .iter()
+            .map(|word| {
+                subject
+                    .iter()
+                    .map(|candidate| similarity(word, candidate))
+                    .fold(0.0, f64::max)
+            })
+            .sum();
+
+        total / self.words.len() as f64 >= threshold
+    }
+}
+
+/// Byte offset of the `~subject` keyword in `query`, as a word of
+/// its own.
+fn find_keyword(query: &str) -> Option<usize> {
+    query.match_indices(KEYWORD).map(|(i, _)| i).find(|i| {
+        let prev = query[..*i].chars().next_back();
+        let next = query[i + KEYWORD.len()..].chars().next();
+        prev.is_none_or(char::is_whitespace) && next.is_none_or(|c| c.is_whitespace() || c == '"')
+    })
+}
+
+/// `text` without its last word when it is `word`, trimmed.
+fn strip_last_word<'a>(text: &'a str, word: &str) -> Option<&'a str> {
+    let rest = text.strip_suffix(word)?;
+    let whole = rest.is_empty() || rest.ends_with(char::is_whitespace);
+    whole.then(|| rest.trim_end())
+}
+
+/// Lowercased alphanumeric words of `text`.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Similarity of two words, from 0 (nothing in common) to 1 (equal).
+fn similarity(a: &str, b: &str) -> f64 {
+    let len = a.chars().count().max(b.chars().count());
+
+    if len == 0 {
+        return 1.0;
+    }
+
+    1.0 - levenshtein(a, b) as f64 / len as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_clause_out_of_query() {
+        let (rest, fuzzy) =
+            FuzzySubject::take(r#"from bob and ~subject "weekly reprot" order by date"#).unwrap();
+        assert_eq!(rest, "from bob order by date");
+        assert_eq!(fuzzy.unwrap().words, ["weekly", "reprot"]);
+
+        let (rest, fuzzy) = FuzzySubject::take("~subject weekly reprot and flag seen").unwrap();
+        assert_eq!(rest, "flag seen");
+        assert_eq!(fuzzy.unwrap().text, "weekly reprot");
+
+        let (rest, fuzzy) = FuzzySubject::take("subject report").unwrap();
+        assert_eq!(rest, "subject report");
+        assert!(fuzzy.is_none());
+    }
+
+    #[test]
+    fn takes_negated_clause_and_refuses_or() {
+        let (rest, fuzzy) = FuzzySubject::take("from bob and not ~subject newsletter").unwrap();
+        assert_eq!(rest, "from bob");
+        let fuzzy = fuzzy.unwrap();
+        assert!(fuzzy.negated);
+        assert!(!fuzzy.matches("Weekly newsletter", 0.8));
+        assert!(fuzzy.matches("Invoice", 0.8));
+
+        let (rest, fuzzy) = FuzzySubject::take("not ~subject newsletter and flag seen").unwrap();
+        assert_eq!(rest, "flag seen");
+        assert!(fuzzy.unwrap().negated);
+
+        assert!(FuzzySubject::take("from bob or ~subject newsletter").is_err());
+        assert!(FuzzySubject::take("~subject newsletter or from bob").is_err());
+        assert!(FuzzySubject::take("from bob or not ~subject newsletter").is_err());
+    }
+
+    #[test]
+    fn matches_approximate_subjects() {
+        let (_, fuzzy) = FuzzySubject::take("~subject weekly reprot").unwrap();
+        let fuzzy = fuzzy.unwrap();
+
+        assert!(fuzzy.matches("Re: Weekly report (W19)", 0.8));
+        assert!(!fuzzy.matches("Monthly invoice", 0.8));
+        assert!(fuzzy.matches("weekly reprot", 1.0));
+    }
+}
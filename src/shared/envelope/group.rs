@@ -0,0 +1,168 @@
+//! `envelope list --group-by`: a presentation layer partitioning the
+//! fetched [`Envelopes`] into titled sections.
+
+use std::{fmt, mem};
+
+use chrono::{DateTime, Datelike, Days, FixedOffset, NaiveDate};
+use clap::ValueEnum;
+use io_email::envelope::types::Envelope;
+use serde::{Serialize, Serializer, ser::SerializeMap};
+
+use crate::config::TimezoneConfig;
+use crate::shared::envelope::list::{Envelopes, EnvelopesOutput, format_addresses};
+
+/// Envelope property sections are built from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum GroupBy {
+    /// One section per sender.
+    From,
+    /// Relative date buckets: today, yesterday, this week, this
+    /// month, then older.
+    Date,
+    /// One section per mailbox.
+    Folder,
+}
+
+/// Envelopes split into sections, in order of first appearance
+/// (most recent first, like the listing itself). Renders one titled
+/// table per section, or a JSON object keyed by section title.
+pub struct GroupedEnvelopes {
+    groups: Vec<(String, Envelopes)>,
+    /// Line rendered when there is no section at all.
+    empty: String,
+}
+
+impl GroupedEnvelopes {
+    /// Splits `envelopes` listed from `mailbox` by `group_by`. Each
+    /// envelope's own mailbox wins over `mailbox` when the listing
+    /// spans several mailboxes.
+    pub fn new(mut template: Envelopes, group_by: GroupBy, mailbox: &str) -> Self {
+        let tz = template.datetime_tz;
+        let today = tz.today();
+        let mut groups: Vec<(String, Envelopes)> = Vec::new();
+
+        let envelopes = mem::take(&mut template.envelopes);
+        let mut accounts = mem::take(&mut template.accounts).into_iter();
+        let mut mailboxes = mem::take(&mut template.mailboxes).into_iter();
+        let mut headers = mem::take(&mut template.headers);
+        let invalid = mem::take(&mut template.invalid);
+        let empty = template.empty.clone();
+
+        for envelope in envelopes {
+            let source = mailboxes.next();
+
+            let title = match group_by {
+                GroupBy::From => from_title(&envelope),
+                GroupBy::Date => date_title(envelope.date, tz, today),
+                GroupBy::Folder => source.clone().unwrap_or_else(|| mailbox.to_owned()),
+            };
+
+            let index = match groups.iter().position(|(t, _)| *t == title) {
+                Some(index) => index,
+                None => {
+                    groups.push((title, template.clone()));
+                    groups.len() - 1
+                }
+            };
+
+            let group = &mut groups[index].1;
+
+            if let Some(headers) = headers.remove(&envelope.id) {
+                group.headers.insert(envelope.id.clone(), headers);
+            }
+
+            if let Some(account) = accounts.next() {
+                group.accounts.push(account);
+            }
+
+            // NOTE: a folder section names its mailbox in the title
+            // already, no need for the column
+            if let Some(source) = source.filter(|_| group_by != GroupBy::Folder) {
+                group.mailboxes.push(source);
+            }
+
+            group.envelopes.push(envelope);
+        }
+
+        if !invalid.is_empty() {
+            let mut group = template;
+            group.invalid = invalid;
+            groups.push((String::from("Unparseable"), group));
+        }
+
+        Self { groups, empty }
+    }
+}
+
+fn from_title(envelope: &Envelope) -> String {
+    let from = format_addresses(&envelope.from);
+
+    if from.is_empty() {
+        String::from("Unknown sender")
+    } else {
+        from
+    }
+}
+
+fn date_title(date: Option<DateTime<FixedOffset>>, tz: TimezoneConfig, today: NaiveDate) -> String {
+    let Some(date) = date else {
+        return String::from("No date");
+    };
+
+    let date = tz.date_naive(date);
+    let yesterday = today.checked_sub_days(Days::new(1));
+    let week_start =
+        today.checked_sub_days(Days::new(today.weekday().num_days_from_monday() as u64));
+
+    let title = if date >= today {
+        "Today"
+    } else if Some(date) == yesterday {
+        "Yesterday"
+    } else if week_start.is_some_and(|start| date >= start) {
+        "This week"
+    } else if date.year() == today.year() && date.month() == today.month() {
+        "This month"
+    } else {
+        "Older"
+    };
+
+    title.to_owned()
+}
+
+impl Serialize for GroupedEnvelopes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.groups.len()))?;
+        for (title, envelopes) in &self.groups {
+            map.serialize_entry(title, envelopes)?;
+        }
+        map.end()
+    }
+}
+
+impl fmt::Display for GroupedEnvelopes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.groups.is_empty() {
+            writeln!(f)?;
+            return writeln!(f, "{}", self.empty);
+        }
+
+        for (title, envelopes) in &self.groups {
+            let count = envelopes.envelopes.len() + envelopes.invalid.len();
+
+            writeln!(f)?;
+
+            // NOTE: Markdown tables need a blank line above them, the
+            // comfy table brings its own line break
+            if envelopes.output == EnvelopesOutput::Markdown {
+                writeln!(f, "## {title} ({count})")?;
+                writeln!(f)?;
+            } else {
+                write!(f, "{title} ({count})")?;
+            }
+
+            write!(f, "{envelopes}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+//! `envelope list --highlight`: emphasis of the subject and sender
+//! portions matching a pattern.
+//!
+//! Table cells can only be styled as a whole, so matches are first
+//! wrapped in zero-width marker characters, which the table ignores
+//! when measuring and truncating cells. Once the table is rendered,
+//! [`render`] turns the markers into background color escapes, or
+//! drops them when the table is rendered without styling.
+
+use crossterm::style::{Color, SetBackgroundColor};
+use regex::Regex;
+
+/// Pattern to emphasize and the background color emphasizing it.
+#[derive(Clone, Debug)]
+pub struct Highlight {
+    pub regex: Regex,
+    pub color: Color,
+}
+
+/// Marks the start of a match (ZERO WIDTH SPACE).
+const START: char = '\u{200B}';
+
+/// Marks the end of a match (WORD JOINER).
+const END: char = '\u{2060}';
+
+/// Wraps the non-empty matches of `regex` in `text` with markers.
+pub fn mark(text: &str, regex: &Regex) -> String {
+    let text: String = text.chars().filter(|c| *c != START && *c != END).collect();
+    let mut marked = String::with_capacity(text.len() + 8);
+    let mut last = 0;
+
+    for m in regex.find_iter(&text).filter(|m| !m.is_empty()) {
+        marked.push_str(&text[last..m.start()]);
+        marked.push(START);
+        marked.push_str(m.as_str());
+        marked.push(END);
+        last = m.end();
+    }
+
+    marked.push_str(&text[last..]);
+    marked
+}
+
+/// Replaces the markers of the rendered `table` by escapes setting
+/// the `color` background, or removes them when `color` is `None`.
+///
+/// A match cut by cell truncation loses its end marker, so any
+/// highlight still open is closed at the end of its line.
+pub fn render(table: &str, color: Option<Color>) -> String {
+    let Some(color) = color else {
+        return table.replace([START, END], "");
+    };
+
+    let start = SetBackgroundColor(color).to_string();
+    let end = SetBackgroundColor(Color::Reset).to_string();
+    let mut rendered = String::with_capacity(table.len());
+
+    for line in table.split_inclusive('\n') {
+        let mut open = false;
+
+        for c in line.chars() {
+            match c {
+                START => {
+                    rendered.push_str(&start);
+                    open = true;
+                }
+                END => {
+                    rendered.push_str(&end);
+                    open = false;
+                }
+                '\n' if open => {
+                    rendered.push_str(&end);
+                    rendered.push('\n');
+                    open = false;
+                }
+                c => rendered.push(c),
+            }
+        }
+
+        if open {
+            rendered.push_str(&end);
+        }
+    }
+
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_matches_and_closes_truncated_ones() {
+        let regex = Regex::new("(?i)invoice").unwrap();
+        let marked = mark("Invoice 42, invoice 43", &regex);
+
+        assert_eq!(render(&marked, None), "Invoice 42, invoice 43");
+        assert_eq!(
+            render(&marked, Some(Color::Yellow)),
+            "\x1b[48;5;11mInvoice\x1b[49m 42, \x1b[48;5;11minvoice\x1b[49m 43"
+        );
+
+        let truncated = format!("| {START}Invoi... |\n| x |");
+        assert_eq!(
+            render(&truncated, Some(Color::Yellow)),
+            "| \x1b[48;5;11mInvoi... |\x1b[49m\n| x |"
+        );
+    }
+}
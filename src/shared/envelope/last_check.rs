@@ -0,0 +1,61 @@
+//! `envelope list --since-last-check`: when each account mailbox was
+//! last listed, persisted between invocations.
+//!
+//! Markers live in `last-check.toml` under the user data directory
+//! (`$XDG_DATA_HOME/himalaya` on Linux), as RFC 3339 dates keyed by
+//! account name then mailbox name.
+
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, FixedOffset};
+
+/// Last check markers of every account mailbox.
+pub struct LastChecks {
+    path: PathBuf,
+    checks: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl LastChecks {
+    /// Loads the markers, none when the file does not exist yet.
+    pub fn load() -> Result<Self> {
+        let Some(data_dir) = dirs::data_dir() else {
+            bail!("Cannot find the user data directory for the last check markers");
+        };
+
+        let path = data_dir.join("himalaya").join("last-check.toml");
+
+        let checks = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Read `{}` error", path.display()))?;
+            toml::from_str(&content).with_context(|| format!("Parse `{}` error", path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self { path, checks })
+    }
+
+    /// Date `mailbox` of `account` was last checked at.
+    pub fn get(&self, account: &str, mailbox: &str) -> Option<DateTime<FixedOffset>> {
+        let date = self.checks.get(account)?.get(mailbox)?;
+        DateTime::parse_from_rfc3339(date).ok()
+    }
+
+    /// Records `date` as the last check of `mailbox` of `account`,
+    /// then writes the markers back.
+    pub fn set(&mut self, account: &str, mailbox: &str, date: DateTime<FixedOffset>) -> Result<()> {
+        self.checks
+            .entry(account.to_owned())
+            .or_default()
+            .insert(mailbox.to_owned(), date.to_rfc3339());
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Create `{}` error", dir.display()))?;
+        }
+
+        let content = toml::to_string(&self.checks).context("Serialize last checks error")?;
+        fs::write(&self.path, content)
+            .with_context(|| format!("Write `{}` error", self.path.display()))
+    }
+}
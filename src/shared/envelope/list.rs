@@ -1,16 +1,46 @@
-use std::{collections::BTreeSet, fmt};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+    io::{IsTerminal, Write, stdout},
+    path::PathBuf,
+    thread,
+    time::Duration,
+};
 
-use anyhow::Result;
-use chrono::{DateTime, FixedOffset, Local};
-use clap::Parser;
-use comfy_table::{Cell, Color, ContentArrangement, Row, Table};
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, FixedOffset, Local, TimeDelta};
+use clap::{Parser, ValueEnum};
+use comfy_table::{Attribute, Cell, Color, ContentArrangement, Row, Table};
+use crossterm::{
+    cursor::MoveTo,
+    execute,
+    terminal::{Clear, ClearType},
+};
 use humansize::{BINARY, format_size};
 use io_email::{address::Address, envelope::types::Envelope, flag::types::Flag};
+use log::{debug, warn};
+use mail_parser::MessageParser;
 use pimalaya_cli::printer::Printer;
-use serde::Serialize;
+use regex::Regex;
+use serde::{Serialize, Serializer, ser::SerializeMap};
 
 use crate::account::context::Account;
-use crate::shared::{client::EmailClient, mailbox::arg::MailboxArg};
+use crate::config::{EnvelopeColumn, EnvelopeDateFormat, TimezoneConfig};
+use crate::profile;
+use crate::shared::{
+    client::EmailClient,
+    envelope::{
+        decode::decode_envelopes,
+        dump::dump_raw_fetch,
+        group::{GroupBy, GroupedEnvelopes},
+        highlight::{self, Highlight},
+        last_check::LastChecks,
+        search::parse_query,
+    },
+    mailbox::arg::MailboxesArg,
+    message::read::render_header_value,
+    width::MaxWidth,
+};
 
 /// List envelopes for the active account, regardless of the underlying
 /// backend (IMAP, JMAP or Maildir).
@@ -18,10 +48,15 @@ use crate::shared::{client::EmailClient, mailbox::arg::MailboxArg};
 /// Envelopes are ordered by date descending (most recent first). Use
 /// `envelope search` to filter and/or sort with the shared search
 /// query DSL.
+///
+/// Several mailboxes can be listed at once (`-m INBOX,Archive`):
+/// their envelopes are merged by date into one listing, with a
+/// MAILBOX column telling where each one lives, since ids are only
+/// unique within a mailbox.
 #[derive(Debug, Parser)]
 pub struct EnvelopeListCommand {
     #[command(flatten)]
-    pub mailbox: MailboxArg,
+    pub mailbox: MailboxesArg,
 
     /// Page number, starting from 1. The most recent envelopes are on
     /// page 1.
@@ -37,51 +72,614 @@ pub struct EnvelopeListCommand {
     #[arg(value_name = "N")]
     pub page_size: Option<u32>,
 
+    /// Only list the N most recent envelopes.
+    ///
+    /// Shorthand for the first page of N envelopes, fetched in one
+    /// bounded request whatever the configured page size. Conflicts
+    /// with `--page` and `--page-size`.
+    #[arg(long, short = 'n', conflicts_with_all = ["page", "page_size"])]
+    #[arg(value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub limit: Option<u32>,
+
     /// Maximum width of the rendered table, in terminal columns.
     ///
     /// Overrides comfy-table's auto-detection. Columns shrink with
-    /// ellipsis if needed.
+    /// ellipsis if needed. Pass `auto` to use the terminal width at
+    /// draw time (80 when it cannot be detected).
     #[arg(long = "max-width", short = 'w')]
-    #[arg(value_name = "COLUMNS")]
-    pub max_width: Option<u16>,
+    #[arg(value_name = "COLUMNS|auto")]
+    pub max_width: Option<MaxWidth>,
 
-    /// Render recipients (`To:`) instead of senders (`From:`). Useful
-    /// for sent folders.
+    /// Render recipients (`To:`) instead of senders (`From:`) in the
+    /// FROM column. Implied when listing the sent mailbox
+    /// (`mailbox.alias.sent`), unless a TO column is configured.
     #[arg(long, short)]
     pub recipient: bool,
 
+    /// Only keep the envelopes addressed to the account: whose `To:`
+    /// holds its `email` or one of its `identities`, compared
+    /// case-insensitively.
+    ///
+    /// Messages where the account is only in `Cc:` are kept too,
+    /// their headers being fetched to check it. Only the fetched
+    /// page is filtered, so a page may come out short.
+    #[arg(long)]
+    pub mine: bool,
+
+    /// Only keep the envelopes of unread messages, lacking the `seen`
+    /// flag.
+    ///
+    /// Like `--mine`, only the fetched page is filtered, so a page may
+    /// come out short. Use `envelope search not flag seen` to get full
+    /// pages of unread messages.
+    #[arg(long)]
+    pub unseen_only: bool,
+
     /// Populate the ATT column. Free on JMAP; on IMAP this fetches
     /// `BODYSTRUCTURE` in addition to `ENVELOPE`; Maildir already
     /// parses the message body for subject/from/to so the toggle is
     /// essentially free there.
+    ///
+    /// Messages whose structure cannot be parsed are still listed,
+    /// with `?` in the ATT column.
     #[arg(long = "has-attachment")]
     pub has_attachment: bool,
+
+    /// Include an extra header (e.g. `List-Id`, `X-GitHub-Reason`)
+    /// as an additional column, or as a `headers` entry keyed by
+    /// envelope id in JSON output. Repeatable.
+    ///
+    /// Opt-in because it costs an extra request: the header sections
+    /// of the listed envelopes are fetched in one go on IMAP, other
+    /// backends fetching each full message. Missing headers render
+    /// as empty cells.
+    #[arg(long = "header")]
+    #[arg(value_name = "NAME")]
+    pub headers: Vec<String>,
+
+    /// Render the messages the listing skipped, because their server
+    /// response could not be parsed, as `[unparseable]` placeholder
+    /// rows (or as an `invalid` id list in JSON output).
+    ///
+    /// The placeholder ids can still be passed to `message read
+    /// --raw`. IMAP only: the ids of the page are checked
+    /// against the server over an extra connection.
+    #[arg(long = "include-invalid")]
+    pub include_invalid: bool,
+
+    /// Split the listing into titled sections: by sender, by relative
+    /// date (today, yesterday, this week, this month, older), or by
+    /// mailbox. JSON output becomes an object keyed by section title.
+    ///
+    /// Sections only regroup the current page; they keep the order
+    /// in which their first envelope appears.
+    #[arg(long = "group-by")]
+    #[arg(value_name = "PROPERTY")]
+    pub group_by: Option<GroupBy>,
+
+    /// Render the listing as a comfy table, or as a GitHub-flavored
+    /// Markdown table to paste into issues and docs (no colors, pipes
+    /// escaped). Ignored by the global `--json` flag.
+    ///
+    /// `count-only` prints the unseen and total message counts of
+    /// the mailbox instead (e.g. `3/57`), for shell prompts and status
+    /// bars: nothing is listed, IMAP only asks for its `STATUS`. With
+    /// several mailboxes, their counts are summed.
+    ///
+    /// `json-lines` writes one JSON object per envelope and per line
+    /// (NDJSON) instead, flushed as soon as written, for `jq` and
+    /// other line-oriented tools. An unbounded listing (`--page-size
+    /// 0`) of a single mailbox is fetched and written by chunks, so
+    /// the first lines come out before the whole mailbox is fetched.
+    /// Takes precedence over the global `--json` flag.
+    #[arg(long, short = 'o')]
+    #[arg(value_name = "FORMAT", default_value = "table")]
+    pub output: EnvelopesOutput,
+
+    /// Format of the `--output count-only` line, where `{unseen}` and
+    /// `{total}` are replaced by the counts. Overrides the
+    /// `envelope.list.count-format` config value (`{unseen}/{total}`
+    /// by default).
+    #[arg(long, value_name = "FORMAT")]
+    pub count_format: Option<String>,
+
+    /// Emphasize the portions of the SUBJECT and FROM (or TO) cells
+    /// matching this regular expression, with the
+    /// `envelope.list.table.highlight-color` background. Unlike
+    /// `envelope search`, nothing is filtered out. Prefix the pattern
+    /// with `(?i)` to ignore case.
+    ///
+    /// Only applies to the table output, and only when it is styled
+    /// (not when piped).
+    #[arg(long, value_name = "REGEX")]
+    pub highlight: Option<Regex>,
+
+    /// Reverse the order of the listed envelopes, whatever the sort
+    /// order: the page is fetched as usual, then rendered from its
+    /// last envelope to its first (e.g. newest last).
+    #[arg(long)]
+    pub reverse: bool,
+
+    /// Only list the envelopes dated after the previous
+    /// `--since-last-check` run on the same account and mailbox, then
+    /// record this run. The first run lists the page as usual.
+    ///
+    /// Meant for polling from cron: the marker is stored under the
+    /// user data directory. The server is asked for the recent
+    /// envelopes only, which are then compared by their `Date:`
+    /// header, so undated messages are skipped. When there are more
+    /// than a page of them, the oldest page is listed and the rest is
+    /// left for the next run.
+    #[arg(long, conflicts_with_all = ["include_invalid", "dump_raw_fetch"])]
+    pub since_last_check: bool,
+
+    /// Keep the listing on screen, fetching it again every
+    /// `--interval` seconds and redrawing it in place, like `watch(1)`.
+    /// Runs until interrupted.
+    #[arg(long, conflicts_with_all = ["since_last_check", "dump_raw_fetch"])]
+    pub follow: bool,
+
+    /// Seconds between two refreshes of --follow.
+    #[arg(long, value_name = "SECS", default_value = "30", requires = "follow")]
+    #[arg(value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+
+    /// Write the raw header section of every message of the page to
+    /// PATH, each marked as listed or skipped, to attach to a bug
+    /// report about messages missing from the listing. IMAP only: the
+    /// page is fetched again over an extra connection.
+    #[arg(long, hide = true, value_name = "PATH")]
+    pub dump_raw_fetch: Option<PathBuf>,
+
+    /// Mask the addresses and subjects of the `--dump-raw-fetch`
+    /// output, keeping their structure.
+    #[arg(long, hide = true, requires = "dump_raw_fetch")]
+    pub redact: bool,
+}
+
+/// Text rendering of [`Envelopes`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum EnvelopesOutput {
+    #[default]
+    Table,
+    Markdown,
+    CountOnly,
+    JsonLines,
 }
 
+/// Envelopes fetched per request by `--output json-lines` when the
+/// listing is unbounded (`--page-size 0`).
+const JSON_LINES_CHUNK: u32 = 500;
+
 impl EnvelopeListCommand {
     pub fn execute(
         self,
         printer: &mut impl Printer,
         account: &mut Account,
         client: &mut EmailClient,
+        account_name: &str,
+    ) -> Result<()> {
+        let now = Local::now().fixed_offset();
+        let mailboxes = self.mailbox.resolve(account)?;
+
+        if self.output == EnvelopesOutput::CountOnly {
+            if self.follow {
+                bail!("`--follow` cannot be combined with `--output count-only`");
+            }
+
+            return self.count(printer, account, client, &mailboxes);
+        }
+
+        if self.output == EnvelopesOutput::JsonLines {
+            if self.follow || self.since_last_check || self.group_by.is_some() {
+                bail!(
+                    "`--output json-lines` cannot be combined with `--follow`, \
+                     `--since-last-check` or `--group-by`"
+                );
+            }
+
+            return self.stream(account, client, &mailboxes);
+        }
+
+        if self.follow {
+            if printer.is_json() {
+                bail!("`--follow` and `--json` cannot be combined");
+            }
+
+            return self.follow(printer, account, client, &mailboxes[0]);
+        }
+
+        if self.since_last_check && mailboxes.len() > 1 {
+            bail!("`--since-last-check` only applies to a single mailbox");
+        }
+
+        let envelopes = if self.since_last_check {
+            let mailbox = &mailboxes[0];
+            let mut checks = LastChecks::load()?;
+
+            // NOTE: the date is taken before the listing, so messages
+            // arriving meanwhile are reported by the next run
+            let (envelopes, marker) = match checks.get(account_name, mailbox) {
+                Some(last) => profile::time("fetch", || {
+                    self.list_since(account, client, mailbox, last, now)
+                })?,
+                None => (profile::time("fetch", || self.list(account, client))?, now),
+            };
+
+            checks.set(account_name, mailbox, marker)?;
+            envelopes
+        } else {
+            profile::time("fetch", || self.list(account, client))?
+        };
+
+        let _span = profile::span("render");
+        self.print(printer, envelopes, &mailboxes[0])
+    }
+
+    fn print(&self, printer: &mut impl Printer, envelopes: Envelopes, mailbox: &str) -> Result<()> {
+        match self.group_by {
+            Some(group_by) => printer.out(GroupedEnvelopes::new(envelopes, group_by, mailbox)),
+            None => printer.out(envelopes),
+        }
+    }
+
+    /// Lists then prints the envelopes every `--interval` seconds,
+    /// clearing the terminal before each listing.
+    // NOTE: neither the alternate screen nor the raw mode is used, so
+    // an interrupt leaves the terminal as it was
+    fn follow(
+        &self,
+        printer: &mut impl Printer,
+        account: &mut Account,
+        client: &mut EmailClient,
+        mailbox: &str,
+    ) -> Result<()> {
+        let interval = Duration::from_secs(self.interval);
+
+        loop {
+            let envelopes = self.list(account, client)?;
+
+            if stdout().is_terminal() {
+                execute!(stdout(), Clear(ClearType::All), MoveTo(0, 0))?;
+            }
+
+            self.print(printer, envelopes, mailbox)?;
+            thread::sleep(interval);
+        }
+    }
+
+    /// Writes the envelopes of `mailboxes` as JSON lines, chunk by
+    /// chunk when a single mailbox is listed without bound.
+    fn stream(
+        &self,
+        account: &mut Account,
+        client: &mut EmailClient,
+        mailboxes: &[String],
     ) -> Result<()> {
-        let page = Some(self.page).filter(|p| *p > 0);
-        let page_size = self
-            .page_size
-            .or(Some(account.envelopes_list_page_size()))
-            .filter(|p| *p > 0);
-        let mailbox = self.mailbox.resolve(account)?;
+        let mut out = stdout().lock();
+
+        let (_, page_size) = self.page_window(account);
+
+        // NOTE: a reversed listing cannot be written before its last
+        // envelope is fetched
+        let (None, [mailbox], false) = (page_size, mailboxes, self.reverse) else {
+            return self.list(account, client)?.write_json_lines(&mut out);
+        };
+
+        for page in 1.. {
+            let mut envelopes =
+                with_attachment_fallback(self.has_attachment, Some(JSON_LINES_CHUNK), |has_att| {
+                    Ok(client.list_envelopes(
+                        mailbox,
+                        Some(page),
+                        Some(JSON_LINES_CHUNK),
+                        has_att,
+                    )?)
+                })?;
+
+            let fetched = envelopes.len();
+            decode_envelopes(&mut envelopes);
+            self.retain_filtered(account, client, mailbox, &mut envelopes)?;
+
+            let mut chunk = self.template(account);
+            chunk.headers = fetch_headers(client, mailbox, &envelopes, &self.headers)?;
+            chunk.envelopes = envelopes;
+            chunk.write_json_lines(&mut out)?;
 
-        let envelopes = client.list_envelopes(&mailbox, page, page_size, self.has_attachment)?;
+            if fetched < JSON_LINES_CHUNK as usize {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prints the message counts of `mailboxes`, for `--output
+    /// count-only`.
+    fn count(
+        &self,
+        printer: &mut impl Printer,
+        account: &Account,
+        client: &mut EmailClient,
+        mailboxes: &[String],
+    ) -> Result<()> {
+        // NOTE: counts come from the server, listing filters cannot
+        // apply to them
+        if self.mine || self.unseen_only || self.since_last_check {
+            bail!(
+                "`--output count-only` cannot be combined with `--mine`, `--unseen-only` \
+                 or `--since-last-check`"
+            );
+        }
+
+        let mut count = EnvelopeCount {
+            format: match &self.count_format {
+                Some(format) => format.clone(),
+                None => account.envelopes_list_count_format().to_owned(),
+            },
+            unseen: 0,
+            total: 0,
+        };
+
+        for mailbox in mailboxes {
+            let (unseen, total) = client
+                .message_counts(mailbox)
+                .with_context(|| format!("Count messages of mailbox {mailbox} error"))?;
+            count.unseen += unseen;
+            count.total += total;
+        }
+
+        printer.out(count)
+    }
+
+    /// Lists the envelopes of `account` without printing them, so
+    /// `--account all` can gather them across accounts.
+    pub fn list(&self, account: &mut Account, client: &mut EmailClient) -> Result<Envelopes> {
+        let (page, page_size) = self.page_window(account);
+        let mailboxes = self.mailbox.resolve(account)?;
+        let mut output = self.template(account);
+        output.empty = self.empty_line(page, &mailboxes.join(", "));
+        output.recipient = shows_recipients(self.recipient, account, &mailboxes, &output.columns);
+
+        match mailboxes.as_slice() {
+            [mailbox] => {
+                let mut envelopes =
+                    with_attachment_fallback(self.has_attachment, page_size, |has_att| {
+                        Ok(client.list_envelopes(mailbox, page, page_size, has_att)?)
+                    })?;
+
+                if !self.has_attachment && !self.include_invalid {
+                    warn_skipped(client, mailbox, page, page_size, &envelopes);
+                }
+
+                decode_envelopes(&mut envelopes);
+                self.retain_filtered(account, client, mailbox, &mut envelopes)?;
+                output.headers = fetch_headers(client, mailbox, &envelopes, &self.headers)?;
+
+                if self.include_invalid {
+                    output.invalid = find_invalid(client, mailbox, page, page_size, &envelopes)?;
+                }
+
+                if let Some(path) = &self.dump_raw_fetch {
+                    dump_raw_fetch(
+                        client,
+                        mailbox,
+                        page,
+                        page_size,
+                        &envelopes,
+                        path,
+                        self.redact,
+                    )?;
+                }
+
+                output.envelopes = envelopes;
+            }
+            mailboxes => {
+                // NOTE: both are keyed by envelope id, which is only
+                // unique within a mailbox
+                if !self.headers.is_empty() {
+                    bail!("`--header` only applies to a single mailbox");
+                }
+                if self.include_invalid {
+                    bail!("`--include-invalid` only applies to a single mailbox");
+                }
+                if self.dump_raw_fetch.is_some() {
+                    bail!("`--dump-raw-fetch` only applies to a single mailbox");
+                }
+
+                let depth = merge_depth(page, page_size);
+                let mut merged = Vec::new();
+
+                for mailbox in mailboxes {
+                    let mut envelopes = self
+                        .list_depth(client, mailbox, depth)
+                        .with_context(|| format!("List envelopes of mailbox {mailbox} error"))?;
+                    self.retain_filtered(account, client, mailbox, &mut envelopes)?;
+                    merged.extend(envelopes.into_iter().map(|env| (mailbox.clone(), env)));
+                }
 
-        let envelopes = Envelopes {
+                (output.mailboxes, output.envelopes) =
+                    merge_page(merged, page, page_size).into_iter().unzip();
+            }
+        }
+
+        if self.reverse {
+            output.reverse();
+        }
+
+        Ok(output)
+    }
+
+    /// Lists the envelopes of `mailbox` dated after `last`, for
+    /// `--since-last-check`, along with the marker to record in place
+    /// of `last`.
+    fn list_since(
+        &self,
+        account: &mut Account,
+        client: &mut EmailClient,
+        mailbox: &str,
+        last: DateTime<FixedOffset>,
+        now: DateTime<FixedOffset>,
+    ) -> Result<(Envelopes, DateTime<FixedOffset>)> {
+        let (_, page_size) = self.page_window(account);
+        let mut output = self.template(account);
+        output.empty = self.empty_line(None, mailbox);
+        output.recipient = shows_recipients(
+            self.recipient,
+            account,
+            std::slice::from_ref(&mailbox.to_owned()),
+            &output.columns,
+        );
+
+        // NOTE: servers search by day, in a timezone of their own:
+        // the day before the marker is asked for as well, then dates
+        // are compared exactly
+        let since = (last - TimeDelta::days(1)).format("%Y-%m-%d");
+        let query = parse_query(&format!("after {since} order by date asc"))?;
+
+        let mut envelopes = with_attachment_fallback(self.has_attachment, None, |has_att| {
+            Ok(client.search_envelopes(mailbox, query.as_ref(), None, None, has_att)?)
+        })?;
+
+        decode_envelopes(&mut envelopes);
+        envelopes.retain(|envelope| envelope.date.is_some_and(|date| date > last));
+        self.retain_filtered(account, client, mailbox, &mut envelopes)?;
+        envelopes.sort_by_key(|envelope| envelope.date);
+
+        let dates: Vec<_> = envelopes
+            .iter()
+            .filter_map(|envelope| envelope.date)
+            .collect();
+        let (len, marker) = since_window(&dates, page_size, now);
+        envelopes.truncate(len);
+        envelopes.reverse();
+
+        output.headers = fetch_headers(client, mailbox, &envelopes, &self.headers)?;
+        output.envelopes = envelopes;
+
+        if self.reverse {
+            output.reverse();
+        }
+
+        Ok((output, marker))
+    }
+
+    /// Page number and page size of the listing, `None` when
+    /// unbounded.
+    pub(super) fn page_window(&self, account: &Account) -> (Option<u32>, Option<u32>) {
+        match self.limit {
+            Some(limit) => (Some(1), Some(limit)),
+            None => (
+                Some(self.page).filter(|p| *p > 0),
+                self.page_size
+                    .or(Some(account.envelopes_list_page_size()))
+                    .filter(|p| *p > 0),
+            ),
+        }
+    }
+
+    /// Lists the `depth` most recent envelopes of `mailbox`, decoded,
+    /// for a listing merging several sources.
+    pub(super) fn list_depth(
+        &self,
+        client: &mut EmailClient,
+        mailbox: &str,
+        depth: Option<u32>,
+    ) -> Result<Vec<Envelope>> {
+        let mut envelopes = with_attachment_fallback(self.has_attachment, depth, |has_att| {
+            Ok(client.list_envelopes(mailbox, depth.map(|_| 1), depth, has_att)?)
+        })?;
+        decode_envelopes(&mut envelopes);
+        Ok(envelopes)
+    }
+
+    /// Drops the `envelopes` filtered out by `--mine` and
+    /// `--unseen-only`.
+    pub(super) fn retain_filtered(
+        &self,
+        account: &Account,
+        client: &mut EmailClient,
+        mailbox: &str,
+        envelopes: &mut Vec<Envelope>,
+    ) -> Result<()> {
+        self.retain_mine(account, client, mailbox, envelopes)?;
+
+        if self.unseen_only {
+            envelopes.retain(|env| !env.flags.iter().any(Flag::is_seen));
+        }
+
+        Ok(())
+    }
+
+    /// Drops the `envelopes` of `mailbox` not addressed to `account`,
+    /// neither in `To:` nor in `Cc:`, when `--mine` is given.
+    fn retain_mine(
+        &self,
+        account: &Account,
+        client: &mut EmailClient,
+        mailbox: &str,
+        envelopes: &mut Vec<Envelope>,
+    ) -> Result<()> {
+        if !self.mine {
+            return Ok(());
+        }
+
+        let own = account.own_addresses();
+
+        if own.is_empty() {
+            bail!("`--mine` needs the account `email` or `identities` to be configured");
+        }
+
+        let is_own = |email: &str| {
+            own.iter()
+                .any(|addr| email.trim().eq_ignore_ascii_case(addr))
+        };
+        let to_own = |env: &Envelope| env.to.iter().any(|to| is_own(&to.email));
+
+        // NOTE: `Cc:` is not part of the envelope, it is read from the
+        // headers of the messages not addressed to the account
+        let others: Vec<&str> = envelopes
+            .iter()
+            .filter(|env| !to_own(env))
+            .map(|env| env.id.as_str())
+            .collect();
+
+        let copied: BTreeSet<String> = if others.is_empty() {
+            BTreeSet::new()
+        } else {
+            client
+                .get_headers(mailbox, &others)
+                .with_context(|| format!("Get headers of mailbox {mailbox} error"))?
+                .into_iter()
+                .filter(|(_, raw)| cc_own(raw, &is_own))
+                .map(|(id, _)| id)
+                .collect()
+        };
+
+        envelopes.retain(|env| to_own(env) || copied.contains(&env.id));
+
+        Ok(())
+    }
+
+    /// Empty listing carrying the rendering options of `account` and
+    /// of the command line.
+    pub(super) fn template(&self, account: &Account) -> Envelopes {
+        Envelopes {
             preset: account.table_preset().to_string(),
             arrangement: account.table_arrangement(),
             max_width: self.max_width,
             datetime_fmt: account.datetime_fmt().to_string(),
-            datetime_local_tz: account.datetime_local_tz(),
+            datetime_tz: account.datetime_tz(),
+            date_format: account.envelopes_list_table_date_format(),
             recipient: self.recipient,
             with_attachment: self.has_attachment,
+            columns: account.envelopes_list_table_columns(),
+            header_names: self.headers.clone(),
+            output: self.output,
+            highlight: self.highlight.clone().map(|regex| Highlight {
+                regex,
+                color: account.envelopes_list_table_highlight_color(),
+            }),
             chars: FlagChars {
                 unseen: account.envelopes_list_table_unseen_char(),
                 replied: account.envelopes_list_table_replied_char(),
@@ -97,12 +695,364 @@ impl EnvelopeListCommand {
                 to: account.envelopes_list_table_to_color(),
                 date: account.envelopes_list_table_date_color(),
                 size: account.envelopes_list_table_size_color(),
+                sender: account.envelopes_list_table_highlight_senders_color(),
             },
-            envelopes,
+            highlight_senders: account.envelopes_list_table_highlight_senders(),
+            envelopes: Vec::new(),
+            accounts: Vec::new(),
+            mailboxes: Vec::new(),
+            headers: HashMap::new(),
+            invalid: Vec::new(),
+            empty: String::new(),
+        }
+    }
+
+    /// Line rendered when the listing of `source` comes out empty.
+    pub(super) fn empty_line(&self, page: Option<u32>, source: &str) -> String {
+        let filters: Vec<&str> = [
+            self.mine.then_some("--mine"),
+            self.unseen_only.then_some("--unseen-only"),
+            self.since_last_check.then_some("--since-last-check"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        let filter = filters.join(" ");
+        empty_line(
+            Some(filter.as_str()).filter(|f| !f.is_empty()),
+            page,
+            source,
+        )
+    }
+}
+
+/// `date` relative to `now`, calendar days being those of `now`, or
+/// `None` when in the future or older than four weeks.
+pub(super) fn format_relative_date(
+    date: DateTime<FixedOffset>,
+    now: DateTime<FixedOffset>,
+) -> Option<String> {
+    let elapsed = now.signed_duration_since(date);
+
+    if elapsed < TimeDelta::zero() || elapsed >= TimeDelta::weeks(4) {
+        return None;
+    }
+
+    let days = (now.date_naive() - date.with_timezone(&now.timezone()).date_naive()).num_days();
+
+    let relative = match days {
+        _ if elapsed < TimeDelta::minutes(1) => String::from("just now"),
+        _ if elapsed < TimeDelta::hours(1) => format!("{}m ago", elapsed.num_minutes()),
+        0 => format!("{}h ago", elapsed.num_hours()),
+        1 => String::from("yesterday"),
+        2..7 => format!("{days} days ago"),
+        7..14 => String::from("1 week ago"),
+        _ => format!("{} weeks ago", days / 7),
+    };
+
+    Some(relative)
+}
+
+/// Whether the FROM column shows the recipients: with `--recipient`,
+/// or when only the sent mailbox is listed and there is no TO column.
+pub(super) fn shows_recipients(
+    recipient: bool,
+    account: &Account,
+    mailboxes: &[String],
+    columns: &[EnvelopeColumn],
+) -> bool {
+    let sent = !mailboxes.is_empty() && mailboxes.iter().all(|m| account.is_sent_mailbox(m));
+    recipient || (sent && !columns.contains(&EnvelopeColumn::To))
+}
+
+/// Line rendered in place of an empty listing of `source`, telling
+/// an empty mailbox apart from a `filter` (options or search query)
+/// that matched nothing, and from a page past the last one.
+pub(super) fn empty_line(filter: Option<&str>, page: Option<u32>, source: &str) -> String {
+    let mut line = String::from("No messages");
+
+    if let Some(filter) = filter {
+        line.push_str(&format!(" matched `{filter}`"));
+    }
+
+    match page {
+        Some(page) if page > 1 => line.push_str(&format!(" on page {page} of {source}")),
+        _ => line.push_str(&format!(" in {source}")),
+    }
+
+    line
+}
+
+/// Number of envelopes to list from each source of a merged listing
+/// so that the requested page can be cut from the merge: every page
+/// up to the requested one.
+pub(super) fn merge_depth(page: Option<u32>, page_size: Option<u32>) -> Option<u32> {
+    match (page, page_size) {
+        (Some(page), Some(size)) => Some(page.saturating_mul(size)),
+        _ => None,
+    }
+}
+
+/// Sorts envelopes gathered from several sources by date (most
+/// recent first, undated last), then keeps the requested page only,
+/// so pages are the same as if the sources were one.
+pub(super) fn merge_page<T>(
+    mut merged: Vec<(T, Envelope)>,
+    page: Option<u32>,
+    page_size: Option<u32>,
+) -> Vec<(T, Envelope)> {
+    merged.sort_by(|(_, a), (_, b)| b.date.cmp(&a.date));
+
+    match (page, page_size) {
+        (Some(page), Some(size)) => {
+            let skip = (page as usize - 1) * size as usize;
+            merged.into_iter().skip(skip).take(size as usize).collect()
+        }
+        _ => merged,
+    }
+}
+
+/// Runs `list` with attachment detection when `has_attachment` is
+/// set, without losing the messages the backend could not detect
+/// attachments of.
+///
+/// Detecting attachments requires the message structure (IMAP
+/// `BODYSTRUCTURE`); a structure the backend fails to parse either
+/// fails the whole listing or silently drops the message. Unless a
+/// full page came back, the listing is run again without attachment
+/// detection, and the envelopes missing from the first run are kept
+/// with an unknown attachment status.
+pub(super) fn with_attachment_fallback(
+    has_attachment: bool,
+    page_size: Option<u32>,
+    mut list: impl FnMut(bool) -> Result<Vec<Envelope>>,
+) -> Result<Vec<Envelope>> {
+    if !has_attachment {
+        return list(false);
+    }
+
+    let detailed = match list(true) {
+        Ok(envelopes) => envelopes,
+        Err(err) => {
+            warn!("cannot list envelopes with attachments, retrying without: {err}");
+            debug!("{err:?}");
+            Vec::new()
+        }
+    };
+
+    if page_size.is_some_and(|size| detailed.len() >= size as usize) {
+        return Ok(detailed);
+    }
+
+    let mut detailed: HashMap<String, Envelope> = detailed
+        .into_iter()
+        .map(|envelope| (envelope.id.clone(), envelope))
+        .collect();
+
+    let envelopes = list(false)?
+        .into_iter()
+        .map(|mut envelope| match detailed.remove(&envelope.id) {
+            Some(envelope) => envelope,
+            None => {
+                debug!(
+                    "envelope {} skipped by attachment detection, kept as unknown",
+                    envelope.id
+                );
+                envelope.has_attachment = None;
+                envelope
+            }
+        })
+        .collect();
+
+    Ok(envelopes)
+}
+
+/// Warns on stderr, whatever the log level, when the listed page
+/// holds fewer envelopes than the mailbox has messages there: the
+/// others were skipped because their server response could not be
+/// parsed.
+///
+/// Only IMAP can tell, and only short pages are checked. The total
+/// of the mailbox, read over the session of the listing, first tells
+/// whether the page may have a gap; only then is the page searched
+/// over the raw session, see [`count_skipped`] for the messages
+/// counted.
+#[cfg(feature = "imap")]
+fn warn_skipped(
+    client: &mut EmailClient,
+    mailbox: &str,
+    page: Option<u32>,
+    page_size: Option<u32>,
+    envelopes: &[Envelope],
+) {
+    if page_size.is_some_and(|size| envelopes.len() >= size as usize) {
+        return;
+    }
+
+    if !client.uses_imap() {
+        return;
+    }
+
+    let total = match client.list_mailboxes(true) {
+        Ok(mailboxes) => mailboxes
+            .into_iter()
+            .find(|m| m.name == mailbox)
+            .and_then(|m| m.total),
+        Err(err) => {
+            debug!("cannot count the messages of mailbox {mailbox}: {err:?}");
+            return;
+        }
+    };
+
+    let Some(total) = total else {
+        return;
+    };
+
+    if envelopes.len() as u64 >= page_len(total, page, page_size) {
+        return;
+    }
+
+    let listed: Vec<&str> = envelopes.iter().map(|env| env.id.as_str()).collect();
+
+    let uids = match client.imap_page_uids(mailbox, page, page_size, &listed) {
+        Ok(Some(uids)) => uids,
+        Ok(None) => return,
+        Err(err) => {
+            debug!("cannot search the messages of the listed page: {err:?}");
+            return;
+        }
+    };
+
+    let skipped = count_skipped(&listed, &uids);
+
+    if skipped > 0 {
+        eprintln!(
+            "Warning: {skipped} envelope(s) skipped due to parse errors, \
+             re-run with --include-invalid to list them"
+        );
+    }
+}
+
+/// Number of messages the given page of a mailbox of `total`
+/// messages holds.
+#[cfg(feature = "imap")]
+fn page_len(total: u64, page: Option<u32>, page_size: Option<u32>) -> u64 {
+    let Some(size) = page_size else {
+        return total;
+    };
+
+    let size = size as u64;
+    let before = page.unwrap_or(1).saturating_sub(1) as u64 * size;
+    total.saturating_sub(before).min(size)
+}
+
+#[cfg(not(feature = "imap"))]
+fn warn_skipped(
+    _client: &mut EmailClient,
+    _mailbox: &str,
+    _page: Option<u32>,
+    _page_size: Option<u32>,
+    _envelopes: &[Envelope],
+) {
+}
+
+/// Number of the page `uids` missing from the `listed` ones, between
+/// the lowest and the highest listed UIDs. Messages newer than the
+/// listed ones are left out, as they may have arrived since the
+/// listing.
+#[cfg(feature = "imap")]
+fn count_skipped(listed: &[&str], uids: &[String]) -> usize {
+    use std::collections::HashSet;
+
+    let listed: HashSet<u32> = listed.iter().filter_map(|id| id.parse().ok()).collect();
+
+    let (Some(&lo), Some(&hi)) = (listed.iter().min(), listed.iter().max()) else {
+        return 0;
+    };
+
+    uids.iter()
+        .filter_map(|uid| uid.parse::<u32>().ok())
+        .filter(|uid| (lo..=hi).contains(&uid) && !listed.contains(uid))
+        .count()
+}
+
+/// Ids of the messages of the listed page missing from `envelopes`,
+/// highest UID first.
+#[cfg(feature = "imap")]
+fn find_invalid(
+    client: &mut EmailClient,
+    mailbox: &str,
+    page: Option<u32>,
+    page_size: Option<u32>,
+    envelopes: &[Envelope],
+) -> Result<Vec<String>> {
+    use std::collections::HashSet;
+
+    let listed: Vec<&str> = envelopes.iter().map(|env| env.id.as_str()).collect();
+
+    let Some(ids) = client.imap_page_uids(mailbox, page, page_size, &listed)? else {
+        warn!("--include-invalid is only supported by the IMAP backend, ignoring it");
+        return Ok(Vec::new());
+    };
+
+    let listed: HashSet<&str> = listed.into_iter().collect();
+
+    let invalid = ids
+        .into_iter()
+        .filter(|id| !listed.contains(id.as_str()))
+        .collect();
+
+    Ok(invalid)
+}
+
+#[cfg(not(feature = "imap"))]
+fn find_invalid(
+    _client: &mut EmailClient,
+    _mailbox: &str,
+    _page: Option<u32>,
+    _page_size: Option<u32>,
+    _envelopes: &[Envelope],
+) -> Result<Vec<String>> {
+    warn!("--include-invalid is only supported by the IMAP backend, ignoring it");
+    Ok(Vec::new())
+}
+
+/// Fetches the header sections of the envelopes (see
+/// [`EmailClient::get_headers`]) and extracts the requested header
+/// `names`, decoded. Returns an empty map without
+/// fetching anything when no name is requested.
+fn fetch_headers(
+    client: &mut EmailClient,
+    mailbox: &str,
+    envelopes: &[Envelope],
+    names: &[String],
+) -> Result<HashMap<String, BTreeMap<String, String>>> {
+    let mut headers = HashMap::new();
+
+    if names.is_empty() {
+        return Ok(headers);
+    }
+
+    let ids: Vec<&str> = envelopes.iter().map(|env| env.id.as_str()).collect();
+
+    for (id, raw) in client.get_headers(mailbox, &ids)? {
+        let Some(msg) = MessageParser::new().parse_headers(&raw) else {
+            continue;
         };
 
-        printer.out(envelopes)
+        let values = names
+            .iter()
+            .filter_map(|name| {
+                let value = msg.header(name.as_str())?;
+                Some((name.clone(), render_header_value(value)))
+            })
+            .collect();
+
+        headers.insert(id, values);
     }
+
+    Ok(headers)
 }
 
 /// Glyphs the FLAGS / ATT columns substitute in, sourced from the
@@ -127,93 +1077,498 @@ pub(super) struct EnvelopeColors {
     pub to: Color,
     pub date: Color,
     pub size: Color,
+    /// Row color of the envelopes sent by a highlighted sender.
+    pub sender: Option<Color>,
 }
 
 /// Table of envelope rows rendered to the terminal or as JSON.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug)]
 pub struct Envelopes {
-    #[serde(skip)]
     pub preset: String,
-    #[serde(skip)]
     pub arrangement: ContentArrangement,
-    #[serde(skip)]
-    pub max_width: Option<u16>,
-    #[serde(skip)]
+    pub max_width: Option<MaxWidth>,
     pub datetime_fmt: String,
-    #[serde(skip)]
-    pub datetime_local_tz: bool,
-    #[serde(skip)]
+    pub datetime_tz: TimezoneConfig,
+    pub date_format: EnvelopeDateFormat,
     pub recipient: bool,
-    #[serde(skip)]
     pub with_attachment: bool,
-    #[serde(skip)]
+    /// Columns of the table, see `envelope.list.table.columns`.
+    pub columns: Vec<EnvelopeColumn>,
+    /// Extra header columns requested with `--header`, in order.
+    pub header_names: Vec<String>,
+    pub output: EnvelopesOutput,
+    /// Pattern emphasized in the SUBJECT and FROM/TO cells.
+    pub(super) highlight: Option<Highlight>,
+    /// Lowercased addresses of the senders whose rows stand out.
+    pub(super) highlight_senders: Vec<String>,
     pub(super) chars: FlagChars,
-    #[serde(skip)]
     pub(super) colors: EnvelopeColors,
     pub envelopes: Vec<Envelope>,
+    /// Account of each envelope, in the same order, when listing a
+    /// virtual mailbox spanning several accounts. Empty otherwise.
+    pub accounts: Vec<String>,
+    /// Mailbox of each envelope, in the same order, when several
+    /// mailboxes are listed at once. Empty otherwise.
+    pub mailboxes: Vec<String>,
+    /// Extra header values keyed by envelope id, then header name.
+    pub headers: HashMap<String, BTreeMap<String, String>>,
+    /// Ids of the messages the listing skipped as unparseable,
+    /// requested with `--include-invalid`.
+    pub invalid: Vec<String>,
+    /// Line rendered instead of the table when nothing is listed, see
+    /// [`empty_line`].
+    pub empty: String,
+}
+
+/// Message counts printed by `--output count-only`.
+#[derive(Debug, Serialize)]
+pub struct EnvelopeCount {
+    #[serde(skip)]
+    pub format: String,
+    pub unseen: u64,
+    pub total: u64,
+}
+
+impl fmt::Display for EnvelopeCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = self
+            .format
+            .replace("{unseen}", &self.unseen.to_string())
+            .replace("{total}", &self.total.to_string());
+
+        write!(f, "{line}")
+    }
+}
+
+/// One line of `--output json-lines`: an envelope, tagged with its
+/// account and mailbox when several are listed, and with its extra
+/// headers when requested.
+#[derive(Serialize)]
+struct EnvelopeLine<'a, E = Envelope> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mailbox: Option<&'a str>,
+    #[serde(flatten)]
+    envelope: &'a E,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    headers: Option<&'a BTreeMap<String, String>>,
+}
+
+impl Envelopes {
+    /// Reverses the order of the envelopes, keeping their account and
+    /// mailbox labels in step.
+    pub fn reverse(&mut self) {
+        self.envelopes.reverse();
+        self.accounts.reverse();
+        self.mailboxes.reverse();
+        self.invalid.reverse();
+    }
+
+    /// Envelopes and unparseable placeholders in display order, see
+    /// [`envelope_rows`].
+    fn rows(&self) -> Vec<EnvelopeRow<'_>> {
+        let ids: Vec<&str> = self.envelopes.iter().map(|env| env.id.as_str()).collect();
+        envelope_rows(&ids, &self.invalid)
+    }
+
+    /// Writes one JSON object per envelope and per line to `out`,
+    /// flushing after each line. Unparseable messages are left out.
+    pub fn write_json_lines(&self, out: &mut impl Write) -> Result<()> {
+        let lines = self
+            .envelopes
+            .iter()
+            .enumerate()
+            .map(|(i, envelope)| EnvelopeLine {
+                account: self.accounts.get(i).map(String::as_str),
+                mailbox: self.mailboxes.get(i).map(String::as_str),
+                envelope,
+                headers: self.headers.get(&envelope.id),
+            });
+
+        write_json_lines(out, lines)
+    }
+}
+
+/// Writes each of the `lines` to `out` as a JSON object on its own
+/// line, flushing after each one.
+fn write_json_lines(
+    out: &mut impl Write,
+    lines: impl IntoIterator<Item = impl Serialize>,
+) -> Result<()> {
+    for line in lines {
+        serde_json::to_writer(&mut *out, &line).context("Serialize envelope error")?;
+        writeln!(out).context("Write to stdout error")?;
+        out.flush().context("Flush stdout error")?;
+    }
+
+    Ok(())
+}
+
+/// Row of the envelope table, see [`envelope_rows`].
+#[derive(Debug, Eq, PartialEq)]
+enum EnvelopeRow<'a> {
+    /// Index of a listed envelope.
+    Envelope(usize),
+    /// Id of a message the listing skipped as unparseable.
+    Invalid(&'a str),
+}
+
+/// Rows of the listed envelope `ids` and of the `invalid` ids, in
+/// display order: each placeholder goes where its UID sorts among the
+/// listed ones, whichever the direction of the listing. `invalid` is
+/// expected in the same direction as `ids`.
+fn envelope_rows<'a>(ids: &[&str], invalid: &'a [String]) -> Vec<EnvelopeRow<'a>> {
+    let uid = |id: &str| id.parse::<u64>().ok();
+    let descending = match (ids.first(), ids.last()) {
+        (Some(first), Some(last)) => uid(first) >= uid(last),
+        _ => true,
+    };
+
+    let mut invalid_ids = invalid.iter().map(String::as_str).peekable();
+    let mut rows = Vec::with_capacity(ids.len() + invalid.len());
+
+    for (i, id) in ids.iter().enumerate() {
+        while let Some(invalid) = invalid_ids.next_if(|invalid| match (uid(invalid), uid(id)) {
+            (Some(a), Some(b)) => (a > b) == descending,
+            _ => false,
+        }) {
+            rows.push(EnvelopeRow::Invalid(invalid));
+        }
+
+        rows.push(EnvelopeRow::Envelope(i));
+    }
+
+    rows.extend(invalid_ids.map(EnvelopeRow::Invalid));
+    rows
+}
+
+/// Envelope tagged with its account and mailbox, in the JSON output
+/// of a listing spanning several mailboxes.
+#[derive(Serialize)]
+struct MailboxEnvelope<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account: Option<&'a str>,
+    mailbox: &'a str,
+    #[serde(flatten)]
+    envelope: &'a Envelope,
+}
+
+impl Serialize for Envelopes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+
+        if self.mailboxes.is_empty() {
+            map.serialize_entry("envelopes", &self.envelopes)?;
+        } else {
+            let envelopes: Vec<MailboxEnvelope<'_>> = self
+                .mailboxes
+                .iter()
+                .zip(&self.envelopes)
+                .enumerate()
+                .map(|(i, (mailbox, envelope))| MailboxEnvelope {
+                    account: self.accounts.get(i).map(String::as_str),
+                    mailbox,
+                    envelope,
+                })
+                .collect();
+            map.serialize_entry("envelopes", &envelopes)?;
+        }
+
+        if !self.headers.is_empty() {
+            map.serialize_entry("headers", &self.headers)?;
+        }
+
+        if !self.invalid.is_empty() {
+            map.serialize_entry("invalid", &self.invalid)?;
+        }
+
+        map.end()
+    }
 }
 
 impl fmt::Display for Envelopes {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut table = Table::new();
+        if self.envelopes.is_empty() && self.invalid.is_empty() {
+            writeln!(f)?;
+            return writeln!(f, "{}", self.empty);
+        }
 
-        let mut header = vec![Cell::new("ID"), Cell::new("FLAGS")];
-        if self.with_attachment {
-            header.push(Cell::new("ATT"));
+        if self.output == EnvelopesOutput::Markdown {
+            return self.fmt_markdown(f);
         }
-        header.push(Cell::new("SUBJECT"));
-        header.push(Cell::new(if self.recipient { "TO" } else { "FROM" }));
-        header.push(Cell::new("DATE"));
-        header.push(Cell::new("SIZE"));
+
+        let columns = self.layout();
+        let mut table = Table::new();
+        let header: Vec<Cell> = self
+            .column_titles(&columns)
+            .into_iter()
+            .map(Cell::new)
+            .collect();
 
         table
             .load_preset(&self.preset)
             .set_content_arrangement(self.arrangement.clone())
             .set_header(Row::from(header))
-            .add_rows(self.envelopes.iter().map(|env| {
+            .add_rows(self.rows().into_iter().map(|row| {
+                let (i, env) = match row {
+                    EnvelopeRow::Envelope(i) => (i, &self.envelopes[i]),
+                    EnvelopeRow::Invalid(id) => return self.invalid_row(&columns, id),
+                };
+
                 let mut row = Row::new();
                 row.max_height(1);
-                row.add_cell(Cell::new(&env.id).fg(self.colors.id));
-                row.add_cell(
-                    Cell::new(format_flags(&env.flags, &self.chars)).fg(self.colors.flags),
-                );
-                if self.with_attachment {
-                    row.add_cell(
-                        Cell::new(format_attachment(env.has_attachment, self.chars.attachment))
-                            .fg(self.colors.att),
-                    );
+
+                let highlighted = self.is_highlighted_sender(env);
+
+                for column in &columns {
+                    let (text, mut color) = self.cell(*column, i, env, true);
+                    let mut cell = Cell::new(text);
+
+                    if highlighted {
+                        color = self.colors.sender.or(color);
+                        cell = cell.add_attribute(Attribute::Bold);
+                    }
+
+                    row.add_cell(match color {
+                        Some(color) => cell.fg(color),
+                        None => cell,
+                    });
+                }
+
+                let values = self.headers.get(&env.id);
+                for name in &self.header_names {
+                    let value = values.and_then(|values| values.get(name));
+                    row.add_cell(Cell::new(value.map(String::as_str).unwrap_or_default()));
                 }
-                row.add_cell(Cell::new(&env.subject).fg(self.colors.subject));
 
-                let addresses = if self.recipient { &env.to } else { &env.from };
-                let from_or_to_color = if self.recipient {
-                    self.colors.to
-                } else {
-                    self.colors.from
-                };
-                row.add_cell(Cell::new(format_addresses(addresses)).fg(from_or_to_color));
-
-                row.add_cell(
-                    Cell::new(format_date(
-                        env.date,
-                        &self.datetime_fmt,
-                        self.datetime_local_tz,
-                    ))
-                    .fg(self.colors.date),
-                );
-                row.add_cell(Cell::new(format_size(env.size, BINARY)).fg(self.colors.size));
                 row
             }));
 
         if let Some(width) = self.max_width {
-            table.set_width(width);
+            table.set_width(width.columns());
         }
 
         writeln!(f)?;
-        writeln!(f, "{table}")
+
+        match &self.highlight {
+            Some(h) => {
+                let color = table.should_style().then_some(h.color);
+                writeln!(f, "{}", highlight::render(&table.to_string(), color))
+            }
+            None => writeln!(f, "{table}"),
+        }
     }
 }
 
+impl Envelopes {
+    /// Placeholder row of a message the listing skipped.
+    fn invalid_row(&self, columns: &[EnvelopeColumn], id: &str) -> Row {
+        let mut row = Row::new();
+        row.max_height(1);
+
+        for column in columns {
+            row.add_cell(match column {
+                EnvelopeColumn::Id => Cell::new(id).fg(self.colors.id),
+                EnvelopeColumn::Subject => Cell::new("[unparseable]").fg(self.colors.subject),
+                _ => Cell::new(""),
+            });
+        }
+
+        row
+    }
+
+    /// `text` with the `--highlight` matches marked, if any.
+    fn highlighted(&self, text: &str) -> String {
+        match &self.highlight {
+            Some(h) => highlight::mark(text, &h.regex),
+            None => text.to_owned(),
+        }
+    }
+
+    /// Columns to render: the configured ones, minus those not
+    /// applying to the listing, plus the ACCOUNT and MAILBOX labels of
+    /// a listing spanning several sources.
+    fn layout(&self) -> Vec<EnvelopeColumn> {
+        let mut columns = self.columns.clone();
+
+        let labels = [
+            (EnvelopeColumn::Mailbox, !self.mailboxes.is_empty()),
+            (EnvelopeColumn::Account, !self.accounts.is_empty()),
+        ];
+
+        for (label, shown) in labels {
+            if shown && !columns.contains(&label) {
+                let at = columns
+                    .iter()
+                    .position(|column| *column == EnvelopeColumn::Id)
+                    .map_or(0, |i| i + 1);
+                columns.insert(at, label);
+            }
+        }
+
+        columns.retain(|column| match column {
+            EnvelopeColumn::Account => !self.accounts.is_empty(),
+            EnvelopeColumn::Mailbox => !self.mailboxes.is_empty(),
+            EnvelopeColumn::Att => self.with_attachment,
+            _ => true,
+        });
+
+        columns
+    }
+
+    fn column_titles(&self, columns: &[EnvelopeColumn]) -> Vec<String> {
+        let mut titles: Vec<String> = columns
+            .iter()
+            .map(|column| {
+                let title = match column {
+                    EnvelopeColumn::Id => "ID",
+                    EnvelopeColumn::Account => "ACCOUNT",
+                    EnvelopeColumn::Mailbox => "MAILBOX",
+                    EnvelopeColumn::Flags => "FLAGS",
+                    EnvelopeColumn::Att => "ATT",
+                    EnvelopeColumn::Subject => "SUBJECT",
+                    EnvelopeColumn::From if self.recipient => "TO",
+                    EnvelopeColumn::From => "FROM",
+                    EnvelopeColumn::To => "TO",
+                    EnvelopeColumn::Date => "DATE",
+                    EnvelopeColumn::Size => "SIZE",
+                };
+                String::from(title)
+            })
+            .collect();
+
+        for name in &self.header_names {
+            titles.push(name.to_uppercase());
+        }
+
+        titles
+    }
+
+    /// Text and color of the `column` cell of the `i`th envelope,
+    /// with the `--highlight` matches marked when `styled`.
+    fn cell(
+        &self,
+        column: EnvelopeColumn,
+        i: usize,
+        env: &Envelope,
+        styled: bool,
+    ) -> (String, Option<Color>) {
+        let marked = |text: &str| {
+            if styled {
+                self.highlighted(text)
+            } else {
+                text.to_owned()
+            }
+        };
+
+        match column {
+            EnvelopeColumn::Id => (env.id.clone(), Some(self.colors.id)),
+            EnvelopeColumn::Account => (self.accounts.get(i).cloned().unwrap_or_default(), None),
+            EnvelopeColumn::Mailbox => (self.mailboxes.get(i).cloned().unwrap_or_default(), None),
+            EnvelopeColumn::Flags => (
+                format_flags(&env.flags, &self.chars),
+                Some(self.colors.flags),
+            ),
+            EnvelopeColumn::Att => (
+                format_attachment(env.has_attachment, self.chars.attachment),
+                Some(self.colors.att),
+            ),
+            EnvelopeColumn::Subject => (marked(&env.subject), Some(self.colors.subject)),
+            EnvelopeColumn::From if self.recipient => {
+                (marked(&format_addresses(&env.to)), Some(self.colors.to))
+            }
+            EnvelopeColumn::From => (marked(&format_addresses(&env.from)), Some(self.colors.from)),
+            EnvelopeColumn::To => (marked(&format_addresses(&env.to)), Some(self.colors.to)),
+            EnvelopeColumn::Date => (self.date(env.date), Some(self.colors.date)),
+            EnvelopeColumn::Size => (format_envelope_size(env.size), Some(self.colors.size)),
+        }
+    }
+
+    /// Whether `env` was sent by one of `highlight-senders`.
+    fn is_highlighted_sender(&self, env: &Envelope) -> bool {
+        env.from.iter().any(|from| {
+            let email = from.email.trim();
+            self.highlight_senders
+                .iter()
+                .any(|addr| addr.eq_ignore_ascii_case(email))
+        })
+    }
+
+    /// DATE cell of an envelope, relative to now when configured and
+    /// recent enough.
+    fn date(&self, date: Option<DateTime<FixedOffset>>) -> String {
+        let relative = match (self.date_format, date) {
+            (EnvelopeDateFormat::Relative, Some(date)) => {
+                format_relative_date(date, Local::now().fixed_offset())
+            }
+            _ => None,
+        };
+
+        match relative {
+            Some(relative) => relative,
+            None => format_date(date, &self.datetime_fmt, self.datetime_tz),
+        }
+    }
+
+    /// Renders a GitHub-flavored Markdown table: plain text cells,
+    /// pipes escaped, and the same column count on every row
+    /// (unparseable placeholders included).
+    fn fmt_markdown(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let columns = self.layout();
+        let titles = self.column_titles(&columns);
+        let width = titles.len();
+
+        write_markdown_row(f, &titles)?;
+        write_markdown_row(f, &vec![String::from("---"); width])?;
+
+        for row in self.rows() {
+            let (i, env) = match row {
+                EnvelopeRow::Envelope(i) => (i, &self.envelopes[i]),
+                EnvelopeRow::Invalid(id) => {
+                    let row: Vec<String> = (0..width)
+                        .map(|i| match columns.get(i) {
+                            Some(EnvelopeColumn::Id) => id.to_owned(),
+                            Some(EnvelopeColumn::Subject) => String::from("[unparseable]"),
+                            _ => String::new(),
+                        })
+                        .collect();
+                    write_markdown_row(f, &row)?;
+                    continue;
+                }
+            };
+
+            let mut row: Vec<String> = columns
+                .iter()
+                .map(|column| self.cell(*column, i, env, false).0)
+                .collect();
+
+            let values = self.headers.get(&env.id);
+            for name in &self.header_names {
+                let value = values.and_then(|values| values.get(name));
+                row.push(value.cloned().unwrap_or_default());
+            }
+
+            write_markdown_row(f, &row)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_markdown_row(f: &mut fmt::Formatter<'_>, cells: &[String]) -> fmt::Result {
+    write!(f, "|")?;
+    for cell in cells {
+        let cell = cell
+            .trim()
+            .replace('\\', "\\\\")
+            .replace('|', "\\|")
+            .replace(['\r', '\n'], " ");
+        write!(f, " {cell} |")?;
+    }
+    writeln!(f)
+}
+
 /// 3-character flag widget: unseen, replied, flagged. Each slot is a
 /// space when the flag is absent, otherwise the configured glyph
 /// (v1.2.0 defaults: `*`, `R`, `!`).
@@ -237,6 +1592,16 @@ pub(super) fn format_flags(flags: &BTreeSet<Flag>, chars: &FlagChars) -> String
     out
 }
 
+/// Human-readable size of a message, empty when the backend did not
+/// report it: no message is zero bytes long.
+pub(super) fn format_envelope_size(size: u64) -> String {
+    if size == 0 {
+        return String::new();
+    }
+
+    format_size(size, BINARY)
+}
+
 pub(super) fn format_attachment(has: Option<bool>, glyph: char) -> String {
     match has {
         Some(true) => glyph.to_string(),
@@ -259,14 +1624,194 @@ pub(super) fn format_addresses(addrs: &[Address]) -> String {
 pub(super) fn format_date(
     date: Option<DateTime<FixedOffset>>,
     fmt: &str,
-    local_tz: bool,
+    tz: TimezoneConfig,
 ) -> String {
-    let Some(date) = date else {
-        return String::new();
+    match date {
+        Some(date) => tz.format(date, fmt),
+        None => String::new(),
+    }
+}
+
+/// How many of the new envelope `dates` (oldest first) a
+/// `--since-last-check` run lists, and the marker it records: the
+/// date of the newest listed envelope when some are left for the next
+/// run, `now` otherwise.
+fn since_window(
+    dates: &[DateTime<FixedOffset>],
+    page_size: Option<u32>,
+    now: DateTime<FixedOffset>,
+) -> (usize, DateTime<FixedOffset>) {
+    match page_size.map(|size| size as usize) {
+        Some(size) if dates.len() > size && size > 0 => (size, dates[size - 1]),
+        _ => (dates.len(), now),
+    }
+}
+
+/// Whether the `Cc:` header of the message `raw` holds an address
+/// matching `is_own`.
+fn cc_own(raw: &[u8], is_own: impl Fn(&str) -> bool) -> bool {
+    let Some(message) = MessageParser::new().parse_headers(raw) else {
+        return false;
     };
-    if local_tz {
-        date.with_timezone(&Local).format(fmt).to_string()
-    } else {
-        date.format(fmt).to_string()
+
+    message
+        .cc()
+        .into_iter()
+        .flat_map(|addrs| addrs.iter())
+        .filter_map(|addr| addr.address())
+        .any(is_own)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn places_invalid_rows_at_their_uid() {
+        use EnvelopeRow::*;
+
+        let invalid = [String::from("9"), String::from("6"), String::from("1")];
+        let rows = envelope_rows(&["8", "7", "5"], &invalid);
+        assert_eq!(
+            rows,
+            [
+                Invalid("9"),
+                Envelope(0),
+                Envelope(1),
+                Invalid("6"),
+                Envelope(2),
+                Invalid("1")
+            ]
+        );
+
+        let invalid = [String::from("1"), String::from("6")];
+        let rows = envelope_rows(&["5", "7"], &invalid);
+        assert_eq!(rows, [Invalid("1"), Envelope(0), Invalid("6"), Envelope(1)]);
+    }
+
+    #[test]
+    fn since_last_check_marks_the_listed_envelopes_only() {
+        let date = |s| DateTime::parse_from_rfc3339(s).unwrap();
+        let now = date("2024-01-03T00:00:00Z");
+        let dates = [
+            date("2024-01-01T10:00:00Z"),
+            date("2024-01-01T11:00:00Z"),
+            date("2024-01-02T09:00:00Z"),
+        ];
+
+        assert_eq!(since_window(&dates, None, now), (3, now));
+        assert_eq!(since_window(&dates, Some(3), now), (3, now));
+        assert_eq!(since_window(&dates, Some(2), now), (2, dates[1]));
+        assert_eq!(since_window(&[], Some(2), now), (0, now));
+    }
+
+    #[test]
+    fn mine_matches_cc_addresses() {
+        let is_own = |email: &str| email.eq_ignore_ascii_case("me@localhost");
+
+        let raw = b"From: a@b\r\nTo: team@b\r\nCc: Bob <bob@b>, Me <ME@localhost>\r\n\r\n";
+        assert!(cc_own(raw, is_own));
+
+        let raw = b"From: a@b\r\nTo: me@localhost\r\nCc: bob@b\r\n\r\n";
+        assert!(!cc_own(raw, is_own));
+    }
+
+    #[cfg(feature = "imap")]
+    #[test]
+    fn counts_skipped_messages_within_the_listed_ones() {
+        let uids = |ids: &[&str]| -> Vec<String> { ids.iter().map(|id| id.to_string()).collect() };
+
+        // NOTE: 12 arrived after the listing, 7 was skipped
+        let page = uids(&["12", "10", "9", "7", "5"]);
+        assert_eq!(count_skipped(&["10", "9", "5"], &page), 1);
+        assert_eq!(count_skipped(&["10", "9", "7", "5"], &page), 0);
+        assert_eq!(count_skipped(&[], &page), 0);
+    }
+
+    #[cfg(feature = "imap")]
+    #[test]
+    fn computes_the_length_of_listed_pages() {
+        assert_eq!(page_len(25, None, None), 25);
+        assert_eq!(page_len(25, None, Some(10)), 10);
+        assert_eq!(page_len(25, Some(3), Some(10)), 5);
+        assert_eq!(page_len(25, Some(4), Some(10)), 0);
+        assert_eq!(page_len(3, Some(1), Some(10)), 3);
+    }
+
+    #[test]
+    fn formats_relative_dates() {
+        let date = |s| DateTime::parse_from_rfc3339(s).unwrap();
+        let now = date("2024-03-20T12:00:00+01:00");
+
+        let cases = [
+            ("2024-03-20T11:59:30+01:00", Some("just now")),
+            ("2024-03-20T11:55:00+01:00", Some("5m ago")),
+            ("2024-03-20T09:00:00+01:00", Some("3h ago")),
+            // NOTE: same calendar day as `now` once in its timezone
+            ("2024-03-19T23:30:00Z", Some("11h ago")),
+            ("2024-03-19T23:30:00+01:00", Some("yesterday")),
+            ("2024-03-17T12:00:00+01:00", Some("3 days ago")),
+            ("2024-03-12T12:00:00+01:00", Some("1 week ago")),
+            ("2024-03-05T12:00:00+01:00", Some("2 weeks ago")),
+            ("2024-02-27T12:00:00+01:00", Some("3 weeks ago")),
+            // NOTE: four calendar weeks ago, just within the cut-off
+            ("2024-02-21T12:00:01+01:00", Some("4 weeks ago")),
+            ("2024-02-21T12:00:00+01:00", None),
+            ("2024-03-20T12:01:00+01:00", None),
+        ];
+
+        for (date_str, expected) in cases {
+            let relative = format_relative_date(date(date_str), now);
+            assert_eq!(relative.as_deref(), expected, "{date_str}");
+        }
+    }
+
+    #[test]
+    fn writes_one_tagged_envelope_per_line() {
+        let (first, second) = (
+            serde_json::json!({ "id": "1", "subject": "Hello" }),
+            serde_json::json!({ "id": "2", "subject": "World" }),
+        );
+        let headers = BTreeMap::from([(String::from("list-id"), String::from("<dev.lists>"))]);
+
+        let lines = [
+            EnvelopeLine {
+                account: Some("work"),
+                mailbox: Some("INBOX"),
+                envelope: &first,
+                headers: Some(&headers),
+            },
+            EnvelopeLine {
+                account: None,
+                mailbox: None,
+                envelope: &second,
+                headers: None,
+            },
+        ];
+
+        let mut out = Vec::new();
+        write_json_lines(&mut out, lines).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let lines: Vec<serde_json::Value> = out
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(out.lines().count(), 2);
+        assert_eq!(
+            lines[0],
+            serde_json::json!({
+                "account": "work",
+                "mailbox": "INBOX",
+                "id": "1",
+                "subject": "Hello",
+                "headers": { "list-id": "<dev.lists>" },
+            })
+        );
+        assert_eq!(
+            lines[1],
+            serde_json::json!({ "id": "2", "subject": "World" })
+        );
     }
 }
@@ -1,3 +1,11 @@
 pub mod cli;
+pub mod count;
+pub mod decode;
+pub mod dump;
+pub mod fuzzy;
+pub mod group;
+pub mod highlight;
+pub mod last_check;
 pub mod list;
 pub mod search;
+pub mod virtual_mailbox;
@@ -7,10 +7,19 @@ use io_email::search::{error::Error as SearchQueryError, query::SearchEmailsQuer
 use pimalaya_cli::printer::Printer;
 
 use crate::account::context::Account;
+use crate::profile;
 use crate::shared::{
     client::EmailClient,
-    envelope::list::{EnvelopeColors, Envelopes, FlagChars},
+    envelope::{
+        decode::decode_envelopes,
+        fuzzy::FuzzySubject,
+        list::{
+            EnvelopeColors, Envelopes, FlagChars, empty_line, shows_recipients,
+            with_attachment_fallback,
+        },
+    },
     mailbox::arg::MailboxArg,
+    width::MaxWidth,
 };
 
 /// Search envelopes for the active account using the shared search
@@ -39,10 +48,11 @@ pub struct EnvelopeSearchCommand {
     #[arg(value_name = "N")]
     pub page_size: Option<u32>,
 
-    /// Maximum width of the rendered table, in terminal columns.
+    /// Maximum width of the rendered table, in terminal columns, or
+    /// `auto` for the terminal width at draw time.
     #[arg(long = "max-width", short = 'w')]
-    #[arg(value_name = "COLUMNS")]
-    pub max_width: Option<u16>,
+    #[arg(value_name = "COLUMNS|auto")]
+    pub max_width: Option<MaxWidth>,
 
     /// Render recipients (`To:`) instead of senders (`From:`).
     #[arg(long, short)]
@@ -59,9 +69,22 @@ pub struct EnvelopeSearchCommand {
     /// `body <pattern>`, `flag <seen|answered|flagged|draft>`. Combine
     /// with `and`, `or`, `not`, group with parentheses. Sort with
     /// `order by <date|from|to|subject> [asc|desc]…`.
+    ///
+    /// `~subject <text>` matches subjects approximately, tolerating
+    /// typos and misremembered words. Quote the text to stop it
+    /// before a condition, e.g. `~subject '"weekly reprot"' and flag
+    /// seen`. The clause can be negated with `not`, and is always
+    /// combined with `and` (`or` is refused): the rest of the query
+    /// runs on the server, then the envelopes of the page are
+    /// filtered client-side, so a page may come out short.
     #[arg(value_name = "QUERY")]
     #[arg(allow_hyphen_values = true, trailing_var_arg = true)]
     pub query: Option<Vec<String>>,
+
+    /// Minimum similarity between the `~subject` text and a subject,
+    /// from 0 (anything) to 1 (exact words).
+    #[arg(long, value_name = "RATIO", default_value_t = 0.8)]
+    pub fuzzy_threshold: f64,
 }
 
 impl EnvelopeSearchCommand {
@@ -71,30 +94,53 @@ impl EnvelopeSearchCommand {
         account: &mut Account,
         client: &mut EmailClient,
     ) -> Result<()> {
+        let envelopes = profile::time("fetch", || self.search(account, client))?;
+        profile::time("render", || printer.out(envelopes))
+    }
+
+    /// Searches the envelopes of `account` without printing them, so
+    /// `--account all` can gather them across accounts.
+    pub fn search(&self, account: &mut Account, client: &mut EmailClient) -> Result<Envelopes> {
         let page = Some(self.page).filter(|p| *p > 0);
         let page_size = self
             .page_size
             .or(Some(account.envelopes_list_page_size()))
             .filter(|p| *p > 0);
         let mailbox = self.mailbox.resolve(account)?;
-        let query = parse_query(self.query.as_deref())?;
-
-        let envelopes = client.search_envelopes(
-            &mailbox,
-            query.as_ref(),
-            page,
-            page_size,
-            self.has_attachment,
-        )?;
-
-        let envelopes = Envelopes {
+        let joined = self.query.as_ref().map(|words| words.join(" "));
+        let (joined, fuzzy) = FuzzySubject::take(joined.as_deref().unwrap_or_default())?;
+        let query = parse_query(&joined)?;
+
+        let mut envelopes = with_attachment_fallback(self.has_attachment, page_size, |has_att| {
+            Ok(client.search_envelopes(&mailbox, query.as_ref(), page, page_size, has_att)?)
+        })?;
+        decode_envelopes(&mut envelopes);
+
+        if let Some(fuzzy) = fuzzy {
+            envelopes.retain(|env| fuzzy.matches(&env.subject, self.fuzzy_threshold));
+        }
+
+        let columns = account.envelopes_list_table_columns();
+        let recipient = shows_recipients(
+            self.recipient,
+            account,
+            std::slice::from_ref(&mailbox),
+            &columns,
+        );
+
+        Ok(Envelopes {
             preset: account.table_preset().to_string(),
             arrangement: account.table_arrangement(),
             max_width: self.max_width,
             datetime_fmt: account.datetime_fmt().to_string(),
-            datetime_local_tz: account.datetime_local_tz(),
-            recipient: self.recipient,
+            datetime_tz: account.datetime_tz(),
+            date_format: account.envelopes_list_table_date_format(),
+            recipient,
             with_attachment: self.has_attachment,
+            columns,
+            header_names: Vec::new(),
+            output: Default::default(),
+            highlight: None,
             chars: FlagChars {
                 unseen: account.envelopes_list_table_unseen_char(),
                 replied: account.envelopes_list_table_replied_char(),
@@ -110,28 +156,32 @@ impl EnvelopeSearchCommand {
                 to: account.envelopes_list_table_to_color(),
                 date: account.envelopes_list_table_date_color(),
                 size: account.envelopes_list_table_size_color(),
+                sender: account.envelopes_list_table_highlight_senders_color(),
             },
+            highlight_senders: account.envelopes_list_table_highlight_senders(),
             envelopes,
-        };
-
-        printer.out(envelopes)
+            accounts: Vec::new(),
+            mailboxes: Vec::new(),
+            headers: Default::default(),
+            invalid: Vec::new(),
+            empty: empty_line(
+                self.query
+                    .as_ref()
+                    .map(|words| words.join(" "))
+                    .filter(|query| !query.is_empty())
+                    .as_deref(),
+                page,
+                &mailbox,
+            ),
+        })
     }
 }
 
-/// Joins the trailing-positional words and feeds them to
+/// Feeds the joined trailing-positional words to
 /// [`SearchEmailsQuery::from_str`]. Returns `Ok(None)` when the input
 /// is empty (no query) so `client.search_envelopes` keeps its default
 /// behaviour, or bails with the ariadne-rendered parse error.
-fn parse_query(words: Option<&[String]>) -> Result<Option<SearchEmailsQuery>> {
-    let Some(words) = words else {
-        return Ok(None);
-    };
-
-    let joined = words
-        .iter()
-        .map(String::as_str)
-        .collect::<Vec<_>>()
-        .join(" ");
+pub(super) fn parse_query(joined: &str) -> Result<Option<SearchEmailsQuery>> {
     let trimmed = joined.trim();
     if trimmed.is_empty() {
         return Ok(None);
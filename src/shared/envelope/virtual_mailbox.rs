@@ -0,0 +1,117 @@
+//! `envelope list -m @<name>`: read-only listing of a virtual
+//! mailbox, merging mailboxes of several accounts by date.
+
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::{
+    account::context::Account,
+    backend::Backend,
+    config::Config,
+    shared::{
+        client::{EmailClient, build_email_client},
+        envelope::list::{EnvelopeListCommand, Envelopes, merge_depth, merge_page},
+    },
+};
+
+impl EnvelopeListCommand {
+    /// Lists the virtual mailbox `-m @<name>` targets, connecting to
+    /// each of its accounts once. Envelopes are labelled with their
+    /// account and mailbox.
+    ///
+    /// Rendering options and the page size are taken from the first
+    /// account of the virtual mailbox.
+    pub fn list_virtual(
+        &self,
+        config: &Config,
+        config_paths: &[PathBuf],
+        backend: Backend,
+    ) -> Result<Envelopes> {
+        let Some(name) = self.mailbox.virtual_name() else {
+            bail!("No virtual mailbox given");
+        };
+
+        if self.since_last_check
+            || self.group_by.is_some()
+            || self.include_invalid
+            || self.dump_raw_fetch.is_some()
+            || !self.headers.is_empty()
+        {
+            bail!(
+                "Virtual mailbox @{name} only supports plain listings: `--since-last-check`, \
+                 `--group-by`, `--include-invalid`, `--dump-raw-fetch` and `--header` are not \
+                 supported"
+            );
+        }
+
+        let Some(sources) = config
+            .virtual_mailboxes
+            .get(name)
+            .map(|v| v.mailboxes.clone())
+        else {
+            bail!("Cannot find virtual mailbox @{name}: declare it under `[virtual.{name}]`");
+        };
+
+        if sources.is_empty() {
+            bail!("Virtual mailbox @{name} has no mailbox");
+        }
+
+        let mut clients: HashMap<String, (Account, EmailClient)> = HashMap::new();
+        let mut output = None;
+        let (mut page, mut page_size) = (None, None);
+        let mut merged = Vec::new();
+
+        for source in sources {
+            let (account, client) = match clients.entry(source.account.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let client = build_email_client(config_paths, Some(&source.account), backend)
+                        .with_context(|| {
+                        format!("Build client for account `{}` error", source.account)
+                    })?;
+                    entry.insert(client)
+                }
+            };
+
+            if output.is_none() {
+                (page, page_size) = self.page_window(account);
+                output = Some(self.template(account));
+            }
+
+            let mailbox = account.resolve_mailbox(&source.mailbox).to_owned();
+            let mut envelopes = self
+                .list_depth(client, &mailbox, merge_depth(page, page_size))
+                .with_context(|| {
+                    format!(
+                        "List envelopes of mailbox {mailbox} of account `{}` error",
+                        source.account
+                    )
+                })?;
+            self.retain_filtered(account, client, &mailbox, &mut envelopes)?;
+
+            merged.extend(
+                envelopes
+                    .into_iter()
+                    .map(|env| ((source.account.clone(), mailbox.clone()), env)),
+            );
+        }
+
+        let mut output = output.ok_or_else(|| anyhow!("Virtual mailbox @{name} is empty"))?;
+        let (labels, envelopes): (Vec<_>, Vec<_>) =
+            merge_page(merged, page, page_size).into_iter().unzip();
+
+        (output.accounts, output.mailboxes) = labels.into_iter().unzip();
+        output.envelopes = envelopes;
+        output.empty = self.empty_line(page, &format!("@{name}"));
+
+        if self.reverse {
+            output.reverse();
+        }
+
+        Ok(output)
+    }
+}
@@ -25,11 +25,12 @@ impl FlagCommand {
         printer: &mut impl Printer,
         account: &mut Account,
         client: &mut EmailClient,
+        account_name: &str,
     ) -> Result<()> {
         match self {
-            Self::Add(cmd) => cmd.execute(printer, account, client),
-            Self::Set(cmd) => cmd.execute(printer, account, client),
-            Self::Remove(cmd) => cmd.execute(printer, account, client),
+            Self::Add(cmd) => cmd.execute(printer, account, client, account_name),
+            Self::Set(cmd) => cmd.execute(printer, account, client, account_name),
+            Self::Remove(cmd) => cmd.execute(printer, account, client, account_name),
         }
     }
 }
@@ -7,10 +7,12 @@ use pimalaya_cli::printer::Printer;
 use serde::Serialize;
 
 use crate::account::context::Account;
+use crate::dry_run::{self, DryRun};
 use crate::shared::{
     client::EmailClient,
     flag::arg::{FlagsArg, MessageIdsArg},
     mailbox::arg::MailboxArg,
+    message::undo::Operation,
 };
 
 /// Remove flag(s) from message(s) for the active account.
@@ -30,12 +32,21 @@ impl FlagRemoveCommand {
         printer: &mut impl Printer,
         account: &mut Account,
         client: &mut EmailClient,
+        account_name: &str,
     ) -> Result<()> {
         let mailbox = self.mailbox.resolve(account)?;
         let ids: Vec<&str> = self.message_ids.inner.iter().map(String::as_str).collect();
         let flags: Vec<Flag> = self.flags.inner.iter().map(Into::into).collect();
 
+        if dry_run::enabled() {
+            let flags: Vec<String> = self.flags.inner.iter().map(ToString::to_string).collect();
+            let action = format!("remove flags {}", flags.join(", "));
+            return printer.out(DryRun::new(action, &mailbox, &self.message_ids.inner));
+        }
+
+        let current = client.get_flags(&mailbox, &ids)?;
         client.store_flags(&mailbox, &ids, &flags, FlagOp::Remove)?;
+        Operation::flags(&mailbox, &ids, &self.flags.inner, &current, false).record(account_name);
 
         let flags: Vec<String> = self.flags.inner.iter().map(ToString::to_string).collect();
         printer.out(RemovedFlags { flags })
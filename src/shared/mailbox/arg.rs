@@ -1,8 +1,18 @@
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail};
 use clap::Parser;
 
 use crate::account::context::Account;
 
+/// Prefix of the virtual mailbox names (`[virtual.<name>]`).
+pub const VIRTUAL_PREFIX: char = '@';
+
+/// Whether `name` targets a virtual mailbox: prefixed and declared.
+/// Otherwise it is an ordinary mailbox name starting with `@`.
+fn is_virtual(account: &Account, name: &str) -> bool {
+    name.strip_prefix(VIRTUAL_PREFIX)
+        .is_some_and(|name| account.has_virtual_mailbox(name))
+}
+
 /// Optional `-m|--mailbox <NAME>` flag shared by every cross-protocol
 /// command that targets a single mailbox. The argument is resolved
 /// through [`Self::resolve`] so callers can transparently consult the
@@ -28,7 +38,7 @@ impl MailboxArg {
     /// no `inbox` alias configured.
     pub fn resolve(&self, account: &Account) -> Result<String> {
         match self.inner.as_deref() {
-            Some(name) => Ok(account.resolve_mailbox(name).to_string()),
+            Some(name) => Self::resolve_name(account, name),
             None => account.default_mailbox().map(str::to_owned).ok_or_else(|| {
                 anyhow!(
                     "Mailbox is required: pass -m/--mailbox <NAME>, \
@@ -37,4 +47,91 @@ impl MailboxArg {
             }),
         }
     }
+
+    /// Resolves the mailbox `name` given to another flag (e.g.
+    /// `--from`, `--to`) like `-m <name>`, refusing virtual
+    /// mailboxes.
+    pub fn resolve_name(account: &Account, name: &str) -> Result<String> {
+        if is_virtual(account, name) {
+            bail!(
+                "Virtual mailbox `{name}` is read-only: only `envelope list -m {name}` supports it"
+            );
+        }
+
+        Ok(account.resolve_mailbox(name).to_string())
+    }
+}
+
+/// Repeatable `-m|--mailbox <NAME>` flag of the commands gathering
+/// several mailboxes at once. Each name is resolved like
+/// [`MailboxArg`], and the `inbox` alias is the fallback when the
+/// flag is omitted.
+#[derive(Clone, Debug, Default, Parser)]
+pub struct MailboxesArg {
+    /// Mailbox name(s). Repeat the flag, or separate names with
+    /// commas, to target several mailboxes. Looked up against
+    /// `[mailbox.alias]` like single mailbox names, or `@<name>` for
+    /// a virtual mailbox declared under `[virtual.<name>]`. Omit to
+    /// fall back to the id mapped to the `inbox` alias.
+    #[arg(short = 'm', long = "mailbox", alias = "folder", value_name = "NAME")]
+    #[arg(value_delimiter = ',')]
+    pub inner: Vec<String>,
+}
+
+impl MailboxesArg {
+    /// Resolves the mailbox names to backend-native ids, without
+    /// duplicates, in the given order.
+    pub fn resolve(&self, account: &Account) -> Result<Vec<String>> {
+        if self.inner.is_empty() {
+            return Ok(vec![MailboxArg::default().resolve(account)?]);
+        }
+
+        let mut ids: Vec<String> = Vec::with_capacity(self.inner.len());
+
+        for name in &self.inner {
+            if is_virtual(account, name) {
+                bail!("Virtual mailbox `{name}` must be listed alone, with `-m {name}`");
+            }
+
+            let id = account.resolve_mailbox(name).to_owned();
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+
+        Ok(ids)
+    }
+
+    /// Name of the virtual mailbox targeted, without its prefix, when
+    /// it is the only mailbox given. It may not be declared, in which
+    /// case it names an ordinary mailbox.
+    pub fn virtual_name(&self) -> Option<&str> {
+        match self.inner.as_slice() {
+            [name] => name.strip_prefix(VIRTUAL_PREFIX),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_virtual_mailboxes_for_mutations() {
+        let account = Account {
+            virtual_mailboxes: vec![String::from("unread")],
+            ..Account::default()
+        };
+
+        assert!(MailboxArg::resolve_name(&account, "@unread").is_err());
+        assert_eq!(
+            MailboxArg::resolve_name(&account, "@other").unwrap(),
+            "@other"
+        );
+        assert_eq!(
+            MailboxArg::resolve_name(&account, "Archive").unwrap(),
+            "Archive"
+        );
+    }
 }
@@ -0,0 +1,138 @@
+//! `mailbox list` cache: the mailboxes of each account, persisted
+//! between invocations so listings within `mailbox.list.cache-ttl`
+//! are served without connecting.
+//!
+//! Entries live in `mailboxes.json` under the user cache directory
+//! (`$XDG_CACHE_HOME/himalaya` on Linux), keyed by configuration
+//! files, `--backend` and account name, see [`key`]. The whole file
+//! is removed whenever a mailbox is created, deleted or renamed
+//! through himalaya, see [`invalidate`].
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use io_email::mailbox::types::Mailbox;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::Backend;
+
+/// Cached mailboxes of an account.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CachedMailboxes {
+    /// Unix timestamp of the listing.
+    pub fetched_at: i64,
+    /// Hierarchy delimiter reported by the server, when it was asked
+    /// for.
+    pub delimiter: Option<char>,
+    pub mailboxes: Vec<Mailbox>,
+}
+
+/// Cached mailboxes of every account.
+pub struct MailboxCache {
+    path: PathBuf,
+    entries: BTreeMap<String, CachedMailboxes>,
+}
+
+impl MailboxCache {
+    /// Loads the cache, empty when the file does not exist yet or
+    /// cannot be parsed (e.g. written by another version).
+    pub fn load() -> Result<Self> {
+        let path = path()?;
+
+        let entries = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|err| {
+                debug!("ignoring invalid mailbox cache `{}`: {err}", path.display());
+                BTreeMap::new()
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Read `{}` error", path.display()));
+            }
+        };
+
+        Ok(Self { path, entries })
+    }
+
+    /// Mailboxes cached under `key`, when listed less than `ttl`
+    /// seconds ago.
+    pub fn get(&self, key: &str, ttl: u64) -> Option<&CachedMailboxes> {
+        let cached = self.entries.get(key)?;
+        let age = Utc::now().timestamp().saturating_sub(cached.fetched_at);
+
+        (0..ttl as i64).contains(&age).then_some(cached)
+    }
+
+    /// Records `mailboxes` as the current ones under `key`, then
+    /// writes the cache back.
+    pub fn set(
+        &mut self,
+        key: &str,
+        mailboxes: Vec<Mailbox>,
+        delimiter: Option<char>,
+    ) -> Result<()> {
+        let cached = CachedMailboxes {
+            fetched_at: Utc::now().timestamp(),
+            delimiter,
+            mailboxes,
+        };
+
+        self.entries.insert(key.to_owned(), cached);
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Create `{}` error", dir.display()))?;
+        }
+
+        let content = serde_json::to_string(&self.entries).context("Serialize mailboxes error")?;
+        fs::write(&self.path, content)
+            .with_context(|| format!("Write `{}` error", self.path.display()))
+    }
+}
+
+/// Cache key of the mailboxes of `account`: two configurations may
+/// define accounts of the same name, and `--backend` may list the
+/// mailboxes of another backend of the account.
+pub fn key(config_paths: &[PathBuf], backend: Backend, account: &str) -> String {
+    let paths: Vec<String> = config_paths
+        .iter()
+        .map(|path| canonical(path).display().to_string())
+        .collect();
+
+    format!("{}|{backend:?}|{account}", paths.join(":"))
+}
+
+/// Drops the cached mailboxes of every account, after a mailbox
+/// mutation. Failing to do so only warns.
+pub fn invalidate() {
+    let result = path().and_then(|path| match fs::remove_file(&path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("Remove `{}` error", path.display()))
+        }
+        _ => Ok(()),
+    });
+
+    if let Err(err) = result {
+        warn!("cannot invalidate mailbox cache: {err}");
+        debug!("{err:?}");
+    }
+}
+
+fn path() -> Result<PathBuf> {
+    let Some(cache_dir) = dirs::cache_dir() else {
+        bail!("Cannot find the user cache directory for the mailbox cache");
+    };
+
+    Ok(cache_dir.join("himalaya").join("mailboxes.json"))
+}
+
+/// Absolute `path` with symlinks resolved, so the same file reached
+/// through different paths shares its entries. `path` as is when it
+/// cannot be resolved.
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
@@ -3,7 +3,10 @@ use clap::Subcommand;
 use pimalaya_cli::printer::Printer;
 
 use crate::account::context::Account;
-use crate::shared::{client::EmailClient, mailbox::list::MailboxListCommand};
+use crate::shared::{
+    client::EmailClient,
+    mailbox::{cache::CachedMailboxes, list::MailboxListCommand},
+};
 
 /// Manage mailboxes using the shared API.
 ///
@@ -20,9 +23,30 @@ impl MailboxCommand {
         printer: &mut impl Printer,
         account: &mut Account,
         client: &mut EmailClient,
+        cache_key: &str,
     ) -> Result<()> {
         match self {
-            Self::List(cmd) => cmd.execute(printer, account, client),
+            Self::List(cmd) => cmd.execute(printer, account, client, cache_key),
+        }
+    }
+
+    /// Cached output of the command under `cache_key` (see
+    /// [`key`](crate::shared::mailbox::cache::key)), when it can be
+    /// served without connecting.
+    pub fn cached(&self, cache_key: &str, ttl: u64) -> Result<Option<CachedMailboxes>> {
+        match self {
+            Self::List(cmd) => cmd.cached(cache_key, ttl),
+        }
+    }
+
+    pub fn execute_cached(
+        self,
+        printer: &mut impl Printer,
+        account: &mut Account,
+        cached: CachedMailboxes,
+    ) -> Result<()> {
+        match self {
+            Self::List(cmd) => cmd.execute_cached(printer, account, cached),
         }
     }
 }
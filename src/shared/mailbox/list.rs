@@ -4,11 +4,17 @@ use anyhow::Result;
 use clap::Parser;
 use comfy_table::{Cell, Color, ContentArrangement, Row, Table};
 use io_email::mailbox::types::Mailbox;
+use log::{debug, warn};
 use pimalaya_cli::printer::Printer;
 use serde::Serialize;
 
 use crate::account::context::Account;
-use crate::shared::client::EmailClient;
+use crate::profile;
+use crate::shared::{
+    client::EmailClient,
+    mailbox::cache::{CachedMailboxes, MailboxCache},
+    width::MaxWidth,
+};
 
 /// Shared API to list mailboxes for the active account.
 #[derive(Debug, Parser)]
@@ -25,10 +31,50 @@ pub struct MailboxListCommand {
     ///
     /// Overrides comfy-table's auto-detection. Columns shrink with
     /// ellipsis if needed. Useful when piping through `less -S` or
-    /// rendering into a fixed-width log.
+    /// rendering into a fixed-width log. Pass `auto` to use the
+    /// terminal width at draw time (80 when it cannot be detected).
     #[arg(long = "max-width", short = 'w')]
-    #[arg(value_name = "COLUMNS")]
-    pub max_width: Option<u16>,
+    #[arg(value_name = "COLUMNS|auto")]
+    pub max_width: Option<MaxWidth>,
+
+    /// Render mailboxes as a tree, each one indented under its parent.
+    ///
+    /// Names are split on the hierarchy delimiter and only their last
+    /// segment is shown. Mailboxes are sorted so children follow
+    /// their parent.
+    #[arg(long, overrides_with = "flat")]
+    pub tree: bool,
+
+    /// Render mailboxes as a flat table of full names (default).
+    ///
+    /// Names keep every segment, joined by the hierarchy delimiter.
+    /// Overrides a previous --tree, e.g. from a shell alias.
+    #[arg(long, visible_alias = "flat-with-delimiter", overrides_with = "tree")]
+    pub flat: bool,
+
+    /// Hide mailboxes nested deeper than N levels.
+    ///
+    /// Top-level mailboxes are at level 1: `--depth 1` only lists
+    /// them, collapsing their children.
+    #[arg(long, value_name = "N")]
+    #[arg(value_parser = clap::value_parser!(u16).range(1..))]
+    pub depth: Option<u16>,
+
+    /// Hierarchy delimiter used by --tree and --depth.
+    ///
+    /// Defaults to the IMAP `hierarchy-delimiter` config, then to the
+    /// delimiter the IMAP server reports, then to `/`.
+    #[arg(long, value_name = "CHAR")]
+    pub delimiter: Option<char>,
+
+    /// Fetch the mailboxes from the server even when cached.
+    ///
+    /// Listings without --counts are cached per account for
+    /// `mailbox.list.cache-ttl` seconds (300 by default), and the
+    /// cache is dropped whenever a mailbox is created, deleted or
+    /// renamed through himalaya.
+    #[arg(long)]
+    pub refresh: bool,
 }
 
 impl MailboxListCommand {
@@ -37,14 +83,109 @@ impl MailboxListCommand {
         printer: &mut impl Printer,
         account: &mut Account,
         client: &mut EmailClient,
+        cache_key: &str,
     ) -> Result<()> {
+        let (mailboxes, delimiter) = profile::time("fetch", || self.fetch(client))?;
+
+        if !self.counts && account.mailboxes_list_cache_ttl() > 0 {
+            let result = MailboxCache::load()
+                .and_then(|mut cache| cache.set(cache_key, mailboxes.clone(), delimiter));
+
+            if let Err(err) = result {
+                warn!("cannot cache mailboxes: {err}");
+                debug!("{err:?}");
+            }
+        }
+
+        let mailboxes = self.render(account, mailboxes, delimiter);
+        profile::time("render", || printer.out(mailboxes))
+    }
+
+    /// Mailboxes cached under `cache_key` less than `ttl` seconds
+    /// ago, so the listing can be served without connecting. Counts
+    /// are never cached, and neither is a listing missing the
+    /// hierarchy delimiter --tree or --depth need.
+    pub fn cached(&self, cache_key: &str, ttl: u64) -> Result<Option<CachedMailboxes>> {
+        if self.refresh || self.counts || ttl == 0 {
+            return Ok(None);
+        }
+
+        let cache = MailboxCache::load()?;
+
+        let Some(cached) = cache.get(cache_key, ttl) else {
+            return Ok(None);
+        };
+
+        if self.needs_delimiter() && self.delimiter.is_none() && cached.delimiter.is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(cached.clone()))
+    }
+
+    /// Prints the `cached` mailboxes (see [`Self::cached`]).
+    pub fn execute_cached(
+        self,
+        printer: &mut impl Printer,
+        account: &mut Account,
+        cached: CachedMailboxes,
+    ) -> Result<()> {
+        debug!("serving mailboxes from cache");
+        let mailboxes = self.render(account, cached.mailboxes, cached.delimiter);
+        printer.out(mailboxes)
+    }
+
+    /// Lists the mailboxes of `account` without printing them, so
+    /// `--account all` can gather them across accounts.
+    pub fn list(&self, account: &mut Account, client: &mut EmailClient) -> Result<Mailboxes> {
+        let (mailboxes, delimiter) = self.fetch(client)?;
+        Ok(self.render(account, mailboxes, delimiter))
+    }
+
+    /// Fetches the mailboxes, and the hierarchy delimiter when
+    /// --tree or --depth need it.
+    fn fetch(&self, client: &mut EmailClient) -> Result<(Vec<Mailbox>, Option<char>)> {
         let mailboxes = client.list_mailboxes(self.counts)?;
 
-        let mailboxes = Mailboxes {
+        let delimiter = if self.needs_delimiter() && self.delimiter.is_none() {
+            client.hierarchy_delimiter()?
+        } else {
+            None
+        };
+
+        Ok((mailboxes, delimiter))
+    }
+
+    fn needs_delimiter(&self) -> bool {
+        self.tree && !self.flat || self.depth.is_some()
+    }
+
+    /// Filters and sorts `mailboxes` as requested, for rendering.
+    /// `server_delimiter` is the hierarchy delimiter the server
+    /// reported, if any.
+    fn render(
+        &self,
+        account: &Account,
+        mut mailboxes: Vec<Mailbox>,
+        server_delimiter: Option<char>,
+    ) -> Mailboxes {
+        let tree = self.tree && !self.flat;
+        let delimiter = self.delimiter.or(server_delimiter).unwrap_or('/');
+
+        if let Some(depth) = self.depth {
+            mailboxes.retain(|m| level(&m.name, delimiter) <= depth as usize);
+        }
+
+        if tree {
+            mailboxes.sort_by(|a, b| a.name.split(delimiter).cmp(b.name.split(delimiter)));
+        }
+
+        Mailboxes {
             preset: account.table_preset().to_string(),
             arrangement: account.table_arrangement(),
             max_width: self.max_width,
             with_counts: self.counts,
+            tree: tree.then_some(delimiter),
             colors: MailboxColors {
                 id: account.mailboxes_list_table_id_color(),
                 name: account.mailboxes_list_table_name_color(),
@@ -52,9 +193,7 @@ impl MailboxListCommand {
                 unread: account.mailboxes_list_table_unread_color(),
             },
             mailboxes,
-        };
-
-        printer.out(mailboxes)
+        }
     }
 }
 
@@ -74,9 +213,13 @@ pub struct Mailboxes {
     #[serde(skip)]
     pub arrangement: ContentArrangement,
     #[serde(skip)]
-    pub max_width: Option<u16>,
+    pub max_width: Option<MaxWidth>,
     #[serde(skip)]
     pub with_counts: bool,
+    /// Hierarchy delimiter to indent names with, when rendered as a
+    /// tree.
+    #[serde(skip)]
+    pub tree: Option<char>,
     #[serde(skip)]
     colors: MailboxColors,
     pub mailboxes: Vec<Mailbox>,
@@ -100,7 +243,11 @@ impl fmt::Display for Mailboxes {
                 let mut row = Row::new();
                 row.max_height(1);
                 row.add_cell(Cell::new(&m.id).fg(self.colors.id));
-                row.add_cell(Cell::new(&m.name).fg(self.colors.name));
+                let name = match self.tree {
+                    Some(delimiter) => tree_label(&m.name, delimiter),
+                    None => m.name.clone(),
+                };
+                row.add_cell(Cell::new(name).fg(self.colors.name));
                 if self.with_counts {
                     row.add_cell(count_cell(m.total).fg(self.colors.total));
                     row.add_cell(count_cell(m.unread).fg(self.colors.unread));
@@ -109,7 +256,7 @@ impl fmt::Display for Mailboxes {
             }));
 
         if let Some(width) = self.max_width {
-            table.set_width(width);
+            table.set_width(width.columns());
         }
 
         writeln!(f)?;
@@ -117,6 +264,19 @@ impl fmt::Display for Mailboxes {
     }
 }
 
+/// Nesting level of the mailbox `name`, top-level mailboxes being at
+/// level 1.
+fn level(name: &str, delimiter: char) -> usize {
+    name.split(delimiter).count()
+}
+
+/// Last segment of the mailbox `name`, indented by its level.
+fn tree_label(name: &str, delimiter: char) -> String {
+    let leaf = name.rsplit(delimiter).next().unwrap_or(name);
+    let indent = "  ".repeat(level(name, delimiter) - 1);
+    format!("{indent}{leaf}")
+}
+
 fn count_cell(value: Option<u64>) -> Cell {
     match value {
         Some(n) => Cell::new(n),
@@ -1,3 +1,4 @@
 pub mod arg;
+pub mod cache;
 pub mod cli;
 pub mod list;
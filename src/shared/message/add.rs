@@ -45,6 +45,10 @@ pub struct MessageAddCommand {
     #[arg(long)]
     pub send: bool,
 
+    /// Send even when the message exceeds the `send.max-size` limit.
+    #[arg(long)]
+    pub force: bool,
+
     #[command(flatten)]
     pub message: MessageArg,
 }
@@ -58,7 +62,15 @@ impl MessageAddCommand {
     ) -> Result<()> {
         let raw = self.message.parse()?.into_bytes();
         let flags: Vec<Flag> = self.flag.iter().map(Into::into).collect();
-        let outcome = handler::apply(account, client, raw, &flags, Some(&self.mailbox), self.send)?;
+        let outcome = handler::apply(
+            account,
+            client,
+            raw,
+            &flags,
+            Some(&self.mailbox),
+            self.send,
+            self.force,
+        )?;
         let Outcome::Saved { id, sent } = outcome else {
             unreachable!("--mailbox is mandatory; handler::apply always reports Saved");
         };
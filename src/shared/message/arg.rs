@@ -19,24 +19,30 @@ use pimalaya_cli::clap::parsers::path_parser;
 ///
 /// Resolution order:
 ///
-/// 1. When the positional arg is non-empty: join the tokens with a
+/// 1. When the positional arg is a single `-`, read stdin, even from
+///    a terminal (end the input with Ctrl-D).
+/// 2. When the positional arg is non-empty: join the tokens with a
 ///    space, strip `\r` literals and turn `\n` literals into `\r\n`,
 ///    then treat the result as a path. If the path parses and the file
 ///    is readable, return its contents; otherwise treat the joined
 ///    value as the raw message verbatim.
-/// 2. Otherwise, when stdin is piped, return stdin lines joined with
+/// 3. Otherwise, when stdin is piped, return stdin lines joined with
 ///    `\r\n`.
-/// 3. Otherwise, bail.
+/// 4. Otherwise, bail.
 #[derive(Debug, Parser)]
 pub struct MessageArg {
-    /// Can be a path to a file, raw message contents or nothing if
-    /// piped via standard input.
+    /// Can be a path to a file, raw message contents, `-` or nothing
+    /// to read it from standard input.
     #[arg(name = "message-raw", value_name = "MESSAGE", raw = true)]
     pub raw: Vec<String>,
 }
 
 impl MessageArg {
     pub fn parse(&self) -> anyhow::Result<String> {
+        if self.raw == ["-"] {
+            return Ok(read_stdin());
+        }
+
         if !self.raw.is_empty() {
             let mime = self.raw.join(" ").replace("\\r", "").replace("\\n", "\r\n");
 
@@ -52,10 +58,14 @@ impl MessageArg {
         }
 
         if !stdin().is_terminal() {
-            let lines: Vec<_> = stdin().lines().map_while(Result::ok).collect();
-            return Ok(lines.join("\r\n"));
+            return Ok(read_stdin());
         }
 
         bail!("Message cannot be empty");
     }
 }
+
+fn read_stdin() -> String {
+    let lines: Vec<_> = stdin().lines().map_while(Result::ok).collect();
+    lines.join("\r\n")
+}
@@ -14,15 +14,19 @@
 use std::{
     io::{IsTerminal, Read as _, stdin},
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail};
 use clap::ValueEnum;
 use mail_builder::{
     MessageBuilder,
-    headers::{address::Address, raw::Raw},
+    headers::{address::Address, content_type::ContentType, raw::Raw, text::Text},
+    mime::{BodyPart, MimePart},
 };
-use mail_parser::{HeaderValue, MessageParser};
+use mail_parser::{HeaderValue, MessageParser, MimeHeaders};
+
+use crate::shared::message::flowed;
 
 /// How a quoted source body is laid out relative to the user's body
 /// when replying or forwarding.
@@ -39,15 +43,112 @@ pub enum PostingStyle {
 /// populates these from its own clap struct.
 pub struct BuilderArgs<'a> {
     pub from: Option<&'a str>,
+    /// Display name of the `From` address.
+    pub from_name: Option<&'a str>,
     pub to: &'a [String],
     pub cc: &'a [String],
     pub bcc: &'a [String],
+    pub reply_to: &'a [String],
+    /// Extra headers, already checked with [`check_headers`].
+    pub headers: &'a [CustomHeader],
     pub subject: Option<&'a str>,
     pub body: Option<&'a str>,
     pub body_file: Option<&'a Path>,
     pub attach: &'a [PathBuf],
     pub signature: Option<&'a str>,
     pub signature_file: Option<&'a Path>,
+    /// Emit the text body as `format=flowed` (RFC 3676).
+    pub flowed: bool,
+    /// Ask for a read receipt (`Disposition-Notification-To`).
+    pub read_receipt: bool,
+}
+
+/// Headers set by the builder itself or through a dedicated option,
+/// never accepted from `--header`.
+const GENERATED_HEADERS: [&str; 10] = [
+    "To",
+    "Cc",
+    "Bcc",
+    "Reply-To",
+    "Subject",
+    "In-Reply-To",
+    "References",
+    "MIME-Version",
+    "Content-Type",
+    "Content-Transfer-Encoding",
+];
+
+/// Headers accepted from `--header` only when the configuration
+/// allows it (`compose.allow-header-override`).
+const PROTECTED_HEADERS: [&str; 4] = ["From", "Sender", "Date", "Message-ID"];
+
+/// A `Name: Value` header line passed with `--header`.
+#[derive(Clone, Debug)]
+pub struct CustomHeader {
+    pub name: String,
+    pub value: String,
+}
+
+impl FromStr for CustomHeader {
+    type Err = String;
+
+    /// Parses a well-formed header line: a name made of printable
+    /// ASCII characters other than `:` (RFC 5322 §2.2), and a value
+    /// on a single line.
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(String::from("expected `Name: Value`"));
+        };
+
+        let name = name.trim();
+
+        if name.is_empty() {
+            return Err(String::from("header name is empty"));
+        }
+
+        if !name.bytes().all(|b| b.is_ascii_graphic()) {
+            return Err(format!("invalid header name `{name}`"));
+        }
+
+        if value.contains(['\r', '\n']) {
+            return Err(format!("value of header `{name}` spans several lines"));
+        }
+
+        Ok(Self {
+            name: name.to_owned(),
+            value: value.trim().to_owned(),
+        })
+    }
+}
+
+/// Refuses the `headers` the builder generates itself, and the
+/// protected ones (`From`, `Date`, …) unless `allow_override` is set.
+pub fn check_headers(headers: &[CustomHeader], allow_override: bool) -> Result<()> {
+    for header in headers {
+        if let Some(name) = find_header(&GENERATED_HEADERS, &header.name) {
+            bail!("Cannot set the `{name}` header with --header, use its dedicated option");
+        }
+
+        if !allow_override {
+            if let Some(name) = find_header(&PROTECTED_HEADERS, &header.name) {
+                bail!(
+                    "Cannot override the `{name}` header unless \
+                     `compose.allow-header-override` is enabled"
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Canonical spelling of `name` in `names`, compared
+/// case-insensitively.
+fn find_header(names: &[&'static str], name: &str) -> Option<&'static str> {
+    names
+        .iter()
+        .find(|known| known.eq_ignore_ascii_case(name))
+        .copied()
 }
 
 /// Source-message metadata, populated for reply/forward subcommands.
@@ -70,8 +171,31 @@ pub enum SourceMode {
 pub fn build(args: BuilderArgs<'_>, source: Option<SourceArgs<'_>>) -> Result<Vec<u8>> {
     let mut builder = MessageBuilder::new();
 
+    for header in args.headers {
+        // NOTE: `mail_builder` only skips generating `Date` and
+        // `Message-ID` when spelled exactly so
+        let name = find_header(&PROTECTED_HEADERS, &header.name)
+            .map(str::to_owned)
+            .unwrap_or_else(|| header.name.clone());
+
+        if name == "From" && args.from.is_some() {
+            bail!("Cannot set the `From` header with both --from and --header");
+        }
+
+        builder = builder.header(name, Text::new(header.value.clone()));
+    }
+
     if let Some(from) = args.from {
-        builder = builder.from(from);
+        builder = match args.from_name {
+            Some(name) => builder.from((name, from)),
+            None => builder.from(from),
+        };
+    }
+    if args.read_receipt {
+        let Some(from) = args.from else {
+            bail!("Cannot ask for a read receipt without a From address, pass --from");
+        };
+        builder = builder.header("Disposition-Notification-To", Address::from(from));
     }
     if !args.to.is_empty() {
         builder = builder.to(addresses(args.to));
@@ -82,6 +206,9 @@ pub fn build(args: BuilderArgs<'_>, source: Option<SourceArgs<'_>>) -> Result<Ve
     if !args.bcc.is_empty() {
         builder = builder.bcc(addresses(args.bcc));
     }
+    if !args.reply_to.is_empty() {
+        builder = builder.reply_to(addresses(args.reply_to));
+    }
 
     let parsed_source = source
         .as_ref()
@@ -120,10 +247,7 @@ pub fn build(args: BuilderArgs<'_>, source: Option<SourceArgs<'_>>) -> Result<Ve
             }
         }
 
-        source_text = parsed
-            .body_text(0)
-            .map(|c| c.into_owned())
-            .unwrap_or_default();
+        source_text = source_body_text(parsed);
     }
 
     if let Some(s) = subject {
@@ -143,7 +267,15 @@ pub fn build(args: BuilderArgs<'_>, source: Option<SourceArgs<'_>>) -> Result<Ve
         signature.as_deref().unwrap_or(""),
         style,
     );
-    builder = builder.text_body(body);
+    if args.flowed {
+        let ctype = ContentType::new("text/plain")
+            .attribute("charset", "utf-8")
+            .attribute("format", "flowed");
+        let body = BodyPart::Text(flowed::encode(&body).into());
+        builder.text_body = Some(MimePart::new(ctype, body));
+    } else {
+        builder = builder.text_body(body);
+    }
 
     for path in args.attach {
         let bytes = std::fs::read(path)
@@ -161,6 +293,53 @@ pub fn build(args: BuilderArgs<'_>, source: Option<SourceArgs<'_>>) -> Result<Ve
         .map_err(|err| anyhow!("serialize composed message: {err}"))
 }
 
+/// Text body a message would start from: `body`, then the quote
+/// headline and quoted body of the reply/forward `source` (if any),
+/// then the signature. Nothing is read from stdin.
+pub fn template(
+    body: &str,
+    source: Option<SourceArgs<'_>>,
+    signature: Option<&str>,
+    signature_file: Option<&Path>,
+) -> Result<String> {
+    let source_text = source
+        .as_ref()
+        .and_then(|s| MessageParser::new().parse(s.raw))
+        .map(|parsed| source_body_text(&parsed))
+        .unwrap_or_default();
+    let signature = read_signature(signature, signature_file)?;
+    let (style, headline) = match source.as_ref() {
+        Some(s) => (s.posting_style, s.quote_headline),
+        None => (PostingStyle::Top, ""),
+    };
+
+    Ok(compose_body(
+        body,
+        &source_text,
+        headline,
+        signature.as_deref().unwrap_or(""),
+        style,
+    ))
+}
+
+/// First text body of the source message, reflowed when it was sent
+/// as `format=flowed` so quoting does not carry the sender's soft
+/// line breaks over.
+fn source_body_text(msg: &mail_parser::Message<'_>) -> String {
+    let Some(text) = msg.body_text(0) else {
+        return String::new();
+    };
+
+    let params = msg
+        .text_part(0)
+        .and_then(|part| flowed::params(part.content_type()));
+
+    match params {
+        Some(delsp) => flowed::decode(&text, delsp),
+        None => text.into_owned(),
+    }
+}
+
 fn addresses(values: &[String]) -> Address<'static> {
     Address::new_list(
         values
@@ -363,3 +542,30 @@ fn mime_for(path: &Path) -> String {
         .essence_str()
         .to_owned()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_header_lines() {
+        let header: CustomHeader = "X-Mailer:  himalaya ".parse().unwrap();
+        assert_eq!(header.name, "X-Mailer");
+        assert_eq!(header.value, "himalaya");
+
+        assert!("X-Mailer".parse::<CustomHeader>().is_err());
+        assert!(": value".parse::<CustomHeader>().is_err());
+        assert!("X Mailer: value".parse::<CustomHeader>().is_err());
+        assert!("X-Mailer: one\r\nBcc: two".parse::<CustomHeader>().is_err());
+    }
+
+    #[test]
+    fn refuses_generated_and_protected_headers() {
+        let headers = |line: &str| [line.parse::<CustomHeader>().unwrap()];
+
+        assert!(check_headers(&headers("X-Mailer: himalaya"), false).is_ok());
+        assert!(check_headers(&headers("subject: hi"), true).is_err());
+        assert!(check_headers(&headers("date: now"), false).is_err());
+        assert!(check_headers(&headers("date: now"), true).is_ok());
+    }
+}
@@ -0,0 +1,219 @@
+//! `message read --part-charset-report`: declared versus detected
+//! charset of each text part, to diagnose garbled (mojibake) text.
+
+use std::{borrow::Cow, fmt};
+
+use chardetng::EncodingDetector;
+use comfy_table::{Cell, Row, Table};
+use encoding_rs::Encoding;
+use mail_parser::{
+    Message, MessagePart, MimeHeaders, PartType,
+    decoders::{base64::base64_decode, quoted_printable::quoted_printable_decode},
+};
+use serde::Serialize;
+
+/// Charset diagnosis of the text parts of a message.
+#[derive(Debug, Serialize)]
+pub struct CharsetReport {
+    #[serde(skip)]
+    pub preset: String,
+    pub parts: Vec<PartCharset>,
+}
+
+/// Charset diagnosis of one text part.
+#[derive(Debug, Serialize)]
+pub struct PartCharset {
+    /// Index of the part in the message, depth first.
+    pub part: usize,
+    pub content_type: String,
+    pub transfer_encoding: Option<String>,
+    /// Charset of the `Content-Type` header.
+    pub declared: Option<String>,
+    /// Charset the part is decoded with: the `--charset` or
+    /// `read.charsets` override, otherwise the declared one
+    /// (`us-ascii` when none).
+    pub decoded_with: String,
+    /// Charset guessed from the transfer-decoded bytes, `us-ascii`
+    /// when they are all ASCII.
+    pub detected: String,
+    pub status: CharsetStatus,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CharsetStatus {
+    Ok,
+    /// The charset decoded with is unknown.
+    Unknown,
+    /// Decoding produced replacement characters.
+    Invalid,
+    /// The part decodes, but the detected charset differs.
+    Mismatch,
+}
+
+impl fmt::Display for CharsetStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok => write!(f, "ok"),
+            Self::Unknown => write!(f, "UNKNOWN CHARSET"),
+            Self::Invalid => write!(f, "INVALID"),
+            Self::Mismatch => write!(f, "MISMATCH"),
+        }
+    }
+}
+
+impl CharsetReport {
+    /// Diagnoses the text parts of `message`, decoded with `charset`
+    /// when forced.
+    pub fn new(message: &Message<'_>, charset: Option<&str>, preset: String) -> Self {
+        let parts = message
+            .parts
+            .iter()
+            .enumerate()
+            .filter(|(_, part)| matches!(part.body, PartType::Text(_) | PartType::Html(_)))
+            .map(|(i, part)| PartCharset::new(message, i, part, charset))
+            .collect();
+
+        Self { preset, parts }
+    }
+}
+
+impl PartCharset {
+    fn new(message: &Message<'_>, i: usize, part: &MessagePart<'_>, forced: Option<&str>) -> Self {
+        let ctype = part.content_type();
+
+        let content_type = match ctype {
+            Some(ctype) => match ctype.subtype() {
+                Some(subtype) => format!("{}/{subtype}", ctype.ctype()),
+                None => ctype.ctype().to_owned(),
+            },
+            None => String::from("text/plain"),
+        };
+
+        let declared = ctype
+            .and_then(|c| c.attribute("charset"))
+            .map(str::to_owned);
+
+        let decoded_with = forced
+            .map(str::to_owned)
+            .or_else(|| declared.clone())
+            .unwrap_or_else(|| String::from("us-ascii"));
+
+        let bytes = transfer_decoded(message, part);
+        let detected = detect(&bytes);
+
+        let status = match Encoding::for_label(decoded_with.trim().as_bytes()) {
+            None => CharsetStatus::Unknown,
+            Some(encoding) if encoding.decode_without_bom_handling(&bytes).1 => {
+                CharsetStatus::Invalid
+            }
+            Some(_) if bytes.is_ascii() => CharsetStatus::Ok,
+            Some(encoding) if Some(encoding) != Encoding::for_label(detected.as_bytes()) => {
+                CharsetStatus::Mismatch
+            }
+            Some(_) => CharsetStatus::Ok,
+        };
+
+        Self {
+            part: i,
+            content_type,
+            transfer_encoding: part.content_transfer_encoding().map(str::to_owned),
+            declared,
+            decoded_with,
+            detected,
+            status,
+        }
+    }
+}
+
+impl fmt::Display for CharsetReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut table = Table::new();
+
+        table
+            .load_preset(&self.preset)
+            .set_header(Row::from([
+                Cell::new("PART"),
+                Cell::new("TYPE"),
+                Cell::new("ENCODING"),
+                Cell::new("DECLARED"),
+                Cell::new("DECODED WITH"),
+                Cell::new("DETECTED"),
+                Cell::new("STATUS"),
+            ]))
+            .add_rows(self.parts.iter().map(|p| {
+                Row::from([
+                    Cell::new(p.part),
+                    Cell::new(&p.content_type),
+                    Cell::new(p.transfer_encoding.as_deref().unwrap_or("")),
+                    Cell::new(p.declared.as_deref().unwrap_or("")),
+                    Cell::new(&p.decoded_with),
+                    Cell::new(&p.detected),
+                    Cell::new(p.status),
+                ])
+            }));
+
+        writeln!(f)?;
+        writeln!(f, "{table}")
+    }
+}
+
+/// Body of `part` with its transfer encoding (base64 or
+/// quoted-printable) undone, but not its charset.
+pub fn transfer_decoded<'a>(message: &'a Message<'_>, part: &MessagePart<'_>) -> Cow<'a, [u8]> {
+    let raw = message.raw_message();
+    let start = part.raw_body_offset() as usize;
+    let end = (part.raw_end_offset() as usize).min(raw.len());
+    let body = raw.get(start..end).unwrap_or_default();
+
+    let decoded = match part.content_transfer_encoding() {
+        Some(enc) if enc.eq_ignore_ascii_case("base64") => base64_decode(body),
+        Some(enc) if enc.eq_ignore_ascii_case("quoted-printable") => quoted_printable_decode(body),
+        _ => None,
+    };
+
+    match decoded {
+        Some(decoded) => Cow::Owned(decoded),
+        None => Cow::Borrowed(body),
+    }
+}
+
+/// Charset guessed for `bytes`, lowercased.
+fn detect(bytes: &[u8]) -> String {
+    if bytes.is_ascii() {
+        return String::from("us-ascii");
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(None, true).name().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use mail_parser::MessageParser;
+
+    use super::*;
+
+    #[test]
+    fn reports_undecodable_parts() {
+        let raw = b"Content-Type: multipart/alternative; boundary=b\r\n\r\n\
+            --b\r\nContent-Type: text/plain; charset=utf-8\r\n\r\ncaf\xe9\r\n\
+            --b\r\nContent-Type: text/html; charset=iso-8859-1\r\n\
+            Content-Transfer-Encoding: quoted-printable\r\n\r\ncaf=E9\r\n--b--\r\n";
+        let message = MessageParser::new().parse(&raw[..]).unwrap();
+
+        let report = CharsetReport::new(&message, None, String::new());
+        assert_eq!(report.parts.len(), 2);
+        assert_eq!(report.parts[0].status, CharsetStatus::Invalid);
+        assert_eq!(report.parts[1].declared.as_deref(), Some("iso-8859-1"));
+        assert_ne!(report.parts[1].status, CharsetStatus::Invalid);
+
+        let report = CharsetReport::new(&message, Some("latin1"), String::new());
+        assert_eq!(report.parts[0].decoded_with, "latin1");
+        assert_ne!(report.parts[0].status, CharsetStatus::Invalid);
+
+        let report = CharsetReport::new(&message, Some("klingon"), String::new());
+        assert_eq!(report.parts[0].status, CharsetStatus::Unknown);
+    }
+}
@@ -1,14 +1,28 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
 use clap::Subcommand;
 use pimalaya_cli::printer::Printer;
 
 use crate::account::context::Account;
+use crate::backend::Backend;
 use crate::shared::{
     client::EmailClient,
     message::{
-        add::MessageAddCommand, compose::MessageComposeCommand, copy::MessageCopyCommand,
-        forward::MessageForwardCommand, mv::MessageMoveCommand, read::MessageReadCommand,
-        reply::MessageReplyCommand, send::MessageSendCommand,
+        add::MessageAddCommand,
+        compose::MessageComposeCommand,
+        copy::MessageCopyCommand,
+        dedupe::MessageDedupeCommand,
+        export::MessageExportCommand,
+        forward::MessageForwardCommand,
+        import::MessageImportCommand,
+        junk::{MessageJunkCommand, MessageNotJunkCommand},
+        mv::MessageMoveCommand,
+        read::MessageReadCommand,
+        reply::MessageReplyCommand,
+        send::MessageSendCommand,
+        undo::MessageUndoCommand,
+        unsubscribe::MessageUnsubscribeCommand,
     },
 };
 
@@ -28,13 +42,22 @@ pub enum MessageCommand {
     Compose(MessageComposeCommand),
     #[command(visible_alias = "cp")]
     Copy(MessageCopyCommand),
+    Dedupe(MessageDedupeCommand),
+    Export(MessageExportCommand),
     #[command(visible_alias = "fwd")]
     Forward(MessageForwardCommand),
+    Import(MessageImportCommand),
+    #[command(visible_alias = "spam")]
+    Junk(MessageJunkCommand),
     #[command(visible_alias = "mv")]
     Move(MessageMoveCommand),
+    #[command(visible_alias = "ham")]
+    NotJunk(MessageNotJunkCommand),
     Read(MessageReadCommand),
     Reply(MessageReplyCommand),
     Send(MessageSendCommand),
+    Undo(MessageUndoCommand),
+    Unsubscribe(MessageUnsubscribeCommand),
 }
 
 impl MessageCommand {
@@ -43,16 +66,40 @@ impl MessageCommand {
         printer: &mut impl Printer,
         account: &mut Account,
         client: &mut EmailClient,
+        account_name: &str,
+        config_paths: &[PathBuf],
+        backend: Backend,
     ) -> Result<()> {
         match self {
             Self::Add(cmd) => cmd.execute(printer, account, client),
             Self::Compose(cmd) => cmd.execute(printer, account, client),
-            Self::Copy(cmd) => cmd.execute(printer, account, client),
+            Self::Copy(cmd) => cmd.execute(
+                printer,
+                account,
+                client,
+                account_name,
+                config_paths,
+                backend,
+            ),
+            Self::Dedupe(cmd) => cmd.execute(printer, account, client, account_name),
+            Self::Export(cmd) => cmd.execute(printer, account, client),
             Self::Forward(cmd) => cmd.execute(printer, account, client),
-            Self::Move(cmd) => cmd.execute(printer, account, client),
+            Self::Import(cmd) => cmd.execute(printer, account, client),
+            Self::Junk(cmd) => cmd.execute(printer, account, client, account_name),
+            Self::Move(cmd) => cmd.execute(
+                printer,
+                account,
+                client,
+                account_name,
+                config_paths,
+                backend,
+            ),
+            Self::NotJunk(cmd) => cmd.execute(printer, account, client, account_name),
             Self::Read(cmd) => cmd.execute(printer, account, client),
             Self::Reply(cmd) => cmd.execute(printer, account, client),
-            Self::Send(cmd) => cmd.execute(printer, account, client),
+            Self::Send(cmd) => cmd.execute(printer, account, client, account_name),
+            Self::Undo(cmd) => cmd.execute(printer, account, client, account_name),
+            Self::Unsubscribe(cmd) => cmd.execute(printer, account, client),
         }
     }
 }
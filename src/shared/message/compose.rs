@@ -1,15 +1,17 @@
 use std::path::PathBuf;
 
 use anyhow::Result;
+use chrono::{Local, NaiveDateTime};
 use clap::Parser;
 use pimalaya_cli::printer::Printer;
 
 use crate::account::context::Account;
+use crate::config::TemplateConfig;
 use crate::shared::{
     client::EmailClient,
     message::{
-        builder::{self, BuilderArgs},
-        handler,
+        builder::{self, BuilderArgs, CustomHeader},
+        handler, spellcheck,
     },
 };
 
@@ -24,12 +26,31 @@ use crate::shared::{
 /// workflows), chain a standalone composer like
 /// [`mml`](https://github.com/pimalaya/mml) into `messages send` /
 /// `messages add` via a tempfile or bash/zsh process substitution.
+///
+/// Pass `--template <name>` to pre-fill the message from a template
+/// declared under `[templates.<name>]` in the configuration, and
+/// `--print-template` to only print the text body it would start
+/// from.
 #[derive(Debug, Parser)]
 pub struct MessageComposeCommand {
+    /// Pre-fill the message from the named `[templates.<name>]`
+    /// configuration block. Flags passed on the command line take
+    /// precedence over the template values. The `{date}`, `{time}`
+    /// and `{datetime}` placeholders of the template subject and
+    /// body are expanded.
+    #[arg(long, value_name = "NAME")]
+    pub template: Option<String>,
+
     /// Sender address (`From` header).
     #[arg(long, value_name = "ADDR")]
     pub from: Option<String>,
 
+    /// Send as the configured identity matching this address or
+    /// display name: sets the `From` header, and the signature when
+    /// none is passed. Defaults to the account `email`.
+    #[arg(long, value_name = "ADDR|NAME", conflicts_with = "from")]
+    pub identity: Option<String>,
+
     /// Recipient address(es) (`To` header). Repeat the flag or use a
     /// comma-separated list.
     #[arg(long, short = 't', value_name = "ADDR", value_delimiter = ',')]
@@ -43,10 +64,21 @@ pub struct MessageComposeCommand {
     #[arg(long, value_name = "ADDR", value_delimiter = ',')]
     pub bcc: Vec<String>,
 
+    /// Address(es) replies should go to (`Reply-To` header).
+    #[arg(long, value_name = "ADDR", value_delimiter = ',')]
+    pub reply_to: Vec<String>,
+
     /// Subject line.
     #[arg(long, short = 's', value_name = "TEXT")]
     pub subject: Option<String>,
 
+    /// Extra header, as a `Name: Value` line (e.g. `X-Mailer: foo`);
+    /// repeatable. Headers with a dedicated option are refused, and
+    /// so are `From`, `Sender`, `Date` and `Message-ID` unless
+    /// `compose.allow-header-override` is enabled.
+    #[arg(long = "header", short = 'H', value_name = "LINE")]
+    pub headers: Vec<CustomHeader>,
+
     /// Inline body. Conflicts with `--body-file`; stdin is used as a
     /// fallback when neither is given.
     #[arg(long, value_name = "TEXT", conflicts_with = "body_file")]
@@ -75,14 +107,38 @@ pub struct MessageComposeCommand {
     )]
     pub signature_file: Option<PathBuf>,
 
-    /// Append a copy of the composed message to this mailbox.
-    #[arg(long, value_name = "MAILBOX")]
+    /// Emit the text body as `format=flowed` (RFC 3676): long lines
+    /// are soft-wrapped so they reflow on the recipient's client.
+    #[arg(long)]
+    pub flowed: bool,
+
+    /// Ask the recipients for a read receipt, with a
+    /// `Disposition-Notification-To` header set to the `From`
+    /// address (RFC 8098). Recipients are free to ignore it.
+    #[arg(long)]
+    pub read_receipt: bool,
+
+    /// Print the text body the message would start from (template
+    /// body and signature) to stdout instead of building the message.
+    /// Nothing is read from stdin, saved or sent.
+    #[arg(long, visible_aliases = ["stdout", "template-only"])]
+    #[arg(conflicts_with_all = ["body", "body_file", "save", "send"])]
+    pub print_template: bool,
+
+    /// Append a copy of the composed message to this mailbox. When
+    /// sending, overrides the `send.save-to` mailbox, `-` saving no
+    /// copy.
+    #[arg(long, visible_alias = "save-to", value_name = "MAILBOX")]
     pub save: Option<String>,
 
     /// Send the composed message through the account's SMTP/JMAP path.
     /// Combines with `--save` to also keep a copy.
     #[arg(long)]
     pub send: bool,
+
+    /// Send even when the message exceeds the `send.max-size` limit.
+    #[arg(long)]
+    pub force: bool,
 }
 
 impl MessageComposeCommand {
@@ -92,29 +148,202 @@ impl MessageComposeCommand {
         account: &mut Account,
         client: &mut EmailClient,
     ) -> Result<()> {
+        let mut prefill = Prefill {
+            from: self.from,
+            to: self.to,
+            cc: self.cc,
+            bcc: self.bcc,
+            subject: self.subject,
+            body: self.body,
+        };
+
+        if let Some(name) = &self.template {
+            let now = Local::now().naive_local();
+            prefill.merge(account.template(name)?, self.body_file.is_some(), now);
+        }
+
+        let Prefill {
+            from,
+            to,
+            cc,
+            bcc,
+            subject,
+            body,
+        } = prefill;
+
+        let identity = account.identity(self.identity.as_deref())?;
+        let (from, from_name) = identity.from(from.as_deref());
+        let signature =
+            identity.signature(self.signature.as_deref(), self.signature_file.as_deref());
+
+        if self.print_template {
+            let template = builder::template(
+                body.as_deref().unwrap_or(""),
+                None,
+                signature,
+                self.signature_file.as_deref(),
+            )?;
+            return printer.out(template);
+        }
+
+        builder::check_headers(&self.headers, account.compose_allow_header_override())?;
+
         let raw = builder::build(
             BuilderArgs {
-                from: self.from.as_deref(),
-                to: &self.to,
-                cc: &self.cc,
-                bcc: &self.bcc,
-                subject: self.subject.as_deref(),
-                body: self.body.as_deref(),
+                from,
+                from_name,
+                to: &to,
+                cc: &cc,
+                bcc: &bcc,
+                reply_to: &self.reply_to,
+                headers: &self.headers,
+                subject: subject.as_deref(),
+                body: body.as_deref(),
                 body_file: self.body_file.as_deref(),
                 attach: &self.attach,
-                signature: self.signature.as_deref(),
+                signature,
                 signature_file: self.signature_file.as_deref(),
+                flowed: self.flowed,
+                read_receipt: self.read_receipt,
             },
             None,
         )?;
 
+        if self.send {
+            if let (Some(_), Some(from)) = (&self.identity, from) {
+                handler::check_sender(client, from)?;
+            }
+            spellcheck::check(account, &raw)?;
+        }
+
         handler::route(
             printer,
             account,
             client,
             raw,
-            self.save.as_deref(),
+            handler::save_to(account, self.save.as_deref(), self.send),
             self.send,
+            self.force,
         )
     }
 }
+
+/// Headers and body the message starts from.
+#[derive(Debug, Default, PartialEq)]
+struct Prefill {
+    from: Option<String>,
+    to: Vec<String>,
+    cc: Vec<String>,
+    bcc: Vec<String>,
+    subject: Option<String>,
+    body: Option<String>,
+}
+
+impl Prefill {
+    /// Fills the values not passed on the command line with the ones
+    /// of `template`, its placeholders expanded at `now`. The body
+    /// is left alone when read from a file (`has_body_file`).
+    fn merge(&mut self, template: &TemplateConfig, has_body_file: bool, now: NaiveDateTime) {
+        let expand = |text: &String| expand_placeholders(text, now);
+
+        if self.from.is_none() {
+            self.from = template.from.clone();
+        }
+        if self.to.is_empty() {
+            self.to = template.to.clone();
+        }
+        if self.cc.is_empty() {
+            self.cc = template.cc.clone();
+        }
+        if self.bcc.is_empty() {
+            self.bcc = template.bcc.clone();
+        }
+        if self.subject.is_none() {
+            self.subject = template.subject.as_ref().map(expand);
+        }
+        if self.body.is_none() && !has_body_file {
+            self.body = template.body.as_ref().map(expand);
+        }
+    }
+}
+
+/// Expands the `{date}` (`YYYY-MM-DD`), `{time}` (`HH:MM`) and
+/// `{datetime}` placeholders of a template field, `now` being the
+/// local time.
+fn expand_placeholders(text: &str, now: NaiveDateTime) -> String {
+    text.replace("{datetime}", &now.format("%F %R").to_string())
+        .replace("{date}", &now.format("%F").to_string())
+        .replace("{time}", &now.format("%R").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 5, 6)
+            .unwrap()
+            .and_hms_opt(9, 7, 0)
+            .unwrap()
+    }
+
+    fn template() -> TemplateConfig {
+        TemplateConfig {
+            from: Some(String::from("me@example.org")),
+            to: vec![String::from("team@example.org")],
+            cc: vec![String::from("boss@example.org")],
+            bcc: Vec::new(),
+            subject: Some(String::from("Standup {date}")),
+            body: Some(String::from("Written at {time}\n")),
+        }
+    }
+
+    #[test]
+    fn expands_placeholders() {
+        assert_eq!(
+            expand_placeholders("{date} {time} ({datetime}) {unknown}", now()),
+            "2024-05-06 09:07 (2024-05-06 09:07) {unknown}"
+        );
+    }
+
+    #[test]
+    fn fills_missing_values_from_template() {
+        let mut prefill = Prefill::default();
+        prefill.merge(&template(), false, now());
+
+        assert_eq!(
+            prefill,
+            Prefill {
+                from: Some(String::from("me@example.org")),
+                to: vec![String::from("team@example.org")],
+                cc: vec![String::from("boss@example.org")],
+                bcc: Vec::new(),
+                subject: Some(String::from("Standup 2024-05-06")),
+                body: Some(String::from("Written at 09:07\n")),
+            }
+        );
+    }
+
+    #[test]
+    fn command_line_takes_precedence_over_template() {
+        let mut prefill = Prefill {
+            to: vec![String::from("alice@example.org")],
+            subject: Some(String::from("Standup {date}")),
+            body: Some(String::from("Custom body")),
+            ..Prefill::default()
+        };
+        prefill.merge(&template(), false, now());
+
+        assert_eq!(prefill.to, [String::from("alice@example.org")]);
+        assert_eq!(prefill.cc, [String::from("boss@example.org")]);
+        // NOTE: placeholders are only expanded in template values
+        assert_eq!(prefill.subject.as_deref(), Some("Standup {date}"));
+        assert_eq!(prefill.body.as_deref(), Some("Custom body"));
+
+        let mut prefill = Prefill::default();
+        prefill.merge(&template(), true, now());
+        assert_eq!(prefill.body, None);
+    }
+}
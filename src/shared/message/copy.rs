@@ -1,9 +1,23 @@
-use anyhow::Result;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result, bail};
 use clap::Parser;
+use io_email::flag::types::{Flag, FlagOp};
+use log::{info, warn};
 use pimalaya_cli::printer::{Message, Printer};
 
 use crate::account::context::Account;
-use crate::shared::{client::EmailClient, flag::arg::MessageIdsArg};
+use crate::backend::Backend;
+use crate::shared::{
+    client::{EmailClient, build_email_client},
+    flag::arg::MessageIdsArg,
+    mailbox::arg::MailboxArg,
+    message::undo::{MovedMessage, locate_moved},
+};
 
 /// Copy message(s) from one mailbox to another within the active
 /// account.
@@ -11,9 +25,14 @@ use crate::shared::{client::EmailClient, flag::arg::MessageIdsArg};
 /// Both `--from` and `--to` are resolved through the account's
 /// `[mailbox.alias]` map before the backend call. IMAP uses
 /// `UID COPY` (RFC 3501); JMAP uses `Email/set` patches that add the
-/// destination to each email's `mailboxIds`; Maildir copies the
-/// underlying file. Cross-account / cross-backend copy is out of
-/// scope.
+/// destination to each email's `mailboxIds`; Maildir and m2dir store
+/// the copy as a new message, then set the flags of the original on
+/// it.
+///
+/// Pass `--to-account <name>` to copy into a mailbox of another
+/// account instead: each message is fetched from the active account
+/// and added to the destination account with its flags. When the copy
+/// fails midway, running it again skips the messages already added.
 #[derive(Debug, Parser)]
 pub struct MessageCopyCommand {
     #[command(flatten)]
@@ -32,6 +51,11 @@ pub struct MessageCopyCommand {
     /// Destination mailbox name or alias. Mandatory.
     #[arg(long = "to", short = 't', value_name = "NAME")]
     pub to: String,
+
+    /// Copy into the `--to` mailbox of this account (resolved through
+    /// its own aliases) instead of the active one.
+    #[arg(long = "to-account", value_name = "ACCOUNT")]
+    pub to_account: Option<String>,
 }
 
 impl MessageCopyCommand {
@@ -40,11 +64,318 @@ impl MessageCopyCommand {
         printer: &mut impl Printer,
         account: &mut Account,
         client: &mut EmailClient,
+        account_name: &str,
+        config_paths: &[PathBuf],
+        backend: Backend,
     ) -> Result<()> {
-        let from = account.resolve_mailbox(&self.from).to_owned();
-        let to = account.resolve_mailbox(&self.to).to_owned();
+        let from = MailboxArg::resolve_name(account, &self.from)?;
         let ids: Vec<&str> = self.ids.inner.iter().map(String::as_str).collect();
+
+        if let Some(name) = &self.to_account {
+            let (target, mut target_client) =
+                build_email_client(config_paths, Some(name), backend)?;
+            let to = MailboxArg::resolve_name(&target, &self.to)?;
+            copy_across_accounts(
+                (client, account_name, &from),
+                &ids,
+                (&mut target_client, name, &to),
+            )?;
+            return printer.out(Message::new(format!(
+                "Message(s) successfully copied to account {name}"
+            )));
+        }
+
+        let to = MailboxArg::resolve_name(account, &self.to)?;
+        let flags = CopiedFlags::read(client, &from, &ids)?;
         client.copy_messages(&from, &to, &ids)?;
+        flags.restore(client, &to)?;
         printer.out(Message::new("Message(s) successfully copied"))
     }
 }
+
+/// Adds the messages `ids` of the `from` mailbox to the `to` mailbox
+/// of another account's client, keeping their flags. Both ends are
+/// given as `(client, account name, mailbox)`.
+///
+/// Records each message in the [`CopyJournal`] once added, so that
+/// running the same copy again after a failure (or an interruption)
+/// skips it, and clears the record when the copy completes. Stops at
+/// the first failure, reporting how many messages made it. The source is never touched: callers
+/// moving messages must only delete them once this returns `Ok`.
+pub(super) fn copy_across_accounts(
+    (client, account_name, from): (&mut EmailClient, &str, &str),
+    ids: &[&str],
+    (target_client, target_name, to): (&mut EmailClient, &str, &str),
+) -> Result<()> {
+    let key = format!("{account_name}:{from} -> {target_name}:{to}");
+    let mut journal = CopyJournal::load()?;
+    let done = journal.take(&key);
+
+    let mut copied: Vec<String> = ids
+        .iter()
+        .filter(|id| done.contains(**id))
+        .map(|id| id.to_string())
+        .collect();
+
+    if !copied.is_empty() {
+        info!(
+            "skipping {} message(s) already copied by a previous run",
+            copied.len()
+        );
+    }
+
+    let pending: Vec<&str> = ids
+        .iter()
+        .copied()
+        .filter(|id| !done.contains(*id))
+        .collect();
+    let mut flags = client.get_flags(from, &pending)?;
+
+    for id in pending {
+        let result = (|| {
+            let Some(flags) = flags.remove(id) else {
+                bail!("Cannot find message {id} in mailbox {from}");
+            };
+
+            let raw = client.get_message(from, id)?;
+            target_client.add_message(to, &flags, raw)?;
+            Ok(())
+        })();
+
+        if let Err(err) = result {
+            let n = copied.len();
+            return Err(err).with_context(|| {
+                format!(
+                    "Add message {id} to destination error ({n} message(s) copied, \
+                     skipped when running the same command again)"
+                )
+            });
+        }
+
+        copied.push(id.to_owned());
+        journal.copies.insert(key.clone(), copied.clone());
+        journal.save()?;
+    }
+
+    if journal.copies.remove(&key).is_some() || !done.is_empty() {
+        journal.save()?;
+    }
+
+    Ok(())
+}
+
+/// Messages already added to the destination by interrupted
+/// cross-account copies, keyed by source and destination, kept in
+/// the user data directory until the copy completes.
+struct CopyJournal {
+    path: PathBuf,
+    copies: BTreeMap<String, Vec<String>>,
+}
+
+impl CopyJournal {
+    /// Loads the journal, empty when the file does not exist yet.
+    fn load() -> Result<Self> {
+        let Some(data_dir) = dirs::data_dir() else {
+            bail!("Cannot find the user data directory for the copy journal");
+        };
+
+        let path = data_dir.join("himalaya").join("copies.toml");
+
+        let copies = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Read `{}` error", path.display()))?;
+            toml::from_str(&content).with_context(|| format!("Parse `{}` error", path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self { path, copies })
+    }
+
+    /// Removes and returns the ids recorded for `key`.
+    fn take(&mut self, key: &str) -> HashSet<String> {
+        self.copies
+            .remove(key)
+            .unwrap_or_default()
+            .into_iter()
+            .collect()
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Create `{}` error", dir.display()))?;
+        }
+
+        let content = toml::to_string(&self.copies).context("Serialize copy journal error")?;
+        fs::write(&self.path, content)
+            .with_context(|| format!("Write `{}` error", self.path.display()))
+    }
+}
+
+/// Flags of messages about to be copied within the account, to set
+/// again on the copies when the backend does not keep them (see
+/// [`EmailClient::keeps_flags_on_copy`]). Moves keep them everywhere.
+///
+/// Ids do not survive a copy, so messages are found again in the
+/// destination by `Message-ID` and `Date:` header, like `message
+/// undo` does. Messages with neither flags nor such headers are left
+/// out.
+#[derive(Debug, Default)]
+pub(super) struct CopiedFlags {
+    /// Copied messages, grouped by their (non-empty) set of flags.
+    groups: BTreeMap<Vec<Flag>, Vec<MovedMessage>>,
+}
+
+impl CopiedFlags {
+    /// Reads the flags of the messages `ids` of `from`. Reads nothing
+    /// when the backend keeps them on its own.
+    pub(super) fn read(client: &mut EmailClient, from: &str, ids: &[&str]) -> Result<Self> {
+        if client.keeps_flags_on_copy() {
+            return Ok(Self::default());
+        }
+
+        let mut flags = client.get_flags(from, ids)?;
+        let headers = client.get_headers(from, ids)?;
+
+        let messages = ids.iter().filter_map(|id| {
+            let flags = flags.remove(*id)?;
+            let message = MovedMessage::parse(headers.get(*id)?)?;
+            Some((flags, message))
+        });
+
+        Ok(Self::group(messages))
+    }
+
+    fn group(messages: impl IntoIterator<Item = (Vec<Flag>, MovedMessage)>) -> Self {
+        let mut groups: BTreeMap<Vec<Flag>, Vec<MovedMessage>> = BTreeMap::new();
+
+        for (mut flags, message) in messages {
+            if flags.is_empty() {
+                continue;
+            }
+
+            flags.sort();
+            groups.entry(flags).or_default().push(message);
+        }
+
+        Self { groups }
+    }
+
+    /// Sets the flags read beforehand on the copies found in `to`.
+    /// The destination is searched once for the copies of every
+    /// group.
+    pub(super) fn restore(self, client: &mut EmailClient, to: &str) -> Result<()> {
+        if self.groups.is_empty() {
+            return Ok(());
+        }
+
+        let messages: Vec<MovedMessage> = self.groups.values().flatten().cloned().collect();
+        let located = locate_moved(client, to, &messages)?;
+        let missing = located.iter().filter(|id| id.is_none()).count();
+
+        if missing > 0 {
+            warn!("{missing} copied message(s) not found in mailbox {to}, flags not kept");
+        }
+
+        for (flags, ids) in self.assign(located) {
+            let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+            client.store_flags(to, &ids, &flags, FlagOp::Add)?;
+        }
+
+        Ok(())
+    }
+
+    /// Ids of the copies to set each group of flags on, given the
+    /// `located` ids of the messages of every group, in order.
+    fn assign(self, located: Vec<Option<String>>) -> Vec<(Vec<Flag>, Vec<String>)> {
+        let mut located = located.into_iter();
+
+        self.groups
+            .into_iter()
+            .map(|(flags, messages)| {
+                let ids: Vec<String> = located.by_ref().take(messages.len()).flatten().collect();
+                (flags, ids)
+            })
+            .filter(|(_, ids)| !ids.is_empty())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use io_email::flag::types::IanaFlag;
+
+    use super::*;
+
+    fn message(id: &str) -> MovedMessage {
+        MovedMessage {
+            message_id: id.to_owned(),
+            date: String::from("2024-01-01T00:00:00+00:00"),
+        }
+    }
+
+    #[test]
+    fn groups_copied_messages_by_flags() {
+        let seen = Flag::from_iana(IanaFlag::Seen);
+        let flagged = Flag::from_iana(IanaFlag::Flagged);
+
+        let flags = CopiedFlags::group([
+            (vec![flagged.clone()], message("<unseen-flagged@test>")),
+            (
+                vec![flagged.clone(), seen.clone()],
+                message("<seen-flagged@test>"),
+            ),
+            (
+                vec![seen.clone(), flagged.clone()],
+                message("<flagged-seen@test>"),
+            ),
+            (vec![], message("<unflagged@test>")),
+        ]);
+
+        assert_eq!(flags.groups.len(), 2);
+
+        // the unseen flagged message gets flagged again, not seen
+        let unseen = &flags.groups[&vec![flagged.clone()]];
+        assert_eq!(unseen.len(), 1);
+        assert_eq!(unseen[0].message_id, "<unseen-flagged@test>");
+
+        let mut both = vec![seen, flagged];
+        both.sort();
+        assert_eq!(flags.groups[&both].len(), 2);
+    }
+
+    #[test]
+    fn copied_unseen_flagged_message_stays_unseen_and_flagged() {
+        let seen = Flag::from_iana(IanaFlag::Seen);
+        let flagged = Flag::from_iana(IanaFlag::Flagged);
+
+        let flags = CopiedFlags::group([
+            (vec![flagged.clone()], message("<unseen-flagged@test>")),
+            (vec![seen.clone()], message("<seen@test>")),
+            (vec![seen.clone()], message("<missing@test>")),
+        ]);
+
+        let mut located = Vec::new();
+        for messages in flags.groups.values() {
+            for message in messages {
+                let id = match message.message_id.as_str() {
+                    "<unseen-flagged@test>" => Some(String::from("1")),
+                    "<seen@test>" => Some(String::from("2")),
+                    _ => None,
+                };
+                located.push(id);
+            }
+        }
+
+        let mut assigned = flags.assign(located);
+        assigned.sort();
+
+        let mut expected = vec![
+            (vec![flagged], vec![String::from("1")]),
+            (vec![seen], vec![String::from("2")]),
+        ];
+        expected.sort();
+
+        assert_eq!(assigned, expected);
+    }
+}
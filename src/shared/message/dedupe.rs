@@ -0,0 +1,164 @@
+use std::{collections::BTreeMap, fmt};
+
+use anyhow::{Result, bail};
+use clap::Parser;
+use mail_parser::MessageParser;
+use pimalaya_cli::printer::Printer;
+use serde::Serialize;
+
+use crate::account::context::Account;
+use crate::shared::{client::EmailClient, mailbox::arg::MailboxArg, message::undo::Operation};
+
+/// Detect duplicate messages within a mailbox.
+///
+/// Envelopes are grouped by their `Message-ID` header, which requires
+/// fetching the header section of every message of the mailbox (in
+/// one request with IMAP, bodies are never downloaded). Runs as a
+/// dry-run by default: duplicate sets are reported, nothing is
+/// touched. With `--delete`, the first message of each set is kept
+/// and the others are moved to the trash mailbox. Messages without a
+/// `Message-ID` are skipped and never removed.
+///
+/// The removal can be reverted with `message undo`.
+#[derive(Debug, Parser)]
+pub struct MessageDedupeCommand {
+    #[command(flatten)]
+    pub mailbox: MailboxArg,
+
+    /// Remove duplicates, keeping one message per set.
+    #[arg(long)]
+    pub delete: bool,
+
+    /// Mailbox name or alias duplicates are moved to by `--delete`.
+    /// Defaults to the mailbox bound to the `trash` alias, `Trash`
+    /// when unset.
+    #[arg(long, value_name = "NAME")]
+    pub trash: Option<String>,
+}
+
+impl MessageDedupeCommand {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        account: &mut Account,
+        client: &mut EmailClient,
+        account_name: &str,
+    ) -> Result<()> {
+        let mailbox = self.mailbox.resolve(account)?;
+        let trash = match &self.trash {
+            Some(name) => MailboxArg::resolve_name(account, name)?,
+            None => account.trash_mailbox().to_owned(),
+        };
+
+        if self.delete && mailbox == trash {
+            bail!("Cannot move duplicates of {mailbox} to itself, pass another `--trash` mailbox");
+        }
+
+        let envelopes = client.list_envelopes(&mailbox, None, None, false)?;
+        let ids: Vec<&str> = envelopes.iter().map(|env| env.id.as_str()).collect();
+        let mut headers = client.get_headers(&mailbox, &ids)?;
+
+        let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut skipped = 0;
+
+        for envelope in &envelopes {
+            let message_id = headers.remove(&envelope.id).and_then(|raw| {
+                MessageParser::new()
+                    .parse_headers(&raw)
+                    .and_then(|msg| msg.message_id().map(str::to_owned))
+            });
+
+            match message_id {
+                Some(message_id) => groups
+                    .entry(message_id)
+                    .or_default()
+                    .push(envelope.id.clone()),
+                None => skipped += 1,
+            }
+        }
+
+        let sets: Vec<DuplicateSet> = groups
+            .into_iter()
+            .filter(|(_, ids)| ids.len() > 1)
+            .map(|(message_id, mut ids)| DuplicateSet {
+                message_id,
+                kept: ids.remove(0),
+                duplicates: ids,
+            })
+            .collect();
+
+        let found = sets.iter().map(|set| set.duplicates.len()).sum();
+        let mut removed = 0;
+
+        if self.delete && found > 0 {
+            let ids: Vec<&str> = sets
+                .iter()
+                .flat_map(|set| set.duplicates.iter().map(String::as_str))
+                .collect();
+            let operation = Operation::move_messages(client, &mailbox, &trash, &ids)?;
+            operation.record(account_name);
+            removed = ids.len();
+        }
+
+        printer.out(DedupeReport {
+            mailbox,
+            sets,
+            found,
+            removed,
+            skipped,
+        })
+    }
+}
+
+/// Messages sharing the same `Message-ID`.
+#[derive(Clone, Debug, Serialize)]
+pub struct DuplicateSet {
+    pub message_id: String,
+    pub kept: String,
+    pub duplicates: Vec<String>,
+}
+
+/// Outcome of a `message dedupe` run.
+#[derive(Clone, Debug, Serialize)]
+pub struct DedupeReport {
+    pub mailbox: String,
+    pub sets: Vec<DuplicateSet>,
+    /// Number of duplicates found (kept messages excluded).
+    pub found: usize,
+    /// Number of duplicates removed, always 0 without `--delete`.
+    pub removed: usize,
+    /// Number of messages skipped for lack of `Message-ID`.
+    pub skipped: usize,
+}
+
+impl fmt::Display for DedupeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for set in &self.sets {
+            writeln!(f, "{}", set.message_id)?;
+            writeln!(f, "  keep: {}", set.kept)?;
+            for id in &set.duplicates {
+                writeln!(f, "  duplicate: {id}")?;
+            }
+        }
+
+        if !self.sets.is_empty() {
+            writeln!(f)?;
+        }
+
+        writeln!(
+            f,
+            "Mailbox {}: {} duplicate(s) found, {} removed",
+            self.mailbox, self.found, self.removed
+        )?;
+
+        if self.skipped > 0 {
+            writeln!(f, "{} message(s) without Message-ID skipped", self.skipped)?;
+        }
+
+        if self.found > self.removed {
+            writeln!(f, "Run again with `--delete` to remove duplicates")?;
+        }
+
+        Ok(())
+    }
+}
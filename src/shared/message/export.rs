@@ -0,0 +1,363 @@
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::{BufWriter, IsTerminal, Write, stderr},
+    path::{Path, PathBuf},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local};
+use clap::{Parser, ValueEnum};
+use io_email::flag::types::{Flag, IanaFlag};
+use mail_parser::MessageParser;
+use pimalaya_cli::printer::{Message, Printer};
+
+use crate::account::context::Account;
+use crate::shared::{client::EmailClient, mailbox::arg::MailboxArg};
+
+/// Export messages of a mailbox to local files, as a backup.
+///
+/// Exports the given messages, or the whole mailbox when no id is
+/// given. With `--format maildir`, `--output` becomes a Maildir
+/// (`cur/`, `new/` and `tmp/` are created as needed) and each message
+/// is written to `cur/` under a unique name carrying its flags
+/// (`:2,S` for seen, …), so the backup can be read or re-imported by
+/// any Maildir-aware tool. With `--format mbox`, `--output` becomes a
+/// single mbox file (mboxrd flavour), the flags being kept in the
+/// `Status` and `X-Status` headers.
+#[derive(Debug, Parser)]
+pub struct MessageExportCommand {
+    #[command(flatten)]
+    pub mailbox: MailboxArg,
+
+    /// Identifiers of the messages to export. Exports the whole
+    /// mailbox when omitted.
+    #[arg(value_name = "ID")]
+    pub ids: Vec<String>,
+
+    /// Format of the export.
+    #[arg(long, short = 'f', value_name = "FORMAT", default_value = "maildir")]
+    pub format: ExportFormat,
+
+    /// Destination of the export.
+    #[arg(long, short, value_name = "PATH")]
+    pub output: PathBuf,
+}
+
+/// Layout messages are exported to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    /// One file per message in a Maildir `cur/` directory.
+    Maildir,
+    /// Every message in a single mbox file.
+    Mbox,
+}
+
+impl MessageExportCommand {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        account: &mut Account,
+        client: &mut EmailClient,
+    ) -> Result<()> {
+        let mailbox = self.mailbox.resolve(account)?;
+
+        let mut envelopes = client.list_envelopes(&mailbox, None, None, false)?;
+
+        if !self.ids.is_empty() {
+            for id in &self.ids {
+                if !envelopes.iter().any(|envelope| &envelope.id == id) {
+                    bail!("Cannot find message `{id}` in mailbox `{mailbox}`");
+                }
+            }
+
+            envelopes.retain(|envelope| self.ids.contains(&envelope.id));
+        }
+
+        let total = envelopes.len();
+
+        match self.format {
+            ExportFormat::Maildir => {
+                let mut maildir = MaildirWriter::create(&self.output)?;
+
+                for (i, envelope) in envelopes.iter().enumerate() {
+                    let raw = client.get_message(&mailbox, &envelope.id)?;
+                    maildir.write(&raw, &envelope.flags)?;
+                    progress(i + 1, total);
+                }
+            }
+            ExportFormat::Mbox => {
+                let mut mbox = MboxWriter::create(&self.output)?;
+
+                for (i, envelope) in envelopes.iter().enumerate() {
+                    let raw = client.get_message(&mailbox, &envelope.id)?;
+                    mbox.write(&raw, &envelope.flags)?;
+                    progress(i + 1, total);
+                }
+
+                mbox.finish()?;
+            }
+        }
+
+        printer.out(Message::new(format!(
+            "Exported {} message(s) to {}",
+            envelopes.len(),
+            self.output.display()
+        )))
+    }
+}
+
+/// Writes messages into the `cur/` directory of a Maildir.
+struct MaildirWriter {
+    tmp: PathBuf,
+    cur: PathBuf,
+    host: String,
+    pid: u32,
+    deliveries: u64,
+}
+
+impl MaildirWriter {
+    /// Opens the Maildir at `root`, creating `cur/`, `new/` and
+    /// `tmp/` when missing.
+    fn create(root: &Path) -> Result<Self> {
+        for subdir in ["cur", "new", "tmp"] {
+            let path = root.join(subdir);
+            fs::create_dir_all(&path)
+                .with_context(|| format!("Create Maildir `{}` error", path.display()))?;
+        }
+
+        // NOTE: `/` and `:` are forbidden in Maildir names, the spec
+        // encodes them as octal escapes
+        let host = gethostname::gethostname()
+            .into_string()
+            .ok()
+            .filter(|host| !host.is_empty())
+            .unwrap_or_else(|| String::from("localhost"))
+            .replace('/', "\\057")
+            .replace(':', "\\072");
+
+        Ok(Self {
+            tmp: root.join("tmp"),
+            cur: root.join("cur"),
+            host,
+            pid: process::id(),
+            deliveries: 0,
+        })
+    }
+
+    /// Writes `raw` under a fresh unique name with the info suffix
+    /// encoding `flags`.
+    ///
+    /// Names follow the `<secs>.M<usecs>P<pid>Q<n>.<host>` scheme: the
+    /// per-process delivery counter keeps them unique even when many
+    /// messages are written within the same microsecond. As the spec
+    /// requires, the message is written to `tmp/` first then renamed
+    /// into `cur/`, so readers never see a partially written file.
+    fn write(&mut self, raw: &[u8], flags: &BTreeSet<Flag>) -> Result<()> {
+        let info = maildir_info(flags);
+
+        let (tmp, cur) = loop {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+
+            self.deliveries += 1;
+
+            let name = format!(
+                "{}.M{}P{}Q{}.{}",
+                now.as_secs(),
+                now.subsec_micros(),
+                self.pid,
+                self.deliveries,
+                self.host,
+            );
+
+            let tmp = self.tmp.join(&name);
+            let cur = self.cur.join(format!("{name}:2,{info}"));
+
+            if !tmp.exists() && !cur.exists() {
+                break (tmp, cur);
+            }
+        };
+
+        fs::write(&tmp, raw).with_context(|| format!("Write `{}` error", tmp.display()))?;
+
+        fs::rename(&tmp, &cur)
+            .with_context(|| format!("Move `{}` to `{}` error", tmp.display(), cur.display()))
+    }
+}
+
+/// Appends messages to an mbox file, one at a time.
+struct MboxWriter {
+    path: PathBuf,
+    file: BufWriter<File>,
+}
+
+impl MboxWriter {
+    /// Creates the mbox file at `path`, truncating any existing one.
+    fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Create mbox `{}` error", path.display()))?;
+
+        Ok(Self {
+            path: path.to_owned(),
+            file: BufWriter::new(file),
+        })
+    }
+
+    /// Appends `raw`, with `flags` in its `Status` and `X-Status`
+    /// headers.
+    fn write(&mut self, raw: &[u8], flags: &BTreeSet<Flag>) -> Result<()> {
+        let date = from_line_date(raw);
+        let message = mbox_message(raw, flags);
+
+        writeln!(self.file, "From MAILER-DAEMON {date}")
+            .and_then(|()| self.file.write_all(&message))
+            .with_context(|| format!("Write `{}` error", self.path.display()))
+    }
+
+    fn finish(mut self) -> Result<()> {
+        self.file
+            .flush()
+            .with_context(|| format!("Write `{}` error", self.path.display()))
+    }
+}
+
+/// Date of the `From ` line of `raw`: its `Date:` header, or the
+/// current date when it has none.
+fn from_line_date(raw: &[u8]) -> String {
+    const FORMAT: &str = "%a %b %e %T %Y";
+
+    let date = MessageParser::new()
+        .parse_headers(raw)
+        .and_then(|msg| DateTime::parse_from_rfc3339(&msg.date()?.to_rfc3339()).ok());
+
+    match date {
+        Some(date) => date.format(FORMAT).to_string(),
+        None => Local::now().format(FORMAT).to_string(),
+    }
+}
+
+/// `raw` as an mbox entry, without its `From ` line: LF line endings,
+/// `Status` and `X-Status` headers set from `flags` (replacing any
+/// existing one), body lines starting with `From ` (after any number
+/// of `>`) quoted with one more `>` (mboxrd), and a trailing empty
+/// line.
+fn mbox_message(raw: &[u8], flags: &BTreeSet<Flag>) -> Vec<u8> {
+    let mut status = String::new();
+    let mut x_status = String::new();
+
+    if flags.iter().any(Flag::is_seen) {
+        status.push('R');
+    }
+    status.push('O');
+    if flags.iter().any(Flag::is_answered) {
+        x_status.push('A');
+    }
+    if flags.iter().any(Flag::is_flagged) {
+        x_status.push('F');
+    }
+    if flags.contains(&Flag::from_iana(IanaFlag::Draft)) {
+        x_status.push('T');
+    }
+
+    let mut message = Vec::with_capacity(raw.len() + 32);
+    let mut in_headers = true;
+
+    for line in raw.split_inclusive(|b| *b == b'\n') {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        if in_headers {
+            if line.is_empty() {
+                in_headers = false;
+                message.extend_from_slice(format!("Status: {status}\n").as_bytes());
+                if !x_status.is_empty() {
+                    message.extend_from_slice(format!("X-Status: {x_status}\n").as_bytes());
+                }
+            } else if is_header(line, b"status") || is_header(line, b"x-status") {
+                continue;
+            }
+        } else if line[line.iter().take_while(|b| **b == b'>').count()..].starts_with(b"From ") {
+            message.push(b'>');
+        }
+
+        message.extend_from_slice(line);
+        message.push(b'\n');
+    }
+
+    if in_headers {
+        message.extend_from_slice(format!("Status: {status}\n\n").as_bytes());
+    }
+
+    message.push(b'\n');
+    message
+}
+
+/// Whether the header `line` is named `name` (lowercase).
+fn is_header(line: &[u8], name: &[u8]) -> bool {
+    line.len() > name.len()
+        && line[name.len()] == b':'
+        && line[..name.len()].eq_ignore_ascii_case(name)
+}
+
+/// Reports `done` out of `total` exported messages on stderr, when
+/// it is a terminal.
+fn progress(done: usize, total: usize) {
+    let mut stderr = stderr();
+
+    if !stderr.is_terminal() {
+        return;
+    }
+
+    let _ = write!(stderr, "\rExporting message {done}/{total}…");
+
+    if done == total {
+        let _ = writeln!(stderr);
+    }
+}
+
+/// Maildir info flags, in the ASCII order the spec requires.
+fn maildir_info(flags: &BTreeSet<Flag>) -> String {
+    let mut info = String::new();
+
+    if flags.contains(&Flag::from_iana(IanaFlag::Draft)) {
+        info.push('D');
+    }
+    if flags.iter().any(Flag::is_flagged) {
+        info.push('F');
+    }
+    if flags.iter().any(Flag::is_answered) {
+        info.push('R');
+    }
+    if flags.iter().any(Flag::is_seen) {
+        info.push('S');
+    }
+
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_from_lines_like_their_message() {
+        let raw = b"Date: Tue, 5 Mar 2024 09:07:03 +0100\r\nSubject: x\r\n\r\nbody\r\n";
+        assert_eq!(from_line_date(raw), "Tue Mar  5 09:07:03 2024");
+    }
+
+    #[test]
+    fn writes_mboxrd_entries() {
+        let raw = b"Subject: x\r\nStatus: O\r\n\r\nFrom me\r\n>From you\r\nFrom:\r\n";
+        let flags = BTreeSet::from([Flag::from_iana(IanaFlag::Seen)]);
+
+        let message = mbox_message(raw, &flags);
+        assert_eq!(
+            String::from_utf8(message).unwrap(),
+            "Subject: x\nStatus: RO\n\n>From me\n>>From you\nFrom:\n\n"
+        );
+    }
+}
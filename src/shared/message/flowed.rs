@@ -0,0 +1,238 @@
+//! RFC 3676 `format=flowed` helpers.
+//!
+//! [`decode`] joins soft-wrapped lines back into paragraphs, keeping
+//! quote depth apart, so the reader does not render the sender's
+//! wrapping literally. [`encode`] does the opposite for the built-in
+//! composers: long lines are soft-wrapped with a trailing space and
+//! lines that would be misread are space-stuffed.
+
+use mail_parser::ContentType;
+
+/// Maximum length of an encoded line, quote markers included
+/// (RFC 3676 §4.2 recommends 78).
+const MAX_LINE_LEN: usize = 78;
+
+/// The signature separator, never treated as a flowed line (RFC 3676
+/// §4.3).
+const SIGNATURE_SEPARATOR: &str = "-- ";
+
+/// Returns `Some(delsp)` when `ctype` is `format=flowed`, `delsp`
+/// being `true` for `DelSp=yes`; `None` for fixed text.
+pub fn params(ctype: Option<&ContentType>) -> Option<bool> {
+    let ctype = ctype?;
+    let format = ctype.attribute("format")?;
+
+    if !format.eq_ignore_ascii_case("flowed") {
+        return None;
+    }
+
+    let delsp = ctype
+        .attribute("delsp")
+        .is_some_and(|delsp| delsp.eq_ignore_ascii_case("yes"));
+
+    Some(delsp)
+}
+
+/// Decodes a `format=flowed` text body into logical lines.
+///
+/// Soft line breaks (a line ending with a space) are joined with the
+/// next line of the same quote depth; a change of depth always ends
+/// the paragraph. One leading space is removed from space-stuffed
+/// lines. When `delsp` is `true` (`DelSp=yes`), the trailing space of
+/// a soft break is removed too. Quoted paragraphs are rendered back
+/// with `> ` markers.
+pub fn decode(text: &str, delsp: bool) -> String {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut paragraph: Option<(usize, String)> = None;
+
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        let depth = line.chars().take_while(|c| *c == '>').count();
+        let content = &line[depth..];
+        let content = content.strip_prefix(' ').unwrap_or(content);
+
+        let flowed = content != SIGNATURE_SEPARATOR && content.ends_with(' ');
+        let piece = if flowed && delsp {
+            &content[..content.len() - 1]
+        } else {
+            content
+        };
+
+        match paragraph.as_mut() {
+            Some((d, buf)) if *d == depth => buf.push_str(piece),
+            _ => {
+                lines.extend(paragraph.take());
+                paragraph = Some((depth, piece.to_owned()));
+            }
+        }
+
+        if !flowed {
+            lines.extend(paragraph.take());
+        }
+    }
+
+    lines.extend(paragraph);
+
+    lines
+        .into_iter()
+        .map(|(depth, text)| {
+            if depth == 0 {
+                text
+            } else {
+                format!("{} {text}", ">".repeat(depth))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Encodes a plain text body as `format=flowed` (without `DelSp`).
+///
+/// Each input line is a hard line break. Lines longer than
+/// [`MAX_LINE_LEN`] are soft-wrapped at word boundaries, every
+/// segment but the last ending with a space. Trailing spaces of hard
+/// lines are stripped so they are not mistaken for soft breaks, and
+/// unquoted lines starting with a space or `From ` are space-stuffed.
+/// Leading `>` characters are read as quote markers and re-emitted
+/// as `> `.
+pub fn encode(text: &str) -> String {
+    let mut out = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+
+        if line == SIGNATURE_SEPARATOR {
+            out.push(line.to_owned());
+            continue;
+        }
+
+        let depth = line.chars().take_while(|c| *c == '>').count();
+        let content = &line[depth..];
+        let content = if depth > 0 {
+            content.strip_prefix(' ').unwrap_or(content)
+        } else {
+            content
+        };
+        let content = content.trim_end_matches(' ');
+
+        let prefix = if depth > 0 {
+            format!("{} ", ">".repeat(depth))
+        } else {
+            String::new()
+        };
+
+        let width = MAX_LINE_LEN.saturating_sub(prefix.len()).max(1);
+        let segments = wrap(content, width);
+        let last = segments.len() - 1;
+
+        for (i, segment) in segments.into_iter().enumerate() {
+            let stuff = depth == 0 && needs_stuffing(&segment);
+            let soft = if i < last { " " } else { "" };
+            let stuffing = if stuff { " " } else { "" };
+            out.push(format!("{prefix}{stuffing}{segment}{soft}"));
+        }
+    }
+
+    out.join("\n")
+}
+
+/// Whether an unquoted line must be space-stuffed to survive the
+/// round-trip (RFC 3676 §4.4).
+fn needs_stuffing(line: &str) -> bool {
+    line.starts_with(' ') || line.starts_with('>') || line.starts_with("From ")
+}
+
+/// Greedily splits `line` into segments of at most `width` chars,
+/// breaking only on spaces. A single word longer than `width` is
+/// kept whole. Always returns at least one (possibly empty) segment.
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut words = line.split(' ');
+
+    // The first word may be empty when the line starts with a space;
+    // keep it so the leading space survives (and gets stuffed).
+    current.push_str(words.next().unwrap_or_default());
+
+    for word in words {
+        if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > width {
+            segments.push(std::mem::take(&mut current));
+            current.push_str(word);
+        } else {
+            current.push(' ');
+            current.push_str(word);
+        }
+    }
+
+    segments.push(current);
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode};
+
+    #[test]
+    fn decode_joins_soft_breaks() {
+        let text = "Hello there, \r\nhow are \r\nyou?\r\nBye";
+        assert_eq!(decode(text, false), "Hello there, how are you?\nBye");
+    }
+
+    #[test]
+    fn decode_keeps_quote_depths_apart() {
+        let text = ">> deep \r\n>> quote\r\n> shallow \r\n> quote\r\nreply";
+        assert_eq!(decode(text, false), ">> deep quote\n> shallow quote\nreply");
+    }
+
+    #[test]
+    fn decode_ends_paragraph_on_depth_change() {
+        let text = "> flowed \r\nnot quoted";
+        assert_eq!(decode(text, false), "> flowed \nnot quoted");
+    }
+
+    #[test]
+    fn decode_removes_space_stuffing() {
+        let text = " From the start\r\n >not a quote";
+        assert_eq!(decode(text, false), "From the start\n>not a quote");
+    }
+
+    #[test]
+    fn decode_strips_trailing_space_with_delsp() {
+        let text = "split\u{20}\r\nword";
+        assert_eq!(decode(text, true), "splitword");
+    }
+
+    #[test]
+    fn decode_never_flows_signature_separator() {
+        let text = "body\r\n-- \r\nsig";
+        assert_eq!(decode(text, false), "body\n-- \nsig");
+    }
+
+    #[test]
+    fn encode_stuffs_ambiguous_lines() {
+        assert_eq!(encode("From here"), " From here");
+        assert_eq!(encode(" indented"), "  indented");
+    }
+
+    #[test]
+    fn encode_strips_trailing_spaces_of_hard_lines() {
+        assert_eq!(encode("hard   \nline"), "hard\nline");
+    }
+
+    #[test]
+    fn encode_soft_wraps_long_lines() {
+        let long = "word ".repeat(30);
+        let encoded = encode(long.trim_end());
+        let lines: Vec<&str> = encoded.lines().collect();
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|l| l.len() <= 79));
+        assert!(lines[..lines.len() - 1].iter().all(|l| l.ends_with(' ')));
+        assert_eq!(decode(&encoded, false), long.trim_end());
+    }
+
+    #[test]
+    fn encode_round_trips_quoted_text() {
+        let text = "> quoted line\n>> nested\nreply";
+        assert_eq!(decode(&encode(text), false), text);
+    }
+}
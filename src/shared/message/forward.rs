@@ -10,7 +10,7 @@ use crate::shared::{
     mailbox::arg::MailboxArg,
     message::{
         builder::{self, BuilderArgs, PostingStyle, SourceArgs, SourceMode},
-        handler,
+        handler, spellcheck,
     },
 };
 
@@ -19,6 +19,8 @@ use crate::shared::{
 /// Fetches the source, pre-fills `Fwd:` on the subject and the
 /// `References` header, and quotes the source body. The produced
 /// MIME is written to stdout, or routed via `--save` / `--send`.
+/// `--print-template` only prints the quoted text a forward would
+/// start from, for previewing or feeding another tool.
 /// For richer composition, pipe `messages read <id>` into a
 /// standalone composer (`mml forward`, etc.) and feed its output
 /// back into `messages send` / `messages add`.
@@ -36,6 +38,12 @@ pub struct MessageForwardCommand {
     #[arg(long, value_name = "ADDR")]
     pub from: Option<String>,
 
+    /// Send as the configured identity matching this address or
+    /// display name: sets the `From` header, and the signature when
+    /// none is passed. Defaults to the account `email`.
+    #[arg(long, value_name = "ADDR|NAME", conflicts_with = "from")]
+    pub identity: Option<String>,
+
     /// Recipient address(es) (`To` header). Repeat the flag or use a
     /// comma-separated list.
     #[arg(long, short = 't', value_name = "ADDR", value_delimiter = ',')]
@@ -94,11 +102,35 @@ pub struct MessageForwardCommand {
     #[arg(long = "quote-headline", short = 'Q', value_name = "TEXT")]
     pub quote_headline: Option<String>,
 
-    #[arg(long, value_name = "MAILBOX")]
+    /// Emit the text body as `format=flowed` (RFC 3676): long lines
+    /// are soft-wrapped so they reflow on the recipient's client.
+    #[arg(long)]
+    pub flowed: bool,
+
+    /// Ask the recipients for a read receipt, with a
+    /// `Disposition-Notification-To` header set to the `From`
+    /// address (RFC 8098). Recipients are free to ignore it.
+    #[arg(long)]
+    pub read_receipt: bool,
+
+    /// Print the forward template (quote headline, forwarded body and
+    /// signature) to stdout instead of building the message. Nothing
+    /// is read from stdin, saved or sent.
+    #[arg(long, visible_aliases = ["stdout", "template-only"])]
+    #[arg(conflicts_with_all = ["body", "body_file", "save", "send"])]
+    pub print_template: bool,
+
+    /// Append a copy of the message to this mailbox. When sending,
+    /// overrides the `send.save-to` mailbox, `-` saving no copy.
+    #[arg(long, visible_alias = "save-to", value_name = "MAILBOX")]
     pub save: Option<String>,
 
     #[arg(long)]
     pub send: bool,
+
+    /// Send even when the message exceeds the `send.max-size` limit.
+    #[arg(long)]
+    pub force: bool,
 }
 
 impl MessageForwardCommand {
@@ -108,37 +140,67 @@ impl MessageForwardCommand {
         account: &mut Account,
         client: &mut EmailClient,
     ) -> Result<()> {
+        let identity = account.identity(self.identity.as_deref())?;
+        let (from, from_name) = identity.from(self.from.as_deref());
+        let signature =
+            identity.signature(self.signature.as_deref(), self.signature_file.as_deref());
+
         let mailbox = self.mailbox.resolve(account)?;
         let source = client.get_message(&mailbox, &self.id)?;
 
+        let source_args = SourceArgs {
+            raw: &source,
+            mode: SourceMode::Forward,
+            posting_style: self.posting_style,
+            quote_headline: self.quote_headline.as_deref().unwrap_or(""),
+        };
+
+        if self.print_template {
+            let template = builder::template(
+                "",
+                Some(source_args),
+                signature,
+                self.signature_file.as_deref(),
+            )?;
+            return printer.out(template);
+        }
+
         let raw = builder::build(
             BuilderArgs {
-                from: self.from.as_deref(),
+                from,
+                from_name,
                 to: &self.to,
                 cc: &self.cc,
                 bcc: &self.bcc,
+                reply_to: &[],
+                headers: &[],
                 subject: self.subject.as_deref(),
                 body: self.body.as_deref(),
                 body_file: self.body_file.as_deref(),
                 attach: &self.attach,
-                signature: self.signature.as_deref(),
+                signature,
                 signature_file: self.signature_file.as_deref(),
+                flowed: self.flowed,
+                read_receipt: self.read_receipt,
             },
-            Some(SourceArgs {
-                raw: &source,
-                mode: SourceMode::Forward,
-                posting_style: self.posting_style,
-                quote_headline: self.quote_headline.as_deref().unwrap_or(""),
-            }),
+            Some(source_args),
         )?;
 
+        if self.send {
+            if let (Some(_), Some(from)) = (&self.identity, from) {
+                handler::check_sender(client, from)?;
+            }
+            spellcheck::check(account, &raw)?;
+        }
+
         handler::route(
             printer,
             account,
             client,
             raw,
-            self.save.as_deref(),
+            handler::save_to(account, self.save.as_deref(), self.send),
             self.send,
+            self.force,
         )
     }
 }
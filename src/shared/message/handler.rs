@@ -15,8 +15,11 @@
 
 use std::io::{Write, stdout};
 
-use anyhow::Result;
+use anyhow::{Result, bail};
+use humansize::{DECIMAL, format_size};
 use io_email::flag::types::{Flag, IanaFlag};
+use log::{debug, warn};
+use mail_parser::MessageParser;
 use pimalaya_cli::printer::{Message, Printer};
 
 use crate::{account::context::Account, shared::client::EmailClient};
@@ -28,7 +31,8 @@ pub enum Outcome {
     /// Saved to a mailbox; `id` is the backend-assigned id of the
     /// new message, `sent` is `true` when `send` was also requested.
     Saved { id: String, sent: bool },
-    /// Sent only (no save). The send path returns no id.
+    /// Sent without a saved copy, none being requested or the copy
+    /// failing to save. The send path returns no id.
     Sent,
 }
 
@@ -37,6 +41,15 @@ pub enum Outcome {
 /// (resolved through the account's alias map) with `flags` attached;
 /// `send` pushes the message through the configured SMTP / JMAP send
 /// path. With neither set, dumps `raw` to stdout.
+///
+/// When sending, `raw` is checked against the account's maximum
+/// message size first, and refused before any side-effect unless
+/// `force` is set. The copy is only saved once the message is sent,
+/// so a failed send leaves no copy behind to duplicate on retry; a
+/// copy failing to save after a successful send only warns. The
+/// `send.bcc-self` / `send.always-bcc` addresses are added to the
+/// sent copy only (see [`with_auto_bcc`]), so the saved copy never
+/// lists them.
 pub fn apply(
     account: &Account,
     client: &mut EmailClient,
@@ -44,29 +57,175 @@ pub fn apply(
     flags: &[Flag],
     save: Option<&str>,
     send: bool,
+    force: bool,
 ) -> Result<Outcome> {
+    if send && !force {
+        check_size(account, &raw)?;
+    }
+
     if !send && save.is_none() {
         let mut out = stdout().lock();
         out.write_all(&raw)?;
         return Ok(Outcome::Stdout);
     }
 
-    let saved_id = match save {
-        Some(name) => {
-            let mailbox = account.resolve_mailbox(name);
-            Some(client.add_message(mailbox, flags, raw.clone())?)
+    if send {
+        client.send_message(with_auto_bcc(account, raw.clone()))?;
+    }
+
+    let Some(name) = save else {
+        return Ok(Outcome::Sent);
+    };
+
+    let mailbox = account.resolve_mailbox(name);
+
+    match client.add_message(mailbox, flags, raw) {
+        Ok(id) => Ok(Outcome::Saved { id, sent: send }),
+        Err(err) if send => {
+            warn!("message sent, but cannot save a copy to {mailbox}: {err}");
+            debug!("{err:?}");
+            Ok(Outcome::Sent)
         }
-        None => None,
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Adds the account's automatic blind recipients to the `Bcc:`
+/// header of `raw`, which the send path turns into envelope
+/// recipients and strips from the transmitted headers.
+///
+/// Addresses already among the `To:` / `Cc:` / `Bcc:` recipients are
+/// skipped, so they are not delivered the message twice.
+fn with_auto_bcc(account: &Account, raw: Vec<u8>) -> Vec<u8> {
+    let bcc = {
+        let Some(msg) = MessageParser::new().parse_headers(&raw) else {
+            return raw;
+        };
+
+        let mut bcc = Vec::new();
+
+        if account.send_bcc_self == Some(true) {
+            let from = msg
+                .from()
+                .and_then(|from| from.first())
+                .and_then(|from| from.address());
+
+            if let Some(from) = from {
+                bcc.push(from.to_owned());
+            }
+        }
+
+        if let Some(addr) = &account.send_always_bcc {
+            bcc.push(addr.clone());
+        }
+
+        let mut recipients: Vec<String> = [msg.to(), msg.cc(), msg.bcc()]
+            .into_iter()
+            .flatten()
+            .flat_map(|addrs| addrs.iter())
+            .filter_map(|addr| addr.address())
+            .map(str::to_lowercase)
+            .collect();
+
+        bcc.retain(|addr| {
+            let addr = addr.to_lowercase();
+            let is_new = !recipients.contains(&addr);
+            recipients.push(addr);
+            is_new
+        });
+
+        bcc
     };
 
-    if send {
-        client.send_message(raw)?;
+    if bcc.is_empty() {
+        return raw;
     }
 
-    Ok(match saved_id {
-        Some(id) => Outcome::Saved { id, sent: send },
-        None => Outcome::Sent,
-    })
+    append_bcc(raw, &bcc.join(", "))
+}
+
+/// Appends `addrs` to the `Bcc:` header of `raw`, adding the header
+/// at the end of the header block when missing.
+fn append_bcc(mut raw: Vec<u8>, addrs: &str) -> Vec<u8> {
+    let eol: &[u8] = if raw.windows(2).any(|w| w == b"\r\n") {
+        b"\r\n"
+    } else {
+        b"\n"
+    };
+
+    // (start, end) of each header line, without its line break
+    let mut lines = Vec::new();
+    let mut start = 0;
+
+    while start < raw.len() {
+        let end = raw[start..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(raw.len(), |i| start + i);
+        let content_end = if end > start && raw[end - 1] == b'\r' {
+            end - 1
+        } else {
+            end
+        };
+
+        if content_end == start {
+            break;
+        }
+
+        lines.push((start, content_end));
+        start = end + 1;
+    }
+
+    let bcc = lines.iter().enumerate().find_map(|(i, (start, end))| {
+        let name = raw[*start..*end].get(..4)?;
+        if !name.eq_ignore_ascii_case(b"bcc:") {
+            return None;
+        }
+
+        // the value ends with the last continuation line
+        let end = lines[i + 1..]
+            .iter()
+            .take_while(|(start, _)| matches!(raw[*start], b' ' | b'\t'))
+            .last()
+            .map_or(*end, |(_, end)| *end);
+
+        Some((*start + 4, end))
+    });
+
+    match bcc {
+        Some((value_start, end)) => {
+            let is_empty = raw[value_start..end].iter().all(u8::is_ascii_whitespace);
+            let sep = if is_empty { " " } else { ", " };
+            let value = format!("{sep}{addrs}");
+            raw.splice(end..end, value.into_bytes());
+        }
+        None => {
+            // NOTE: `start` is where the header block ends
+            let at = start.min(raw.len());
+            let mut header = Vec::new();
+            if at > 0 && raw[at - 1] != b'\n' {
+                // headers-only message without final line break
+                header.extend_from_slice(eol);
+            }
+            header.extend_from_slice(format!("Bcc: {addrs}").as_bytes());
+            header.extend_from_slice(eol);
+            raw.splice(at..at, header);
+        }
+    }
+
+    raw
+}
+
+/// Mailbox a copy of the message is saved to: `save` when given, `-`
+/// meaning no copy, otherwise the `send.save-to` mailbox when
+/// sending.
+pub fn save_to<'a>(account: &'a Account, save: Option<&'a str>, send: bool) -> Option<&'a str> {
+    match save {
+        Some("-") => None,
+        Some(mailbox) => Some(mailbox),
+        None if send => account.send_save_to.as_deref(),
+        None => None,
+    }
 }
 
 /// Generic wrapper over [`apply`]: hard-codes `\Seen` as the saved
@@ -79,6 +238,7 @@ pub fn route(
     raw: Vec<u8>,
     save: Option<&str>,
     send: bool,
+    force: bool,
 ) -> Result<()> {
     let outcome = apply(
         account,
@@ -87,6 +247,7 @@ pub fn route(
         &[Flag::from_iana(IanaFlag::Seen)],
         save,
         send,
+        force,
     )?;
     let msg = match outcome {
         Outcome::Stdout => return Ok(()),
@@ -96,3 +257,159 @@ pub fn route(
     };
     printer.out(Message::new(msg))
 }
+
+/// Bails when `raw` exceeds the account's maximum message size, so
+/// the server does not reject the message after a long upload.
+pub fn check_size(account: &Account, raw: &[u8]) -> Result<()> {
+    let Some(max_size) = account.send_max_size() else {
+        return Ok(());
+    };
+
+    let size = raw.len() as u64;
+
+    if size > max_size {
+        bail!(
+            "Message size {} exceeds the {} limit (`send.max-size`); pass `--force` to send anyway",
+            format_size(size, DECIMAL),
+            format_size(max_size, DECIMAL),
+        );
+    }
+
+    Ok(())
+}
+
+/// Bails when the server lists the addresses the account may send
+/// as (see [`EmailClient::sender_addresses`]) and `address` is not
+/// one of them. Wildcard addresses (`*@example.org`) match their
+/// whole domain.
+pub fn check_sender(client: &EmailClient, address: &str) -> Result<()> {
+    let Some(allowed) = client.sender_addresses()? else {
+        return Ok(());
+    };
+
+    let permitted = allowed
+        .iter()
+        .any(|allowed| match allowed.strip_prefix("*@") {
+            Some(domain) => address
+                .rsplit_once('@')
+                .is_some_and(|(_, d)| d.eq_ignore_ascii_case(domain)),
+            None => allowed.eq_ignore_ascii_case(address),
+        });
+
+    if !permitted {
+        bail!(
+            "The server does not allow sending as {address}, allowed: {}",
+            allowed.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Checks that `raw` is a message the send path can deliver before
+/// anything is sent or queued: a well-formed header block, a `From:`
+/// header and at least one recipient. All problems are reported at
+/// once, header syntax errors with their line number.
+pub fn check_message(raw: &[u8]) -> Result<()> {
+    let mut errors = Vec::new();
+
+    for (i, line) in raw.split(|b| *b == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let n = i + 1;
+
+        if line.is_empty() {
+            break;
+        }
+
+        if matches!(line[0], b' ' | b'\t') {
+            if i == 0 {
+                errors.push(format!("line {n}: continuation line without a header"));
+            }
+            continue;
+        }
+
+        let line = String::from_utf8_lossy(line);
+
+        let Some((name, _)) = line.split_once(':') else {
+            errors.push(format!("line {n}: expected `Name: Value`, got `{line}`"));
+            continue;
+        };
+
+        if name.is_empty() || !name.bytes().all(|b| b.is_ascii_graphic()) {
+            errors.push(format!("line {n}: invalid header name `{name}`"));
+        }
+    }
+
+    if let Some(msg) = MessageParser::new().parse_headers(raw) {
+        if msg.from().is_none() {
+            errors.push(String::from("missing `From` header"));
+        }
+
+        let has_recipient = [msg.to(), msg.cc(), msg.bcc()]
+            .into_iter()
+            .flatten()
+            .any(|addrs| addrs.iter().any(|addr| addr.address().is_some()));
+
+        if !has_recipient {
+            errors.push(String::from("no recipient in `To`, `Cc` or `Bcc`"));
+        }
+    }
+
+    if !errors.is_empty() {
+        bail!("Invalid message:\n  {}", errors.join("\n  "));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_to_existing_bcc() {
+        let raw = b"From: a@x.org\r\nBcc: b@x.org,\r\n c@x.org\r\nSubject: Hi\r\n\r\nBody\r\n";
+        let raw = append_bcc(raw.to_vec(), "me@x.org");
+
+        assert_eq!(
+            raw,
+            b"From: a@x.org\r\nBcc: b@x.org,\r\n c@x.org, me@x.org\r\nSubject: Hi\r\n\r\nBody\r\n"
+        );
+    }
+
+    #[test]
+    fn adds_missing_bcc_after_headers() {
+        let raw = append_bcc(b"From: a@x.org\nSubject: Hi\n\nBody\n".to_vec(), "me@x.org");
+        assert_eq!(raw, b"From: a@x.org\nSubject: Hi\nBcc: me@x.org\n\nBody\n");
+    }
+
+    #[test]
+    fn skips_existing_recipients() {
+        let account = Account {
+            send_bcc_self: Some(true),
+            send_always_bcc: Some(String::from("Archive@x.org")),
+            ..Default::default()
+        };
+
+        let raw = b"From: me@x.org\r\nTo: archive@x.org\r\n\r\nBody\r\n";
+        let sent = with_auto_bcc(&account, raw.to_vec());
+
+        assert_eq!(
+            sent,
+            b"From: me@x.org\r\nTo: archive@x.org\r\nBcc: me@x.org\r\n\r\nBody\r\n"
+        );
+    }
+
+    #[test]
+    fn reports_invalid_headers_with_line_numbers() {
+        let raw = b"From: me@x.org\r\nTo: you@x.org\r\nSubject Hi\r\n\r\nBody: text\r\n";
+        let err = check_message(raw).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Invalid message:\n  line 3: expected `Name: Value`, got `Subject Hi`"
+        );
+
+        assert!(check_message(b"From: me@x.org\nTo: you@x.org\n\nBody\n").is_ok());
+        assert!(check_message(b"Subject: Hi\n\nBody\n").is_err());
+    }
+}
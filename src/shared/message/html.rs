@@ -0,0 +1,252 @@
+//! `message read --open`: rendering of the HTML part in the default
+//! browser.
+//!
+//! The browser only gets a local file: images embedded in the message
+//! (`cid:` URLs, RFC 2392) are inlined as `data:` URLs, and remote
+//! resources (images, stylesheets, fonts) are blocked unless
+//! explicitly allowed, so opening a message does not tell its sender
+//! it was read. Blocking is twofold: remote references are rewritten
+//! so they can be counted and reported, and a Content Security Policy
+//! stops whatever the rewriting missed.
+
+use std::{io::Write, thread, time::Duration};
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use log::{debug, warn};
+use mail_parser::{Message, MimeHeaders};
+use regex::{Captures, Regex};
+
+/// How long the rendered file is kept once the browser is launched,
+/// so it has time to load it.
+const CLEANUP_DELAY: Duration = Duration::from_secs(5);
+
+/// Renders the HTML part of `message` to a temporary file, opens it
+/// in the default browser, then removes the file.
+pub fn open(message: &Message<'_>, id: &str, allow_remote: bool) -> Result<()> {
+    let Some(html) = message
+        .html_part(0)
+        .filter(|part| part.is_text_html())
+        .and_then(|part| part.text_contents())
+    else {
+        bail!("Message {id} has no HTML part");
+    };
+
+    let html = inline_cid(html, message);
+
+    let (blocked_html, remote) = block_remote(&html);
+
+    let html = if allow_remote {
+        if remote > 0 {
+            warn!("{remote} remote resource(s) will be loaded, notifying the sender");
+        }
+        html
+    } else {
+        if remote > 0 {
+            warn!("{remote} remote resource(s) blocked, pass --allow-remote to load them");
+        }
+        blocked_html
+    };
+
+    let id: String = id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+
+    // NOTE: created exclusively and readable by the user only, so
+    // neither another user nor a planted file can get the message
+    let mut file = tempfile::Builder::new()
+        .prefix(&format!("himalaya-{id}-"))
+        .suffix(".html")
+        .tempfile()
+        .context("Create temporary HTML file error")?;
+
+    let path = file.path().to_owned();
+
+    file.write_all(html.as_bytes())
+        .and_then(|()| file.flush())
+        .with_context(|| format!("Write `{}` error", path.display()))?;
+
+    let result = open::that(&path).with_context(|| format!("Open `{}` error", path.display()));
+
+    // NOTE: browsers load the file asynchronously, removing it right
+    // away would race them
+    if result.is_ok() {
+        thread::sleep(CLEANUP_DELAY);
+    }
+
+    if let Err(err) = file.close() {
+        debug!("cannot remove `{}`: {err}", path.display());
+    }
+
+    result
+}
+
+/// Replaces the `cid:` URLs of `html` by `data:` URLs holding the
+/// matching inline parts of `message`.
+fn inline_cid(html: &str, message: &Message<'_>) -> String {
+    let mut html = html.to_owned();
+
+    for part in &message.parts {
+        let Some(cid) = part.content_id() else {
+            continue;
+        };
+
+        let cid = cid.trim_start_matches('<').trim_end_matches('>');
+        let url = format!("cid:{cid}");
+
+        if !html.contains(&url) {
+            continue;
+        }
+
+        let mime = match part.content_type() {
+            Some(ctype) => match ctype.subtype() {
+                Some(subtype) => format!("{}/{subtype}", ctype.ctype()),
+                None => ctype.ctype().to_owned(),
+            },
+            None => String::from("application/octet-stream"),
+        };
+
+        let data = BASE64_STANDARD.encode(part.contents());
+        html = html.replace(&url, &format!("data:{mime};base64,{data}"));
+    }
+
+    html
+}
+
+/// Content Security Policy added to the blocked HTML, so that remote
+/// resources missed by [`block_remote`] are not loaded either.
+const BLOCKING_CSP: &str = "<meta http-equiv=\"Content-Security-Policy\" \
+    content=\"default-src 'none'; img-src data:; style-src 'unsafe-inline'; font-src data:\">\n";
+
+/// Neutralizes the remote resources of `html`: remote `src`,
+/// `srcset`, `background` and `poster` attributes and remote
+/// stylesheet links are renamed, CSS `url()`s emptied and CSS
+/// `@import`s removed. Returns the new HTML and the number of blocked
+/// resources.
+fn block_remote(html: &str) -> (String, usize) {
+    const REMOTE: &str = r#"(\s*=\s*["']?\s*(?:https?:)?//)"#;
+
+    let attrs = Regex::new(&format!(r"(?i)(\s)(src|srcset|background|poster){REMOTE}")).unwrap();
+    let links = Regex::new(&format!(r"(?i)(<link\b[^>]*?\s)(href){REMOTE}")).unwrap();
+    let urls = Regex::new(r#"(?i)url\(\s*["']?\s*(?:https?:)?//[^)]*\)"#).unwrap();
+    let imports =
+        Regex::new(r#"(?i)@import\s+(?:url\()?\s*["']?\s*(?:https?:)?//[^;]*;?"#).unwrap();
+
+    let mut blocked = 0;
+
+    let html = attrs.replace_all(html, |caps: &Captures| {
+        blocked += 1;
+        format!(
+            "{}data-blocked-{}{}",
+            &caps[1],
+            caps[2].to_lowercase(),
+            &caps[3]
+        )
+    });
+
+    let html = links.replace_all(&html, |caps: &Captures| {
+        blocked += 1;
+        format!(
+            "{}data-blocked-{}{}",
+            &caps[1],
+            caps[2].to_lowercase(),
+            &caps[3]
+        )
+    });
+
+    let html = imports.replace_all(&html, |_: &Captures| {
+        blocked += 1;
+        ""
+    });
+
+    let html = urls.replace_all(&html, |_: &Captures| {
+        blocked += 1;
+        "url()"
+    });
+
+    (with_csp(&html), blocked)
+}
+
+/// Adds [`BLOCKING_CSP`] to `html` where browsers honour it: at the
+/// start of the `<head>`, otherwise after the doctype, otherwise
+/// first. Before the doctype it would switch pages to quirks mode.
+fn with_csp(html: &str) -> String {
+    let head = Regex::new(r"(?i)<head\b[^>]*>").unwrap();
+    let doctype = Regex::new(r"(?i)^\s*<!doctype\b[^>]*>").unwrap();
+
+    let at = match head.find(html).or_else(|| doctype.find(html)) {
+        Some(tag) => tag.end(),
+        None => 0,
+    };
+
+    format!("{}{BLOCKING_CSP}{}", &html[..at], &html[at..])
+}
+
+#[cfg(test)]
+mod tests {
+    use mail_parser::MessageParser;
+
+    use super::*;
+
+    #[test]
+    fn inlines_cid_images_and_blocks_remote_ones() {
+        let raw = concat!(
+            "Content-Type: multipart/related; boundary=b\r\n\r\n",
+            "--b\r\nContent-Type: text/html\r\n\r\n",
+            "<img src=\"cid:logo@x\"><img SRC='https://t.example/p.gif'>\r\n",
+            "--b\r\nContent-Type: image/png\r\nContent-ID: <logo@x>\r\n\r\n",
+            "png\r\n--b--\r\n",
+        );
+        let message = MessageParser::new().parse(raw.as_bytes()).unwrap();
+        let html = message.html_part(0).unwrap().text_contents().unwrap();
+
+        let (html, blocked) = block_remote(&inline_cid(html, &message));
+
+        assert_eq!(blocked, 1);
+        assert_eq!(
+            html.trim_end(),
+            format!(
+                "{BLOCKING_CSP}<img src=\"data:image/png;base64,cG5n\">\
+                 <img data-blocked-src='https://t.example/p.gif'>"
+            )
+        );
+    }
+
+    #[test]
+    fn places_csp_in_head_or_after_doctype() {
+        let (html, _) = block_remote("<!DOCTYPE html><html><HEAD lang=en><title>t</title></HEAD>");
+        assert_eq!(
+            html,
+            format!("<!DOCTYPE html><html><HEAD lang=en>{BLOCKING_CSP}<title>t</title></HEAD>")
+        );
+
+        let (html, _) = block_remote("\n<!doctype html>\n<p>hi</p>");
+        assert_eq!(html, format!("\n<!doctype html>{BLOCKING_CSP}\n<p>hi</p>"));
+
+        let (html, _) = block_remote("<p>hi</p><header>x</header>");
+        assert_eq!(html, format!("{BLOCKING_CSP}<p>hi</p><header>x</header>"));
+    }
+
+    #[test]
+    fn blocks_remote_css() {
+        let html = concat!(
+            "<link rel=stylesheet href=\"https://x.example/a.css\">",
+            "<style>@import url(\"//x.example/b.css\"); ",
+            "p { background: url('https://x.example/c.png') }</style>",
+            "<td background=\"http://x.example/d.png\" style=\"color: red\">",
+        );
+
+        let (html, blocked) = block_remote(html);
+
+        assert_eq!(blocked, 4);
+        assert_eq!(
+            html.strip_prefix(BLOCKING_CSP).unwrap(),
+            concat!(
+                "<link rel=stylesheet data-blocked-href=\"https://x.example/a.css\">",
+                "<style> p { background: url() }</style>",
+                "<td data-blocked-background=\"http://x.example/d.png\" style=\"color: red\">",
+            )
+        );
+    }
+}
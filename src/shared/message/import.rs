@@ -0,0 +1,235 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use io_email::flag::types::{Flag, IanaFlag};
+use log::warn;
+use mail_parser::MessageParser;
+use pimalaya_cli::printer::{Message, Printer};
+
+use crate::account::context::Account;
+use crate::shared::{client::EmailClient, mailbox::arg::MailboxArg};
+
+/// Import the messages of a local file into a mailbox.
+///
+/// The counterpart of `message export --format mbox`: every message
+/// of the mbox file is added to the mailbox, with the seen, answered,
+/// flagged and draft flags kept in its `Status` and `X-Status`
+/// headers. Messages which cannot be imported are skipped with a
+/// warning, the import goes on with the next one.
+#[derive(Debug, Parser)]
+pub struct MessageImportCommand {
+    #[command(flatten)]
+    pub mailbox: MailboxArg,
+
+    /// Format of the file to import.
+    #[arg(long, short = 'f', value_name = "FORMAT", default_value = "mbox")]
+    pub format: ImportFormat,
+
+    /// Path of the file to import.
+    #[arg(value_name = "PATH")]
+    pub path: PathBuf,
+}
+
+/// Layout messages are imported from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ImportFormat {
+    /// Every message in a single mbox file.
+    Mbox,
+}
+
+impl MessageImportCommand {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        account: &mut Account,
+        client: &mut EmailClient,
+    ) -> Result<()> {
+        let mailbox = self.mailbox.resolve(account)?;
+
+        let file = File::open(&self.path)
+            .with_context(|| format!("Open `{}` error", self.path.display()))?;
+
+        let mut imported = 0;
+        let mut skipped = 0;
+
+        match self.format {
+            ImportFormat::Mbox => {
+                let mut mbox = MboxReader::new(BufReader::new(file));
+
+                while let Some(entry) = mbox
+                    .next_entry()
+                    .with_context(|| format!("Read `{}` error", self.path.display()))?
+                {
+                    let n = imported + skipped + 1;
+                    let (raw, flags) = parse_mbox_entry(&entry);
+
+                    if MessageParser::new().parse_headers(&raw).is_none() {
+                        warn!("skipping malformed message #{n} of the mbox");
+                        skipped += 1;
+                        continue;
+                    }
+
+                    match client.add_message(&mailbox, &flags, raw) {
+                        Ok(_) => imported += 1,
+                        Err(err) => {
+                            warn!("skipping message #{n} of the mbox: {err:#}");
+                            skipped += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        let skipped = match skipped {
+            0 => String::new(),
+            n => format!(" ({n} skipped)"),
+        };
+
+        printer.out(Message::new(format!(
+            "Imported {imported} message(s) to {mailbox}{skipped}"
+        )))
+    }
+}
+
+/// Reads the entries of an mbox file one at a time.
+struct MboxReader<R> {
+    reader: R,
+    /// Whether the `From ` line of the current entry was read.
+    started: bool,
+}
+
+impl<R: BufRead> MboxReader<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            started: false,
+        }
+    }
+
+    /// Next entry, without its `From ` line, `None` at the end of the
+    /// file. Anything before the first `From ` line is ignored.
+    fn next_entry(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut entry = Vec::new();
+        let mut line = Vec::new();
+
+        loop {
+            line.clear();
+
+            if self.reader.read_until(b'\n', &mut line)? == 0 {
+                let started = self.started;
+                self.started = false;
+                return Ok(started.then_some(entry));
+            }
+
+            // NOTE: a `From ` line only separates entries after an
+            // empty line, which keeps unquoted body lines (mboxo) in
+            // their message
+            let separator = line.starts_with(b"From ")
+                && (entry.is_empty() || entry.ends_with(b"\n\n") || entry.ends_with(b"\r\n\r\n"));
+
+            match (separator, self.started) {
+                (true, true) => return Ok(Some(entry)),
+                (true, false) => self.started = true,
+                (false, true) => entry.extend_from_slice(&line),
+                (false, false) => (),
+            }
+        }
+    }
+}
+
+/// Message of the mbox `entry` with CRLF line endings, without its
+/// `Status` and `X-Status` headers but with the flags they carry,
+/// and with the `>` quoting `From ` body lines (mboxrd) removed.
+fn parse_mbox_entry(entry: &[u8]) -> (Vec<u8>, Vec<Flag>) {
+    let mut raw = Vec::with_capacity(entry.len() + entry.len() / 32);
+    let mut flags = Vec::new();
+    let mut in_headers = true;
+
+    // NOTE: the empty line separating entries is not part of the
+    // message
+    let entry = entry.strip_suffix(b"\n").unwrap_or(entry);
+    let entry = entry.strip_suffix(b"\r").unwrap_or(entry);
+
+    for line in entry.split_inclusive(|b| *b == b'\n') {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let mut line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        if in_headers {
+            if line.is_empty() {
+                in_headers = false;
+            } else if let Some(status) = header_value(line, b"status") {
+                if status.contains(&b'R') {
+                    flags.push(Flag::from_iana(IanaFlag::Seen));
+                }
+                continue;
+            } else if let Some(status) = header_value(line, b"x-status") {
+                for (c, flag) in [
+                    (b'A', IanaFlag::Answered),
+                    (b'F', IanaFlag::Flagged),
+                    (b'T', IanaFlag::Draft),
+                ] {
+                    if status.contains(&c) {
+                        flags.push(Flag::from_iana(flag));
+                    }
+                }
+                continue;
+            }
+        } else if line.first() == Some(&b'>')
+            && line[line.iter().take_while(|b| **b == b'>').count()..].starts_with(b"From ")
+        {
+            line = &line[1..];
+        }
+
+        raw.extend_from_slice(line);
+        raw.extend_from_slice(b"\r\n");
+    }
+
+    (raw, flags)
+}
+
+/// Value of the header `line` when it is named `name` (lowercase).
+fn header_value<'a>(line: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    let (key, value) = line.split_at_checked(name.len())?;
+
+    if !key.eq_ignore_ascii_case(name) {
+        return None;
+    }
+
+    value.strip_prefix(b":")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_mboxrd_entries() {
+        let mbox = b"From a@b Mon Jan  1 00:00:00 2024\n\
+            Subject: one\nStatus: RO\nX-Status: F\n\n>From me\nFrom you\n\n\
+            From a@b Mon Jan  1 00:00:00 2024\nSubject: two\n\nbody\n\n";
+        let mut reader = MboxReader::new(&mbox[..]);
+
+        let entry = reader.next_entry().unwrap().unwrap();
+        let (raw, flags) = parse_mbox_entry(&entry);
+        assert_eq!(raw, b"Subject: one\r\n\r\nFrom me\r\nFrom you\r\n");
+        assert_eq!(
+            flags,
+            [
+                Flag::from_iana(IanaFlag::Seen),
+                Flag::from_iana(IanaFlag::Flagged)
+            ]
+        );
+
+        let entry = reader.next_entry().unwrap().unwrap();
+        let (raw, flags) = parse_mbox_entry(&entry);
+        assert_eq!(raw, b"Subject: two\r\n\r\nbody\r\n");
+        assert!(flags.is_empty());
+
+        assert!(reader.next_entry().unwrap().is_none());
+    }
+}
@@ -0,0 +1,177 @@
+use std::{io::Write, process::Stdio};
+
+use anyhow::{Context, Result, bail};
+use clap::Parser;
+use pimalaya_cli::printer::{Message, Printer};
+
+use crate::account::context::Account;
+use crate::dry_run::{self, DryRun};
+use crate::shared::{
+    client::EmailClient, flag::arg::MessageIdsArg, mailbox::arg::MailboxArg,
+    message::undo::Operation, shell,
+};
+
+/// Alias, and fallback name, of the mailbox junk messages go to.
+const JUNK_MAILBOX: &str = "Junk";
+
+/// Mark message(s) as junk: train the spam filter, then move them to
+/// the junk mailbox.
+///
+/// The junk mailbox is the one bound to the `junk` alias, `Junk`
+/// when unset. When `junk.spam-command` is configured (e.g.
+/// `sa-learn --spam`), it is run once per message with the raw
+/// message piped to its stdin before anything is moved; a failing
+/// run aborts the command. `message undo` moves the messages back,
+/// but does not untrain the filter.
+#[derive(Debug, Parser)]
+pub struct MessageJunkCommand {
+    /// Mailbox the messages are in. Defaults to the mailbox bound to
+    /// the `inbox` alias.
+    #[arg(short = 'm', long = "mailbox", value_name = "NAME")]
+    pub mailbox: Option<String>,
+
+    #[command(flatten)]
+    pub ids: MessageIdsArg,
+
+    /// Only move the messages, without running the training command.
+    #[arg(long)]
+    pub no_train: bool,
+}
+
+impl MessageJunkCommand {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        account: &mut Account,
+        client: &mut EmailClient,
+        account_name: &str,
+    ) -> Result<()> {
+        let from = match &self.mailbox {
+            Some(name) => MailboxArg::resolve_name(account, name)?,
+            None => inbox(account),
+        };
+        let to = account.resolve_mailbox(JUNK_MAILBOX).to_owned();
+        let train = account
+            .junk_spam_command
+            .as_deref()
+            .filter(|_| !self.no_train);
+
+        if dry_run::enabled() {
+            let action = format!("mark as junk and move to {to}");
+            return printer.out(DryRun::new(action, &from, &self.ids.inner));
+        }
+
+        classify(client, &from, &to, &self.ids.inner, train)?.record(account_name);
+
+        printer.out(Message::new(format!(
+            "Message(s) successfully marked as junk and moved to {to}"
+        )))
+    }
+}
+
+/// Mark message(s) as not junk: train the spam filter, then move them
+/// back to the inbox.
+///
+/// The messages are taken from the junk mailbox (the one bound to the
+/// `junk` alias, `Junk` when unset) unless `--mailbox` is given. When
+/// `junk.ham-command` is configured (e.g. `sa-learn --ham`), it is
+/// run once per message with the raw message piped to its stdin
+/// before anything is moved.
+#[derive(Debug, Parser)]
+pub struct MessageNotJunkCommand {
+    /// Mailbox the messages are in. Defaults to the junk mailbox.
+    #[arg(short = 'm', long = "mailbox", value_name = "NAME")]
+    pub mailbox: Option<String>,
+
+    #[command(flatten)]
+    pub ids: MessageIdsArg,
+
+    /// Only move the messages, without running the training command.
+    #[arg(long)]
+    pub no_train: bool,
+}
+
+impl MessageNotJunkCommand {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        account: &mut Account,
+        client: &mut EmailClient,
+        account_name: &str,
+    ) -> Result<()> {
+        let name = self.mailbox.as_deref().unwrap_or(JUNK_MAILBOX);
+        let from = MailboxArg::resolve_name(account, name)?;
+        let to = inbox(account);
+        let train = account
+            .junk_ham_command
+            .as_deref()
+            .filter(|_| !self.no_train);
+
+        if dry_run::enabled() {
+            let action = format!("mark as not junk and move to {to}");
+            return printer.out(DryRun::new(action, &from, &self.ids.inner));
+        }
+
+        classify(client, &from, &to, &self.ids.inner, train)?.record(account_name);
+
+        printer.out(Message::new(format!(
+            "Message(s) successfully marked as not junk and moved to {to}"
+        )))
+    }
+}
+
+/// Id of the inbox: the `inbox` alias target, `INBOX` when unset.
+fn inbox(account: &Account) -> String {
+    account.default_mailbox().unwrap_or("INBOX").to_owned()
+}
+
+/// Pipes each message of `from` to the `train` command, if any, then
+/// moves them all to `to`. Returns the move, to record for undo.
+fn classify(
+    client: &mut EmailClient,
+    from: &str,
+    to: &str,
+    ids: &[String],
+    train: Option<&str>,
+) -> Result<Operation> {
+    if from == to {
+        bail!("Messages are already in mailbox {to}");
+    }
+
+    if let Some(cmd) = train {
+        for id in ids {
+            let raw = client.get_message(from, id)?;
+            run_training(cmd, &raw)
+                .with_context(|| format!("Train filter with message {id} error"))?;
+        }
+    }
+
+    let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+    Operation::move_messages(client, from, to, &ids)
+}
+
+/// Runs `cmd` through the system shell with `raw` on its stdin, and
+/// bails when it exits unsuccessfully.
+fn run_training(cmd: &str, raw: &[u8]) -> Result<()> {
+    let mut child = shell::command(cmd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Spawn `{cmd}` error"))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(raw)
+            .context("Write message to stdin error")?;
+    }
+
+    let status = child
+        .wait()
+        .with_context(|| format!("Wait for `{cmd}` error"))?;
+
+    if !status.success() {
+        bail!("Command `{cmd}` failed with {status}");
+    }
+
+    Ok(())
+}
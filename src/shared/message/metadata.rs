@@ -0,0 +1,326 @@
+//! Delivery metadata of a message: the `Received` path and the
+//! `Authentication-Results` verdicts (RFC 5321 §4.4, RFC 8601).
+//!
+//! Headers are read straight from the raw header block rather than
+//! through `mail_parser`, so that odd-looking trace headers (missing
+//! clauses, non-standard comments) still show up as they were sent.
+
+use std::fmt;
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Serialize, Serializer};
+
+use crate::config::TimezoneConfig;
+
+/// Methods always reported, even when absent from the headers.
+const AUTH_METHODS: [&str; 3] = ["spf", "dkim", "dmarc"];
+
+/// Compact delivery summary of a message.
+#[derive(Debug, Default, Serialize)]
+pub struct MessageMetadata {
+    /// Relays the message went through, from the origin to the
+    /// final recipient (the reverse of the header order).
+    pub hops: Vec<Hop>,
+    /// Seconds between the first and the last dated hop.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency: Option<i64>,
+    /// Authentication verdicts, SPF, DKIM and DMARC first.
+    pub auth: Vec<AuthResult>,
+    /// Timezone the hop dates are rendered in.
+    #[serde(skip)]
+    pub timezone: TimezoneConfig,
+}
+
+/// One `Received` header.
+#[derive(Debug, Default, Serialize)]
+pub struct Hop {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "serialize_date")]
+    pub date: Option<DateTime<FixedOffset>>,
+}
+
+fn serialize_date<S: Serializer>(
+    date: &Option<DateTime<FixedOffset>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match date {
+        Some(date) => serializer.serialize_some(&date.to_rfc3339()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// One authentication method verdict, `None` meaning the message
+/// carries no result for it.
+#[derive(Debug, Serialize)]
+pub struct AuthResult {
+    pub method: String,
+    pub result: Option<String>,
+}
+
+impl MessageMetadata {
+    /// Extracts the delivery metadata from the header block of
+    /// `raw`.
+    pub fn from_raw(raw: &[u8]) -> Self {
+        let headers = unfold_headers(raw);
+
+        let mut hops: Vec<Hop> = headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("Received"))
+            .map(|(_, value)| parse_received(value))
+            .collect();
+        hops.reverse();
+
+        let mut dates = hops.iter().filter_map(|hop| hop.date);
+        let latency = match (dates.next(), dates.last()) {
+            (Some(first), Some(last)) => Some((last - first).num_seconds()),
+            _ => None,
+        };
+
+        let mut auth: Vec<AuthResult> = AUTH_METHODS
+            .iter()
+            .map(|method| AuthResult {
+                method: method.to_string(),
+                result: None,
+            })
+            .collect();
+
+        // NOTE: the topmost header was added by the receiving server,
+        // the one to trust, so only the first verdict per method is
+        // kept
+        for (name, value) in &headers {
+            if name.eq_ignore_ascii_case("Authentication-Results") {
+                for (method, result) in parse_auth_results(value) {
+                    set_auth_result(&mut auth, method, result);
+                }
+            } else if name.eq_ignore_ascii_case("Received-SPF") {
+                if let Some(result) = value.split_whitespace().next() {
+                    set_auth_result(&mut auth, "spf".into(), result.to_lowercase());
+                }
+            }
+        }
+
+        Self {
+            hops,
+            latency,
+            auth,
+            timezone: TimezoneConfig::default(),
+        }
+    }
+}
+
+fn set_auth_result(auth: &mut Vec<AuthResult>, method: String, result: String) {
+    match auth.iter_mut().find(|r| r.method == method) {
+        Some(AuthResult { result: found, .. }) => {
+            found.get_or_insert(result);
+        }
+        None => auth.push(AuthResult {
+            method,
+            result: Some(result),
+        }),
+    }
+}
+
+/// Header fields of `raw` in order, continuation lines unfolded.
+fn unfold_headers(raw: &[u8]) -> Vec<(String, String)> {
+    let text = String::from_utf8_lossy(raw);
+    let mut headers: Vec<(String, String)> = Vec::new();
+
+    for line in text.lines() {
+        if line.is_empty() {
+            break;
+        }
+
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_owned(), value.trim().to_owned()));
+        }
+    }
+
+    headers
+}
+
+/// Parses `from <host> … by <host> …; <date>`.
+fn parse_received(value: &str) -> Hop {
+    let (clauses, date) = match value.rsplit_once(';') {
+        Some((clauses, date)) => (clauses, parse_date(date)),
+        None => (value, None),
+    };
+
+    let mut hop = Hop {
+        date,
+        ..Default::default()
+    };
+
+    let mut words = clauses.split_whitespace();
+
+    while let Some(word) = words.next() {
+        let slot = if word.eq_ignore_ascii_case("from") {
+            &mut hop.from
+        } else if word.eq_ignore_ascii_case("by") {
+            &mut hop.by
+        } else {
+            continue;
+        };
+
+        if slot.is_none() {
+            *slot = words.next().map(str::to_owned);
+        }
+    }
+
+    hop
+}
+
+/// Parses an RFC 5322 date, ignoring a trailing comment such as
+/// `(UTC)` or `(PST)`.
+fn parse_date(date: &str) -> Option<DateTime<FixedOffset>> {
+    let date = match date.find('(') {
+        Some(i) => &date[..i],
+        None => date,
+    };
+
+    DateTime::parse_from_rfc2822(date.trim()).ok()
+}
+
+/// Parses `authserv-id; method=result …; method=result …` into
+/// lowercased `(method, result)` pairs.
+fn parse_auth_results(value: &str) -> Vec<(String, String)> {
+    value
+        .split(';')
+        .skip(1)
+        .filter_map(|resinfo| {
+            let (method, rest) = resinfo.trim().split_once('=')?;
+            let result = rest.split(|c: char| c.is_whitespace() || c == '(').next()?;
+            let method = method.trim().to_lowercase();
+
+            // NOTE: the method may carry a version, as in `dkim/1`
+            let method = match method.split_once('/') {
+                Some((method, _)) => method.to_owned(),
+                None => method,
+            };
+
+            Some((method, result.to_lowercase()))
+        })
+        .collect()
+}
+
+impl fmt::Display for MessageMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Hops: {}", self.hops.len())?;
+
+        match self.latency {
+            Some(secs) => writeln!(f, "Delivery latency: {}", format_duration(secs))?,
+            None => writeln!(f, "Delivery latency: unknown")?,
+        }
+
+        if self.hops.is_empty() {
+            writeln!(f, "Path: no Received header")?;
+        } else {
+            writeln!(f, "Path:")?;
+        }
+
+        let mut prev = None;
+
+        for (i, hop) in self.hops.iter().enumerate() {
+            let from = hop.from.as_deref().unwrap_or("?");
+            let by = hop.by.as_deref().unwrap_or("?");
+            write!(f, "  {}. {from} → {by}", i + 1)?;
+
+            if let Some(date) = hop.date {
+                write!(f, " at {}", self.timezone.format(date, "%F %T%:z"))?;
+                if let Some(prev) = prev {
+                    write!(f, " (+{})", format_duration((date - prev).num_seconds()))?;
+                }
+                prev = Some(date);
+            }
+
+            writeln!(f)?;
+        }
+
+        writeln!(f, "Authentication:")?;
+
+        for auth in &self.auth {
+            let result = auth.result.as_deref().unwrap_or("none (no result)");
+            writeln!(f, "  {}: {result}", auth.method.to_uppercase())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn format_duration(secs: i64) -> String {
+    let sign = if secs < 0 { "-" } else { "" };
+    let secs = secs.unsigned_abs();
+
+    match secs {
+        0..60 => format!("{sign}{secs}s"),
+        60..3600 => format!("{sign}{}m {}s", secs / 60, secs % 60),
+        _ => format!("{sign}{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAW: &str = "\
+Received: by 2002:a05:6a10:1234 with SMTP id abc;
+        Tue, 12 Mar 2024 09:00:05 -0700 (PDT)
+Received: from mail.example.org (mail.example.org [192.0.2.1])
+        by mx.google.com with ESMTPS id xyz
+        for <bob@gmail.com>; Tue, 12 Mar 2024 16:00:02 +0000 (UTC)
+Authentication-Results: mx.google.com;
+       dkim=pass header.i=@example.org header.s=s1;
+       spf=softfail (google.com: domain of alice@example.org) smtp.mailfrom=alice@example.org;
+       dmarc=fail (p=NONE sp=NONE dis=NONE) header.from=example.org
+Authentication-Results: spoofed.example; spf=pass
+From: alice@example.org
+Subject: Hello
+
+Body
+";
+
+    #[test]
+    fn summarizes_delivery() {
+        let metadata = MessageMetadata::from_raw(RAW.as_bytes());
+
+        assert_eq!(metadata.hops.len(), 2);
+        assert_eq!(metadata.hops[0].from.as_deref(), Some("mail.example.org"));
+        assert_eq!(metadata.hops[0].by.as_deref(), Some("mx.google.com"));
+        assert_eq!(metadata.hops[1].from, None);
+        assert_eq!(metadata.latency, Some(3));
+
+        let auth: Vec<(&str, Option<&str>)> = metadata
+            .auth
+            .iter()
+            .map(|auth| (auth.method.as_str(), auth.result.as_deref()))
+            .collect();
+        assert_eq!(
+            auth,
+            [
+                ("spf", Some("softfail")),
+                ("dkim", Some("pass")),
+                ("dmarc", Some("fail")),
+            ]
+        );
+    }
+
+    #[test]
+    fn falls_back_without_trace_headers() {
+        let metadata = MessageMetadata::from_raw(b"Subject: Hi\r\n\r\nBody\r\n");
+
+        assert!(metadata.hops.is_empty());
+        assert_eq!(metadata.latency, None);
+        assert!(metadata.auth.iter().all(|auth| auth.result.is_none()));
+    }
+}
@@ -1,12 +1,26 @@
 pub mod add;
 pub mod arg;
 pub mod builder;
+pub mod charset;
 pub mod cli;
 pub mod compose;
 pub mod copy;
+pub mod dedupe;
+pub mod export;
+pub mod flowed;
 pub mod forward;
 pub mod handler;
+pub mod html;
+pub mod import;
+pub mod junk;
+pub mod metadata;
 pub mod mv;
 pub mod read;
+pub mod receipt;
+pub mod redecode;
 pub mod reply;
 pub mod send;
+pub mod spellcheck;
+pub mod structure;
+pub mod undo;
+pub mod unsubscribe;
@@ -1,9 +1,18 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
 use clap::Parser;
 use pimalaya_cli::printer::{Message, Printer};
 
 use crate::account::context::Account;
-use crate::shared::{client::EmailClient, flag::arg::MessageIdsArg};
+use crate::backend::Backend;
+use crate::dry_run::{self, DryRun};
+use crate::shared::{
+    client::{EmailClient, build_email_client},
+    flag::arg::MessageIdsArg,
+    mailbox::arg::MailboxArg,
+    message::{copy::copy_across_accounts, undo::Operation},
+};
 
 /// Move message(s) from one mailbox to another within the active
 /// account.
@@ -12,8 +21,17 @@ use crate::shared::{client::EmailClient, flag::arg::MessageIdsArg};
 /// `[mailbox.alias]` map before the backend call. IMAP uses
 /// `UID MOVE` (RFC 6851); JMAP uses `Email/set` patches that remove
 /// the source and add the destination from each email's
-/// `mailboxIds`; Maildir renames the underlying file. Cross-account
-/// / cross-backend move is out of scope.
+/// `mailboxIds`; Maildir renames the underlying file, which keeps its
+/// flags.
+///
+/// Pass `--to-account <name>` to move into a mailbox of another
+/// account instead: each message is added to the destination account
+/// with its flags, then the originals are moved to the trash mailbox
+/// of the active account. Nothing is removed from the source unless
+/// every message was added; running the move again after a failure
+/// skips the messages already added.
+///
+/// A move within the account can be reverted with `message undo`.
 #[derive(Debug, Parser)]
 pub struct MessageMoveCommand {
     #[command(flatten)]
@@ -32,6 +50,19 @@ pub struct MessageMoveCommand {
     /// Destination mailbox name or alias. Mandatory.
     #[arg(long = "to", short = 't', value_name = "NAME")]
     pub to: String,
+
+    /// Move into the `--to` mailbox of this account (resolved through
+    /// its own aliases) instead of the active one.
+    #[arg(long = "to-account", value_name = "ACCOUNT")]
+    pub to_account: Option<String>,
+
+    /// Mailbox name or alias of the active account the originals are
+    /// moved to once copied to `--to-account`, the shared API cannot
+    /// delete messages outright. Defaults to the mailbox bound to the
+    /// `trash` alias, `Trash` when unset.
+    #[arg(long, value_name = "NAME")]
+    #[arg(requires = "to_account")]
+    pub trash: Option<String>,
 }
 
 impl MessageMoveCommand {
@@ -40,11 +71,48 @@ impl MessageMoveCommand {
         printer: &mut impl Printer,
         account: &mut Account,
         client: &mut EmailClient,
+        account_name: &str,
+        config_paths: &[PathBuf],
+        backend: Backend,
     ) -> Result<()> {
-        let from = account.resolve_mailbox(&self.from).to_owned();
-        let to = account.resolve_mailbox(&self.to).to_owned();
+        let from = MailboxArg::resolve_name(account, &self.from)?;
         let ids: Vec<&str> = self.ids.inner.iter().map(String::as_str).collect();
-        client.move_messages(&from, &to, &ids)?;
+
+        if let Some(name) = &self.to_account {
+            if dry_run::enabled() {
+                let action = format!("move to {} of account {name}", self.to);
+                return printer.out(DryRun::new(action, &from, &self.ids.inner));
+            }
+
+            let (target, mut target_client) =
+                build_email_client(config_paths, Some(name), backend)?;
+            let to = MailboxArg::resolve_name(&target, &self.to)?;
+            copy_across_accounts(
+                (client, account_name, &from),
+                &ids,
+                (&mut target_client, name, &to),
+            )?;
+
+            let trash = match &self.trash {
+                Some(name) => MailboxArg::resolve_name(account, name)?,
+                None => account.trash_mailbox().to_owned(),
+            };
+            client.move_messages(&from, &trash, &ids)?;
+            Operation::irreversible(format!("move to account {name}")).record(account_name);
+
+            return printer.out(Message::new(format!(
+                "Message(s) successfully moved to account {name}"
+            )));
+        }
+
+        let to = MailboxArg::resolve_name(account, &self.to)?;
+
+        if dry_run::enabled() {
+            return printer.out(DryRun::new(format!("move to {to}"), &from, &self.ids.inner));
+        }
+
+        let operation = Operation::move_messages(client, &from, &to, &ids)?;
+        operation.record(account_name);
         printer.out(Message::new("Message(s) successfully moved"))
     }
 }
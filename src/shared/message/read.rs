@@ -1,24 +1,79 @@
 use std::{
     fmt,
-    io::{Write, stdout},
+    io::{IsTerminal, Write, stdout},
+    thread,
+    time::Duration,
 };
 
 use anyhow::{Result, bail};
 use clap::Parser;
-use mail_parser::{Addr, Address, HeaderValue, Message, MessageParser};
+use encoding_rs::Encoding;
+use log::warn;
+use mail_parser::{Addr, Address, HeaderValue, Message, MessageParser, MimeHeaders};
 use pimalaya_cli::printer::Printer;
 use serde::Serialize;
 
 use crate::account::context::Account;
-use crate::shared::{client::EmailClient, mailbox::arg::MailboxArg};
+use crate::profile;
+use crate::shared::{
+    client::EmailClient,
+    mailbox::arg::MailboxArg,
+    message::{
+        charset::{self, CharsetReport},
+        flowed, html,
+        metadata::MessageMetadata,
+        receipt::ReceiptRequest,
+        redecode,
+        structure::MimePart,
+    },
+    pager,
+};
 
 /// Read a message from the active account (built-in flag reader).
 ///
-/// Fetches the message and renders headers + text bodies. Pass
-/// `--raw` to dump the original RFC 5322 bytes to stdout instead,
-/// or `--json` to emit the parsed message as JSON. For a custom
-/// pretty-printer (`mml interpret`, w3m, your own viewer), pipe the
-/// `--raw` output into the renderer of your choice.
+/// Fetches the message and renders headers + text bodies. Plain text
+/// parts sent as `format=flowed` (RFC 3676) are reflowed: soft line
+/// breaks are joined and quote depths kept apart. Pass `--raw` to
+/// dump the original RFC 5322 bytes to stdout instead, or `--json` to
+/// emit the parsed message as JSON. For a custom pretty-printer
+/// (`mml interpret`, w3m, your own viewer), pipe the `--raw` output
+/// into the renderer of your choice.
+///
+/// When stdout is a terminal, the rendered message is piped through a
+/// pager: `--pager`, then `read.pager` from the configuration, then
+/// `$PAGER`, then `less -R`. Piped or redirected output, `--raw` and
+/// `--json` are never paged.
+///
+/// Pass `--metadata` to print a delivery summary instead of the
+/// message: the relay path from the `Received` headers with hop
+/// count and delivery latency, and the SPF / DKIM / DMARC verdicts
+/// from `Authentication-Results`. Pass `--show-structure` to print
+/// the MIME part tree instead, to debug how a multipart message is
+/// put together without leaking its content. Pass `--headers-only`
+/// to print the raw headers alone.
+///
+/// Pass `--open` to render the HTML part in the default browser
+/// instead, with its inline images embedded. Remote resources are
+/// blocked unless `--allow-remote` is given, so that opening the
+/// message does not notify its sender.
+///
+/// Text parts are decoded with their declared charset, unless
+/// `--charset` or a `read.charsets` entry matching the sender forces
+/// another one. Text parts still looking quoted-printable or base64
+/// encoded once decoded, because their transfer encoding is
+/// mislabeled, are decoded again (with a warning). Pass
+/// `--part-charset-report` to compare, for each text part, the
+/// charset it is decoded with to the one detected from its bytes,
+/// when the text comes out garbled.
+///
+/// When the message asks for a read receipt, one is sent back once
+/// the message is rendered, according to `read.send-read-receipts`
+/// (never by default).
+///
+/// Pass `--follow` to keep polling the message every `--interval`
+/// seconds and print what gets appended to it, like `tail -f`, for
+/// log-style messages still being written (e.g. a Maildir file).
+/// Runs until interrupted.
 #[derive(Debug, Parser)]
 pub struct MessageReadCommand {
     /// Identifier of the message (IMAP UID, JMAP email id, or Maildir
@@ -33,6 +88,70 @@ pub struct MessageReadCommand {
     /// the global `--json` flag.
     #[arg(long)]
     pub raw: bool,
+
+    /// Print the delivery path and authentication results instead of
+    /// the message.
+    #[arg(long, conflicts_with = "raw")]
+    pub metadata: bool,
+
+    /// Print the MIME part tree (types, charsets, encodings,
+    /// boundaries, sizes) instead of the message, without any part
+    /// content, so it can be shared safely.
+    #[arg(long, conflicts_with_all = ["raw", "metadata"])]
+    pub show_structure: bool,
+
+    /// Print the raw header section only, without downloading the
+    /// body when the backend can fetch headers alone (IMAP
+    /// `BODY.PEEK[HEADER]`). Other backends fetch the whole message
+    /// and print its headers. Mutually exclusive with the global
+    /// `--json` flag.
+    #[arg(long, conflicts_with_all = ["raw", "metadata", "show_structure"])]
+    pub headers_only: bool,
+
+    /// Decode the text parts with this charset (e.g. `latin1`,
+    /// `windows-1252`) instead of the declared one, for mislabeled
+    /// messages. Overrides `read.charsets`.
+    #[arg(long, value_name = "CHARSET")]
+    pub charset: Option<String>,
+
+    /// Print, for each text part, its transfer encoding, declared
+    /// charset, the charset it is decoded with (see --charset), the
+    /// charset detected from its bytes, and whether they disagree,
+    /// instead of the message.
+    #[arg(long, conflicts_with_all = ["raw", "metadata", "show_structure", "headers_only"])]
+    pub part_charset_report: bool,
+
+    /// Pager command to pipe the rendered message through, overriding
+    /// `read.pager`.
+    #[arg(long, value_name = "CMD", conflicts_with = "no_pager")]
+    pub pager: Option<String>,
+
+    /// Print the rendered message directly, even on a terminal.
+    #[arg(long)]
+    pub no_pager: bool,
+
+    /// Open the HTML part in the default browser instead of printing
+    /// the message.
+    #[arg(long, conflicts_with_all = ["raw", "metadata", "show_structure", "headers_only"])]
+    #[arg(conflicts_with = "part_charset_report")]
+    pub open: bool,
+
+    /// Load the remote resources (images, stylesheets, fonts) of the
+    /// HTML part opened with --open.
+    #[arg(long, requires = "open")]
+    pub allow_remote: bool,
+
+    /// Print the message, then keep fetching it again and print the
+    /// content appended since, until interrupted. The message is
+    /// printed again in full when it changes otherwise. Never paged.
+    #[arg(long, conflicts_with_all = ["metadata", "show_structure", "headers_only"])]
+    #[arg(conflicts_with_all = ["part_charset_report", "open"])]
+    pub follow: bool,
+
+    /// Seconds between two fetches of --follow.
+    #[arg(long, value_name = "SECS", default_value = "5", requires = "follow")]
+    #[arg(value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
 }
 
 impl MessageReadCommand {
@@ -46,8 +165,27 @@ impl MessageReadCommand {
             bail!("`--raw` and `--json` cannot be combined");
         }
 
+        if self.headers_only && printer.is_json() {
+            bail!("`--headers-only` and `--json` cannot be combined");
+        }
+
+        if self.follow && printer.is_json() {
+            bail!("`--follow` and `--json` cannot be combined");
+        }
+
         let mailbox = self.mailbox.resolve(account)?;
-        let raw = client.get_message(&mailbox, &self.id)?;
+
+        if self.follow {
+            return self.follow(account, client, &mailbox);
+        }
+
+        if self.headers_only {
+            let headers = profile::time("fetch", || fetch_headers(client, &mailbox, &self.id))?;
+            let mut out = stdout().lock();
+            out.write_all(&headers)?;
+            return Ok(());
+        }
+        let raw = profile::time("fetch", || client.get_message(&mailbox, &self.id))?;
 
         if self.raw {
             let mut out = stdout().lock();
@@ -55,18 +193,128 @@ impl MessageReadCommand {
             return Ok(());
         }
 
+        if self.metadata {
+            let mut metadata = MessageMetadata::from_raw(&raw);
+            metadata.timezone = account.datetime_tz();
+            return printer.out(metadata);
+        }
+
         let Some(parsed) = MessageParser::new().parse(&raw) else {
             bail!("Failed to parse RFC 5322 message");
         };
 
-        printer.out(MessageView(parsed.into_owned()))
+        if self.show_structure {
+            let Some(mut structure) = MimePart::from_message(&parsed) else {
+                bail!("Message has no MIME part");
+            };
+            structure.ascii = account.tree_ascii();
+            return printer.out(structure);
+        }
+
+        if self.open {
+            return html::open(&parsed, &self.id, self.allow_remote);
+        }
+
+        if self.part_charset_report {
+            let charset = self.forced_charset(account, &parsed);
+            let report = CharsetReport::new(&parsed, charset, account.table_preset().to_string());
+            return printer.out(report);
+        }
+
+        let receipt = ReceiptRequest::from_message(&parsed).filter(|_| !printer.is_json());
+        let view = self.view(account, parsed)?;
+
+        let pager = match self.pager {
+            _ if self.no_pager || printer.is_json() || !stdout().is_terminal() => None,
+            Some(pager) => Some(pager),
+            None => account.read_pager(),
+        };
+
+        // NOTE: only the rendering is timed, not the time spent
+        // reading in the pager
+        match pager {
+            Some(pager) => {
+                let text = profile::time("render", || format!("{view}\n"));
+                pager::page(&pager, &text)?;
+            }
+            None => profile::time("render", || printer.out(view))?,
+        }
+
+        match receipt {
+            Some(receipt) => receipt.answer(printer, account, client),
+            None => Ok(()),
+        }
+    }
+
+    /// Charset the text parts are decoded with, when forced by
+    /// `--charset` or by a `read.charsets` entry matching the sender.
+    fn forced_charset<'a>(&'a self, account: &'a Account, parsed: &Message<'_>) -> Option<&'a str> {
+        if let Some(charset) = self.charset.as_deref() {
+            return Some(charset);
+        }
+
+        let sender = parsed
+            .from()
+            .and_then(|from| from.first())
+            .and_then(|from| from.address())?;
+
+        account.read_charset(sender)
+    }
+
+    fn view(&self, account: &Account, parsed: Message<'_>) -> Result<MessageView> {
+        let bodies = match self.forced_charset(account, &parsed) {
+            Some(charset) => Some(decode_text_bodies(&parsed, charset)?),
+            None => None,
+        };
+        let bodies = redecode_text_bodies(&parsed, bodies);
+
+        Ok(MessageView(parsed.into_owned(), bodies))
+    }
+
+    /// Prints the message (rendered, or raw with `--raw`), then what
+    /// gets appended to it every `--interval` seconds.
+    fn follow(&self, account: &Account, client: &mut EmailClient, mailbox: &str) -> Result<()> {
+        let interval = Duration::from_secs(self.interval);
+        let mut printed: Vec<u8> = Vec::new();
+
+        loop {
+            let raw = client.get_message(mailbox, &self.id)?;
+
+            let next = if self.raw {
+                raw
+            } else {
+                let Some(parsed) = MessageParser::new().parse(&raw) else {
+                    bail!("Failed to parse RFC 5322 message");
+                };
+                self.view(account, parsed)?.to_string().into_bytes()
+            };
+
+            let mut out = stdout().lock();
+
+            match next.strip_prefix(printed.as_slice()) {
+                Some(appended) => out.write_all(appended)?,
+                None => {
+                    warn!("message {} changed, printing it again", self.id);
+                    out.write_all(&next)?;
+                }
+            }
+
+            out.flush()?;
+            drop(out);
+
+            printed = next;
+            thread::sleep(interval);
+        }
     }
 }
 
 /// Parsed message rendered as headers plus text bodies, or as JSON.
+///
+/// The second field holds the text bodies decoded with a forced
+/// charset, replacing the ones decoded by `mail_parser`.
 #[derive(Serialize)]
 #[serde(transparent)]
-pub struct MessageView(Message<'static>);
+pub struct MessageView(Message<'static>, #[serde(skip)] Option<Vec<String>>);
 
 impl fmt::Display for MessageView {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -83,8 +331,18 @@ impl fmt::Display for MessageView {
                 writeln!(f)?;
             }
 
-            if let Some(contents) = part.text_contents() {
-                write!(f, "{}", contents.trim_end())?;
+            let contents = match &self.1 {
+                Some(bodies) => bodies.get(i).map(String::as_str),
+                None => part.text_contents(),
+            };
+
+            let Some(contents) = contents else {
+                continue;
+            };
+
+            match flowed::params(part.content_type()) {
+                Some(delsp) => write!(f, "{}", flowed::decode(contents, delsp).trim_end())?,
+                None => write!(f, "{}", contents.trim_end())?,
             }
         }
 
@@ -92,9 +350,100 @@ impl fmt::Display for MessageView {
     }
 }
 
+/// Decodes the text bodies of `message` with `charset`, from their
+/// transfer-decoded bytes. Warns when the result contains
+/// replacement characters, a hint that the charset is wrong too.
+fn decode_text_bodies(message: &Message<'_>, charset: &str) -> Result<Vec<String>> {
+    let Some(encoding) = Encoding::for_label(charset.trim().as_bytes()) else {
+        bail!("Unknown charset `{charset}`");
+    };
+
+    let mut bodies = Vec::new();
+
+    for part in message.text_bodies() {
+        let (text, _, had_errors) = encoding.decode(&charset::transfer_decoded(message, part));
+
+        if had_errors {
+            warn!(
+                "decoding with charset {} produced replacement characters, it may be wrong",
+                encoding.name()
+            );
+        }
+
+        bodies.push(text.into_owned());
+    }
+
+    Ok(bodies)
+}
+
+/// Decodes again the text bodies (`bodies` when forced, otherwise
+/// the ones decoded by `mail_parser`) that still look
+/// transfer-encoded. Returns `bodies` untouched when none does.
+fn redecode_text_bodies(message: &Message<'_>, bodies: Option<Vec<String>>) -> Option<Vec<String>> {
+    let forced = bodies.is_some();
+    let current: Vec<String> = match bodies {
+        Some(bodies) => bodies,
+        None => message
+            .text_bodies()
+            .map(|part| part.text_contents().unwrap_or_default().to_owned())
+            .collect(),
+    };
+
+    let mut redecoded = false;
+    let mut rescued = Vec::with_capacity(current.len());
+
+    for (i, body) in current.iter().enumerate() {
+        match redecode::redecode(body) {
+            Some((text, encoding)) => {
+                let n = i + 1;
+                warn!("text part {n} still looked {encoding} encoded, decoded it again");
+                redecoded = true;
+                rescued.push(text);
+            }
+            None => rescued.push(body.clone()),
+        }
+    }
+
+    if redecoded {
+        Some(rescued)
+    } else if forced {
+        Some(current)
+    } else {
+        None
+    }
+}
+
+/// Raw header section of the message `id`, blank separator line
+/// included. Only IMAP can skip the body download.
+fn fetch_headers(client: &mut EmailClient, mailbox: &str, id: &str) -> Result<Vec<u8>> {
+    #[cfg(feature = "imap")]
+    match client.imap_get_headers(mailbox, id)? {
+        Some(Some(headers)) => return Ok(headers),
+        Some(None) => bail!("Cannot find message {id} in mailbox {mailbox}"),
+        None => (),
+    }
+
+    let mut raw = client.get_message(mailbox, id)?;
+
+    let end = [&b"\r\n\r\n"[..], b"\n\n"]
+        .iter()
+        .filter_map(|sep| {
+            raw.windows(sep.len())
+                .position(|w| w == *sep)
+                .map(|i| i + sep.len())
+        })
+        .min();
+
+    if let Some(end) = end {
+        raw.truncate(end);
+    }
+
+    Ok(raw)
+}
+
 /// Renders a parsed header value as decoded, human-readable text rather
 /// than its `Debug` form.
-fn render_header_value(value: &HeaderValue) -> String {
+pub(crate) fn render_header_value(value: &HeaderValue) -> String {
     match value {
         HeaderValue::Text(text) => text.to_string(),
         HeaderValue::TextList(list) => list
@@ -0,0 +1,324 @@
+//! Read receipts: answering the `Disposition-Notification-To`
+//! request of a read message with a message disposition notification
+//! (MDN, RFC 8098), according to `read.send-read-receipts`.
+//!
+//! Every request answered (or declined) is recorded by `Message-ID`
+//! in `receipts.toml` under the user data directory
+//! (`$XDG_DATA_HOME/himalaya` on Linux), so a message is never
+//! answered twice nor asked about again.
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{IsTerminal, stdin},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result, bail};
+use log::{debug, warn};
+use mail_builder::{MessageBuilder, headers::content_type::ContentType, mime::MimePart};
+use mail_parser::Message;
+use pimalaya_cli::{
+    printer::{Message as PrinterMessage, Printer},
+    prompt,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{account::context::Account, config::ReadReceipts, shared::client::EmailClient};
+
+/// Read receipt requested by a message.
+#[derive(Clone, Debug)]
+pub struct ReceiptRequest {
+    /// Address the receipt goes to.
+    to: String,
+    /// `Return-Path` address, which must match `to` for a receipt to
+    /// be sent without asking.
+    return_path: Option<String>,
+    /// Address the message was delivered to, reported as the final
+    /// recipient when the account has no `email`.
+    recipient: Option<String>,
+    message_id: String,
+    subject: String,
+}
+
+impl ReceiptRequest {
+    /// Read receipt requested by `message`, if any. Messages without
+    /// `Message-ID` cannot be referred to and are never answered,
+    /// nor are messages with a null `Return-Path` (`<>`), which are
+    /// themselves notifications such as bounces (RFC 8098).
+    pub fn from_message(message: &Message<'_>) -> Option<Self> {
+        let to = message
+            .header_raw("Disposition-Notification-To")
+            .and_then(parse_address)?;
+
+        let return_path = message.header_raw("Return-Path");
+
+        if return_path.is_some_and(is_null_path) {
+            debug!("null return path, ignoring read receipt request");
+            return None;
+        }
+
+        let recipient = message
+            .header_raw("Delivered-To")
+            .and_then(parse_address)
+            .or_else(|| {
+                let to = message.to()?.first()?.address()?;
+                Some(to.to_owned())
+            });
+
+        Some(Self {
+            to,
+            return_path: return_path.and_then(parse_address),
+            recipient,
+            message_id: message.message_id()?.to_owned(),
+            subject: message.subject().unwrap_or_default().to_owned(),
+        })
+    }
+
+    /// Sends the read receipt if the account allows it, asking first
+    /// unless `read.send-read-receipts` is `always`.
+    ///
+    /// A receipt is sent at most once per message, and never when
+    /// there is no terminal to ask on, unless sending is automatic.
+    /// Sending an automatic receipt still asks when the receipt does
+    /// not go back to the `Return-Path` of the message, or when the
+    /// message has none (RFC 8098).
+    pub fn answer(
+        self,
+        printer: &mut impl Printer,
+        account: &Account,
+        client: &mut EmailClient,
+    ) -> Result<()> {
+        let policy = account.read_send_receipts();
+
+        if policy == ReadReceipts::Never {
+            return Ok(());
+        }
+
+        let mut log = ReceiptLog::load()?;
+
+        if log.answered.contains(&self.message_id) {
+            debug!("read receipt of <{}> already answered", self.message_id);
+            return Ok(());
+        }
+
+        let Some(from) = account.email.clone().or_else(|| self.recipient.clone()) else {
+            warn!("cannot tell the address the message was sent to, skipping read receipt");
+            return Ok(());
+        };
+
+        let manual = policy == ReadReceipts::Ask || !self.returns_to_sender();
+
+        if manual {
+            if !stdin().is_terminal() {
+                debug!("no terminal to confirm on, skipping read receipt");
+                return Ok(());
+            }
+
+            let question = format!(
+                "The sender asks for a read receipt, send one to {}?",
+                self.to
+            );
+
+            if !prompt::bool(&question, false)? {
+                log.answered.insert(self.message_id);
+                return log.save();
+            }
+        }
+
+        let raw = self.mdn(&from, manual)?;
+        client.send_message(raw)?;
+
+        log.answered.insert(self.message_id.clone());
+        log.save()?;
+
+        printer.out(PrinterMessage::new(format!(
+            "Read receipt sent to {}",
+            self.to
+        )))
+    }
+
+    /// Whether the receipt goes back to the `Return-Path` of the
+    /// message, the only case it may be sent automatically.
+    fn returns_to_sender(&self) -> bool {
+        self.return_path
+            .as_ref()
+            .is_some_and(|path| path.eq_ignore_ascii_case(&self.to))
+    }
+
+    /// Message disposition notification telling the message was
+    /// displayed to `from`, sent with or without user action.
+    fn mdn(&self, from: &str, manual: bool) -> Result<Vec<u8>> {
+        let disposition = if manual {
+            "manual-action/MDN-sent-manually"
+        } else {
+            "automatic-action/MDN-sent-automatically"
+        };
+
+        let text = format!(
+            "The message sent to {from} with subject \"{}\" has been displayed.\r\n\
+             This is no guarantee that the message has been read or understood.\r\n",
+            self.subject
+        );
+
+        let report = format!(
+            "Reporting-UA: {name}; {name} {version}\r\n\
+             Final-Recipient: rfc822; {from}\r\n\
+             Original-Message-ID: <{id}>\r\n\
+             Disposition: {disposition}; displayed\r\n",
+            name = env!("CARGO_PKG_NAME"),
+            version = env!("CARGO_PKG_VERSION"),
+            id = self.message_id,
+        );
+
+        let ctype = ContentType::new("multipart/report")
+            .attribute("report-type", "disposition-notification");
+
+        let body = MimePart::new(
+            ctype,
+            vec![
+                MimePart::new("text/plain", text),
+                MimePart::new("message/disposition-notification", report),
+            ],
+        );
+
+        Ok(MessageBuilder::new()
+            .from(from)
+            .to(self.to.as_str())
+            .subject(format!("Read: {}", self.subject))
+            .body(body)
+            .write_to_vec()?)
+    }
+}
+
+/// Address of a single-address header value such as
+/// `Jane <jane@example.org>`.
+fn parse_address(value: &str) -> Option<String> {
+    let value = value.trim();
+
+    let addr = match value.split_once('<') {
+        Some((_, rest)) => rest.split_once('>')?.0,
+        None => value,
+    };
+
+    let addr = addr.trim();
+    addr.contains('@').then(|| addr.to_owned())
+}
+
+/// Whether the `Return-Path` header `value` is the null path `<>`.
+fn is_null_path(value: &str) -> bool {
+    let value = value.trim();
+
+    match value.strip_prefix('<').and_then(|v| v.strip_suffix('>')) {
+        Some(path) => path.trim().is_empty(),
+        None => value.is_empty(),
+    }
+}
+
+/// `Message-ID` of every message whose read receipt was answered.
+#[derive(Default, Deserialize, Serialize)]
+struct ReceiptLog {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(default)]
+    answered: BTreeSet<String>,
+}
+
+impl ReceiptLog {
+    fn load() -> Result<Self> {
+        let Some(data_dir) = dirs::data_dir() else {
+            bail!("Cannot find the user data directory for the read receipts log");
+        };
+
+        let path = data_dir.join("himalaya").join("receipts.toml");
+
+        let mut log: Self = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Read `{}` error", path.display()))?;
+            toml::from_str(&content).with_context(|| format!("Parse `{}` error", path.display()))?
+        } else {
+            Self::default()
+        };
+
+        log.path = path;
+        Ok(log)
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Create `{}` error", dir.display()))?;
+        }
+
+        let content = toml::to_string(self).context("Serialize read receipts log error")?;
+        fs::write(&self.path, content)
+            .with_context(|| format!("Write `{}` error", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mail_parser::{MessageParser, MimeHeaders};
+
+    use super::*;
+
+    #[test]
+    fn answers_request_with_mdn() {
+        let raw = b"From: Boss <boss@example.org>\r\n\
+            To: me@example.com\r\n\
+            Return-Path: <boss@example.org>\r\n\
+            Disposition-Notification-To: Boss <boss@example.org>\r\n\
+            Message-ID: <42@example.org>\r\n\
+            Subject: Report\r\n\r\nHello\r\n";
+        let message = MessageParser::new().parse(&raw[..]).unwrap();
+
+        let request = ReceiptRequest::from_message(&message).unwrap();
+        assert_eq!(request.to, "boss@example.org");
+        assert_eq!(request.return_path.as_deref(), Some("boss@example.org"));
+        assert_eq!(request.recipient.as_deref(), Some("me@example.com"));
+
+        let mdn = request.mdn("me@example.com", true).unwrap();
+        let mdn = MessageParser::new().parse(&mdn).unwrap();
+        let ctype = mdn.content_type().unwrap();
+        assert_eq!(
+            ctype.attribute("report-type"),
+            Some("disposition-notification")
+        );
+        assert_eq!(mdn.subject(), Some("Read: Report"));
+
+        let report = String::from_utf8_lossy(mdn.parts[2].contents());
+        assert!(report.contains("Original-Message-ID: <42@example.org>"));
+        assert!(report.contains("Disposition: manual-action/MDN-sent-manually; displayed"));
+
+        let message = MessageParser::new()
+            .parse(&b"Subject: x\r\n\r\n"[..])
+            .unwrap();
+        assert!(ReceiptRequest::from_message(&message).is_none());
+    }
+
+    #[test]
+    fn asks_unless_receipt_returns_to_sender() {
+        let request = |return_path: &str| {
+            let raw = format!(
+                "From: boss@example.org\r\n\
+                 To: me@example.com\r\n\
+                 {return_path}\
+                 Disposition-Notification-To: <Boss@Example.org>\r\n\
+                 Message-ID: <42@example.org>\r\n\r\nHello\r\n"
+            );
+            let message = MessageParser::new().parse(raw.as_bytes()).unwrap();
+            ReceiptRequest::from_message(&message)
+        };
+
+        let same = request("Return-Path: <boss@example.org>\r\n").unwrap();
+        assert!(same.returns_to_sender());
+
+        let other = request("Return-Path: <bounces@lists.example.org>\r\n").unwrap();
+        assert!(!other.returns_to_sender());
+
+        let missing = request("").unwrap();
+        assert!(!missing.returns_to_sender());
+
+        assert!(request("Return-Path: <>\r\n").is_none());
+        assert!(request("Return-Path: < >\r\n").is_none());
+    }
+}
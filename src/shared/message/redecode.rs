@@ -0,0 +1,132 @@
+//! Rescue of text parts whose transfer encoding is mislabeled.
+//!
+//! Some senders declare `7bit` (or nothing) for a quoted-printable or
+//! base64 body, which then shows up as `=3D` sequences or as one big
+//! base64 block. [`redecode`] spots such bodies and decodes them
+//! again. It is deliberately conservative: the whole body must be a
+//! well-formed encoding, and the result must be readable UTF-8 text,
+//! so `=`-heavy content (code, math, ASCII art) is left alone.
+
+use mail_parser::decoders::{base64::base64_decode, quoted_printable::quoted_printable_decode};
+
+/// Minimum number of `=XX` escapes for a body to be taken for
+/// quoted-printable.
+const MIN_QP_ESCAPES: usize = 4;
+
+/// Minimum number of base64 characters for a body to be taken for
+/// base64. Short single words (`Hello`, `deadbeef`) would be valid
+/// base64 too.
+const MIN_BASE64_LEN: usize = 40;
+
+/// Decodes `text` again when it still looks transfer-encoded.
+/// Returns the decoded text and the name of the encoding it was
+/// decoded from, `None` when `text` does not look encoded.
+pub fn redecode(text: &str) -> Option<(String, &'static str)> {
+    if looks_quoted_printable(text) {
+        let decoded = quoted_printable_decode(text.as_bytes())?;
+        return readable(decoded).map(|text| (text, "quoted-printable"));
+    }
+
+    if looks_base64(text) {
+        let decoded = base64_decode(text.as_bytes())?;
+        return readable(decoded).map(|text| (text, "base64"));
+    }
+
+    None
+}
+
+/// Every `=` starts a valid escape (two uppercase hex digits) or a
+/// soft line break, and there are enough escapes.
+fn looks_quoted_printable(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let mut escapes = 0;
+
+    for (i, byte) in bytes.iter().enumerate() {
+        if *byte != b'=' {
+            continue;
+        }
+
+        match (bytes.get(i + 1), bytes.get(i + 2)) {
+            (Some(a), Some(b)) if is_upper_hex(*a) && is_upper_hex(*b) => escapes += 1,
+            (Some(b'\n'), _) | (Some(b'\r'), Some(b'\n')) | (None, _) => (),
+            _ => return false,
+        }
+    }
+
+    escapes >= MIN_QP_ESCAPES
+}
+
+fn is_upper_hex(byte: u8) -> bool {
+    byte.is_ascii_digit() || (b'A'..=b'F').contains(&byte)
+}
+
+/// The body is nothing but lines of the base64 alphabet, long enough
+/// and of a plausible total length.
+fn looks_base64(text: &str) -> bool {
+    let text = text.trim();
+    let mut len = 0;
+
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+
+        if !line
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"+/=".contains(&b))
+        {
+            return false;
+        }
+
+        len += line.len();
+    }
+
+    len >= MIN_BASE64_LEN && len % 4 == 0
+}
+
+/// `decoded` as text when it is valid UTF-8 without control
+/// characters other than line breaks and tabs.
+fn readable(decoded: Vec<u8>) -> Option<String> {
+    let text = String::from_utf8(decoded).ok()?;
+
+    let binary = text
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'));
+
+    if binary { None } else { Some(text) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::redecode;
+
+    #[test]
+    fn redecodes_mislabeled_bodies() {
+        let qp = "Caf=C3=A9 cr=C3=A8me, 1 + 1 =3D 2, d=C3=A9j=C3=A0 vu=\nsoft break\n";
+        assert_eq!(
+            redecode(qp),
+            Some((
+                String::from("Café crème, 1 + 1 = 2, déjà vusoft break\n"),
+                "quoted-printable"
+            ))
+        );
+
+        let base64 =
+            "SGVsbG8sIHRoaXMgYm9keSB3YXMgc2VudCB3aXRob3V0\r\nIGl0cyB0cmFuc2Zlci1lbmNvZGluZy4=\r\n";
+        assert_eq!(
+            redecode(base64),
+            Some((
+                String::from("Hello, this body was sent without its transfer-encoding."),
+                "base64"
+            ))
+        );
+    }
+
+    #[test]
+    fn leaves_plain_bodies_untouched() {
+        assert_eq!(redecode("if a == b { x = 0xFF; } =3D =3D =3D =3D"), None);
+        assert_eq!(redecode("Hello"), None);
+        assert_eq!(
+            redecode("See https://example.org/?a=1&b=2 for details"),
+            None
+        );
+    }
+}
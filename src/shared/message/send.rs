@@ -1,11 +1,19 @@
+use std::io::{IsTerminal, stdin};
+
 use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
 use clap::Parser;
-use pimalaya_cli::printer::Printer;
+use log::{debug, warn};
+use pimalaya_cli::{
+    printer::{Message, Printer},
+    prompt,
+};
 
 use crate::account::context::Account;
+use crate::outbox::queue::{Outbox, QueuedMessage, parse_send_at};
 use crate::shared::{
     client::EmailClient,
-    message::{arg::MessageArg, handler},
+    message::{arg::MessageArg, handler, spellcheck},
 };
 
 /// Send a message via the active account.
@@ -14,17 +22,47 @@ use crate::shared::{
 /// outgoing backend. The envelope sender is taken from the `From:`
 /// header and recipients are collected from `To:` / `Cc:` / `Bcc:`.
 ///
-/// The message can be passed as a positional file path, an inline
-/// raw string, or piped via stdin (see [`MessageArg`] for resolution
-/// order). Pass `--save <MAILBOX>` to also append a copy of the
-/// sent message to a mailbox; the mailbox name is resolved through
-/// the account's `[mailbox.alias]` map before the backend call.
+/// The message can be passed as a positional file path, an inline raw
+/// string, or piped via stdin (see [`MessageArg`] for resolution
+/// order). The message is checked before anything is sent or queued:
+/// malformed header lines are reported with their line number. When
+/// `compose.spellcheck-command` is set and a terminal is available,
+/// its text is spell checked too, and sending is confirmed when
+/// misspelled words are found. A copy of the sent message is appended
+/// to the `send.save-to` mailbox when configured; pass `--save-to
+/// <MAILBOX>` to file it elsewhere, or `--save-to -` to skip it. The
+/// mailbox name is resolved through the account's `[mailbox.alias]`
+/// map before the backend call.
+///
+/// Pass `--schedule <DATE>` to queue the message in the local outbox
+/// instead; it is sent by the first `outbox flush` run after that
+/// date. When sending fails, the message can be queued the same way
+/// (see `--queue-on-failure`) and sent later with `outbox retry`,
+/// instead of being lost.
 #[derive(Debug, Parser)]
 pub struct MessageSendCommand {
-    /// Append a copy of the sent message to this mailbox.
-    #[arg(long, value_name = "MAILBOX")]
+    /// Append a copy of the sent message to this mailbox instead of
+    /// the `send.save-to` one, or to none with `-`.
+    #[arg(long, visible_alias = "save-to", value_name = "MAILBOX")]
     pub save: Option<String>,
 
+    /// Send even when the message exceeds the `send.max-size` limit.
+    #[arg(long)]
+    pub force: bool,
+
+    /// Queue the message in the local outbox instead of sending it,
+    /// to be sent by `outbox flush` from this date on.
+    ///
+    /// Accepts `YYYY-MM-DD HH:MM` in the local time zone, or an
+    /// RFC 3339 date. No connection is opened to queue a message.
+    #[arg(long, value_name = "DATE", value_parser = parse_send_at)]
+    pub schedule: Option<DateTime<FixedOffset>>,
+
+    /// Queue the message in the local outbox when sending fails,
+    /// without asking. Interactive sessions are asked instead.
+    #[arg(long, conflicts_with = "schedule")]
+    pub queue_on_failure: bool,
+
     #[command(flatten)]
     pub message: MessageArg,
 }
@@ -35,8 +73,88 @@ impl MessageSendCommand {
         printer: &mut impl Printer,
         account: &mut Account,
         client: &mut EmailClient,
+        account_name: &str,
+    ) -> Result<()> {
+        let raw = self.message.parse()?.into_bytes();
+        handler::check_message(&raw)?;
+
+        // NOTE: an oversized message would fail the same way from the
+        // outbox, so it is refused before offering to queue it
+        if !self.force {
+            handler::check_size(account, &raw)?;
+        }
+
+        spellcheck::check(account, &raw)?;
+
+        let err = match handler::route(
+            printer,
+            account,
+            client,
+            raw.clone(),
+            handler::save_to(account, self.save.as_deref(), true),
+            true,
+            self.force,
+        ) {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+
+        // NOTE: prompting requires a terminal, which a message piped
+        // via stdin rules out
+        let queue = self.queue_on_failure
+            || stdin().is_terminal()
+                && prompt::bool(
+                    &format!("{err:#}\nSending failed, queue the message in the outbox?"),
+                    true,
+                )?;
+
+        if !queue {
+            return Err(err);
+        }
+
+        warn!("cannot send message, queuing it in the outbox: {err}");
+        debug!("{err:?}");
+
+        let mut queued = QueuedMessage::new(account_name, &raw, None);
+        queued.save = self.save;
+        queued.force = self.force;
+        queued.attempts = 1;
+        queued.last_error = Some(format!("{err:#}"));
+
+        let id = Outbox::open()?.push(&queued, &raw)?;
+
+        printer.out(Message::new(format!(
+            "Message {id} queued in the outbox, send it later with `outbox retry`"
+        )))
+    }
+
+    /// Queues the message in the outbox for `account_name`, to be
+    /// sent at the `--schedule` date. An oversized message is refused
+    /// now rather than when the outbox is flushed.
+    pub fn schedule(
+        self,
+        printer: &mut impl Printer,
+        account: &Account,
+        account_name: &str,
     ) -> Result<()> {
         let raw = self.message.parse()?.into_bytes();
-        handler::route(printer, account, client, raw, self.save.as_deref(), true)
+        handler::check_message(&raw)?;
+
+        if !self.force {
+            handler::check_size(account, &raw)?;
+        }
+
+        let mut queued = QueuedMessage::new(account_name, &raw, self.schedule);
+        queued.save = self.save;
+        queued.force = self.force;
+
+        let id = Outbox::open()?.push(&queued, &raw)?;
+
+        let msg = match self.schedule {
+            Some(date) => format!("Message {id} scheduled for {}", date.format("%F %R%:z")),
+            None => format!("Message {id} queued in the outbox"),
+        };
+
+        printer.out(Message::new(msg))
     }
 }
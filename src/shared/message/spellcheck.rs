@@ -0,0 +1,176 @@
+//! Spell checking of outgoing messages through an external checker
+//! (`compose.spellcheck-command`).
+
+use std::{
+    io::{IsTerminal, Write, stdin},
+    process::Stdio,
+    thread,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use log::{debug, warn};
+use mail_parser::MessageParser;
+use pimalaya_cli::prompt;
+
+use crate::{account::context::Account, shared::shell};
+
+/// Runs the text body of `raw` through the configured spell checker,
+/// then asks whether to send anyway when it reports misspelled words.
+/// Bails when the user declines.
+///
+/// Skipped when no checker is configured or when there is no
+/// terminal to ask on. A checker that cannot be run only warns, so a
+/// broken setup never blocks sending.
+pub fn check(account: &Account, raw: &[u8]) -> Result<()> {
+    let Some(cmd) = account.compose_spellcheck_command.as_deref() else {
+        return Ok(());
+    };
+
+    if !stdin().is_terminal() {
+        debug!("no terminal to confirm on, skipping spell check");
+        return Ok(());
+    }
+
+    let text = own_text(raw);
+
+    if text.trim().is_empty() {
+        return Ok(());
+    }
+
+    let words = match run(cmd, &text) {
+        Ok(words) => words,
+        Err(err) => {
+            warn!("cannot spell check message: {err}");
+            debug!("{err:?}");
+            return Ok(());
+        }
+    };
+
+    if words.is_empty() {
+        return Ok(());
+    }
+
+    let question = format!(
+        "{} possibly misspelled word(s): {}\nSend anyway?",
+        words.len(),
+        words.join(", ")
+    );
+
+    if !prompt::bool(&question, false)? {
+        bail!("Sending aborted, fix the spelling and send again");
+    }
+
+    Ok(())
+}
+
+/// Text written by the sender: the text bodies of `raw`, without
+/// quoted lines nor signature.
+fn own_text(raw: &[u8]) -> String {
+    let Some(message) = MessageParser::new().parse(raw) else {
+        return String::new();
+    };
+
+    let mut text = String::new();
+
+    for body in (0..).map_while(|i| message.body_text(i)) {
+        for line in body.lines() {
+            if line == "-- " {
+                break;
+            }
+
+            if !line.trim_start().starts_with('>') {
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+    }
+
+    text
+}
+
+/// Pipes `text` to `cmd` and returns the distinct words it prints,
+/// one per line as `aspell list` and `hunspell -l` do.
+fn run(cmd: &str, text: &str) -> Result<Vec<String>> {
+    let mut child = shell::command(cmd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Spawn `{cmd}` error"))?;
+
+    let stdin = child.stdin.take();
+
+    // NOTE: the text is written from another thread while the output
+    // is read, a checker filling its stdout pipe before reading the
+    // whole text would block both ends otherwise
+    let (written, output) = thread::scope(|scope| {
+        let writer = scope.spawn(move || match stdin {
+            Some(mut stdin) => stdin.write_all(text.as_bytes()),
+            None => Ok(()),
+        });
+
+        let output = child.wait_with_output();
+        (writer.join(), output)
+    });
+
+    let output = output.with_context(|| format!("Wait for `{cmd}` error"))?;
+
+    written
+        .map_err(|_| anyhow!("Write message to stdin error"))?
+        .context("Write message to stdin error")?;
+
+    if !output.status.success() {
+        bail!("Command `{cmd}` failed with {}", output.status);
+    }
+
+    let mut words: Vec<String> = Vec::new();
+
+    for word in String::from_utf8_lossy(&output.stdout).split_whitespace() {
+        if !words.iter().any(|w| w == word) {
+            words.push(word.to_owned());
+        }
+    }
+
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_own_text_only() {
+        let raw = concat!(
+            "From: a@b\r\nContent-Type: text/plain\r\n\r\n",
+            "Thanks, see you tomorow.\r\n",
+            "\r\n",
+            "> Are you comming?\r\n",
+            "  >> nested quoet\r\n",
+            "-- \r\n",
+            "Jon Doe, Acme Corportion\r\n",
+        );
+
+        assert_eq!(own_text(raw.as_bytes()), "Thanks, see you tomorow.\n\n");
+    }
+
+    #[test]
+    fn gathers_every_text_part() {
+        let raw = concat!(
+            "From: a@b\r\nContent-Type: multipart/mixed; boundary=b\r\n\r\n",
+            "--b\r\nContent-Type: text/plain\r\n\r\nfirst part\r\n",
+            "--b\r\nContent-Type: text/plain\r\n\r\nsecond part\r\n",
+            "--b--\r\n",
+        );
+
+        assert_eq!(own_text(raw.as_bytes()), "first part\nsecond part\n");
+        assert_eq!(own_text(b""), "");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reads_output_while_writing_large_texts() {
+        // NOTE: more than a pipe buffer, echoed back by the checker
+        let text = "word\n".repeat(100_000);
+        let words = run("cat", &text).unwrap();
+        assert_eq!(words, ["word"]);
+    }
+}
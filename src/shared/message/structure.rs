@@ -0,0 +1,161 @@
+//! MIME part tree of a parsed message, without its contents.
+
+use std::fmt;
+
+use humansize::{BINARY, format_size};
+use mail_parser::{Message, MessagePart, MimeHeaders, PartType};
+use serde::Serialize;
+
+/// One MIME part and its children.
+#[derive(Debug, Serialize)]
+pub struct MimePart {
+    pub content_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charset: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disposition: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub boundary: Option<String>,
+    /// Size of the encoded body, in bytes.
+    pub size: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<MimePart>,
+    /// Draw the tree with ASCII characters, see `tree.ascii`. Only
+    /// read on the root part.
+    #[serde(skip)]
+    pub ascii: bool,
+}
+
+/// Characters drawing the branches of a tree.
+struct TreeGlyphs {
+    branch: &'static str,
+    last: &'static str,
+    pipe: &'static str,
+}
+
+const UNICODE_GLYPHS: TreeGlyphs = TreeGlyphs {
+    branch: "├─ ",
+    last: "└─ ",
+    pipe: "│  ",
+};
+
+const ASCII_GLYPHS: TreeGlyphs = TreeGlyphs {
+    branch: "+- ",
+    last: "`- ",
+    pipe: "|  ",
+};
+
+impl MimePart {
+    /// Structure of `message`, rooted at its top-level part. `None`
+    /// when the message has no part at all.
+    pub fn from_message(message: &Message<'_>) -> Option<Self> {
+        message
+            .parts
+            .first()
+            .map(|root| Self::from_part(message, root))
+    }
+
+    fn from_part(message: &Message<'_>, part: &MessagePart<'_>) -> Self {
+        let ctype = part.content_type();
+
+        let content_type = match ctype {
+            Some(ctype) => match ctype.subtype() {
+                Some(subtype) => format!("{}/{subtype}", ctype.ctype()),
+                None => ctype.ctype().to_owned(),
+            },
+            // NOTE: RFC 2045 default, also what mail_parser assumes
+            None => String::from("text/plain"),
+        };
+
+        let parts = match &part.body {
+            PartType::Multipart(ids) => ids
+                .iter()
+                .filter_map(|id| message.parts.get(*id as usize))
+                .map(|child| Self::from_part(message, child))
+                .collect(),
+            PartType::Message(nested) => Self::from_message(nested).into_iter().collect(),
+            _ => Vec::new(),
+        };
+
+        Self {
+            content_type,
+            charset: ctype
+                .and_then(|c| c.attribute("charset"))
+                .map(str::to_owned),
+            encoding: part.content_transfer_encoding().map(str::to_owned),
+            disposition: part.content_disposition().map(|cd| cd.ctype().to_owned()),
+            name: part.attachment_name().map(str::to_owned),
+            boundary: ctype
+                .and_then(|c| c.attribute("boundary"))
+                .map(str::to_owned),
+            size: part.raw_end_offset().saturating_sub(part.raw_body_offset()) as usize,
+            parts,
+            ascii: false,
+        }
+    }
+
+    /// Writes the part as a tree line drawn with `glyphs`, then its
+    /// children one level deeper.
+    fn write_tree(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        glyphs: &TreeGlyphs,
+        prefix: &str,
+        is_last: bool,
+    ) -> fmt::Result {
+        let connector = if is_last { glyphs.last } else { glyphs.branch };
+
+        let mut desc = self.content_type.clone();
+        if let Some(name) = &self.name {
+            desc.push_str(&format!(" \"{name}\""));
+        }
+        desc.push_str(&format!(" ({})", format_size(self.size, BINARY)));
+
+        let mut details = Vec::new();
+        if let Some(charset) = &self.charset {
+            details.push(format!("charset={charset}"));
+        }
+        if let Some(encoding) = &self.encoding {
+            details.push(format!("encoding={encoding}"));
+        }
+        if let Some(disposition) = &self.disposition {
+            details.push(format!("disposition={disposition}"));
+        }
+        if let Some(boundary) = &self.boundary {
+            details.push(format!("boundary=\"{boundary}\""));
+        }
+        if !details.is_empty() {
+            desc.push_str(&format!(" [{}]", details.join(", ")));
+        }
+
+        writeln!(f, "{prefix}{connector}{desc}")?;
+
+        let child_prefix = if is_last {
+            format!("{prefix}   ")
+        } else {
+            format!("{prefix}{}", glyphs.pipe)
+        };
+
+        for (i, child) in self.parts.iter().enumerate() {
+            child.write_tree(f, glyphs, &child_prefix, i == self.parts.len() - 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for MimePart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let glyphs = if self.ascii {
+            &ASCII_GLYPHS
+        } else {
+            &UNICODE_GLYPHS
+        };
+
+        self.write_tree(f, glyphs, "", true)
+    }
+}
@@ -0,0 +1,469 @@
+//! `message undo`: reverts the last move or flag change of an
+//! account.
+//!
+//! The last operation of each account is recorded in `undo.toml`
+//! under the user data directory (`$XDG_DATA_HOME/himalaya` on
+//! Linux), each new one replacing the previous: undo is single-level.
+//!
+//! Moved messages are recorded by the ids they got in the destination
+//! when the backend tells them (see
+//! [`EmailClient::move_messages_tracked`]). Otherwise they are
+//! recorded by `Message-ID` and `Date:` header: undoing the move lists
+//! the destination, fetches the headers of the envelopes dated like a
+//! moved message, and moves back the ones whose `Message-ID` matches.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fs,
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, FixedOffset};
+use clap::{Parser, ValueEnum};
+use io_email::flag::types::{Flag, FlagOp};
+use log::{debug, warn};
+use mail_parser::MessageParser;
+use pimalaya_cli::printer::{Message, Printer};
+use serde::{Deserialize, Serialize};
+
+use crate::account::context::Account;
+use crate::shared::{client::EmailClient, flag::arg::FlagArg};
+
+/// Revert the last move or flag change of the active account.
+///
+/// `message move`, `message junk`, `message not-junk`, `message
+/// dedupe --delete` and `flag add`/`remove` record what they did;
+/// only the last operation can be undone, once. Moved messages are
+/// moved back to their source mailbox, added flags are removed from
+/// the messages that did not have them and removed flags added back
+/// to the messages that had them.
+///
+/// On local storage, messages moved without a `Message-ID` or
+/// `Date:` header cannot be found again and stay where they are.
+/// `flag set`, moves to another account and anything done through
+/// the protocol-specific commands (e.g. `imap expunge`) cannot be
+/// undone.
+#[derive(Debug, Parser)]
+pub struct MessageUndoCommand {}
+
+impl MessageUndoCommand {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        _account: &mut Account,
+        client: &mut EmailClient,
+        account_name: &str,
+    ) -> Result<()> {
+        let mut log = UndoLog::load()?;
+
+        let Some(operation) = log.operations.remove(account_name) else {
+            bail!("Nothing to undo for account {account_name}");
+        };
+
+        let msg = match operation {
+            Operation::Move {
+                from,
+                to,
+                ids,
+                messages,
+            } => {
+                let (found, missing) = match ids.is_empty() {
+                    false => (ids, 0),
+                    true => find_moved(client, &to, &messages)?,
+                };
+                let ids: Vec<&str> = found.iter().map(String::as_str).collect();
+
+                if !ids.is_empty() {
+                    client.move_messages(&to, &from, &ids)?;
+                }
+
+                if missing > 0 {
+                    warn!("{missing} moved message(s) not found in mailbox {to}");
+                }
+
+                format!("{} message(s) moved back from {to} to {from}", ids.len())
+            }
+            Operation::Flags {
+                mailbox,
+                changed,
+                added,
+            } => {
+                let mut messages = HashSet::new();
+
+                for (flag, ids) in &changed {
+                    let Ok(flag) = FlagArg::from_str(flag, true) else {
+                        continue;
+                    };
+
+                    if ids.is_empty() {
+                        continue;
+                    }
+
+                    let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+                    let op = if added { FlagOp::Remove } else { FlagOp::Add };
+
+                    client.store_flags(&mailbox, &ids, &[Flag::from(&flag)], op)?;
+                    messages.extend(ids);
+                }
+
+                let verb = if added {
+                    "removed from"
+                } else {
+                    "added back to"
+                };
+
+                format!("Flag(s) {verb} {} message(s)", messages.len())
+            }
+            Operation::Irreversible { what } => {
+                bail!("The last operation of account {account_name} ({what}) cannot be undone")
+            }
+        };
+
+        log.save()?;
+
+        printer.out(Message::new(msg))
+    }
+}
+
+/// Operation recorded for `message undo`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Operation {
+    /// Messages moved from one mailbox to another of the account.
+    Move {
+        from: String,
+        to: String,
+        /// Ids of the messages in `to`, when known.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        ids: Vec<String>,
+        /// Headers identifying the messages otherwise.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        messages: Vec<MovedMessage>,
+    },
+    /// Flags added to (or removed from) messages.
+    Flags {
+        mailbox: String,
+        /// Ids of the messages each flag was actually added to (or
+        /// removed from), keyed by flag: messages which already had
+        /// (or lacked) it are left out, so undoing leaves them as
+        /// they were.
+        changed: BTreeMap<String, Vec<String>>,
+        added: bool,
+    },
+    /// An operation that cannot be undone, recorded so the one before
+    /// it is not undone instead.
+    Irreversible { what: String },
+}
+
+impl Operation {
+    /// Moves `ids` of `from` to `to`, and returns the operation to
+    /// record for undoing it. The headers of the messages are only
+    /// fetched beforehand when the backend cannot tell where they end
+    /// up.
+    pub fn move_messages(
+        client: &mut EmailClient,
+        from: &str,
+        to: &str,
+        ids: &[&str],
+    ) -> Result<Self> {
+        let headers = match client.tracks_moved_ids() {
+            true => HashMap::new(),
+            false => client.get_headers(from, ids)?,
+        };
+
+        let moved = client.move_messages_tracked(from, to, ids)?;
+
+        let messages = ids
+            .iter()
+            .filter_map(|id| MovedMessage::parse(headers.get(*id)?))
+            .collect();
+
+        Ok(match moved {
+            Some(ids) => Self::moved(from, to, ids, Vec::new()),
+            None if !headers.is_empty() => Self::moved(from, to, Vec::new(), messages),
+            // NOTE: messages arrived in `to` during the move, they
+            // cannot be told apart from the moved ones
+            None => Self::irreversible(format!("move to {to}")),
+        })
+    }
+
+    fn moved(from: &str, to: &str, ids: Vec<String>, messages: Vec<MovedMessage>) -> Self {
+        Self::Move {
+            from: from.to_owned(),
+            to: to.to_owned(),
+            ids,
+            messages,
+        }
+    }
+
+    /// `flags` added to (or removed from) messages `ids` of
+    /// `mailbox`, whose flags were `current` beforehand (see
+    /// [`EmailClient::get_flags`]).
+    pub fn flags(
+        mailbox: &str,
+        ids: &[&str],
+        flags: &[FlagArg],
+        current: &HashMap<String, Vec<Flag>>,
+        added: bool,
+    ) -> Self {
+        let changed = flags
+            .iter()
+            .map(|flag| {
+                let ids = ids
+                    .iter()
+                    .filter(|id| {
+                        let had = current
+                            .get(**id)
+                            .is_some_and(|flags| flags.contains(&Flag::from(flag)));
+                        had != added
+                    })
+                    .map(|id| id.to_string())
+                    .collect();
+
+                (flag.to_string(), ids)
+            })
+            .collect();
+
+        Self::Flags {
+            mailbox: mailbox.to_owned(),
+            changed,
+            added,
+        }
+    }
+
+    pub fn irreversible(what: impl ToString) -> Self {
+        Self::Irreversible {
+            what: what.to_string(),
+        }
+    }
+
+    /// Records `self` as the last operation of `account_name`. The
+    /// operation itself is already done, so failing to record it only
+    /// warns.
+    pub fn record(self, account_name: &str) {
+        let result = UndoLog::load().and_then(|mut log| {
+            log.operations.insert(account_name.to_owned(), self);
+            log.save()
+        });
+
+        if let Err(err) = result {
+            warn!("cannot record operation for undo: {err}");
+            debug!("{err:?}");
+        }
+    }
+}
+
+/// Moved message, identified by its headers.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MovedMessage {
+    pub message_id: String,
+    /// `Date:` header, as an RFC 3339 date.
+    pub date: String,
+}
+
+impl MovedMessage {
+    pub(super) fn parse(headers: &[u8]) -> Option<Self> {
+        let msg = MessageParser::new().parse_headers(headers)?;
+
+        Some(Self {
+            message_id: msg.message_id()?.to_owned(),
+            date: msg.date()?.to_rfc3339(),
+        })
+    }
+}
+
+/// Ids of the `messages` found in `mailbox`, and the number of the
+/// ones not found.
+pub(super) fn find_moved(
+    client: &mut EmailClient,
+    mailbox: &str,
+    messages: &[MovedMessage],
+) -> Result<(Vec<String>, usize)> {
+    let located = locate_moved(client, mailbox, messages)?;
+    let missing = located.iter().filter(|id| id.is_none()).count();
+    Ok((located.into_iter().flatten().collect(), missing))
+}
+
+/// Id in `mailbox` of each of the `messages`, in order, `None` for
+/// the ones not found.
+pub(super) fn locate_moved(
+    client: &mut EmailClient,
+    mailbox: &str,
+    messages: &[MovedMessage],
+) -> Result<Vec<Option<String>>> {
+    let dates: HashSet<DateTime<FixedOffset>> = messages
+        .iter()
+        .filter_map(|msg| DateTime::parse_from_rfc3339(&msg.date).ok())
+        .collect();
+
+    let envelopes = client.list_envelopes(mailbox, None, None, false)?;
+
+    // NOTE: only the envelopes dated like a moved message are
+    // fetched, their ids are then confirmed by `Message-ID`
+    let candidates: Vec<&str> = envelopes
+        .iter()
+        .filter(|env| env.date.is_some_and(|date| dates.contains(&date)))
+        .map(|env| env.id.as_str())
+        .collect();
+
+    let headers = client.get_headers(mailbox, &candidates)?;
+
+    // NOTE: queued, since duplicates moved by `message dedupe`
+    // share their `Message-ID`
+    let mut found: HashMap<String, VecDeque<&str>> = HashMap::new();
+
+    for id in candidates {
+        if let Some(moved) = headers.get(id).and_then(|h| MovedMessage::parse(h)) {
+            found.entry(moved.message_id).or_default().push_back(id);
+        }
+    }
+
+    let ids = messages
+        .iter()
+        .map(|msg| {
+            let id = found.get_mut(&msg.message_id)?.pop_front()?;
+            Some(id.to_owned())
+        })
+        .collect();
+
+    Ok(ids)
+}
+
+/// Last operation of every account.
+#[derive(Debug)]
+struct UndoLog {
+    path: PathBuf,
+    operations: BTreeMap<String, Operation>,
+}
+
+impl UndoLog {
+    /// Loads the operations, none when the file does not exist yet.
+    fn load() -> Result<Self> {
+        let Some(data_dir) = dirs::data_dir() else {
+            bail!("Cannot find the user data directory for the undo log");
+        };
+
+        Self::load_from(data_dir.join("himalaya").join("undo.toml"))
+    }
+
+    fn load_from(path: PathBuf) -> Result<Self> {
+        let operations = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Read `{}` error", path.display()))?;
+            toml::from_str(&content).with_context(|| format!("Parse `{}` error", path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self { path, operations })
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Create `{}` error", dir.display()))?;
+        }
+
+        let content = toml::to_string(&self.operations).context("Serialize undo log error")?;
+        fs::write(&self.path, content)
+            .with_context(|| format!("Write `{}` error", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    #[test]
+    fn round_trips_the_undo_log() {
+        let dir = env::temp_dir().join(format!("himalaya-undo-{}", process::id()));
+        let path = dir.join("undo.toml");
+
+        let mut log = UndoLog::load_from(path.clone()).unwrap();
+        assert!(log.operations.is_empty());
+
+        let moved = MovedMessage {
+            message_id: String::from("<a@b>"),
+            date: String::from("2024-01-01T00:00:00+00:00"),
+        };
+
+        let operations = [
+            Operation::moved("INBOX", "Trash", vec![String::from("42")], Vec::new()),
+            Operation::moved("INBOX", "Archive", Vec::new(), vec![moved]),
+            Operation::flags(
+                "INBOX",
+                &["1", "2"],
+                &[FlagArg::Seen],
+                &HashMap::new(),
+                true,
+            ),
+            Operation::irreversible("flag set"),
+        ];
+
+        for (i, operation) in operations.into_iter().enumerate() {
+            log.operations.insert(format!("account{i}"), operation);
+        }
+
+        log.save().unwrap();
+        let loaded = UndoLog::load_from(path).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(
+            format!("{:?}", loaded.operations),
+            format!("{:?}", log.operations)
+        );
+        assert!(matches!(
+            &loaded.operations["account0"],
+            Operation::Move { ids, messages, .. } if ids == &["42"] && messages.is_empty()
+        ));
+        assert!(matches!(
+            &loaded.operations["account1"],
+            Operation::Move { ids, messages, .. } if ids.is_empty() && messages[0].message_id == "<a@b>"
+        ));
+    }
+
+    #[test]
+    fn flag_changes_only_record_the_messages_they_changed() {
+        let seen = Flag::from(&FlagArg::Seen);
+        let flagged = Flag::from(&FlagArg::Flagged);
+
+        let current = HashMap::from([
+            (String::from("1"), vec![seen.clone()]),
+            (String::from("2"), Vec::new()),
+            (String::from("3"), vec![seen, flagged]),
+        ]);
+
+        let ids = ["1", "2", "3"];
+        let flags = [FlagArg::Seen, FlagArg::Flagged];
+
+        let changed = |added| match Operation::flags("INBOX", &ids, &flags, &current, added) {
+            Operation::Flags { changed, .. } => changed,
+            operation => panic!("unexpected operation {operation:?}"),
+        };
+
+        let added = changed(true);
+        assert_eq!(added["seen"], ["2"]);
+        assert_eq!(added["flagged"], ["1", "2"]);
+
+        let removed = changed(false);
+        assert_eq!(removed["seen"], ["1", "3"]);
+        assert_eq!(removed["flagged"], ["3"]);
+    }
+
+    #[test]
+    fn reads_undo_logs_without_moved_ids() {
+        let operation: Operation = toml::from_str(
+            r#"
+            kind = "move"
+            from = "INBOX"
+            to = "Trash"
+            messages = [{ message_id = "<a@b>", date = "2024-01-01T00:00:00+00:00" }]
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(operation, Operation::Move { ids, .. } if ids.is_empty()));
+    }
+}
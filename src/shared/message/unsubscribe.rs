@@ -0,0 +1,291 @@
+//! `message unsubscribe`: acts on the `List-Unsubscribe` header of a
+//! mailing list message (RFC 2369), taking `List-Unsubscribe-Post`
+//! (RFC 8058) into account.
+
+use std::io::{IsTerminal, stdin};
+
+use anyhow::{Result, bail};
+use clap::Parser;
+use mail_builder::MessageBuilder;
+use mail_parser::MessageParser;
+use percent_encoding::percent_decode_str;
+use pimalaya_cli::{
+    printer::{Message, Printer},
+    prompt,
+};
+use url::Url;
+
+use crate::account::context::Account;
+use crate::shared::{client::EmailClient, mailbox::arg::MailboxArg, message::handler};
+
+/// Unsubscribe from the mailing list a message was sent through.
+///
+/// Reads the `List-Unsubscribe` header of the message. A `mailto:`
+/// target is preferred: the unsubscribe email it describes is sent
+/// through the account's send path, from the address the message was
+/// delivered to unless `--from` is given. Otherwise the `https:`
+/// target is printed, and opened in the default browser with
+/// `--open`; when the list supports one-click unsubscription
+/// (`List-Unsubscribe-Post`, RFC 8058), the printed line says so, the
+/// POST request itself being left to the user.
+///
+/// The action is confirmed first, showing the recipient, subject and
+/// body of the unsubscribe email, unless `--yes` is given; without a
+/// terminal to confirm on, `--yes` is required.
+#[derive(Debug, Parser)]
+pub struct MessageUnsubscribeCommand {
+    /// Identifier of the message (IMAP UID, JMAP email id, or Maildir
+    /// filename id).
+    #[arg(value_name = "ID")]
+    pub id: String,
+
+    #[command(flatten)]
+    pub mailbox: MailboxArg,
+
+    /// Address the unsubscribe email is sent from. Defaults to the
+    /// first `Delivered-To`, then `To`, address of the message.
+    #[arg(long, value_name = "ADDR")]
+    pub from: Option<String>,
+
+    /// Use the `https:` target even when a `mailto:` one is offered.
+    #[arg(long)]
+    pub web: bool,
+
+    /// Open the `https:` target in the default browser.
+    #[arg(long)]
+    pub open: bool,
+
+    /// Do not ask for confirmation.
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+impl MessageUnsubscribeCommand {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        account: &mut Account,
+        client: &mut EmailClient,
+    ) -> Result<()> {
+        let mailbox = self.mailbox.resolve(account)?;
+        let mut headers = client.get_headers(&mailbox, &[self.id.as_str()])?;
+
+        let Some(headers) = headers.remove(&self.id) else {
+            bail!("Cannot find message {} in mailbox {mailbox}", self.id);
+        };
+
+        let Some(parsed) = MessageParser::new().parse_headers(&headers) else {
+            bail!("Failed to parse the headers of message {}", self.id);
+        };
+
+        let targets = parsed
+            .header_raw("List-Unsubscribe")
+            .map(parse_targets)
+            .unwrap_or_default();
+
+        let mailto = targets.iter().find(|url| url.scheme() == "mailto");
+        let https = targets.iter().find(|url| url.scheme() == "https");
+
+        match (mailto.filter(|_| !self.web), https) {
+            (Some(mailto), _) => {
+                let from = match self.from {
+                    Some(from) => from,
+                    None => match recipient(&parsed) {
+                        Some(from) => from,
+                        None => bail!("Cannot find the address to unsubscribe, pass --from"),
+                    },
+                };
+
+                let email = UnsubscribeEmail::parse(mailto)?;
+                let raw = email.build(&from)?;
+
+                if !confirm(self.yes, &email.question(&from))? {
+                    bail!("Unsubscribe aborted");
+                }
+
+                handler::route(printer, account, client, raw, None, true, false)
+            }
+            (None, Some(https)) => {
+                // NOTE: RFC 8058 one-click unsubscription is a POST
+                // request, which is left to the user: opening the
+                // URL only GETs the list's unsubscribe page
+                let one_click = parsed
+                    .header_raw("List-Unsubscribe-Post")
+                    .is_some_and(|post| {
+                        post.trim()
+                            .eq_ignore_ascii_case("List-Unsubscribe=One-Click")
+                    });
+
+                if self.open {
+                    if !confirm(self.yes, &format!("Open {https} in the browser?"))? {
+                        bail!("Unsubscribe aborted");
+                    }
+
+                    open::that(https.as_str())?;
+                }
+
+                let msg = if one_click {
+                    format!(
+                        "Unsubscribe URL (one-click, POST `List-Unsubscribe=One-Click` to it): {https}"
+                    )
+                } else {
+                    format!("Unsubscribe URL: {https}")
+                };
+
+                printer.out(Message::new(msg))
+            }
+            (None, None) if mailto.is_some() => {
+                bail!("Message {} has no https: unsubscribe target", self.id)
+            }
+            (None, None) => bail!("Message {} has no usable List-Unsubscribe header", self.id),
+        }
+    }
+}
+
+/// `mailto:` and `https:` URLs of a `List-Unsubscribe` header value,
+/// each enclosed in angle brackets, in order of preference.
+fn parse_targets(value: &str) -> Vec<Url> {
+    value
+        .split('<')
+        .skip(1)
+        .filter_map(|part| part.split_once('>'))
+        .filter_map(|(url, _)| Url::parse(&url.split_whitespace().collect::<String>()).ok())
+        .filter(|url| matches!(url.scheme(), "mailto" | "https"))
+        .collect()
+}
+
+/// Unsubscribe email described by a `mailto:` URL (RFC 6068).
+///
+/// Every field comes from the untrusted message, hence shown in full
+/// before sending.
+#[derive(Debug)]
+struct UnsubscribeEmail {
+    to: String,
+    subject: String,
+    body: String,
+}
+
+impl UnsubscribeEmail {
+    /// Reads the address of `url`, and its `subject` and `body`
+    /// fields when present.
+    fn parse(url: &Url) -> Result<Self> {
+        let to = percent_decode_str(url.path())
+            .decode_utf8_lossy()
+            .into_owned();
+
+        if to.is_empty() {
+            bail!("Invalid unsubscribe target {url}: missing address");
+        }
+
+        let mut subject = String::from("unsubscribe");
+        let mut body = String::from("unsubscribe");
+
+        for (key, value) in url.query_pairs() {
+            if key.eq_ignore_ascii_case("subject") {
+                subject = value.into_owned();
+            } else if key.eq_ignore_ascii_case("body") {
+                body = value.into_owned();
+            }
+        }
+
+        Ok(Self { to, subject, body })
+    }
+
+    fn build(&self, from: &str) -> Result<Vec<u8>> {
+        Ok(MessageBuilder::new()
+            .from(from)
+            .to(self.to.as_str())
+            .subject(self.subject.as_str())
+            .text_body(self.body.as_str())
+            .write_to_vec()?)
+    }
+
+    /// Confirmation question, showing the email sent from `from`.
+    /// Control characters are escaped, so the email cannot rewrite
+    /// what the terminal shows.
+    fn question(&self, from: &str) -> String {
+        let mut question = String::from("Send this unsubscribe email?\n");
+
+        question.push_str(&format!("  From: {}\n", from.escape_debug()));
+        question.push_str(&format!("  To: {}\n", self.to.escape_debug()));
+        question.push_str(&format!("  Subject: {}\n", self.subject.escape_debug()));
+        question.push_str("  Body:\n");
+
+        for line in self.body.lines() {
+            question.push_str(&format!("    {}\n", line.escape_debug()));
+        }
+
+        question
+    }
+}
+
+/// Address the message was delivered to.
+fn recipient(parsed: &mail_parser::Message<'_>) -> Option<String> {
+    if let Some(delivered_to) = parsed.header_raw("Delivered-To") {
+        return Some(delivered_to.trim().to_owned());
+    }
+
+    parsed
+        .to()
+        .and_then(|to| to.first())
+        .and_then(|addr| addr.address())
+        .map(str::to_owned)
+}
+
+/// Asks `question`, unless `yes` is set. Refuses to go on without a
+/// terminal to ask on.
+fn confirm(yes: bool, question: &str) -> Result<bool> {
+    if yes {
+        return Ok(true);
+    }
+
+    if !stdin().is_terminal() {
+        bail!("Cannot confirm without a terminal, pass --yes");
+    }
+
+    Ok(prompt::bool(question, false)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_unsubscribe_targets() {
+        let targets = parse_targets(
+            "<mailto:leave-42@lists.example?subject=unsubscribe%2042>,\r\n \
+             <https://lists.example/u?id=42>, <ftp://old.example/>",
+        );
+
+        let targets: Vec<&str> = targets.iter().map(Url::as_str).collect();
+        assert_eq!(
+            targets,
+            [
+                "mailto:leave-42@lists.example?subject=unsubscribe%2042",
+                "https://lists.example/u?id=42",
+            ]
+        );
+
+        let email = UnsubscribeEmail::parse(&Url::parse(targets[0]).unwrap()).unwrap();
+        let raw = String::from_utf8(email.build("me@example.org").unwrap()).unwrap();
+        assert!(raw.contains("To: <leave-42@lists.example>\r\n"));
+        assert!(raw.contains("Subject: unsubscribe 42\r\n"));
+    }
+
+    #[test]
+    fn shows_the_whole_unsubscribe_email() {
+        let url = "mailto:boss@corp.example?subject=I%20quit&body=Bye%0Aall%1B%5B2J";
+        let email = UnsubscribeEmail::parse(&Url::parse(url).unwrap()).unwrap();
+
+        assert_eq!(
+            email.question("me@example.org"),
+            "Send this unsubscribe email?\n  \
+             From: me@example.org\n  \
+             To: boss@corp.example\n  \
+             Subject: I quit\n  \
+             Body:\n    \
+             Bye\n    \
+             all\\u{1b}[2J\n"
+        );
+    }
+}
@@ -3,7 +3,11 @@ pub mod attachment;
 pub mod client;
 pub mod envelope;
 pub mod flag;
+pub mod levenshtein;
 pub mod mailbox;
 pub mod message;
 #[cfg(any(feature = "gmail", feature = "msgraph"))]
 pub mod output;
+pub mod pager;
+pub mod shell;
+pub mod width;
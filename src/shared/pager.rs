@@ -0,0 +1,53 @@
+//! Pipes rendered output through an interactive pager (`less -R`,
+//! `$PAGER`, …).
+
+use std::{
+    env,
+    io::{ErrorKind, Write},
+    process::Stdio,
+};
+
+use anyhow::{Context, Result, bail};
+
+use crate::shared::shell;
+
+/// Writes `text` to the stdin of the pager `cmd`, run through the
+/// system shell, then waits for the pager to exit.
+///
+/// `LESS=R` is exported when `LESS` is unset so ANSI color codes
+/// survive into `less` whatever the command line. Quitting the pager
+/// before the whole text is consumed is not an error, a pager exiting
+/// with a non-zero status is.
+pub fn page(cmd: &str, text: &str) -> Result<()> {
+    let mut command = shell::command(cmd);
+
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "R");
+    }
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Spawn pager `{cmd}` error"))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // NOTE: the pager closes its stdin when quit early, which
+        // surfaces here as a broken pipe
+        match stdin.write_all(text.as_bytes()) {
+            Err(err) if err.kind() != ErrorKind::BrokenPipe => {
+                return Err(err).context("Write to pager error");
+            }
+            _ => (),
+        }
+    }
+
+    let status = child
+        .wait()
+        .with_context(|| format!("Wait for pager `{cmd}` error"))?;
+
+    if !status.success() {
+        bail!("Pager `{cmd}` failed with {status}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,18 @@
+//! Commands run through the system shell.
+
+use std::process::Command;
+
+/// Builds a command running `cmd` through the system shell (`sh -c`,
+/// or `cmd /C` on Windows), so user-configured command lines can use
+/// pipes, quotes and environment variables.
+pub fn command(cmd: &str) -> Command {
+    if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", cmd]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", cmd]);
+        command
+    }
+}
@@ -0,0 +1,56 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{Error, Result, bail};
+
+/// Width used when `auto` cannot detect the terminal size (not a
+/// TTY, or a reported width of 0).
+const FALLBACK_WIDTH: u16 = 80;
+
+/// Value of the `--max-width` option shared by the table-rendering
+/// commands: either a fixed number of columns, or `auto` to track
+/// the terminal width.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MaxWidth {
+    /// Query the terminal size when the table is drawn, so a resize
+    /// between the config read and the draw is taken into account.
+    Auto,
+    Columns(u16),
+}
+
+impl MaxWidth {
+    /// Returns the width to apply to the table, queried at call time
+    /// for [`MaxWidth::Auto`].
+    pub fn columns(self) -> u16 {
+        match self {
+            Self::Columns(columns) => columns,
+            Self::Auto => match crossterm::terminal::size() {
+                Ok((columns, _)) if columns > 0 => columns,
+                _ => FALLBACK_WIDTH,
+            },
+        }
+    }
+}
+
+impl FromStr for MaxWidth {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto);
+        }
+
+        match s.parse() {
+            Ok(columns) => Ok(Self::Columns(columns)),
+            Err(_) => bail!("invalid width `{s}`: expected a number of columns or `auto`"),
+        }
+    }
+}
+
+impl fmt::Display for MaxWidth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => f.write_str("auto"),
+            Self::Columns(columns) => write!(f, "{columns}"),
+        }
+    }
+}
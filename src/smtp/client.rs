@@ -10,19 +10,16 @@
 use std::{
     net::Ipv4Addr,
     ops::{Deref, DerefMut},
-    path::PathBuf,
 };
 
 use anyhow::{Result, anyhow};
 use io_smtp::{client::SmtpClientStd as Inner, rfc5321::types::ehlo_domain::EhloDomain};
-use pimalaya_config::toml::TomlConfig;
 use pimalaya_stream::sasl::Sasl;
 use url::Url;
 
 use crate::{
     account::context::Account,
-    cli::load_or_wizard,
-    config::{SmtpConfig, parse_server},
+    config::{Config, SmtpConfig, parse_server},
 };
 
 /// SMTP client wrapping the inner stream for sending messages.
@@ -34,7 +31,7 @@ impl SmtpClient {
     /// Opens the SMTP connection (TCP/TLS/STARTTLS, greeting, EHLO,
     /// SASL).
     pub fn new(config: SmtpConfig) -> Result<Self> {
-        let tls = config.tls.into_tls(config.alpn);
+        let tls = config.tls.into_tls(config.alpn)?;
         let domain: EhloDomain<'static> = Ipv4Addr::new(127, 0, 0, 1).into();
         let server = parse_smtp_server(&config.server)?;
         let sasl: Option<Sasl> = match config.sasl {
@@ -80,20 +77,17 @@ impl DerefMut for SmtpClient {
     }
 }
 
-/// Loads the configuration, picks the active account, builds the
+/// Picks the active account of `config`, builds the
 /// merged [`Account`] then opens the SMTP session. Bails when the
 /// account has no `[smtp]` block. Returns the live client paired with
 /// the merged account for dispatch uniformity with the other
 /// `build_*_client` helpers, though SMTP subcommands ignore the
 /// account.
 pub fn build_smtp_client(
-    config_paths: &[PathBuf],
+    mut config: Config,
     account_name: Option<&str>,
 ) -> Result<(Account, SmtpClient)> {
-    let mut config = load_or_wizard(config_paths)?;
-    let (name, mut ac) = config
-        .take_account(account_name)?
-        .ok_or_else(|| anyhow!("Cannot find account"))?;
+    let (name, mut ac) = config.select_account(account_name)?;
     let smtp_config = ac
         .smtp
         .take()
@@ -0,0 +1,142 @@
+//! App-specific password guidance for the well-known providers that
+//! reject the account password over IMAP/SMTP.
+//!
+//! Used by the wizards to warn before the password prompt, and by
+//! `account check` to turn an opaque authentication failure into a
+//! pointer to the provider's app password documentation.
+
+use anyhow::Error;
+
+/// A provider requiring an app-specific password for IMAP/SMTP.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AppPasswordProvider {
+    pub label: &'static str,
+    pub help_url: &'static str,
+    /// Server host suffixes identifying the provider.
+    domains: &'static [&'static str],
+}
+
+const PROVIDERS: &[AppPasswordProvider] = &[
+    AppPasswordProvider {
+        label: "Gmail",
+        help_url: "https://support.google.com/accounts/answer/185833",
+        domains: &["gmail.com", "googlemail.com"],
+    },
+    AppPasswordProvider {
+        label: "Outlook",
+        help_url: "https://support.microsoft.com/account-billing/5896ed9b-4263-e681-128a-a6f2979a7944",
+        domains: &["office365.com", "outlook.com", "hotmail.com", "live.com"],
+    },
+    AppPasswordProvider {
+        label: "Yahoo Mail",
+        help_url: "https://help.yahoo.com/kb/SLN15241.html",
+        domains: &["yahoo.com"],
+    },
+    AppPasswordProvider {
+        label: "iCloud Mail",
+        help_url: "https://support.apple.com/102654",
+        domains: &["me.com", "icloud.com", "mac.com"],
+    },
+    AppPasswordProvider {
+        label: "AOL Mail",
+        help_url: "https://help.aol.com/articles/Create-and-manage-app-password",
+        domains: &["aol.com"],
+    },
+];
+
+/// Lowercase fragments of server responses typical of a rejected
+/// login (IMAP `[AUTHENTICATIONFAILED]`, provider specific wording).
+/// SMTP reply codes are matched apart, see [`has_smtp_auth_code`].
+const AUTH_FAILURES: &[&str] = &[
+    "authenticationfailed",
+    "authentication failed",
+    "authenticate failed",
+    "invalid credentials",
+    "username and password not accepted",
+    "application-specific password",
+    "app password",
+    "login failed",
+    "logon failure",
+];
+
+/// SMTP reply codes of a rejected login (RFC 4954): `535` for invalid
+/// credentials, `534` when the provider asks for a stronger mechanism
+/// or an app-specific password.
+const SMTP_AUTH_CODES: &[&str] = &["534", "535"];
+
+/// Returns the provider matching `host` (a server host or an email
+/// domain), if it requires an app-specific password.
+pub fn find(host: &str) -> Option<&'static AppPasswordProvider> {
+    let host = host.trim_end_matches('.').to_lowercase();
+
+    PROVIDERS.iter().find(|provider| {
+        provider.domains.iter().any(|domain| {
+            host == *domain
+                || host
+                    .strip_suffix(domain)
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
+    })
+}
+
+/// Whether the error message looks like a rejected login.
+pub fn is_auth_failure(msg: &str) -> bool {
+    let msg = msg.to_lowercase();
+    AUTH_FAILURES.iter().any(|failure| msg.contains(failure)) || has_smtp_auth_code(&msg)
+}
+
+/// Whether `msg` holds one of the [`SMTP_AUTH_CODES`] as a whole
+/// word, so that sizes, ports or enhanced status codes containing the
+/// same digits (`15350`, `5.3.5`) do not count.
+fn has_smtp_auth_code(msg: &str) -> bool {
+    let bytes = msg.as_bytes();
+    let is_word = |b: &u8| b.is_ascii_alphanumeric() || *b == b'.';
+
+    SMTP_AUTH_CODES.iter().any(|code| {
+        msg.match_indices(code).any(|(i, _)| {
+            let before = i.checked_sub(1).and_then(|i| bytes.get(i));
+            let after = bytes.get(i + code.len());
+            !before.is_some_and(is_word) && !after.is_some_and(is_word)
+        })
+    })
+}
+
+/// Note printed by the wizards before the password prompt.
+pub fn note(provider: &AppPasswordProvider) -> String {
+    format!(
+        "Note: {} requires an app-specific password for IMAP/SMTP, not your account password. See {}",
+        provider.label, provider.help_url,
+    )
+}
+
+/// Adds app password guidance on top of `err` when it looks like a
+/// rejected login against a provider requiring one, otherwise
+/// returns it untouched.
+pub fn explain(host: Option<&str>, err: Error) -> Error {
+    let Some(provider) = host.and_then(find) else {
+        return err;
+    };
+
+    if !is_auth_failure(&format!("{err:#}")) {
+        return err;
+    }
+
+    err.context(format!(
+        "{} needs an app-specific password for IMAP/SMTP; see {}",
+        provider.label, provider.help_url,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_smtp_reply_codes_only() {
+        assert!(is_auth_failure("535 5.7.8 Error: bad credentials"));
+        assert!(is_auth_failure("unexpected reply: 534-5.7.9 Please log in"));
+        assert!(!is_auth_failure("message of 15350 bytes rejected"));
+        assert!(!is_auth_failure("connection to 10.0.0.535 refused"));
+        assert!(!is_auth_failure("552 5.3.4 message too big"));
+    }
+}
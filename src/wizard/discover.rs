@@ -36,12 +36,13 @@ use pimconf::autoconfig::types::Autoconfig;
 use url::Url;
 
 use crate::{
+    account::all::check_account_name,
     config::{
         AccountConfig, Config, GmailAuthConfig, GmailConfig, MsgraphAuthConfig, MsgraphConfig,
     },
     wizard::{
         account::{imap_to_config, jmap_to_config, smtp_to_config},
-        autoconfig, pacc, srv,
+        app_password, autoconfig, pacc, srv,
     },
 };
 
@@ -138,6 +139,7 @@ pub fn run(target: &Path) -> Result<Option<Config>> {
         .ok_or_else(|| anyhow!("Invalid email address `{email}`: expected `local@domain`"))?;
 
     let account_name = prompt::text("Account name:", Some(local_part))?;
+    check_account_name(&account_name)?;
 
     let (discovery, provider) = discover(local_part, domain);
     let account = build_account(
@@ -215,16 +217,14 @@ fn build_account(
             }
         }
         BackendChoice::ImapSmtp => {
-            if matches!(provider, Provider::Google | Provider::Microsoft) {
-                println!(
-                    "Note: {} requires an app-specific password for IMAP/SMTP, not your account password.",
-                    provider_label(provider),
-                );
-            }
-
             let imap_default = imap.or_else(|| provider_imap_default(provider, email));
             let smtp_default = smtp.or_else(|| provider_smtp_default(provider, email));
 
+            let host = imap_default.as_ref().map_or(domain, |c| c.host.as_str());
+            if let Some(app_password) = app_password::find(host) {
+                println!("{}", app_password::note(app_password));
+            }
+
             let imap = imap_wizard::run(account_name, local_part, domain, imap_default.as_ref())?;
             let smtp = smtp_wizard::run(account_name, local_part, domain, smtp_default.as_ref())?;
 
@@ -385,15 +385,6 @@ fn provider_from_autoconfig(autoconfig: &Autoconfig) -> Provider {
     }
 }
 
-/// Human-facing provider name for notices.
-fn provider_label(provider: Provider) -> &'static str {
-    match provider {
-        Provider::Google => "Gmail",
-        Provider::Microsoft => "Outlook",
-        Provider::Other => "This provider",
-    }
-}
-
 /// Pre-filled IMAP defaults for the well-known providers, so the user
 /// does not have to type the host and port by hand.
 fn provider_imap_default(provider: Provider, email: &str) -> Option<WizardImapConfig> {
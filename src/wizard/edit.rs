@@ -2,11 +2,17 @@
 //! existing account. Skips provider discovery entirely: this is meant
 //! for accounts the user already configured. Pre-fills the wizard
 //! prompts with the account's current values; the auth secret is
-//! never reused, the user is re-prompted for it.
+//! never shown, but leaving its prompt empty keeps the current one.
+//! Settings the wizard does not ask for are left untouched, and the
+//! resulting changes are shown for confirmation before writing.
 
-use std::path::Path;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::Path,
+};
 
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow};
 use log::info;
 use pimalaya_cli::{
     prompt,
@@ -23,19 +29,33 @@ use pimalaya_cli::{
     },
 };
 
+use pimalaya_config::secret::Secret;
+use secrecy::ExposeSecret;
+use toml::Value;
+
 use crate::{
     config::{
         AccountConfig, Config, ImapConfig, JmapAuthConfig, JmapConfig, SaslConfig, SmtpConfig,
     },
-    wizard::account::{imap_to_config, jmap_to_config, smtp_to_config},
+    wizard::{
+        account::{imap_to_config, jmap_to_config, smtp_to_config},
+        app_password,
+    },
 };
 
 /// Edits (or creates) the account named `account_name`. Uses the
 /// account's current `jmap` or `imap`/`smtp` blocks as defaults; an
 /// existing JMAP block routes to the JMAP wizard, otherwise the
-/// IMAP+SMTP wizards run. Writes the updated config to `target`
-/// before returning.
-pub fn edit_account(target: &Path, mut config: Config, account_name: &str) -> Result<Config> {
+/// IMAP+SMTP wizards run. Empty secrets are replaced by the current
+/// ones unless `reset_secrets`. Prints the changes, then writes the
+/// updated config to `target` once confirmed; nothing is written
+/// when nothing changed.
+pub fn edit_account(
+    target: &Path,
+    mut config: Config,
+    account_name: &str,
+    reset_secrets: bool,
+) -> Result<Config> {
     let existing = config.accounts.remove(account_name);
 
     let jmap_defaults = existing
@@ -71,78 +91,262 @@ pub fn edit_account(target: &Path, mut config: Config, account_name: &str) -> Re
         .rsplit_once('@')
         .ok_or_else(|| anyhow!("Invalid email address `{email}`: missing `@`"))?;
 
-    let is_first_account = config.accounts.is_empty() && existing.is_none();
-    let default = existing
-        .as_ref()
-        .map(|a| a.default)
-        .unwrap_or(is_first_account);
-    let downloads_dir = existing.as_ref().and_then(|a| a.downloads_dir.clone());
-    let table = existing
+    let host = imap_defaults
         .as_ref()
-        .map(|a| a.table.clone())
-        .unwrap_or_default();
-    let envelope = existing
-        .as_ref()
-        .map(|a| a.envelope.clone())
-        .unwrap_or_default();
-    let mailbox = existing
-        .as_ref()
-        .map(|a| a.mailbox.clone())
-        .unwrap_or_default();
-    let attachment = existing
-        .as_ref()
-        .map(|a| a.attachment.clone())
-        .unwrap_or_default();
-    let maildir = existing.as_ref().and_then(|a| a.maildir.clone());
-    let m2dir = existing.as_ref().and_then(|a| a.m2dir.clone());
+        .map(|c| c.host.as_str())
+        .filter(|host| !host.is_empty())
+        .unwrap_or(domain);
+    if let Some(app_password) = app_password::find(host).filter(|_| jmap_defaults.is_none()) {
+        println!("{}", app_password::note(app_password));
+    }
+
+    let is_first_account = config.accounts.is_empty() && existing.is_none();
+    let before = flatten_account(existing.as_ref())?;
+    let mut account = existing.unwrap_or_else(|| AccountConfig {
+        default: is_first_account,
+        ..Default::default()
+    });
 
-    let account = if jmap_defaults.is_some() {
+    if jmap_defaults.is_some() {
         let jmap = jmap_wizard::run(account_name, local_part, domain, jmap_defaults.as_ref())?;
-        AccountConfig {
-            default,
-            downloads_dir,
-            table,
-            envelope,
-            mailbox,
-            attachment,
-            imap: None,
-            jmap: Some(jmap_to_config(jmap)?),
-            gmail: None,
-            msgraph: None,
-            maildir,
-            m2dir,
-            smtp: None,
-        }
+        let jmap = merge_jmap(jmap_to_config(jmap)?, account.jmap.take(), reset_secrets);
+        account.jmap = Some(jmap);
+        account.imap = None;
+        account.smtp = None;
     } else {
         let imap = imap_wizard::run(account_name, local_part, domain, imap_defaults.as_ref())?;
         let smtp = smtp_wizard::run(account_name, local_part, domain, smtp_defaults.as_ref())?;
-        AccountConfig {
-            default,
-            downloads_dir,
-            table,
-            envelope,
-            mailbox,
-            attachment,
-            imap: Some(imap_to_config(imap)?),
-            jmap: None,
-            gmail: None,
-            msgraph: None,
-            maildir,
-            m2dir,
-            smtp: Some(smtp_to_config(smtp)?),
-        }
-    };
+        let imap = merge_imap(imap_to_config(imap)?, account.imap.take(), reset_secrets);
+        let smtp = merge_smtp(smtp_to_config(smtp)?, account.smtp.take(), reset_secrets);
+        account.imap = Some(imap);
+        account.smtp = Some(smtp);
+        account.jmap = None;
+    }
+
+    account.gmail = None;
+    account.msgraph = None;
 
+    let changes = diff(&before, &flatten_account(Some(&account))?);
     config.accounts.insert(account_name.to_owned(), account);
+
+    if changes.is_empty() {
+        println!("No changes, configuration left untouched.");
+        return Ok(config);
+    }
+
+    println!("Changes to account `{account_name}`:");
+    for change in &changes {
+        println!("  {change}");
+    }
+
+    if !prompt::bool("Write these changes?", true)? {
+        println!("Configuration left untouched.");
+        return Ok(config);
+    }
+
     config.write(target)?;
     info!("Configuration written to {}.", target.display());
 
     Ok(config)
 }
 
+/// Keeps the settings of `old` the wizard does not ask for (TLS,
+/// ALPN, ID, sort) and, unless `reset_secrets`, its secret when the
+/// password prompt was left empty.
+fn merge_imap(new: ImapConfig, old: Option<ImapConfig>, reset_secrets: bool) -> ImapConfig {
+    let Some(old) = old else {
+        return new;
+    };
+
+    ImapConfig {
+        server: new.server,
+        starttls: new.starttls,
+        sasl: merge_sasl(new.sasl, old.sasl, reset_secrets),
+        ..old
+    }
+}
+
+/// Same as [`merge_imap`] but for SMTP.
+fn merge_smtp(new: SmtpConfig, old: Option<SmtpConfig>, reset_secrets: bool) -> SmtpConfig {
+    let Some(old) = old else {
+        return new;
+    };
+
+    SmtpConfig {
+        server: new.server,
+        starttls: new.starttls,
+        sasl: merge_sasl(new.sasl, old.sasl, reset_secrets),
+        ..old
+    }
+}
+
+/// Same as [`merge_imap`] but for JMAP, keeping the identity and
+/// drafts mailbox ids as well.
+fn merge_jmap(new: JmapConfig, old: Option<JmapConfig>, reset_secrets: bool) -> JmapConfig {
+    let Some(old) = old else {
+        return new;
+    };
+
+    let auth = match (new.auth, &old.auth) {
+        (
+            JmapAuthConfig::Basic { username, password },
+            JmapAuthConfig::Basic { password: prev, .. },
+        ) if is_blank(&password) && !reset_secrets => JmapAuthConfig::Basic {
+            username,
+            password: prev.clone(),
+        },
+        (
+            JmapAuthConfig::Bearer { token },
+            prev @ (JmapAuthConfig::Bearer { .. } | JmapAuthConfig::Header(_)),
+        ) if is_blank(&token) && !reset_secrets => prev.clone(),
+        (auth, _) => auth,
+    };
+
+    JmapConfig {
+        server: new.server,
+        auth,
+        ..old
+    }
+}
+
+/// Puts the secret of `old` back into the PLAIN block built by the
+/// wizard when its password was left empty. The whole `old` block is
+/// kept when the login did not change, so a non-PLAIN mechanism
+/// (e.g. XOAUTH2 with a token command) survives the edit.
+fn merge_sasl(
+    new: Option<SaslConfig>,
+    old: Option<SaslConfig>,
+    reset_secrets: bool,
+) -> Option<SaslConfig> {
+    let (Some(SaslConfig::Plain(mut plain)), Some(old)) = (new.clone(), old) else {
+        return new;
+    };
+
+    if reset_secrets || !is_blank(&plain.passwd) {
+        return new;
+    }
+
+    if sasl_login(Some(&old)) == plain.authcid {
+        return Some(old);
+    }
+
+    match sasl_secret(old) {
+        Some(secret) => {
+            plain.passwd = secret;
+            Some(SaslConfig::Plain(plain))
+        }
+        None => new,
+    }
+}
+
+fn sasl_secret(sasl: SaslConfig) -> Option<Secret> {
+    match sasl {
+        SaslConfig::Plain(p) => Some(p.passwd),
+        SaslConfig::Login(l) => Some(l.password),
+        SaslConfig::Oauthbearer(o) => Some(o.token),
+        SaslConfig::Xoauth2(x) => Some(x.token),
+        SaslConfig::ScramSha256(s) => Some(s.password),
+        SaslConfig::Anonymous(_) => None,
+    }
+}
+
+/// Whether `secret` is the empty raw value an empty prompt answer
+/// gives.
+fn is_blank(secret: &Secret) -> bool {
+    matches!(secret, Secret::Raw(s) if s.expose_secret().is_empty())
+}
+
+/// Keys whose values are never printed in a diff.
+const SECRET_KEYS: [&str; 4] = ["passwd", "password", "token", "header"];
+
+/// One changed setting between two versions of an account.
+#[derive(Debug, Eq, PartialEq)]
+struct Change {
+    key: String,
+    before: Option<String>,
+    after: Option<String>,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key = &self.key;
+
+        match (&self.before, &self.after) {
+            (None, Some(after)) => write!(f, "+ {key} = {after}"),
+            (Some(before), None) => write!(f, "- {key} = {before}"),
+            (Some(before), Some(after)) => write!(f, "~ {key}: {before} -> {after}"),
+            (None, None) => write!(f, "  {key}"),
+        }
+    }
+}
+
+/// Dotted key paths of `account` mapped to their TOML values. Empty
+/// when there is no account.
+fn flatten_account(account: Option<&AccountConfig>) -> Result<BTreeMap<String, Value>> {
+    let mut keys = BTreeMap::new();
+
+    if let Some(account) = account {
+        let value = Value::try_from(account).context("Serialize account config error")?;
+        flatten("", value, &mut keys);
+    }
+
+    Ok(keys)
+}
+
+fn flatten(prefix: &str, value: Value, keys: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten(&key, value, keys);
+            }
+        }
+        value => {
+            keys.insert(prefix.to_owned(), value);
+        }
+    }
+}
+
+/// Settings added, removed or modified from `before` to `after`,
+/// sorted by key. Secret values are masked.
+fn diff(before: &BTreeMap<String, Value>, after: &BTreeMap<String, Value>) -> Vec<Change> {
+    let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    let mut changes = Vec::new();
+
+    for key in keys {
+        let (old, new) = (before.get(key), after.get(key));
+
+        if old == new {
+            continue;
+        }
+
+        let secret = key.split('.').any(|k| SECRET_KEYS.contains(&k));
+        let show = |value: &Value| {
+            if secret {
+                String::from("(secret)")
+            } else {
+                value.to_string()
+            }
+        };
+
+        changes.push(Change {
+            key: key.clone(),
+            before: old.map(show),
+            after: new.map(show),
+        });
+    }
+
+    changes
+}
+
 /// Derives default [`WizardImapConfig`] values from an existing
-/// [`ImapConfig`]. The auth secret is never reused; the wizard
-/// re-prompts the user for it.
+/// [`ImapConfig`]. The auth secret is left empty: the wizard
+/// re-prompts the user for it, an empty answer keeping the current
+/// one (see [`merge_imap`]).
 pub fn imap_to_wizard(c: &ImapConfig) -> WizardImapConfig {
     let url = crate::imap::client::parse_imap_server(&c.server).ok();
     let scheme = url.as_ref().map(|u| u.scheme()).unwrap_or("imaps");
@@ -239,3 +443,44 @@ pub fn jmap_to_wizard(c: &JmapConfig) -> WizardJmapConfig {
         auth,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_changed_keys_and_masks_secrets() {
+        let keys = |pairs: &[(&str, &str)]| -> BTreeMap<String, Value> {
+            pairs
+                .iter()
+                .map(|(k, v)| ((*k).to_owned(), Value::from(*v)))
+                .collect()
+        };
+
+        let before = keys(&[
+            ("imap.server", "imaps://old:993"),
+            ("imap.sasl.plain.passwd", "hunter2"),
+            ("downloads-dir", "/tmp"),
+        ]);
+        let after = keys(&[
+            ("imap.server", "imaps://new:993"),
+            ("imap.sasl.plain.passwd", "hunter3"),
+            ("downloads-dir", "/tmp"),
+            ("smtp.server", "smtps://new:465"),
+        ]);
+
+        let changes: Vec<String> = diff(&before, &after)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            changes,
+            [
+                r#"~ imap.sasl.plain.passwd: (secret) -> (secret)"#,
+                r#"~ imap.server: "imaps://old:993" -> "imaps://new:993""#,
+                r#"+ smtp.server = "smtps://new:465""#,
+            ]
+        );
+    }
+}
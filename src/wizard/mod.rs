@@ -1,4 +1,5 @@
 pub mod account;
+pub mod app_password;
 pub mod autoconfig;
 pub mod discover;
 pub mod edit;